@@ -8,20 +8,101 @@ pub mod api {
     use log::{error, warn};
     use serde_json::json;
     use tokio::sync::Mutex;
+    use crate::audit::audit::{is_audited, now_secs, AuditFilter, AuditLog, AuditRecord};
+    use crate::auth::auth::{AuthPolicy, ClientIdentity};
+    use crate::config::config::SettingsManager;
     use crate::protocol::JsonProtocol;
-    use crate::ros2_server::ros2_server::Ros2Discoverer;
-    use crate::ros2entites::ros2entities::{Ros2State, Ros2Topic};
+    use crate::resources::resources::ResourceMonitor;
+    use crate::traffic::traffic::TrafficMonitor;
+    use crate::ros2_server::ros2_server::{Ros2Discoverer, SharedRos2Discoverer};
+    use crate::interfaces::interfaces::TypeRegistry;
+    use crate::ros2entites::ros2entities::{node_fqn, split_node_fqn, Ros2DdsStatistics, Ros2Node, Ros2State, Ros2Topic, WireEntityKind};
+
+    fn ambiguous_node_msg(node_name: &str, nodes: &[&Ros2Node]) -> String {
+        let mut fqns: Vec<String> = nodes.iter().map(|node| node.fqn()).collect();
+        fqns.sort();
+        fqns.dedup();
+        return format!("Node name {} is ambiguous, use fully qualified name or namespace argument: {}", node_name, fqns.join(", "));
+    }
+
+    /// Provides the most recent ros2 state snapshot to the transports (control socket, http)
+    pub type StateProvider = Arc<dyn Fn() -> Arc<Mutex<Ros2State>> + Send + Sync>;
 
     #[derive(Clone)]
     pub struct Api {
-        pub ros2discoverer: Box<Ros2Discoverer>,
+        pub ros2discoverer: SharedRos2Discoverer,
+        pub policy: AuthPolicy,
+        pub audit_log: Option<Arc<AuditLog>>,
+        pub settings: Arc<SettingsManager>,
+        pub resources: Arc<ResourceMonitor>,
+        pub traffic: Arc<TrafficMonitor>,
+        pub types: Arc<TypeRegistry>,
     }
 
     impl Api {
-        pub fn new(ros2discoverer: Box<Ros2Discoverer>) -> Api {
+        pub fn new(ros2discoverer: SharedRos2Discoverer, policy: AuthPolicy, audit_log: Option<Arc<AuditLog>>, settings: Arc<SettingsManager>,
+                   resources: Arc<ResourceMonitor>, traffic: Arc<TrafficMonitor>, types: Arc<TypeRegistry>) -> Api {
             return Api {
-                ros2discoverer
+                ros2discoverer,
+                policy,
+                audit_log,
+                settings,
+                resources,
+                traffic,
+                types,
+            };
+        }
+
+        /// Discoverer of the current settings, it is replaced when settings change
+        fn ros2discoverer(&self) -> Ros2Discoverer {
+            return self.ros2discoverer.read().unwrap().clone();
+        }
+
+        /// Discoverer running ros2 cli in the domain of `domain_id` argument, the default one without it
+        fn domain_discoverer(&self, request: &JsonProtocol) -> Result<Ros2Discoverer, String> {
+            return match request.arguments.get("domain_id") {
+                Some(domain_id) => match domain_id.parse::<u32>() {
+                    Ok(domain_id) => Ok(self.ros2discoverer().for_domain(domain_id)),
+                    Err(_) => Err(format!("Invalid domain id {}", domain_id))
+                },
+                None => Ok(self.ros2discoverer())
+            };
+        }
+
+        /// Fully qualified name of the node the command acts on, the discoverer of its domain and PID of its local process.
+        /// Without `domain_id` argument the node must be discovered in the single domain, unknown nodes are looked for
+        /// in the default domain
+        /// # Arguments
+        ///
+        /// * `request`: request with node_name and optional namespace and domain_id arguments
+        /// * `current_state`: state of all domains
+        ///
+        /// returns: Result<(Ros2Discoverer, String, Option<u32>), String>
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        async fn target_node(&self, request: &JsonProtocol, current_state: Arc<Mutex<Ros2State>>) -> Result<(Ros2Discoverer, String, Option<u32>), String> {
+            let state = self.domain_state(current_state, request.arguments.get("domain_id")).await?;
+            let node_name = self.resolve_node_name(request, state.clone()).await?;
+            let state_obj = state.lock().await;
+            let nodes = state_obj.find_nodes(node_name.as_str(), None);
+            let pid = nodes.iter().find_map(|node| state_obj.node_pid(node));
+            if request.arguments.contains_key("domain_id") {
+                return Ok((self.domain_discoverer(request)?, node_name, pid));
+            }
+
+            let mut domain_ids: Vec<u32> = nodes.iter().map(|node| node.domain_id).collect();
+            domain_ids.sort();
+            domain_ids.dedup();
+            let discoverer = match domain_ids.as_slice() {
+                [] => self.ros2discoverer(),
+                [domain_id] => self.ros2discoverer().for_domain(*domain_id),
+                _ => return Err(format!("Node {} is discovered in domains {:?}, use domain_id argument", node_name, domain_ids))
             };
+            return Ok((discoverer, node_name, pid));
         }
 
         /**
@@ -31,35 +112,283 @@ pub mod api {
             let state_obj: &Ros2State = &state.lock().await.to_owned();
 
             let json_str = json!({
+                "domains": state_obj.domain_ids(),
                 "packages": state_obj.packages,
-                "nodes": state_obj.nodes,
-                "topics": state_obj.topics
+                "nodes": state_obj.nodes(),
+                "topics": state_obj.topics(),
+                "contexts": state_obj.contexts(),
+                "hosts": state_obj.hosts(),
+                "wire": state_obj.wire_activity()
             });
 
             return json_str.to_string();
         }
 
-        pub async fn handle_request(&self, request: String, current_state: Arc<Mutex<Ros2State>>) -> String {
+        /// Restrict state to the single domain if domain id is specified. Otherwise state of all
+        /// monitored domains is returned as is
+        /// # Arguments
+        ///
+        /// * `state`: state of all domains
+        /// * `domain_id`: optional domain id as it comes in request
+        ///
+        /// returns: Result<Arc<Mutex<Ros2State>>, String>
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub async fn domain_state(&self, state: Arc<Mutex<Ros2State>>, domain_id: Option<&String>) -> Result<Arc<Mutex<Ros2State>>, String> {
+            let domain_id: u32 = match domain_id {
+                Some(domain_id) => match domain_id.parse() {
+                    Ok(domain_id) => domain_id,
+                    Err(_) => return Err(format!("Invalid domain id {}", domain_id))
+                },
+                None => return Ok(state)
+            };
+            let filtered = state.lock().await.filter_domain(domain_id);
+            return Ok(Arc::new(Mutex::new(filtered)));
+        }
+
+        /// Generate json array of all nodes or nodes under the namespace, including nested namespaces
+        pub async fn nodes_json(&self, state: Arc<Mutex<Ros2State>>, namespace: Option<&String>) -> String {
+            let state_obj: &Ros2State = &state.lock().await.to_owned();
+            return match namespace {
+                Some(namespace) => json!(state_obj.nodes_in_namespace(namespace.as_str())).to_string(),
+                None => json!(state_obj.nodes()).to_string()
+            };
+        }
+
+        /// Generate json for the node with specified fully qualified name or bare name and namespace.
+        /// Returns error if there is no such node or the bare name matches nodes in several namespaces
+        pub async fn node_json(&self, node_name: String, namespace: Option<&String>, state: Arc<Mutex<Ros2State>>) -> Result<String, String> {
+            let state_obj: &Ros2State = &state.lock().await.to_owned();
+            let nodes = state_obj.find_nodes(node_name.as_str(), namespace.map(|namespace| namespace.as_str()));
+            return match nodes.as_slice() {
+                [] => Err(format!("Node {} not found", node_name)),
+                [node] => Ok(json!(node).to_string()),
+                nodes => match nodes.iter().all(|node| node.fqn() == nodes[0].fqn()) {
+                    // The same node in several domains
+                    true => Ok(json!(nodes).to_string()),
+                    false => Err(ambiguous_node_msg(node_name.as_str(), nodes))
+                }
+            };
+        }
+
+        /// Fully qualified name of the node from `node_name` and optional `namespace` arguments.
+        /// Bare name is resolved by the discovered nodes, unknown names are passed as is to ros2 cli
+        /// # Arguments
+        ///
+        /// * `request`: request with node_name argument
+        /// * `state`: current state to resolve bare names
+        ///
+        /// returns: Result<String, String>
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub async fn resolve_node_name(&self, request: &JsonProtocol, state: Arc<Mutex<Ros2State>>) -> Result<String, String> {
+            let node_name = match request.arguments.get("node_name") {
+                Some(node_name) if !node_name.is_empty() => node_name.clone(),
+                _ => return Err(format!("You must provide node_name argument for command {}", request.command))
+            };
+            let namespace = request.arguments.get("namespace").map(|namespace| namespace.as_str());
+            if let Some(namespace) = namespace {
+                return Ok(node_fqn(namespace, node_name.as_str()));
+            }
+
+            let state_obj = state.lock().await;
+            let mut fqns: Vec<String> = state_obj.find_nodes(node_name.as_str(), None).iter().map(|node| node.fqn()).collect();
+            fqns.sort();
+            fqns.dedup();
+            return match fqns.len() {
+                0 => Ok(node_name),
+                1 => Ok(fqns.remove(0)),
+                _ => Err(ambiguous_node_msg(node_name.as_str(), &state_obj.find_nodes(node_name.as_str(), None)))
+            };
+        }
+
+        /// Generate json array of all topics
+        pub async fn topics_json(&self, state: Arc<Mutex<Ros2State>>) -> String {
+            let state_obj: &Ros2State = &state.lock().await.to_owned();
+            return json!(state_obj.topics()).to_string();
+        }
+
+        /// Generate json for the topic with specified name. Returns None if there is no such topic
+        pub async fn topic_json(&self, topic_name: String, state: Arc<Mutex<Ros2State>>) -> Option<String> {
+            let state_obj: &Ros2State = &state.lock().await.to_owned();
+            return state_obj.topics().iter().find(|topic| topic.name == topic_name).map(|topic| json!(topic).to_string());
+        }
+
+        /// Generate json array of hosts with their participants and nodes
+        pub async fn hosts_json(&self, state: Arc<Mutex<Ros2State>>) -> String {
+            let state_obj: &Ros2State = &state.lock().await.to_owned();
+            return json!(state_obj.hosts()).to_string();
+        }
+
+        /// Generate json array of all packages
+        pub async fn packages_json(&self, state: Arc<Mutex<Ros2State>>) -> String {
+            let state_obj: &Ros2State = &state.lock().await.to_owned();
+            return json!(state_obj.packages).to_string();
+        }
+
+        pub async fn handle_request(&self, request: String, identity: &ClientIdentity, current_state: Arc<Mutex<Ros2State>>) -> String {
             // Parse request as json formatted str
             let mut parsed = JsonProtocol::new();
             match parsed.parse_request(request.as_str()) {
                 Ok(()) => (),
-                Err(msg) => error!("{}", msg)
+                Err(msg) => {
+                    error!("{}", msg);
+                    return json!({"result": "failure", "msg": msg}).to_string();
+                }
             }
+            return self.dispatch(&parsed, identity, current_state).await;
+        }
+
+        /// Execute already parsed request on behalf of the client. Shared by all transports (control socket, http)
+        pub async fn dispatch(&self, parsed: &JsonProtocol, identity: &ClientIdentity, current_state: Arc<Mutex<Ros2State>>) -> String {
             let command = parsed.command.clone();
+            // Commands which change the system leave a trace in audit log, even if they were denied.
+            // The policy can't change what is audited, it only authorizes
+            let mut audit_record = if is_audited(command.as_str()) {
+                let arguments = parsed.arguments.iter().map(|(name, value)| (name.clone(), value.clone())).collect();
+                let mut record = AuditRecord::new(identity.clone(), command.clone(), arguments);
+                // Bare names are recorded as fully qualified ones of the discovered nodes
+                if parsed.arguments.contains_key("node_name") && command != "launch" {
+                    if let Ok(fqn) = self.resolve_node_name(parsed, current_state.clone()).await {
+                        record.affected_nodes = vec![fqn];
+                    }
+                }
+                Some(record)
+            } else {
+                None
+            };
+
+            if let Err(msg) = self.policy.authorize(identity, command.as_str()) {
+                warn!("Request {} from {} is denied: {}", command, identity, msg);
+                if let Some(record) = audit_record.as_mut() {
+                    record.result = "denied".to_string();
+                    record.msg = msg.clone();
+                }
+                self.audit(audit_record);
+                return json!({"result": "failure", "msg": msg}).to_string();
+            }
+
             let response: String = match command.as_str() {
-                "state" => self.state_command(&parsed, current_state).await,
-                "shutdown" => self.shutdown_node_command(&parsed, current_state),
-                "configure" => self.configure_node_command(&parsed, current_state),
-                "launch" => self.launch_node_command(&parsed, current_state),
-                "cleanup" => self.cleanup_node_command(&parsed, current_state),
-                "rename_topic" => "".to_string(),
+                "state" => self.state_command(parsed, current_state).await,
+                "nodes" => self.nodes_command(parsed, current_state).await,
+                "node_resources" => self.node_resources_command(parsed, current_state).await,
+                "hosts" => self.hosts_command(parsed, current_state).await,
+                "ports" => self.ports_command(parsed),
+                "traffic" => self.traffic_command(parsed, current_state).await,
+                "dds_statistics" => self.dds_statistics_command(parsed, current_state).await,
+                "type_info" => self.type_info_command(parsed, current_state).await,
+                "shutdown" => self.shutdown_node_command(parsed, current_state).await,
+                "configure" => self.configure_node_command(parsed, current_state).await,
+                "launch" => self.launch_node_command(parsed, current_state),
+                "cleanup" => self.cleanup_node_command(parsed, current_state).await,
+                "lifecycle" => self.lifecycle_command(parsed, current_state).await,
+                "audit_log" => self.audit_log_command(parsed),
+                "get_settings" => self.get_settings_command(),
+                "set_settings" => self.set_settings_command(parsed),
+                "rename_topic" => json!({"result": "failure", "msg": "Command rename_topic isn't implemented"}).to_string(),
                 _ => "Unknown request".to_string()
             };
 
+            if let Some(record) = audit_record.as_mut() {
+                record.set_response(response.as_str());
+            }
+            self.audit(audit_record);
+
             return response;
         }
 
+        fn audit(&self, record: Option<AuditRecord>) {
+            if let (Some(audit_log), Some(record)) = (self.audit_log.as_ref(), record) {
+                if let Err(e) = audit_log.append(&record) {
+                    error!("{}", e);
+                }
+            }
+        }
+
+        pub fn get_settings_command(&self) -> String {
+            return json!({"result": "success", "settings": self.settings.settings()}).to_string();
+        }
+
+        /// Change daemon settings. Domain id, visibility of internal entities, dds type names and
+        /// log level are applied immediately, other settings - after restart. Settings are persisted into config file.
+        /// # Arguments
+        ///
+        /// * `request`: request data, each argument is a setting name with new value
+        ///
+        /// returns: String
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn set_settings_command(&self, request: &JsonProtocol) -> String {
+            return match self.settings.update(&request.arguments) {
+                Ok(change) => json!({
+                    "result": "success",
+                    "msg": "",
+                    "changed": change.changed,
+                    "restart_required": change.restart_required,
+                    "saved_to": change.saved_to
+                }).to_string(),
+                Err(msg) => json!({"result": "failure", "msg": msg}).to_string()
+            };
+        }
+
+        /// Query audit log of mutating commands.
+        /// # Arguments
+        ///
+        /// * `request`: request data with optional arguments: `from` and `to` - time range in seconds
+        /// since unix epoch, `command` - comma separated list of commands, `limit` - max number of the latest records
+        ///
+        /// returns: String
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn audit_log_command(&self, request: &JsonProtocol) -> String {
+            let audit_log = match self.audit_log.as_ref() {
+                Some(audit_log) => audit_log,
+                None => return r#"{"result": "failure", "msg": "Audit log is disabled"}"#.to_string()
+            };
+
+            let mut filter = AuditFilter::default();
+            for (name, value) in request.arguments.iter() {
+                let parse_error = format!("Invalid value {} for argument {}", value, name);
+                match name.as_str() {
+                    "from" => filter.from = match value.parse::<f64>() {
+                        Ok(from) => Some(from),
+                        Err(_) => return json!({"result": "failure", "msg": parse_error}).to_string()
+                    },
+                    "to" => filter.to = match value.parse::<f64>() {
+                        Ok(to) => Some(to),
+                        Err(_) => return json!({"result": "failure", "msg": parse_error}).to_string()
+                    },
+                    "limit" => filter.limit = match value.parse::<usize>() {
+                        Ok(limit) => Some(limit),
+                        Err(_) => return json!({"result": "failure", "msg": parse_error}).to_string()
+                    },
+                    "command" => filter.commands = value.split(',').map(|command| command.trim().to_string()).filter(|command| !command.is_empty()).collect(),
+                    _ => ()
+                }
+            }
+
+            return match audit_log.query(&filter) {
+                Ok(records) => json!({"result": "success", "records": records}).to_string(),
+                Err(msg) => json!({"result": "failure", "msg": msg}).to_string()
+            };
+        }
+
         /// This function renames topic to another name. In order to do this topic's node need to be
         /// restarted. Due this rename_topic_command works well for lifecycle nodes. It can be used with
         /// regular nodes without any guarantee of success. It returns json string contains if command
@@ -90,15 +419,15 @@ pub mod api {
             };
 
             // Find node name for topic
-            let topic: Ros2Topic = current_state.lock().await.topics.iter().find(|&topic| topic.name == new_topic_name).unwrap().clone();
+            let topic: Ros2Topic = current_state.lock().await.topics().iter().find(|&topic| topic.name == new_topic_name).unwrap().clone();
             let node_name = topic.node_name;
             // Check if node is running. If yes then kill it
-            if self.ros2discoverer.is_node_running(node_name.clone()) {
-                self.ros2discoverer.shutdown_node(node_name.clone());
+            if self.ros2discoverer().is_node_running(node_name.clone()) {
+                self.ros2discoverer().shutdown_node(node_name.clone(), None);
             }
 
             // Restart node with altered topic name
-            let res = self.ros2discoverer.run_node(node_name.clone(), ["--ros-args", "-r", old_topic_name.as_str(), new_topic_name.as_str()]);
+            let res = self.ros2discoverer().run_node(node_name.clone(), ["--ros-args", "-r", old_topic_name.as_str(), new_topic_name.as_str()]);
             return res;
         }
 
@@ -139,39 +468,408 @@ pub mod api {
             };
         }
 
-        pub async fn state_command(&self, _request: &JsonProtocol, current_state: Arc<Mutex<Ros2State>>) -> String {
-            return self.ros2_state_json(current_state.clone()).await;
+        /// State of all domains or, if domain_id argument is specified, of the single domain
+        pub async fn state_command(&self, request: &JsonProtocol, current_state: Arc<Mutex<Ros2State>>) -> String {
+            // Traffic captured since the state was published is resolved against the graph
+            let mut state = current_state.lock().await.to_owned();
+            state.add_wire_activity(self.traffic.totals());
+            return match self.domain_state(Arc::new(Mutex::new(state)), request.arguments.get("domain_id")).await {
+                Ok(state) => self.ros2_state_json(state).await,
+                Err(msg) => json!({"result": "failure", "msg": msg}).to_string()
+            };
         }
 
-        pub fn shutdown_node_command(&self, request: &JsonProtocol, _current_state: Arc<Mutex<Ros2State>>) -> String {
-            // Extract node name from request
-            let node_name = if request.arguments.contains_key("node_name") {
-                request.arguments.get("node_name").unwrap().to_string()
-            } else {
-                r#"{"result": "failure", "msg": "You must provide node_name argument for command kill_node"}"#.to_string()
+        /// Nodes under the namespace argument (all nodes by default), optionally of the single domain
+        pub async fn nodes_command(&self, request: &JsonProtocol, current_state: Arc<Mutex<Ros2State>>) -> String {
+            return match self.domain_state(current_state, request.arguments.get("domain_id")).await {
+                Ok(state) => self.nodes_json(state, request.arguments.get("namespace")).await,
+                Err(msg) => json!({"result": "failure", "msg": msg}).to_string()
             };
+        }
+
+        /// Hosts of all domains or, if domain_id argument is specified, hosts which run participants of the domain
+        pub async fn hosts_command(&self, request: &JsonProtocol, current_state: Arc<Mutex<Ros2State>>) -> String {
+            return match self.domain_state(current_state, request.arguments.get("domain_id")).await {
+                Ok(state) => self.hosts_json(state).await,
+                Err(msg) => json!({"result": "failure", "msg": msg}).to_string()
+            };
+        }
 
-            if node_name.is_empty() {
-                warn!("Node {} doesn't running. So, it is impossible to be killed", node_name);
-                return "".to_string();
+        /// RTPS port mapping with the configured port parameters. With `port` argument the port is mapped back
+        /// to its domain, participant and kind of traffic, otherwise ports of the participant are calculated
+        /// # Arguments
+        ///
+        /// * `request`: request with either port or optional domain_id (the first monitored by default) and participant_id (0 by default)
+        ///
+        /// returns: String
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn ports_command(&self, request: &JsonProtocol) -> String {
+            let settings = self.settings.settings();
+            let params = settings.port_params;
+            let parse = |name: &str, default: u32| -> Result<u32, String> {
+                return match request.arguments.get(name) {
+                    Some(value) => value.parse::<u32>().map_err(|_| format!("Invalid {} {}", name, value)),
+                    None => Ok(default)
+                };
+            };
+
+            if request.arguments.contains_key("port") {
+                let port = match parse("port", 0) {
+                    Ok(port) => port,
+                    Err(msg) => return json!({"result": "failure", "msg": msg}).to_string()
+                };
+                return match params.resolve(port) {
+                    Some(usage) => json!({"result": "success", "port": port, "usage": usage}).to_string(),
+                    None => json!({"result": "failure", "msg": format!("Port {} isn't mapped to RTPS traffic", port)}).to_string()
+                };
+            }
+
+            let default_domain = settings.domain_ids.first().cloned().unwrap_or(0);
+            let (domain_id, participant_id) = match (parse("domain_id", default_domain), parse("participant_id", 0)) {
+                (Ok(domain_id), Ok(participant_id)) => (domain_id, participant_id),
+                (Err(msg), _) | (_, Err(msg)) => return json!({"result": "failure", "msg": msg}).to_string()
+            };
+            if domain_id > params.max_domain_id() {
+                return json!({"result": "failure", "msg": format!("Ports of domain {} are out of range", domain_id)}).to_string();
             }
+            if participant_id > params.max_participant_id() {
+                return json!({"result": "failure", "msg": format!("Participant id {} is out of range", participant_id)}).to_string();
+            }
+            let ports = params.ports(domain_id, participant_id);
+            return json!({"result": "success", "domain_id": domain_id, "participant_id": participant_id,
+                "ports": ports, "port_params": params}).to_string();
+        }
+
+        /// Captured traffic over the window: top talkers, bandwidth of hosts and throughput of topics
+        /// # Arguments
+        ///
+        /// * `request`: request with optional window (seconds, 60 by default), limit of top talkers (10 by default) and domain_id
+        /// * `current_state`: current info about ros2 entities
+        ///
+        /// returns: String
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub async fn traffic_command(&self, request: &JsonProtocol, current_state: Arc<Mutex<Ros2State>>) -> String {
+            let window: f64 = match request.arguments.get("window") {
+                Some(window) => match window.parse::<f64>() {
+                    Ok(window) if window > 0.0 => window,
+                    _ => return json!({"result": "failure", "msg": format!("Invalid window {}", window)}).to_string()
+                },
+                None => 60.0
+            };
+            let limit: usize = match request.arguments.get("limit") {
+                Some(limit) => match limit.parse::<usize>() {
+                    Ok(limit) => limit,
+                    Err(_) => return json!({"result": "failure", "msg": format!("Invalid limit {}", limit)}).to_string()
+                },
+                None => 10
+            };
+            let domain_id = request.arguments.get("domain_id");
+            let state = match self.domain_state(current_state, domain_id).await {
+                Ok(state) => state,
+                Err(msg) => return json!({"result": "failure", "msg": msg}).to_string()
+            };
+
+            // Traffic of undiscovered participants has no domain, so it is shown for all domains only
+            let report = self.traffic.report(&*state.lock().await, window, limit, domain_id.is_some());
+            return json!({"result": "success", "traffic": report}).to_string();
+        }
+
+        /// Latest FastDDS statistics of participants and endpoints. Statistics are received only if `dds_statistics`
+        /// setting is enabled and monitored participants publish them
+        /// # Arguments
+        ///
+        /// * `request`: request with optional domain_id and topic_name arguments
+        /// * `current_state`: current info about ros2 entities
+        ///
+        /// returns: String
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub async fn dds_statistics_command(&self, request: &JsonProtocol, current_state: Arc<Mutex<Ros2State>>) -> String {
+            let state = match self.domain_state(current_state, request.arguments.get("domain_id")).await {
+                Ok(state) => state,
+                Err(msg) => return json!({"result": "failure", "msg": msg}).to_string()
+            };
+            let topic_name = request.arguments.get("topic_name");
+
+            let state_obj = state.lock().await;
+            let (participants, endpoints): (Vec<Ros2DdsStatistics>, Vec<Ros2DdsStatistics>) = state_obj.dds_statistics().into_iter()
+                .filter(|statistics| topic_name.is_none() || statistics.topic_name.as_ref() == topic_name)
+                .partition(|statistics| statistics.kind == WireEntityKind::Participant);
+            return json!({
+                "result": "success",
+                "enabled": self.settings.settings().dds_statistics,
+                "participants": participants,
+                "endpoints": endpoints,
+                "network_latency": state_obj.network_latency(),
+            }).to_string();
+        }
+
+        /// Field structure of the message, service or action type with all nested messages. The type is given
+        /// by name or by the topic which has it
+        /// # Arguments
+        ///
+        /// * `request`: request with type_name, or with topic_name and optional domain_id arguments
+        /// * `current_state`: current info about ros2 entities
+        ///
+        /// returns: String
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub async fn type_info_command(&self, request: &JsonProtocol, current_state: Arc<Mutex<Ros2State>>) -> String {
+            let type_name = match (request.arguments.get("type_name"), request.arguments.get("topic_name")) {
+                (Some(type_name), _) => type_name.clone(),
+                (None, Some(topic_name)) => {
+                    let state = match self.domain_state(current_state, request.arguments.get("domain_id")).await {
+                        Ok(state) => state,
+                        Err(msg) => return json!({"result": "failure", "msg": msg}).to_string()
+                    };
+                    let state_obj = state.lock().await;
+                    match state_obj.topics().iter().find(|topic| topic.name == *topic_name) {
+                        Some(topic) => topic.topic_type.clone(),
+                        None => return json!({"result": "failure", "msg": format!("Topic {} not found", topic_name)}).to_string()
+                    }
+                }
+                (None, None) => return json!({"result": "failure", "msg": "Either type_name or topic_name is required"}).to_string()
+            };
+
+            return match self.types.type_info(type_name.as_str()) {
+                Ok(info) => json!({"result": "success", "type": info}).to_string(),
+                Err(msg) => json!({"result": "failure", "msg": msg}).to_string()
+            };
+        }
+
+        /// Resource usage history of the node process for the last `minutes` (10 by default)
+        /// # Arguments
+        ///
+        /// * `request`: request with node_name, optional namespace, domain_id and minutes arguments
+        /// * `current_state`: current info about ros2 entities
+        ///
+        /// returns: String
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub async fn node_resources_command(&self, request: &JsonProtocol, current_state: Arc<Mutex<Ros2State>>) -> String {
+            let minutes: f64 = match request.arguments.get("minutes") {
+                Some(minutes) => match minutes.parse::<f64>() {
+                    Ok(minutes) if minutes > 0.0 => minutes,
+                    _ => return json!({"result": "failure", "msg": format!("Invalid minutes {}", minutes)}).to_string()
+                },
+                None => 10.0
+            };
+            let state = match self.domain_state(current_state, request.arguments.get("domain_id")).await {
+                Ok(state) => state,
+                Err(msg) => return json!({"result": "failure", "msg": msg}).to_string()
+            };
+            let fqn = match self.resolve_node_name(request, state.clone()).await {
+                Ok(fqn) => fqn,
+                Err(msg) => return json!({"result": "failure", "msg": msg}).to_string()
+            };
+
+            let state_obj = state.lock().await;
+            let node = match state_obj.find_nodes(fqn.as_str(), None).first() {
+                Some(node) => (*node).clone(),
+                None => return json!({"result": "failure", "msg": format!("Node {} not found", fqn)}).to_string()
+            };
+            let pid = match state_obj.node_pid(&node) {
+                Some(pid) => pid,
+                None => return json!({"result": "failure", "msg": format!("Process of node {} isn't found on this host", fqn)}).to_string()
+            };
+            let samples = self.resources.history(pid, now_secs() - minutes * 60.0);
+            return json!({"result": "success", "node": fqn, "domain_id": node.domain_id, "pid": pid, "samples": samples}).to_string();
+        }
+
+        pub async fn shutdown_node_command(&self, request: &JsonProtocol, current_state: Arc<Mutex<Ros2State>>) -> String {
+            // Extract node name from request. Process of the node is killed by PID, since nodes of different
+            // namespaces may share the process name
+            return match self.target_node(request, current_state).await {
+                Ok((discoverer, node_name, pid)) => discoverer.shutdown_node(node_name, pid),
+                Err(msg) => {
+                    warn!("Unable to shutdown node: {}", msg);
+                    json!({"result": "failure", "msg": msg}).to_string()
+                }
+            };
+        }
+
+        /// Trigger lifecycle transition for the node. Works only for lifecycle nodes.
+        /// # Arguments
+        ///
+        /// * `request`: request data with node_name, transition, optional namespace and domain_id arguments
+        /// * `current_state`: current info about ros2 entities
+        ///
+        /// returns: String
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub async fn lifecycle_command(&self, request: &JsonProtocol, current_state: Arc<Mutex<Ros2State>>) -> String {
+            let (discoverer, node_name, _) = match self.target_node(request, current_state).await {
+                Ok(target) => target,
+                Err(msg) => return json!({"result": "failure", "msg": msg}).to_string()
+            };
+
+            let transition = match request.arguments.get("transition") {
+                Some(transition) => transition.clone(),
+                None => return r#"{"result": "failure", "msg": "You must provide transition argument for command lifecycle"}"#.to_string()
+            };
 
-            return self.ros2discoverer.shutdown_node(node_name);
+            return discoverer.lifecycle_transition(node_name, transition);
         }
 
-        pub fn configure_node_command(&self, request: &JsonProtocol, _current_state: Arc<Mutex<Ros2State>>) -> String {
-            // TODO: Implement
-            return "".to_string();
+        /// Configure the lifecycle node with fully qualified `node_name` or bare name and `namespace` in the domain of `domain_id`
+        pub async fn configure_node_command(&self, request: &JsonProtocol, current_state: Arc<Mutex<Ros2State>>) -> String {
+            return match self.target_node(request, current_state).await {
+                Ok((discoverer, node_name, _)) => discoverer.lifecycle_transition(node_name, "configure".to_string()),
+                Err(msg) => json!({"result": "failure", "msg": msg}).to_string()
+            };
         }
 
+        /// Run `executable` of `package_name` in the domain of `domain_id` (the default one if it's missing).
+        /// Optional `node_name` (fully qualified or bare) and `namespace` remap the node
+        /// # Arguments
+        ///
+        /// * `request`: request data with package_name, executable and optional node_name, namespace and domain_id arguments
+        /// * `_current_state`: current info about ros2 entities
+        ///
+        /// returns: String
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
         pub fn launch_node_command(&self, request: &JsonProtocol, _current_state: Arc<Mutex<Ros2State>>) -> String {
-            // TODO: Implement
-            return "".to_string();
+            let (package_name, executable) = match (request.arguments.get("package_name"), request.arguments.get("executable")) {
+                (Some(package_name), Some(executable)) => (package_name, executable),
+                _ => return r#"{"result": "failure", "msg": "You must provide package_name and executable arguments for command launch"}"#.to_string()
+            };
+            let (namespace, node_name) = match (request.arguments.get("namespace"), request.arguments.get("node_name")) {
+                (Some(namespace), node_name) => (Some(namespace.clone()), node_name.cloned()),
+                (None, Some(node_name)) if node_name.contains('/') => {
+                    let (namespace, name) = split_node_fqn(node_name.as_str());
+                    (Some(namespace), Some(name))
+                }
+                (None, node_name) => (None, node_name.cloned())
+            };
+            let discoverer = match self.domain_discoverer(request) {
+                Ok(discoverer) => discoverer,
+                Err(msg) => return json!({"result": "failure", "msg": msg}).to_string()
+            };
+            return discoverer.launch_node(package_name.as_str(), executable.as_str(), namespace.as_deref(), node_name.as_deref());
         }
 
-        pub fn cleanup_node_command(&self, request: &JsonProtocol, _current_state: Arc<Mutex<Ros2State>>) -> String {
-            // TODO: Implement
-            return "".to_string();
+        /// Clean up the lifecycle node with fully qualified `node_name` or bare name and `namespace` in the domain of `domain_id`
+        pub async fn cleanup_node_command(&self, request: &JsonProtocol, current_state: Arc<Mutex<Ros2State>>) -> String {
+            return match self.target_node(request, current_state).await {
+                Ok((discoverer, node_name, _)) => discoverer.lifecycle_transition(node_name, "cleanup".to_string()),
+                Err(msg) => json!({"result": "failure", "msg": msg}).to_string()
+            };
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::{Arc, RwLock};
+    use tokio::sync::Mutex;
+    use crate::api::api::Api;
+    use crate::audit::audit::{AuditFilter, AuditLog};
+    use crate::auth::auth::{AuthPolicy, ClientIdentity, CommandAccess};
+    use crate::config::config::SettingsManager;
+    use crate::interfaces::interfaces::TypeRegistry;
+    use crate::protocol::JsonProtocol;
+    use crate::resources::resources::ResourceMonitor;
+    use crate::ros2_server::ros2_server::{Ros2Discoverer, Ros2DiscovererParams};
+    use crate::ros2entites::ros2entities::{Ros2Node, Ros2State, Settings};
+    use crate::traffic::traffic::TrafficMonitor;
+
+    pub(crate) fn test_api() -> Api {
+        let params = Ros2DiscovererParams { domain_id: 0, fastdds_profiles_file: "".to_string(), discovery_server: "".to_string(), simple_discovery: true };
+        let settings = SettingsManager::new(Settings::default(), "".to_string(), flume::unbounded().0);
+        return Api::new(Arc::new(RwLock::new(Ros2Discoverer::new(params))), AuthPolicy::default(), None, Arc::new(settings),
+                        Arc::new(ResourceMonitor::new(60.0)), Arc::new(TrafficMonitor::new(60.0)), Arc::new(TypeRegistry::new(vec![])));
+    }
+
+    /// The same node in domains 0 and 1
+    pub(crate) fn test_state() -> Arc<Mutex<Ros2State>> {
+        let mut state = Ros2State::new(false);
+        for domain_id in [0, 1] {
+            let mut node = Ros2Node::create("/robot1/camera".to_string());
+            node.domain_id = domain_id;
+            state.add_node(node);
+        }
+        return Arc::new(Mutex::new(state));
+    }
+
+    async fn dispatch(api: &Api, request: &str) -> String {
+        let mut parsed = JsonProtocol::new();
+        if let Err(msg) = parsed.parse_request(request) {
+            return msg;
+        }
+        return api.dispatch(&parsed, &ClientIdentity::Unix { uid: 0, gid: 0, pid: None }, test_state()).await;
+    }
+
+    #[tokio::test]
+    async fn node_command_arguments() {
+        let api = test_api();
+        // Arguments reach the handler: the node is resolved by namespace and name, its domain is required
+        for command in ["configure", "cleanup"] {
+            let request = format!(r#"{{"command": "{}", "arguments": [{{"name": "node_name", "value": "camera"}}, {{"name": "namespace", "value": "/robot1"}}]}}"#, command);
+            let response = dispatch(&api, request.as_str()).await;
+            assert!(response.contains("Node /robot1/camera is discovered in domains [0, 1]"), "{}", response);
+
+            let request = format!(r#"{{"command": "{}", "arguments": [{{"name": "node_name", "value": "/robot1/camera"}}, {{"name": "domain_id", "value": "x"}}]}}"#, command);
+            let response = dispatch(&api, request.as_str()).await;
+            assert!(response.contains("Invalid domain id x"), "{}", response);
+        }
+
+        let launch = r#"{"command": "launch", "arguments": [{"name": "package_name", "value": "demo_nodes_cpp"}, {"name": "executable", "value": "talker"},
+            {"name": "node_name", "value": "talker"}, {"name": "namespace", "value": "/robot1"}, {"name": "domain_id", "value": "x"}]}"#;
+        let response = dispatch(&api, launch).await;
+        assert!(response.contains("Invalid domain id x"), "{}", response);
+        let response = dispatch(&api, r#"{"command": "launch", "arguments": [{"name": "node_name", "value": "talker"}]}"#).await;
+        assert!(response.contains("You must provide package_name and executable"), "{}", response);
+    }
+
+    #[tokio::test]
+    async fn audit_doesnt_depend_on_policy() {
+        let path = std::env::temp_dir().join(format!("ros2monitor_api_audit_test_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut api = test_api();
+        api.audit_log = Some(Arc::new(AuditLog::open(path.to_str().unwrap()).unwrap()));
+        // Reclassified command is still audited
+        api.policy.commands.insert("shutdown".to_string(), CommandAccess::ReadOnly);
+
+        dispatch(&api, r#"{"command": "shutdown", "arguments": [{"name": "node_name", "value": "camera"}]}"#).await;
+        dispatch(&api, r#"{"command": "state", "arguments": []}"#).await;
+        let records = api.audit_log.as_ref().unwrap().query(&AuditFilter::default()).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].command, "shutdown");
+        assert_eq!(records[0].result, "failure");
+        // Bare name is recorded as the fully qualified one
+        assert_eq!(records[0].affected_nodes, vec!["/robot1/camera".to_string()]);
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,220 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/// Append-only audit log of mutating commands. Each record is stored as a single json line.
+pub mod audit {
+    use std::collections::BTreeMap;
+    use std::fs::{File, OpenOptions};
+    use std::io::{BufRead, BufReader, Write};
+    use std::sync::Mutex;
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use serde::{Deserialize, Serialize};
+    use crate::auth::auth::ClientIdentity;
+    use crate::ros2entites::ros2entities::node_fqn;
+
+    /// Commands which change the system. They are audited whatever access the policy gives them
+    pub const AUDITED_COMMANDS: [&str; 7] = ["shutdown", "launch", "configure", "cleanup", "lifecycle", "rename_topic", "set_settings"];
+
+    pub fn is_audited(command: &str) -> bool {
+        return AUDITED_COMMANDS.contains(&command);
+    }
+
+    #[derive(Serialize, Deserialize, Clone)]
+    pub struct AuditRecord {
+        /// Seconds since unix epoch
+        pub timestamp: f64,
+        pub client: ClientIdentity,
+        pub command: String,
+        pub arguments: BTreeMap<String, String>,
+        /// success, failure or denied
+        pub result: String,
+        pub msg: String,
+        /// Fully qualified names of the nodes, bare name if it isn't resolved by the discovered nodes
+        pub affected_nodes: Vec<String>,
+    }
+
+    impl AuditRecord {
+        pub fn new(client: ClientIdentity, command: String, arguments: BTreeMap<String, String>) -> AuditRecord {
+            let affected_nodes = match (arguments.get("node_name"), arguments.get("namespace")) {
+                (Some(node_name), Some(namespace)) => vec![node_fqn(namespace, node_name)],
+                (Some(node_name), None) => vec![node_name.clone()],
+                (None, _) => vec![]
+            };
+            return AuditRecord {
+                timestamp: now_secs(),
+                client,
+                command,
+                arguments,
+                result: "unknown".to_string(),
+                msg: "".to_string(),
+                affected_nodes,
+            };
+        }
+
+        /// Fill result of the record from json response of the command. Response without result is a failure
+        pub fn set_response(&mut self, response: &str) {
+            let value = serde_json::from_str::<serde_json::Value>(response).unwrap_or_default();
+            self.result = match value.get("result").and_then(|result| result.as_str()) {
+                Some(result) => result.to_string(),
+                None => "failure".to_string()
+            };
+            self.msg = match value.get("msg").and_then(|msg| msg.as_str()) {
+                Some(msg) => msg.to_string(),
+                None if response.is_empty() => "Empty response".to_string(),
+                None => "".to_string()
+            };
+        }
+    }
+
+    pub fn now_secs() -> f64 {
+        return match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_millis() as f64 / 1000.0,
+            Err(_) => 0.0
+        };
+    }
+
+    #[derive(Default)]
+    pub struct AuditFilter {
+        pub from: Option<f64>,
+        pub to: Option<f64>,
+        pub commands: Vec<String>,
+        pub limit: Option<usize>,
+    }
+
+    impl AuditFilter {
+        pub fn matches(&self, record: &AuditRecord) -> bool {
+            if self.from.map_or(false, |from| record.timestamp < from) {
+                return false;
+            }
+            if self.to.map_or(false, |to| record.timestamp > to) {
+                return false;
+            }
+            return self.commands.is_empty() || self.commands.contains(&record.command);
+        }
+    }
+
+    pub struct AuditLog {
+        pub path: String,
+        file: Mutex<File>,
+    }
+
+    impl AuditLog {
+        pub fn open(path: &str) -> Result<AuditLog, String> {
+            let file = match OpenOptions::new().create(true).append(true).open(path) {
+                Ok(file) => file,
+                Err(e) => return Err(format!("Unable to open audit log {}: {}", path, e))
+            };
+            return Ok(AuditLog { path: path.to_string(), file: Mutex::new(file) });
+        }
+
+        pub fn append(&self, record: &AuditRecord) -> Result<(), String> {
+            let mut line = match serde_json::to_string(record) {
+                Ok(line) => line,
+                Err(e) => return Err(format!("Unable to serialize audit record: {}", e))
+            };
+            line.push('\n');
+
+            let mut file = self.file.lock().unwrap();
+            // Write whole line at once, so records are never interleaved
+            return match file.write_all(line.as_bytes()).and_then(|_| file.flush()) {
+                Ok(()) => Ok(()),
+                Err(e) => Err(format!("Unable to write audit record into {}: {}", self.path, e))
+            };
+        }
+
+        /// Read records matching the filter. If limit is set, the latest records are returned
+        pub fn query(&self, filter: &AuditFilter) -> Result<Vec<AuditRecord>, String> {
+            let file = match File::open(self.path.as_str()) {
+                Ok(file) => file,
+                Err(e) => return Err(format!("Unable to read audit log {}: {}", self.path, e))
+            };
+
+            let mut records: Vec<AuditRecord> = BufReader::new(file).lines()
+                .filter_map(|line| line.ok())
+                .filter_map(|line| serde_json::from_str::<AuditRecord>(line.as_str()).ok())
+                .filter(|record| filter.matches(record))
+                .collect();
+
+            if let Some(limit) = filter.limit {
+                if records.len() > limit {
+                    records.drain(0..records.len() - limit);
+                }
+            }
+
+            return Ok(records);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use crate::audit::audit::{is_audited, AuditFilter, AuditLog, AuditRecord};
+    use crate::auth::auth::ClientIdentity;
+
+    fn record(command: &str, timestamp: f64) -> AuditRecord {
+        let mut arguments = BTreeMap::new();
+        arguments.insert("node_name".to_string(), "talker".to_string());
+        let mut record = AuditRecord::new(ClientIdentity::Unix { uid: 1000, gid: 1000, pid: Some(1) }, command.to_string(), arguments);
+        record.timestamp = timestamp;
+        return record;
+    }
+
+    #[test]
+    fn append_and_query() {
+        let path = std::env::temp_dir().join(format!("ros2monitor_audit_test_{}.jsonl", std::process::id()));
+        let path_str = path.to_str().unwrap();
+        let _ = std::fs::remove_file(&path);
+        let log = AuditLog::open(path_str).unwrap();
+
+        let mut shutdown = record("shutdown", 100.0);
+        shutdown.set_response(r#"{"result": "success"}"#);
+        log.append(&shutdown).unwrap();
+        log.append(&record("launch", 200.0)).unwrap();
+        log.append(&record("shutdown", 300.0)).unwrap();
+
+        let all = log.query(&AuditFilter::default()).unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].result, "success");
+        assert_eq!(all[0].affected_nodes, vec!["talker".to_string()]);
+
+        let shutdowns = log.query(&AuditFilter { commands: vec!["shutdown".to_string()], ..Default::default() }).unwrap();
+        assert_eq!(shutdowns.len(), 2);
+
+        let range = log.query(&AuditFilter { from: Some(150.0), to: Some(250.0), ..Default::default() }).unwrap();
+        assert_eq!(range.len(), 1);
+        assert_eq!(range[0].command, "launch");
+
+        let latest = log.query(&AuditFilter { limit: Some(1), ..Default::default() }).unwrap();
+        assert_eq!(latest[0].timestamp, 300.0);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn namespaced_node() {
+        let mut arguments = BTreeMap::new();
+        arguments.insert("node_name".to_string(), "camera".to_string());
+        arguments.insert("namespace".to_string(), "/robot1".to_string());
+        let record = AuditRecord::new(ClientIdentity::Unix { uid: 0, gid: 0, pid: None }, "shutdown".to_string(), arguments);
+        assert_eq!(record.affected_nodes, vec!["/robot1/camera".to_string()]);
+        assert!(is_audited("shutdown"));
+        assert!(!is_audited("audit_log"));
+    }
+
+    #[test]
+    fn response_without_result_is_failure() {
+        let mut empty = record("configure", 100.0);
+        empty.set_response("");
+        assert_eq!(empty.result, "failure");
+        assert_eq!(empty.msg, "Empty response");
+
+        let mut failed = record("shutdown", 100.0);
+        failed.set_response(r#"{"result": "failure", "msg": "Node not found"}"#);
+        assert_eq!(failed.result, "failure");
+        assert_eq!(failed.msg, "Node not found");
+    }
+}
@@ -0,0 +1,256 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/// Authentication and per-command authorization of api clients.
+/// Clients of the unix socket are identified by peer credentials (SO_PEERCRED), clients of
+/// tcp transports - by optional bearer token. Each command is either read-only or mutating,
+/// and mutating commands are allowed only for privileged clients.
+pub mod auth {
+    use std::collections::HashMap;
+    use std::fmt;
+    use std::os::unix::fs::MetadataExt;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum CommandAccess {
+        ReadOnly,
+        /// Read-only command which exposes data of other clients, allowed only for privileged clients
+        Privileged,
+        Mutating,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(tag = "transport", rename_all = "snake_case")]
+    pub enum ClientIdentity {
+        /// Client of the unix socket
+        Unix { uid: u32, gid: u32, pid: Option<i32> },
+        /// Client of the tcp transport (http). `authenticated` is true if client sent valid token
+        Tcp { peer: String, authenticated: bool },
+    }
+
+    impl fmt::Display for ClientIdentity {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            return match self {
+                ClientIdentity::Unix { uid, gid, pid } => match pid {
+                    Some(pid) => write!(f, "unix(uid={}, gid={}, pid={})", uid, gid, pid),
+                    None => write!(f, "unix(uid={}, gid={})", uid, gid)
+                },
+                ClientIdentity::Tcp { peer, authenticated } => write!(f, "tcp({}, authenticated={})", peer, authenticated)
+            };
+        }
+    }
+
+    /// Role policy. Can be loaded from json file:
+    /// ```json
+    /// {
+    ///     "commands": {"state": "read_only", "shutdown": "mutating"},
+    ///     "privileged_uids": [1000],
+    ///     "privileged_gids": [27],
+    ///     "token": "secret"
+    /// }
+    /// ```
+    /// Commands which are absent in the policy are considered mutating.
+    #[derive(Clone, Serialize, Deserialize)]
+    pub struct AuthPolicy {
+        #[serde(default = "default_commands")]
+        pub commands: HashMap<String, CommandAccess>,
+        /// Root and the user which runs the daemon by default
+        #[serde(default = "default_privileged_uids")]
+        pub privileged_uids: Vec<u32>,
+        #[serde(default)]
+        pub privileged_gids: Vec<u32>,
+        /// Token for tcp transports. If it is set, tcp clients without valid token are rejected
+        #[serde(default)]
+        pub token: Option<String>,
+        /// Mutating commands are denied to everyone, e.g. when the daemon serves an analyzed capture
+        #[serde(skip)]
+        pub read_only: bool,
+    }
+
+    fn default_commands() -> HashMap<String, CommandAccess> {
+        let mut commands = HashMap::new();
+        commands.insert("state".to_string(), CommandAccess::ReadOnly);
+        commands.insert("nodes".to_string(), CommandAccess::ReadOnly);
+        commands.insert("node_resources".to_string(), CommandAccess::ReadOnly);
+        commands.insert("hosts".to_string(), CommandAccess::ReadOnly);
+        commands.insert("ports".to_string(), CommandAccess::ReadOnly);
+        commands.insert("traffic".to_string(), CommandAccess::ReadOnly);
+        commands.insert("dds_statistics".to_string(), CommandAccess::ReadOnly);
+        commands.insert("type_info".to_string(), CommandAccess::ReadOnly);
+        commands.insert("audit_log".to_string(), CommandAccess::Privileged);
+        commands.insert("get_settings".to_string(), CommandAccess::ReadOnly);
+        for command in ["shutdown", "launch", "configure", "cleanup", "lifecycle", "rename_topic", "set_settings"] {
+            commands.insert(command.to_string(), CommandAccess::Mutating);
+        }
+        return commands;
+    }
+
+    /// Uid of the daemon process itself
+    fn current_uid() -> Option<u32> {
+        return std::fs::metadata("/proc/self").map(|metadata| metadata.uid()).ok();
+    }
+
+    fn default_privileged_uids() -> Vec<u32> {
+        let mut privileged_uids = vec![0];
+        if let Some(uid) = current_uid() {
+            if uid != 0 {
+                privileged_uids.push(uid);
+            }
+        }
+        return privileged_uids;
+    }
+
+    /// Compare tokens in time which depends only on the length of the expected one
+    fn tokens_equal(expected: &[u8], token: &[u8]) -> bool {
+        let mut diff = expected.len() ^ token.len();
+        for (pos, byte) in expected.iter().enumerate() {
+            diff |= (byte ^ token.get(pos).copied().unwrap_or(0)) as usize;
+        }
+        return diff == 0;
+    }
+
+    impl AuthPolicy {
+        /// Default policy: root and the user which runs the daemon are privileged
+        pub fn default() -> AuthPolicy {
+            return AuthPolicy {
+                commands: default_commands(),
+                privileged_uids: default_privileged_uids(),
+                privileged_gids: vec![],
+                token: None,
+                read_only: false,
+            };
+        }
+
+        pub fn from_json(json: &str) -> Result<AuthPolicy, String> {
+            let mut policy: AuthPolicy = match serde_json::from_str(json) {
+                Ok(policy) => policy,
+                Err(e) => return Err(format!("Unable to parse policy: {}", e))
+            };
+            // Default commands are always known. Policy file can only override them
+            for (command, access) in default_commands() {
+                policy.commands.entry(command).or_insert(access);
+            }
+            if policy.token.as_ref().map_or(false, |token| token.is_empty()) {
+                return Err("Policy token must not be empty".to_string());
+            }
+            return Ok(policy);
+        }
+
+        pub fn from_file(path: &str) -> Result<AuthPolicy, String> {
+            let json = match std::fs::read_to_string(path) {
+                Ok(json) => json,
+                Err(e) => return Err(format!("Unable to read policy file {}: {}", path, e))
+            };
+            return AuthPolicy::from_json(json.as_str());
+        }
+
+        pub fn command_access(&self, command: &str) -> CommandAccess {
+            return match self.commands.get(command) {
+                Some(access) => *access,
+                None => CommandAccess::Mutating
+            };
+        }
+
+        /// Check token sent by tcp client. Returns true if no token is configured
+        pub fn check_token(&self, token: Option<&str>) -> bool {
+            return match &self.token {
+                Some(expected) => token.map_or(false, |token| tokens_equal(expected.as_bytes(), token.as_bytes())),
+                None => true
+            };
+        }
+
+        pub fn is_privileged(&self, identity: &ClientIdentity) -> bool {
+            return match identity {
+                ClientIdentity::Unix { uid, gid, .. } => self.privileged_uids.contains(uid) || self.privileged_gids.contains(gid),
+                // Tcp client is privileged only if it proved the token
+                ClientIdentity::Tcp { authenticated, .. } => *authenticated && self.token.is_some()
+            };
+        }
+
+        /// Decide if client can execute the command. Returns error message on deny
+        pub fn authorize(&self, identity: &ClientIdentity, command: &str) -> Result<(), String> {
+            if let ClientIdentity::Tcp { authenticated: false, .. } = identity {
+                if self.token.is_some() {
+                    return Err("Authentication required: provide valid token".to_string());
+                }
+            }
+
+            return match self.command_access(command) {
+                CommandAccess::ReadOnly => Ok(()),
+                CommandAccess::Mutating if self.read_only => Err(format!("Command {} isn't available in read-only mode", command)),
+                CommandAccess::Privileged | CommandAccess::Mutating => if self.is_privileged(identity) {
+                    Ok(())
+                } else {
+                    Err(format!("Permission denied: command {} requires privileged client, {} isn't", command, identity))
+                }
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::auth::{AuthPolicy, ClientIdentity, CommandAccess};
+
+    fn policy() -> AuthPolicy {
+        return AuthPolicy::from_json(r#"{"privileged_uids": [1000], "privileged_gids": [27]}"#).unwrap();
+    }
+
+    #[test]
+    fn default_commands_access() {
+        let policy = policy();
+        assert_eq!(policy.command_access("state"), CommandAccess::ReadOnly);
+        assert_eq!(policy.command_access("shutdown"), CommandAccess::Mutating);
+        assert_eq!(policy.command_access("not_existing"), CommandAccess::Mutating);
+    }
+
+    #[test]
+    fn unix_clients() {
+        let policy = policy();
+        let privileged = ClientIdentity::Unix { uid: 1000, gid: 1000, pid: None };
+        let in_group = ClientIdentity::Unix { uid: 1001, gid: 27, pid: None };
+        let unprivileged = ClientIdentity::Unix { uid: 1001, gid: 1001, pid: Some(42) };
+
+        assert!(policy.authorize(&privileged, "shutdown").is_ok());
+        assert!(policy.authorize(&in_group, "shutdown").is_ok());
+        assert!(policy.authorize(&unprivileged, "state").is_ok());
+        assert!(policy.authorize(&unprivileged, "shutdown").is_err());
+        // Audit log is read by privileged clients only
+        assert!(policy.authorize(&unprivileged, "audit_log").is_err());
+        assert!(policy.authorize(&privileged, "audit_log").is_ok());
+
+        // Policy without privileged users keeps root privileged
+        let root = ClientIdentity::Unix { uid: 0, gid: 0, pid: None };
+        assert!(AuthPolicy::from_json(r#"{"privileged_gids": [27]}"#).unwrap().authorize(&root, "shutdown").is_ok());
+
+        // Read-only daemon denies mutating commands even to privileged clients
+        let read_only = AuthPolicy { read_only: true, ..policy };
+        assert!(read_only.authorize(&privileged, "state").is_ok());
+        assert!(read_only.authorize(&privileged, "shutdown").is_err());
+        assert!(read_only.authorize(&privileged, "audit_log").is_ok());
+    }
+
+    #[test]
+    fn tcp_clients() {
+        let without_token = policy();
+        let anonymous = ClientIdentity::Tcp { peer: "127.0.0.1:5000".to_string(), authenticated: false };
+        assert!(without_token.authorize(&anonymous, "state").is_ok());
+        assert!(without_token.authorize(&anonymous, "launch").is_err());
+
+        let with_token = AuthPolicy::from_json(r#"{"token": "secret", "commands": {"launch": "read_only"}}"#).unwrap();
+        assert!(with_token.check_token(Some("secret")));
+        assert!(!with_token.check_token(Some("guess")));
+        assert!(!with_token.check_token(Some("secret1")));
+        assert!(!with_token.check_token(Some("secre")));
+        assert!(!with_token.check_token(None));
+        assert!(with_token.authorize(&anonymous, "state").is_err());
+
+        let authenticated = ClientIdentity::Tcp { peer: "127.0.0.1:5000".to_string(), authenticated: true };
+        assert!(with_token.authorize(&authenticated, "shutdown").is_ok());
+        assert_eq!(with_token.command_access("launch"), CommandAccess::ReadOnly);
+    }
+}
@@ -7,11 +7,14 @@
 #include <fastrtps/fastdds/dds/domain/DomainParticipant.hpp>
 #include <fastrtps/fastdds/rtps/common/Types.h>
 
+#include <algorithm>
+#include <cstring>
+
 #include "discovery_domain_listener.hpp"
 #include "network.hpp"
+#include "type_definition.hpp"
 
 using eprosima::fastrtps::rtps::GuidPrefix_t;
-using eprosima::fastrtps::rtps::Locator_t;
 using eprosima::fastrtps::rtps::octet;
 
 void DiscoveryDomainParticipantListener::on_participant_discovery(
@@ -19,16 +22,27 @@ void DiscoveryDomainParticipantListener::on_participant_discovery(
         eprosima::fastrtps::rtps::ParticipantDiscoveryInfo &&info)
 {
     static_cast<void>(participant);
-    if (info.status == eprosima::fastrtps::rtps::ParticipantDiscoveryInfo::
-                               DISCOVERED_PARTICIPANT) {
-        ParticipantData participant_data;
+    auto participant_info = [&info, participant]() {
+        ParticipantData participant_data{};
         std::memcpy(participant_data.guid, info.info.m_guid.guidPrefix.value,
                     GuidPrefix_t::size * sizeof(octet));
-        participant_data.endpoint.port = info.info.default_locators.unicast.begin()->port;
+        std::memcpy(participant_data.vendor_id, info.info.m_VendorId.data(),
+                    sizeof(participant_data.vendor_id));
+        std::strncpy(participant_data.name, info.info.m_participantName.c_str(),
+                     sizeof(participant_data.name) - 1);
+        // User data is not null terminated and may contain binary data of non ROS2 participants
+        auto &user_data = info.info.m_userData;
+        size_t user_data_size = std::min(user_data.size(), sizeof(participant_data.user_data) - 1);
+        std::memcpy(participant_data.user_data, user_data.data(), user_data_size);
 
-        Locator_t locator = *info.info.default_locators.unicast.begin();
-        r2discoverer::parse_endpoint_fastdds(participant_data.endpoint, locator);
+        r2discoverer::parse_locators_fastdds(participant_data.locators, info.info.default_locators);
         participant_data.participant = static_cast<void *>(participant);
+        return participant_data;
+    };
+
+    if (info.status == eprosima::fastrtps::rtps::ParticipantDiscoveryInfo::
+                               DISCOVERED_PARTICIPANT) {
+        ParticipantData participant_data = participant_info();
 
         if (m_on_participant_discovery) {
             assert(m_on_participant_discovery_data != nullptr);
@@ -41,8 +55,15 @@ void DiscoveryDomainParticipantListener::on_participant_discovery(
         /* Process the case when a DomainParticipant changed its QOS */
 
     } else if (info.status == eprosima::fastrtps::rtps::ParticipantDiscoveryInfo::
-                                      REMOVED_PARTICIPANT) {
-        /* Process the case when a DomainParticipant was removed from the domain */
+                                      REMOVED_PARTICIPANT ||
+               info.status == eprosima::fastrtps::rtps::ParticipantDiscoveryInfo::
+                                      DROPPED_PARTICIPANT) {
+        /* Participant left the domain or its lease duration expired */
+        if (m_on_participant_remove) {
+            assert(m_on_participant_remove_data != nullptr);
+            ParticipantData participant_data = participant_info();
+            m_on_participant_remove(participant_data, m_on_participant_remove_data);
+        }
     }
 }
 void DiscoveryDomainParticipantListener::on_subscriber_discovery(
@@ -51,15 +72,16 @@ void DiscoveryDomainParticipantListener::on_subscriber_discovery(
 {
     static_cast<void>(participant);
     auto reader_info = [info]() {
-        ReaderData reader_data;
+        ReaderData reader_data{};
         std::strcpy(reader_data.topic_name, info.info.topicName().c_str());
         std::strcpy(reader_data.type_name, info.info.typeName().c_str());
         auto guid_size = sizeof(octet) * GuidPrefix_t::size;
         std::memcpy(reader_data.guid_prefix, info.info.guid().guidPrefix.value,
                     guid_size);
+        std::memcpy(reader_data.entity_id, info.info.guid().entityId.value,
+                    sizeof(octet) * ENTITY_ID_SIZE);
 
-        Locator_t locator = *info.info.remote_locators().unicast.begin();
-        r2discoverer::parse_endpoint_fastdds(reader_data.endpoint, locator);
+        r2discoverer::parse_locators_fastdds(reader_data.locators, info.info.remote_locators());
 
         return reader_data;
     };
@@ -92,14 +114,15 @@ void DiscoveryDomainParticipantListener::on_publisher_discovery(
 {
     static_cast<void>(participant);
     auto writer_info = [info]() {
-        WriterData writer_data;
+        WriterData writer_data{};
         std::strcpy(writer_data.topic_name, info.info.topicName().c_str());
         std::strcpy(writer_data.type_name, info.info.typeName().c_str());
         auto guid_size = sizeof(octet) * GuidPrefix_t::size;
         std::memcpy(writer_data.guid_prefix, info.info.guid().guidPrefix.value,
                     guid_size);
-        Locator_t locator = *info.info.remote_locators().unicast.begin();
-        r2discoverer::parse_endpoint_fastdds(writer_data.endpoint, locator);
+        std::memcpy(writer_data.entity_id, info.info.guid().entityId.value,
+                    sizeof(octet) * ENTITY_ID_SIZE);
+        r2discoverer::parse_locators_fastdds(writer_data.locators, info.info.remote_locators());
         return writer_data;
     };
     switch (info.status) {
@@ -141,9 +164,16 @@ void DiscoveryDomainParticipantListener::on_type_discovery(
     static_cast<void>(topic);
     static_cast<void>(identifier);
     static_cast<void>(object);
-    static_cast<void>(dyn_type);
-    std::cout << "New data type of topic '" << topic << "' discovered."
-              << std::endl;
+    if (!m_on_type_discovery || !dyn_type) {
+        return;
+    }
+    // Nested types go first, so the receiver knows them when the topic type arrives
+    for (const auto &definition : r2discoverer::type_definitions(dyn_type)) {
+        TypeData type_data{};
+        std::strncpy(type_data.type_name, definition.first.c_str(), sizeof(type_data.type_name) - 1);
+        type_data.definition = definition.second.c_str();
+        m_on_type_discovery(type_data, m_on_type_discovery_data);
+    }
 }
 
 void DiscoveryDomainParticipantListener::set_participant_discovery_callback(
@@ -183,3 +213,10 @@ void DiscoveryDomainParticipantListener::set_writer_removed_callback(
     m_on_writer_remove = callback;
     m_on_writer_remove_data = data;
 }
+
+void DiscoveryDomainParticipantListener::set_type_discovery_callback(
+        on_type_discovery_callback_t callback, void *data)
+{
+    m_on_type_discovery = callback;
+    m_on_type_discovery_data = data;
+}
@@ -58,6 +58,9 @@ public:
     void set_writer_removed_callback(on_writer_remove_callback_t callback,
                                      void *data);
 
+    void set_type_discovery_callback(on_type_discovery_callback_t callback,
+                                     void *data);
+
 private:
     // On discovery data
     on_participant_discovery_callback_t m_on_participant_discovery;
@@ -74,4 +77,8 @@ private:
     void *m_on_participant_remove_data;
     void *m_on_writer_remove_data;
     void *m_on_reader_remove_data;
+
+    // On type data
+    on_type_discovery_callback_t m_on_type_discovery = nullptr;
+    void *m_on_type_discovery_data = nullptr;
 };
\ No newline at end of file
@@ -9,13 +9,21 @@
 #include <fastrtps/fastdds/dds/domain/qos/DomainParticipantQos.hpp>
 #include <fastrtps/fastrtps/rtps/participant/RTPSParticipant.h>
 #include <fastrtps/rtps/attributes/RTPSParticipantAttributes.h>
+#include <fastrtps/fastrtps/xmlparser/XMLProfileManager.h>
 #include <rclcpp/rclcpp/context.hpp>
 
 #include <cstring>
+#include <fstream>
 #include <istream>
+#include <iterator>
+#include <map>
+#include <memory>
+#include <mutex>
+#include <string>
 
 #include "discovery_domain_listener.hpp"
 #include "discovery_server.h"
+#include "statistics_reader.hpp"
 
 using namespace eprosima::fastdds::dds;
 using namespace eprosima::fastrtps::rtps;
@@ -25,20 +33,38 @@ using eprosima::fastrtps::rtps::GUID_t;
 using namespace std::this_thread;// sleep_for, sleep_until
 using namespace std::chrono;// nanoseconds, system_clock, seconds
 
-static DomainParticipant *server = nullptr;
-static void *user_data_on_discovery_participant = nullptr;
-static void *user_data_on_discovery_reader = nullptr;
-static void *user_data_on_discovery_writer = nullptr;
+/**
+ * User data passed to callbacks of the single domain server
+ */
+struct DomainUserData {
+    void *on_discovery_participant = nullptr;
+    void *on_discovery_reader = nullptr;
+    void *on_discovery_writer = nullptr;
 
-static void *user_data_on_remove_participant = nullptr;
-static void *user_data_on_remove_reader = nullptr;
-static void *user_data_on_remove_writer = nullptr;
+    void *on_remove_participant = nullptr;
+    void *on_remove_reader = nullptr;
+    void *on_remove_writer = nullptr;
+
+    void *on_statistics = nullptr;
+    void *on_type_discovery = nullptr;
+};
+
+// Each monitored domain has its own server participant. Servers are run from threads of their domains, while
+// the discovery loop stops them and registers user data, so all maps below are guarded by servers_mutex
+static std::mutex servers_mutex;
+static std::map<uint32_t, DomainParticipant *> servers;
+static std::map<uint32_t, DomainUserData> user_data;
+// Listeners must outlive their participants, so they are owned here and released on stop
+static std::map<uint32_t, std::unique_ptr<DiscoveryDomainParticipantListener>> listeners;
+// Readers of statistics topics, created only if statistics callback is set
+static std::map<uint32_t, std::unique_ptr<r2discoverer::StatisticsReaders>> statistics_readers;
+// Servers of all domains are started from their own threads, but share the profiles of the factory
+static std::mutex profiles_mutex;
+// Content of the profiles file as it was loaded last time
+static std::string loaded_profiles;
 
 
 static const char *const server_guid = "44.53.00.5f.45.50.52.4f.53.49.4d.41";
-static const octet server_guid_octets[12] = {
-    0x44, 0x53, 0x00, 0x5f, 0x45, 0x50, 0x52, 0x4f, 0x53, 0x49, 0x4d, 0x41
-};
 
 static DomainParticipantQos create_server_attributes(uint32_t server_port,
                                                      uint32_t locator_port,
@@ -61,6 +87,11 @@ static DomainParticipantQos create_server_attributes(uint32_t server_port,
     server_qos.wire_protocol().builtin.metatrafficUnicastLocatorList.push_back(
             locator);
 
+    // Standalone server doesn't connect to other servers
+    if (locator_port == 0) {
+        return server_qos;
+    }
+
     /* Add a remote serve to which this server will connect */
     // Set remote SERVER's GUID prefix
     RemoteServerAttributes remote_server_att;
@@ -80,104 +111,169 @@ static DomainParticipantQos create_server_attributes(uint32_t server_port,
     return server_qos;
 }
 
+/**
+ * Apply port mapping of the monitored system, so the monitor listens on the same ports as other participants
+ */
+static void set_port_params(DomainParticipantQos &qos, const RtpsPortParams &params)
+{
+    if (params.port_base == 0) {
+        return;
+    }
+    auto &port = qos.wire_protocol().port;
+    port.portBase = static_cast<uint16_t>(params.port_base);
+    port.domainIDGain = static_cast<uint16_t>(params.domain_id_gain);
+    port.participantIDGain = static_cast<uint16_t>(params.participant_id_gain);
+    port.offsetd0 = static_cast<uint16_t>(params.offset_d0);
+    port.offsetd1 = static_cast<uint16_t>(params.offset_d1);
+    port.offsetd2 = static_cast<uint16_t>(params.offset_d2);
+    port.offsetd3 = static_cast<uint16_t>(params.offset_d3);
+}
+
+/**
+ * Load participant QoS from the profiles file. FastDDS parses each file only once, so profiles regenerated from
+ * changed settings are reloaded from scratch
+ */
+static bool load_profile(const char *profiles_file, const char *profile_name, DomainParticipantQos &qos)
+{
+    std::lock_guard<std::mutex> lock(profiles_mutex);
+    std::ifstream file(profiles_file);
+    if (!file) {
+        fprintf(stderr, "Unable to read profiles file %s\n", profiles_file);
+        return false;
+    }
+    std::string profiles((std::istreambuf_iterator<char>(file)), std::istreambuf_iterator<char>());
+
+    auto factory = DomainParticipantFactory::get_instance();
+    if (profiles != loaded_profiles) {
+        eprosima::fastrtps::xmlparser::XMLProfileManager::DeleteInstance();
+        if (factory->load_XML_profiles_file(profiles_file) != ReturnCode_t::RETCODE_OK) {
+            fprintf(stderr, "Unable to load profiles file %s\n", profiles_file);
+            return false;
+        }
+        loaded_profiles = profiles;
+    }
+    if (factory->get_participant_qos_from_profile(profile_name, qos) != ReturnCode_t::RETCODE_OK) {
+        fprintf(stderr, "Unable to find participant profile %s\n", profile_name);
+        return false;
+    }
+    return true;
+}
+
+/**
+ * Attributes of passive participant for simple discovery. It doesn't create any
+ * endpoints, so it only receives announcements of other participants via
+ * default multicast locators
+ */
+static DomainParticipantQos create_simple_attributes(const RtpsPortParams &port_params)
+{
+    DomainParticipantQos participant_qos = PARTICIPANT_QOS_DEFAULT;
+    set_port_params(participant_qos, port_params);
+    participant_qos.name("ros2monitor");
+    participant_qos.wire_protocol().builtin.discovery_config.discoveryProtocol =
+            DiscoveryProtocol_t::SIMPLE;
+    participant_qos.wire_protocol().builtin.discovery_config.use_SIMPLE_EndpointDiscoveryProtocol = true;
+
+    return participant_qos;
+}
+
 int run_discovery_server_impl(uint32_t domain_id,
                               DiscoveryServerParams params)
 {
-    printf("run_discovery_server_impl\n");
-    // Get default participant QoS
-    auto server_qos = create_server_attributes(11811, 11812, server_guid);
+    DomainParticipantQos server_qos;
+    if (params.discovery_mode == SIMPLE_DISCOVERY_MODE) {
+        server_qos = create_simple_attributes(params.port_params);
+    } else if (params.profiles_file != nullptr && params.profile_name != nullptr) {
+        // Role, GUID prefix, locators and remote servers are defined by the profile generated from settings
+        if (!load_profile(params.profiles_file, params.profile_name, server_qos)) {
+            return -1;
+        }
+    } else if (params.server_port != 0) {
+        // Standalone server listening on the port from settings
+        server_qos = create_server_attributes(params.server_port, 0, server_guid);
+        set_port_params(server_qos, params.port_params);
+    } else {
+        fprintf(stderr, "Neither server profile nor listening port is set for domain %u\n", domain_id);
+        return -1;
+    }
+    std::lock_guard<std::mutex> lock(servers_mutex);
+    auto &data = user_data[domain_id];
 
     // Create SERVER
-    auto *listener = new DiscoveryDomainParticipantListener();
+    auto listener = std::make_unique<DiscoveryDomainParticipantListener>();
 
     // Set discovery callbacks
     listener->set_participant_discovery_callback(
-            params.participant_discovery_callback, user_data_on_discovery_participant);
+            params.participant_discovery_callback, data.on_discovery_participant);
     listener->set_reader_discovery_callback(params.reader_discovery_callback,
-                                            user_data_on_discovery_reader);
+                                            data.on_discovery_reader);
     listener->set_writer_discovery_callback(params.writer_discovery_callback,
-                                            user_data_on_discovery_writer);
+                                            data.on_discovery_writer);
 
     // Set callbacks on remove
     listener->set_participant_removed_callback(
-            params.participant_removed_callback, user_data_on_remove_participant);
+            params.participant_removed_callback, data.on_remove_participant);
     listener->set_reader_removed_callback(params.reader_removed_callback,
-                                          user_data_on_remove_reader);
-    listener->set_writer_removed_callback(params.writer_removed_callback, user_data_on_remove_writer);
+                                          data.on_remove_reader);
+    listener->set_writer_removed_callback(params.writer_removed_callback, data.on_remove_writer);
 
-    if (server && server->is_enabled()) {
-        server->close();
+    if (params.type_discovery_callback != nullptr) {
+        listener->set_type_discovery_callback(params.type_discovery_callback, data.on_type_discovery);
+        // Type objects are requested from participants which announce type information
+        server_qos.wire_protocol().builtin.typelookup_config.use_client = true;
+    }
+
+    auto server = servers.find(domain_id);
+    if (server != servers.end() && server->second && server->second->is_enabled()) {
+        server->second->close();
         // server->delete_contained_entities();
     }
 
-    printf("Creating server participant with domain id %d\n", domain_id);
     assert(DomainParticipantFactory::get_instance() != nullptr);
-    server = DomainParticipantFactory::get_instance()->create_participant(
-            domain_id, server_qos, listener);
+    auto participant = DomainParticipantFactory::get_instance()->create_participant(
+            domain_id, server_qos, listener.get());
     // DomainParticipantFactory::get_instance()->delete_participant(server);
-    if (nullptr == server) {
+    if (nullptr == participant) {
         fprintf(stderr, "Unable to create server participant\n");
+        // Nothing will be stopped, so user data is released here
+        user_data.erase(domain_id);
         return -1;
     }
-    printf("Participant created");
-
-    return 0;
-}
-
-static void kill_server_callback(ParticipantData participant_data, void *user_data)
-{
-    printf("Kill server callback\n");
-    auto guid = participant_data.guid;
-    size_t guid_size = 12 * sizeof(octet);
-    if (memcmp(guid, server_guid_octets, guid_size) == 0) {
-        auto participant =
-                static_cast<DomainParticipant *>(participant_data.participant);
-        auto ret = DomainParticipantFactory::get_instance()->delete_participant(
-                participant);
-        if (ret != ReturnCode_t::RETCODE_OK) {
-            fprintf(stderr, "Unable to kill the server via callback\n");
+    servers[domain_id] = participant;
+    listeners[domain_id] = std::move(listener);
+
+    if (params.statistics_callback != nullptr) {
+        auto readers = std::make_unique<r2discoverer::StatisticsReaders>();
+        // Discovery keeps working without statistics
+        if (!readers->create(participant, params.statistics_callback, data.on_statistics)) {
+            fprintf(stderr, "Unable to subscribe to all statistics topics\n");
         }
+        statistics_readers[domain_id] = std::move(readers);
     }
-}
 
+    return 0;
+}
 int stop_discovery_server_impl(uint32_t domain_id)
 {
+    std::lock_guard<std::mutex> lock(servers_mutex);
     // Delete server participant
     auto instance = DomainParticipantFactory::get_instance();
-    if (server != nullptr) {
-        ReturnCode_t err = instance->delete_participant(server);
+    auto server = servers.find(domain_id);
+    if (server != servers.end() && server->second != nullptr) {
+        // Readers must be deleted before their participant
+        statistics_readers.erase(domain_id);
+        ReturnCode_t err = instance->delete_participant(server->second);
         if (err != ReturnCode_t::RETCODE_OK) {
             fprintf(stderr, "Unable to delete server participant");
             return -1;
         }
+        // Allow to run server again, e.g. on domain change. Callbacks of the deleted participant
+        // are never called again, so their user data is released too
+        servers.erase(server);
+        listeners.erase(domain_id);
+        user_data.erase(domain_id);
     } else {
-        printf("Server participant is null. Trying to recover state from existing "
-               "process\n");
-
-        // Create server to delete another server. TODO: I don't know better way to
-        // do this.
-        auto *listener = new DiscoveryDomainParticipantListener();
-
-        listener->set_participant_discovery_callback(
-                kill_server_callback, user_data_on_discovery_participant);
-
-        printf("Creating server participant with domain id %d\n", domain_id);
-
-        auto server_qos = create_server_attributes(
-                11814, 11815, "45.53.00.5f.45.50.51.4f.53.49.4d.42");
-        // auto server_qos = create_server_attributes(11813, 11814, server_guid);
-        server = instance->create_participant(domain_id, server_qos, listener);
-
-        if (nullptr == server) {
-            fprintf(stderr, "Unable to create server participant\n");
-            return -1;
-        } else {
-            printf("Recover participant was created successfully\n");
-        }
-
-        std::this_thread::sleep_for(std::chrono::seconds(20));
-
-        // system("killall ")
+        fprintf(stderr, "Server participant of domain %u isn't running\n", domain_id);
+        return -1;
     }
 
     return 0;
@@ -185,8 +281,11 @@ int stop_discovery_server_impl(uint32_t domain_id)
 
 int is_discovery_running_impl(uint32_t domain_id)
 {
-    if (server != nullptr)
-        return 1;
+    {
+        std::lock_guard<std::mutex> lock(servers_mutex);
+        if (servers.count(domain_id) != 0)
+            return 1;
+    }
 
     if (run_discovery_server_impl(domain_id, DiscoveryServerParams()) == -1)
         return 1;
@@ -196,29 +295,45 @@ int is_discovery_running_impl(uint32_t domain_id)
     return 0;
 }
 
-void register_on_participant_discovery_data(void *data)
+void register_on_participant_discovery_data(uint32_t domain_id, void *data)
 {
-    user_data_on_discovery_participant = data;
+    std::lock_guard<std::mutex> lock(servers_mutex);
+    user_data[domain_id].on_discovery_participant = data;
 }
 
-void register_on_reader_discovery_data(void *data)
+void register_on_reader_discovery_data(uint32_t domain_id, void *data)
 {
-    user_data_on_discovery_reader = data;
+    std::lock_guard<std::mutex> lock(servers_mutex);
+    user_data[domain_id].on_discovery_reader = data;
 }
 
-void register_on_writer_discovery_data(void *data)
+void register_on_writer_discovery_data(uint32_t domain_id, void *data)
+{
+    std::lock_guard<std::mutex> lock(servers_mutex);
+    user_data[domain_id].on_discovery_writer = data;
+}
+void register_on_participant_removed_data(uint32_t domain_id, void *data)
+{
+    std::lock_guard<std::mutex> lock(servers_mutex);
+    user_data[domain_id].on_remove_participant = data;
+}
+void register_on_reader_removed_data(uint32_t domain_id, void *data)
 {
-    user_data_on_discovery_writer = data;
+    std::lock_guard<std::mutex> lock(servers_mutex);
+    user_data[domain_id].on_remove_reader = data;
 }
-void register_on_participant_removed_data(void *data)
+void register_on_writer_removed_data(uint32_t domain_id, void *data)
 {
-    user_data_on_remove_participant = data;
+    std::lock_guard<std::mutex> lock(servers_mutex);
+    user_data[domain_id].on_remove_writer = data;
 }
-void register_on_reader_removed_data(void *data)
+void register_on_statistics_data(uint32_t domain_id, void *data)
 {
-    user_data_on_remove_reader = data;
+    std::lock_guard<std::mutex> lock(servers_mutex);
+    user_data[domain_id].on_statistics = data;
 }
-void register_on_writer_removed_data(void *data)
+void register_on_type_discovery_data(uint32_t domain_id, void *data)
 {
-    user_data_on_remove_writer = data;
+    std::lock_guard<std::mutex> lock(servers_mutex);
+    user_data[domain_id].on_type_discovery = data;
 }
@@ -26,8 +26,13 @@ enum rmw_transport {
     TCPV6_TRANSPORT
 };
 
+enum discovery_mode {
+    SERVER_DISCOVERY_MODE = 0,// Participant is a discovery server, clients point ROS_DISCOVERY_SERVER at it
+    SIMPLE_DISCOVERY_MODE// Plain participant observing default SPDP multicast discovery
+};
+
 typedef struct {
-    uint32_t port;
+    uint32_t port;// TCP locators hold logical port in high 16 bits and physical port in low 16 bits
     enum rmw_transport transport;
     union {
         unsigned char endpoint_v4[4];// Empty in case of SHM transport
@@ -35,28 +40,78 @@ typedef struct {
     };
 } FastDDSEndpoint;
 
+#define MAX_LOCATORS 4
+
+typedef struct {
+    FastDDSEndpoint unicast[MAX_LOCATORS];
+    FastDDSEndpoint multicast[MAX_LOCATORS];
+    uint8_t unicast_num;// Number of filled unicast locators, the rest are dropped
+    uint8_t multicast_num;// Number of filled multicast locators, the rest are dropped
+} FastDDSLocators;
+
 typedef struct {
     void *participant;
-    FastDDSEndpoint endpoint;
+    FastDDSLocators locators;// Default (user traffic) locators
     unsigned char guid[12];
+    unsigned char vendor_id[2];
+    char name[255];// Participant name, empty if not set
+    char user_data[255];// QoS user data as is, e.g. "enclave=/;" for ROS2 contexts
 } ParticipantData;
 
 #define GUID_PREFIX_SIZE 12
+#define ENTITY_ID_SIZE 4
 
 typedef struct {
     char topic_name[255];
     char type_name[255];
     unsigned char guid_prefix[GUID_PREFIX_SIZE];
-    FastDDSEndpoint endpoint;
+    unsigned char entity_id[ENTITY_ID_SIZE];
+    FastDDSLocators locators;
 } WriterData;
 
 typedef struct {
     char topic_name[255];
     char type_name[255];
     unsigned char guid_prefix[GUID_PREFIX_SIZE];
-    FastDDSEndpoint endpoint;
+    unsigned char entity_id[ENTITY_ID_SIZE];
+    FastDDSLocators locators;
 } ReaderData;
 
+enum statistics_kind {
+    HISTORY_LATENCY_STATISTICS = 0,// Latency between writer and reader histories, value in nanoseconds
+    NETWORK_LATENCY_STATISTICS,// Latency between source and destination locators, value in nanoseconds
+    PUBLICATION_THROUGHPUT_STATISTICS,// Writer throughput, value in bytes per second
+    SUBSCRIPTION_THROUGHPUT_STATISTICS,// Reader throughput, value in bytes per second
+    RESENT_DATA_STATISTICS,// Count of resent DATA submessages of the writer
+    HEARTBEAT_COUNT_STATISTICS,// Count of HEARTBEAT submessages of the writer
+    ACKNACK_COUNT_STATISTICS,// Count of ACKNACK submessages of the reader
+    RTPS_LOST_STATISTICS// Packets and bytes lost from the participant to the destination locator
+};
+
+/**
+ * Sample of FastDDS statistics topic. Fields which aren't used by the kind are zeroed
+ */
+typedef struct {
+    enum statistics_kind kind;
+    unsigned char guid_prefix[GUID_PREFIX_SIZE];// Writer of history latency, reporting entity of others
+    unsigned char entity_id[ENTITY_ID_SIZE];
+    unsigned char remote_guid_prefix[GUID_PREFIX_SIZE];// Reader of history latency
+    unsigned char remote_entity_id[ENTITY_ID_SIZE];
+    FastDDSEndpoint src_locator;// Source of network latency
+    FastDDSEndpoint dst_locator;// Destination of network latency and lost traffic
+    double value;// Latency or throughput
+    uint64_t count;// Submessages or lost packets
+    uint64_t bytes;// Lost bytes
+} StatisticsData;
+
+/**
+ * Definition of the type received in discovery as XTypes type object
+ */
+typedef struct {
+    char type_name[255];// DDS type name, e.g. std_msgs::msg::dds_::String_
+    const char *definition;// Fields in .msg format, one per line. Valid only during the callback
+} TypeData;
+
 typedef void (*on_participant_discovery_callback_t)(
         ParticipantData participant_data, void *user_data);
 typedef void (*on_reader_discovery_callback_t)(ReaderData reader_data,
@@ -71,6 +126,25 @@ typedef void (*on_reader_remove_callback_t)(ReaderData reader_data,
 typedef void (*on_writer_remove_callback_t)(WriterData writer_data,
                                             void *user_data);
 
+typedef void (*on_statistics_callback_t)(StatisticsData statistics_data,
+                                         void *user_data);
+
+typedef void (*on_type_discovery_callback_t)(TypeData type_data,
+                                             void *user_data);
+
+/**
+ * RTPS port mapping, the same as <port> element of FastDDS participant profile. Zero port base keeps defaults
+ */
+typedef struct {
+    uint32_t port_base;
+    uint32_t domain_id_gain;
+    uint32_t participant_id_gain;
+    uint32_t offset_d0;
+    uint32_t offset_d1;
+    uint32_t offset_d2;
+    uint32_t offset_d3;
+} RtpsPortParams;
+
 typedef struct {
     on_participant_discovery_callback_t participant_discovery_callback;
     on_reader_discovery_callback_t reader_discovery_callback;
@@ -79,6 +153,15 @@ typedef struct {
     on_participant_remove_callback_t participant_removed_callback;
     on_reader_remove_callback_t reader_removed_callback;
     on_writer_remove_callback_t writer_removed_callback;
+
+    const char *profiles_file;// FastDDS XML profiles file with server participant profiles
+    const char *profile_name;// Participant profile of the server for this domain
+    uint8_t discovery_mode;// One of discovery_mode values. Profiles are ignored in simple mode
+
+    on_statistics_callback_t statistics_callback;// Subscribe to FastDDS statistics topics if not null
+    on_type_discovery_callback_t type_discovery_callback;// Request type objects of discovered endpoints if not null
+    uint16_t server_port;// Listening port of the server used if there is no profile, 0 if the monitor has no own server
+    RtpsPortParams port_params;// Port mapping of the monitored system. Profiles define their own mapping
 } DiscoveryServerParams;
 
 /*void on_participant_discovery(
@@ -112,13 +195,21 @@ int run_discovery_server_impl(uint32_t domain_id, DiscoveryServerParams);
 
 int stop_discovery_server_impl(uint32_t domain_id);
 int is_discovery_running_impl(uint32_t domain_id);
-void register_on_participant_discovery_data(void *data);
-void register_on_reader_discovery_data(void *data);
-void register_on_writer_discovery_data(void *data);
 
-void register_on_participant_removed_data(void *data);
-void register_on_reader_removed_data(void *data);
-void register_on_writer_removed_data(void *data);
+/**
+ * Register user data passed to callbacks of the specified domain server.
+ * Must be called before run_discovery_server_impl for this domain.
+ */
+void register_on_participant_discovery_data(uint32_t domain_id, void *data);
+void register_on_reader_discovery_data(uint32_t domain_id, void *data);
+void register_on_writer_discovery_data(uint32_t domain_id, void *data);
+
+void register_on_participant_removed_data(uint32_t domain_id, void *data);
+void register_on_reader_removed_data(uint32_t domain_id, void *data);
+void register_on_writer_removed_data(uint32_t domain_id, void *data);
+
+void register_on_statistics_data(uint32_t domain_id, void *data);
+void register_on_type_discovery_data(uint32_t domain_id, void *data);
 
 
 #ifdef __cplusplus
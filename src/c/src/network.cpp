@@ -12,6 +12,7 @@ using eprosima::fastrtps::rtps::octet;
 
 void parse_endpoint_fastdds(FastDDSEndpoint &endpoint, const eprosima::fastrtps::rtps::Locator_t& locator)
 {
+    endpoint.port = locator.port;
     if (locator.kind == LOCATOR_KIND_UDPv4) {
         endpoint.transport = UPDV4_TRANSPORT;
         std::memcpy(endpoint.endpoint_v4, locator.address + 12 * sizeof(octet), 4 * sizeof(octet));
@@ -30,4 +31,22 @@ void parse_endpoint_fastdds(FastDDSEndpoint &endpoint, const eprosima::fastrtps:
     //endpoint.endpoint_v4[4] = '\0';
     //endpoint.endpoint_v6[16] = '\0';
 }
+
+void parse_locators_fastdds(FastDDSLocators &locators, const eprosima::fastrtps::rtps::RemoteLocatorList& locator_list)
+{
+    locators.unicast_num = 0;
+    for (const auto &locator : locator_list.unicast) {
+        if (locators.unicast_num == MAX_LOCATORS) {
+            break;
+        }
+        parse_endpoint_fastdds(locators.unicast[locators.unicast_num++], locator);
+    }
+    locators.multicast_num = 0;
+    for (const auto &locator : locator_list.multicast) {
+        if (locators.multicast_num == MAX_LOCATORS) {
+            break;
+        }
+        parse_endpoint_fastdds(locators.multicast[locators.multicast_num++], locator);
+    }
+}
 }
\ No newline at end of file
@@ -9,8 +9,10 @@
 #include <cstddef>
 
 #include <fastdds/rtps/common/Locator.h>
+#include <fastdds/rtps/common/RemoteLocators.hpp>
 #include "discovery_server.h"
 
 namespace r2discoverer {
 void parse_endpoint_fastdds(FastDDSEndpoint& endpoint, const eprosima::fastrtps::rtps::Locator_t& locator);
+void parse_locators_fastdds(FastDDSLocators& locators, const eprosima::fastrtps::rtps::RemoteLocatorList& locator_list);
 }
\ No newline at end of file
@@ -0,0 +1,300 @@
+/*
+* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+
+#include <fastcdr/Cdr.h>
+#include <fastcdr/FastBuffer.h>
+#include <fastcdr/exceptions/Exception.h>
+#include <fastrtps/fastdds/dds/subscriber/SampleInfo.hpp>
+#include <fastrtps/fastdds/dds/subscriber/qos/DataReaderQos.hpp>
+#include <fastrtps/fastdds/dds/topic/TypeSupport.hpp>
+#include <fastrtps/utils/md5.h>
+
+#include <cstring>
+#include <set>
+
+#include "network.hpp"
+#include "statistics_reader.hpp"
+
+namespace r2discoverer {
+
+using namespace eprosima::fastdds::dds;
+using eprosima::fastrtps::rtps::InstanceHandle_t;
+using eprosima::fastrtps::rtps::Locator_t;
+using eprosima::fastrtps::rtps::SerializedPayload_t;
+
+// Serialized samples of all statistics types are smaller than this, including the encapsulation
+static const uint32_t STATISTICS_TYPE_SIZE = 128;
+
+struct StatisticsTopic {
+    const char *topic_name;
+    enum statistics_kind kind;
+    StatisticsLayout layout;
+};
+
+// Topic names are defined by FastDDS statistics module (fastdds/statistics/topic_names.hpp)
+static const StatisticsTopic statistics_topics[] = {
+        {"_fastdds_statistics_history2history_latency", HISTORY_LATENCY_STATISTICS, StatisticsLayout::WriterReaderData},
+        {"_fastdds_statistics_network_latency", NETWORK_LATENCY_STATISTICS, StatisticsLayout::Locator2LocatorData},
+        {"_fastdds_statistics_publication_throughput", PUBLICATION_THROUGHPUT_STATISTICS, StatisticsLayout::EntityData},
+        {"_fastdds_statistics_subscription_throughput", SUBSCRIPTION_THROUGHPUT_STATISTICS, StatisticsLayout::EntityData},
+        {"_fastdds_statistics_resent_datas", RESENT_DATA_STATISTICS, StatisticsLayout::EntityCount},
+        {"_fastdds_statistics_heartbeat_count", HEARTBEAT_COUNT_STATISTICS, StatisticsLayout::EntityCount},
+        {"_fastdds_statistics_acknack_count", ACKNACK_COUNT_STATISTICS, StatisticsLayout::EntityCount},
+        {"_fastdds_statistics_rtps_lost", RTPS_LOST_STATISTICS, StatisticsLayout::Entity2LocatorTraffic},
+};
+
+static const char *type_name(StatisticsLayout layout)
+{
+    switch (layout) {
+        case StatisticsLayout::WriterReaderData:
+            return "eprosima::fastdds::statistics::WriterReaderData";
+        case StatisticsLayout::Locator2LocatorData:
+            return "eprosima::fastdds::statistics::Locator2LocatorData";
+        case StatisticsLayout::EntityData:
+            return "eprosima::fastdds::statistics::EntityData";
+        case StatisticsLayout::EntityCount:
+            return "eprosima::fastdds::statistics::EntityCount";
+        case StatisticsLayout::Entity2LocatorTraffic:
+            return "eprosima::fastdds::statistics::Entity2LocatorTraffic";
+    }
+    return "";
+}
+
+/**
+ * Key fields are appended in big endian CDR, as they are hashed by FastDDS
+ */
+static void append_key(std::vector<uint8_t> &key, const uint8_t *bytes, size_t size)
+{
+    key.insert(key.end(), bytes, bytes + size);
+}
+
+static void append_key(std::vector<uint8_t> &key, uint32_t value)
+{
+    for (int shift = 24; shift >= 0; shift -= 8) {
+        key.push_back(static_cast<uint8_t>(value >> shift));
+    }
+}
+
+static void read_guid(eprosima::fastcdr::Cdr &cdr, unsigned char *prefix, unsigned char *entity_id, std::vector<uint8_t> &key)
+{
+    cdr.deserializeArray(prefix, GUID_PREFIX_SIZE);
+    cdr.deserializeArray(entity_id, ENTITY_ID_SIZE);
+    append_key(key, prefix, GUID_PREFIX_SIZE);
+    append_key(key, entity_id, ENTITY_ID_SIZE);
+}
+
+static void read_locator(eprosima::fastcdr::Cdr &cdr, FastDDSEndpoint &endpoint, std::vector<uint8_t> &key)
+{
+    Locator_t locator;
+    int32_t kind = 0;
+    uint32_t port = 0;
+    cdr >> kind;
+    cdr >> port;
+    cdr.deserializeArray(locator.address, sizeof(locator.address));
+    locator.kind = kind;
+    locator.port = port;
+    parse_endpoint_fastdds(endpoint, locator);
+
+    append_key(key, static_cast<uint32_t>(kind));
+    append_key(key, port);
+    append_key(key, locator.address, sizeof(locator.address));
+}
+
+StatisticsPubSubType::StatisticsPubSubType(const char *type_name, StatisticsLayout layout)
+    : m_layout(layout)
+{
+    setName(type_name);
+    m_typeSize = STATISTICS_TYPE_SIZE;
+    // Statistics types are keyed, readers of keyless topics don't match their writers
+    m_isGetKeyDefined = true;
+}
+
+bool StatisticsPubSubType::serialize(void *data, SerializedPayload_t *payload)
+{
+    // The monitor only reads statistics
+    static_cast<void>(data);
+    static_cast<void>(payload);
+    return false;
+}
+
+bool StatisticsPubSubType::deserialize(SerializedPayload_t *payload, void *data)
+{
+    auto *sample = static_cast<StatisticsSample *>(data);
+    sample->data = StatisticsData{};
+    sample->key.clear();
+    auto &statistics = sample->data;
+
+    eprosima::fastcdr::FastBuffer buffer(reinterpret_cast<char *>(payload->data), payload->length);
+    eprosima::fastcdr::Cdr cdr(buffer, eprosima::fastcdr::Cdr::DEFAULT_ENDIAN, eprosima::fastcdr::Cdr::DDS_CDR);
+    try {
+        cdr.read_encapsulation();
+        float value = 0;
+        int16_t magnitude = 0;
+        switch (m_layout) {
+            case StatisticsLayout::WriterReaderData:
+                read_guid(cdr, statistics.guid_prefix, statistics.entity_id, sample->key);
+                read_guid(cdr, statistics.remote_guid_prefix, statistics.remote_entity_id, sample->key);
+                cdr >> value;
+                statistics.value = value;
+                break;
+            case StatisticsLayout::Locator2LocatorData:
+                read_locator(cdr, statistics.src_locator, sample->key);
+                read_locator(cdr, statistics.dst_locator, sample->key);
+                cdr >> value;
+                statistics.value = value;
+                break;
+            case StatisticsLayout::EntityData:
+                read_guid(cdr, statistics.guid_prefix, statistics.entity_id, sample->key);
+                cdr >> value;
+                statistics.value = value;
+                break;
+            case StatisticsLayout::EntityCount:
+                read_guid(cdr, statistics.guid_prefix, statistics.entity_id, sample->key);
+                cdr >> statistics.count;
+                break;
+            case StatisticsLayout::Entity2LocatorTraffic:
+                read_guid(cdr, statistics.guid_prefix, statistics.entity_id, sample->key);
+                read_locator(cdr, statistics.dst_locator, sample->key);
+                cdr >> statistics.count;
+                cdr >> statistics.bytes;
+                cdr >> magnitude;
+                // Byte count overflowed 64 bits
+                if (magnitude > 0) {
+                    statistics.bytes = UINT64_MAX;
+                }
+                break;
+        }
+    } catch (eprosima::fastcdr::exception::Exception &e) {
+        fprintf(stderr, "Unable to deserialize %s: %s\n", getName(), e.what());
+        return false;
+    }
+    return true;
+}
+
+std::function<uint32_t()> StatisticsPubSubType::getSerializedSizeProvider(void *data)
+{
+    static_cast<void>(data);
+    return []() { return STATISTICS_TYPE_SIZE; };
+}
+
+bool StatisticsPubSubType::getKey(void *data, InstanceHandle_t *handle, bool force_md5)
+{
+    auto *sample = static_cast<StatisticsSample *>(data);
+    if (force_md5 || sample->key.size() > 16) {
+        MD5 md5;
+        md5.init();
+        md5.update(sample->key.data(), static_cast<unsigned int>(sample->key.size()));
+        md5.finalize();
+        std::memcpy(handle->value, md5.digest, 16);
+    } else {
+        std::memset(handle->value, 0, 16);
+        std::memcpy(handle->value, sample->key.data(), sample->key.size());
+    }
+    return true;
+}
+
+void *StatisticsPubSubType::createData()
+{
+    return static_cast<void *>(new StatisticsSample());
+}
+
+void StatisticsPubSubType::deleteData(void *data)
+{
+    delete static_cast<StatisticsSample *>(data);
+}
+
+StatisticsReaderListener::StatisticsReaderListener(enum statistics_kind kind, on_statistics_callback_t callback, void *data)
+    : m_kind(kind), m_callback(callback), m_data(data)
+{
+}
+
+void StatisticsReaderListener::on_data_available(DataReader *reader)
+{
+    StatisticsSample sample;
+    SampleInfo info;
+    while (reader->take_next_sample(&sample, &info) == ReturnCode_t::RETCODE_OK) {
+        if (info.valid_data && m_callback) {
+            sample.data.kind = m_kind;
+            m_callback(sample.data, m_data);
+        }
+    }
+}
+
+bool StatisticsReaders::create(DomainParticipant *participant, on_statistics_callback_t callback, void *data)
+{
+    destroy();
+    m_participant = participant;
+    m_subscriber = participant->create_subscriber(SUBSCRIBER_QOS_DEFAULT);
+    if (m_subscriber == nullptr) {
+        fprintf(stderr, "Unable to create subscriber of statistics topics\n");
+        return false;
+    }
+
+    // Best effort volatile readers match statistics writers of any QoS
+    DataReaderQos reader_qos = DATAREADER_QOS_DEFAULT;
+    reader_qos.reliability().kind = BEST_EFFORT_RELIABILITY_QOS;
+    reader_qos.durability().kind = VOLATILE_DURABILITY_QOS;
+
+    bool created = true;
+    std::set<StatisticsLayout> registered;
+    for (const auto &statistics_topic : statistics_topics) {
+        if (registered.count(statistics_topic.layout) == 0) {
+            TypeSupport type(new StatisticsPubSubType(type_name(statistics_topic.layout), statistics_topic.layout));
+            if (type.register_type(participant) != ReturnCode_t::RETCODE_OK) {
+                fprintf(stderr, "Unable to register statistics type %s\n", type_name(statistics_topic.layout));
+                created = false;
+                continue;
+            }
+            registered.insert(statistics_topic.layout);
+        }
+
+        Topic *topic = participant->create_topic(statistics_topic.topic_name, type_name(statistics_topic.layout), TOPIC_QOS_DEFAULT);
+        if (topic == nullptr) {
+            fprintf(stderr, "Unable to create statistics topic %s\n", statistics_topic.topic_name);
+            created = false;
+            continue;
+        }
+        m_topics.push_back(topic);
+
+        auto listener = std::make_unique<StatisticsReaderListener>(statistics_topic.kind, callback, data);
+        DataReader *reader = m_subscriber->create_datareader(topic, reader_qos, listener.get());
+        if (reader == nullptr) {
+            fprintf(stderr, "Unable to create reader of statistics topic %s\n", statistics_topic.topic_name);
+            created = false;
+            continue;
+        }
+        m_readers.push_back(reader);
+        m_listeners.push_back(std::move(listener));
+    }
+    return created;
+}
+
+void StatisticsReaders::destroy()
+{
+    if (m_participant == nullptr) {
+        return;
+    }
+    for (auto *reader : m_readers) {
+        m_subscriber->delete_datareader(reader);
+    }
+    if (m_subscriber != nullptr) {
+        m_participant->delete_subscriber(m_subscriber);
+    }
+    for (auto *topic : m_topics) {
+        m_participant->delete_topic(topic);
+    }
+    m_readers.clear();
+    m_topics.clear();
+    m_listeners.clear();
+    m_subscriber = nullptr;
+    m_participant = nullptr;
+}
+
+StatisticsReaders::~StatisticsReaders()
+{
+    destroy();
+}
+}// namespace r2discoverer
@@ -0,0 +1,100 @@
+/*
+* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+
+#pragma once
+
+#include <fastrtps/fastdds/dds/domain/DomainParticipant.hpp>
+#include <fastrtps/fastdds/dds/subscriber/DataReader.hpp>
+#include <fastrtps/fastdds/dds/subscriber/DataReaderListener.hpp>
+#include <fastrtps/fastdds/dds/subscriber/Subscriber.hpp>
+#include <fastrtps/fastdds/dds/topic/Topic.hpp>
+#include <fastrtps/fastdds/dds/topic/TopicDataType.hpp>
+
+#include <memory>
+#include <vector>
+
+#include "discovery_server.h"
+
+namespace r2discoverer {
+
+/**
+ * Layout of the statistics topic type. Types are defined by types.idl of FastDDS
+ * statistics module, they are decoded here, so the monitor doesn't depend on
+ * generated type support of FastDDS
+ */
+enum class StatisticsLayout {
+    WriterReaderData,// GUID writer_guid, GUID reader_guid, float data
+    Locator2LocatorData,// Locator src_locator, Locator dst_locator, float data
+    EntityData,// GUID guid, float data
+    EntityCount,// GUID guid, uint64 count
+    Entity2LocatorTraffic// GUID src_guid, Locator dst_locator, uint64 packet_count, uint64 byte_count, int16 byte_magnitude_order
+};
+
+/**
+ * Decoded sample with the serialized key, which identifies the instance
+ */
+struct StatisticsSample {
+    StatisticsData data{};
+    std::vector<uint8_t> key;
+};
+
+class StatisticsPubSubType : public eprosima::fastdds::dds::TopicDataType {
+public:
+    StatisticsPubSubType(const char *type_name, StatisticsLayout layout);
+
+    bool serialize(void *data, eprosima::fastrtps::rtps::SerializedPayload_t *payload) override;
+    bool deserialize(eprosima::fastrtps::rtps::SerializedPayload_t *payload, void *data) override;
+    std::function<uint32_t()> getSerializedSizeProvider(void *data) override;
+    bool getKey(void *data, eprosima::fastrtps::rtps::InstanceHandle_t *handle, bool force_md5 = false) override;
+    void *createData() override;
+    void deleteData(void *data) override;
+
+private:
+    StatisticsLayout m_layout;
+};
+
+/**
+ * Passes samples of the single statistics topic to the callback
+ */
+class StatisticsReaderListener : public eprosima::fastdds::dds::DataReaderListener {
+public:
+    StatisticsReaderListener(enum statistics_kind kind, on_statistics_callback_t callback, void *data);
+
+    void on_data_available(eprosima::fastdds::dds::DataReader *reader) override;
+
+private:
+    enum statistics_kind m_kind;
+    on_statistics_callback_t m_callback;
+    void *m_data;
+};
+
+/**
+ * Readers of FastDDS statistics topics of the domain. Statistics are published only by participants
+ * with enabled statistics module, e.g. by FASTDDS_STATISTICS environment variable
+ */
+class StatisticsReaders {
+public:
+    /**
+     * Subscribe to all statistics topics
+     * @return false if any reader isn't created
+     */
+    bool create(eprosima::fastdds::dds::DomainParticipant *participant, on_statistics_callback_t callback, void *data);
+
+    /**
+     * Delete readers, must be called before the participant is deleted
+     */
+    void destroy();
+
+    ~StatisticsReaders();
+
+private:
+    eprosima::fastdds::dds::DomainParticipant *m_participant = nullptr;
+    eprosima::fastdds::dds::Subscriber *m_subscriber = nullptr;
+    std::vector<eprosima::fastdds::dds::Topic *> m_topics;
+    std::vector<eprosima::fastdds::dds::DataReader *> m_readers;
+    std::vector<std::unique_ptr<StatisticsReaderListener>> m_listeners;
+};
+}// namespace r2discoverer
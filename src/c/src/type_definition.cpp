@@ -0,0 +1,137 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+#include <fastrtps/types/DynamicType.h>
+#include <fastrtps/types/DynamicTypeMember.h>
+#include <fastrtps/types/MemberDescriptor.h>
+#include <fastrtps/types/TypesBase.h>
+
+#include <map>
+#include <set>
+#include <sstream>
+
+#include "type_definition.hpp"
+
+namespace r2discoverer {
+
+using namespace eprosima::fastrtps::types;
+
+// FastDDS gives this bound to strings without explicit one
+static const uint32_t DEFAULT_STRING_BOUND = 255;
+
+/**
+ * Name of the field type in .msg format without array suffix. Nested structures are collected into `nested`
+ */
+static std::string base_type_name(const DynamicType_ptr &type, std::vector<DynamicType_ptr> &nested)
+{
+    switch (type->get_kind()) {
+        case TK_BOOLEAN:
+            return "bool";
+        // Both byte and uint8 are octets in DDS
+        case TK_BYTE:
+            return "uint8";
+        case TK_CHAR8:
+            return "char";
+        case TK_INT16:
+            return "int16";
+        case TK_UINT16:
+            return "uint16";
+        case TK_INT32:
+            return "int32";
+        case TK_UINT32:
+            return "uint32";
+        case TK_INT64:
+            return "int64";
+        case TK_UINT64:
+            return "uint64";
+        case TK_FLOAT32:
+            return "float32";
+        case TK_FLOAT64:
+            return "float64";
+        case TK_STRING8:
+        case TK_STRING16: {
+            std::string name = type->get_kind() == TK_STRING8 ? "string" : "wstring";
+            uint32_t bound = type->get_bounds(0);
+            if (bound != LENGTH_UNLIMITED && bound != DEFAULT_STRING_BOUND) {
+                name += "<=" + std::to_string(bound);
+            }
+            return name;
+        }
+        case TK_ALIAS:
+            return base_type_name(type->get_base_type(), nested);
+        case TK_STRUCTURE:
+            nested.push_back(type);
+            return type->get_name();
+        default:
+            return "";
+    }
+}
+
+/**
+ * Field type in .msg format, e.g. `int32[<=5]`. Empty string for types which can't be expressed in .msg format
+ */
+static std::string field_type_name(const DynamicType_ptr &type, std::vector<DynamicType_ptr> &nested)
+{
+    switch (type->get_kind()) {
+        case TK_SEQUENCE: {
+            std::string element = base_type_name(type->get_element_type(), nested);
+            if (element.empty()) {
+                return "";
+            }
+            uint32_t bound = type->get_bounds(0);
+            return bound == LENGTH_UNLIMITED ? element + "[]" : element + "[<=" + std::to_string(bound) + "]";
+        }
+        case TK_ARRAY: {
+            std::string element = base_type_name(type->get_element_type(), nested);
+            if (element.empty()) {
+                return "";
+            }
+            return element + "[" + std::to_string(type->get_total_bounds()) + "]";
+        }
+        default:
+            return base_type_name(type, nested);
+    }
+}
+
+static void add_definitions(const DynamicType_ptr &type,
+                            std::set<std::string> &visited,
+                            std::vector<std::pair<std::string, std::string>> &definitions)
+{
+    if (!visited.insert(type->get_name()).second) {
+        return;
+    }
+
+    std::map<MemberId, DynamicTypeMember *> members;
+    type->get_all_members(members);
+
+    std::vector<DynamicType_ptr> nested;
+    std::ostringstream definition;
+    for (const auto &member : members) {
+        std::string field_type = field_type_name(member.second->get_descriptor()->get_type(), nested);
+        if (field_type.empty()) {
+            definition << "# unsupported type of " << member.second->get_name() << "\n";
+            continue;
+        }
+        definition << field_type << " " << member.second->get_name() << "\n";
+    }
+
+    for (const auto &nested_type : nested) {
+        add_definitions(nested_type, visited, definitions);
+    }
+    definitions.emplace_back(type->get_name(), definition.str());
+}
+
+std::vector<std::pair<std::string, std::string>> type_definitions(const DynamicType_ptr &type)
+{
+    std::vector<std::pair<std::string, std::string>> definitions;
+    if (!type || type->get_kind() != TK_STRUCTURE) {
+        return definitions;
+    }
+    std::set<std::string> visited;
+    add_definitions(type, visited, definitions);
+    return definitions;
+}
+}// namespace r2discoverer
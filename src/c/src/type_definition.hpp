@@ -0,0 +1,22 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+#pragma once
+
+#include <fastrtps/types/DynamicTypePtr.h>
+
+#include <string>
+#include <utility>
+#include <vector>
+
+namespace r2discoverer {
+/**
+ * Definitions of the structure type and structures it depends on in .msg format, dependencies go first.
+ * Nested types are referenced by their DDS names
+ * @return pairs of DDS type name and definition
+ */
+std::vector<std::pair<std::string, std::string>> type_definitions(const eprosima::fastrtps::types::DynamicType_ptr &type);
+}
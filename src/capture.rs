@@ -0,0 +1,910 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/// Passive capture of RTPS traffic. Packets are read from a raw socket of the network interface or from a pcap file,
+/// UDP datagrams with RTPS messages are decoded and their traffic is attributed to the participant and endpoint GUIDs.
+/// The monitor doesn't join the domain for it, so it also sees participants which don't use its discovery server.
+pub mod capture {
+    use std::collections::HashMap;
+    use std::fs::File;
+    use std::io::{BufReader, Read, Seek, SeekFrom};
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+    use std::time::{Duration, Instant};
+    use log::{error, info, warn};
+    use serde::{Deserialize, Serialize};
+    use crate::audit::audit::now_secs;
+    use crate::network::network::{hex_str_from_uc, PortParams};
+    use crate::ros2entites::ros2entities::Settings;
+    use crate::rtps::rtps::{endpoint_guid, is_rtps, parse_message, Submessage};
+
+    /// Period of sending accumulated activity to discovery server
+    pub const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+    /// Where packets come from
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum CaptureSource {
+        /// Raw socket of the interface, `any` for all interfaces. Requires CAP_NET_RAW
+        Interface(String),
+        /// Recorded pcap file, replayed once
+        File(String),
+    }
+
+    impl CaptureSource {
+        /// Capture source configured by settings. File has priority over interface, None if capture is disabled
+        pub fn from_settings(settings: &Settings) -> Option<CaptureSource> {
+            if !settings.capture_file.is_empty() {
+                return Some(CaptureSource::File(settings.capture_file.clone()));
+            }
+            if !settings.capture_interface.is_empty() {
+                return Some(CaptureSource::Interface(settings.capture_interface.clone()));
+            }
+            return None;
+        }
+    }
+
+    /// UDP datagram extracted from the captured frame
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct UdpDatagram {
+        /// Seconds since unix epoch
+        pub timestamp: f64,
+        pub source: SocketAddr,
+        pub destination: SocketAddr,
+        pub payload: Vec<u8>,
+    }
+
+    /// Link layer of captured frames, values are pcap link types
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum LinkType {
+        /// BSD loopback, 4 bytes of address family in host byte order
+        Null,
+        Ethernet,
+        /// Frames start with IPv4 or IPv6 header
+        RawIp,
+        /// Linux cooked capture v1
+        LinuxSll,
+        /// Linux cooked capture v2
+        LinuxSll2,
+    }
+
+    impl LinkType {
+        pub fn from_pcap(link_type: u32) -> Option<LinkType> {
+            return match link_type {
+                0 => Some(LinkType::Null),
+                1 => Some(LinkType::Ethernet),
+                12 | 101 | 228 | 229 => Some(LinkType::RawIp),
+                113 => Some(LinkType::LinuxSll),
+                276 => Some(LinkType::LinuxSll2),
+                _ => None
+            };
+        }
+    }
+
+    const ETHERTYPE_IPV4: u16 = 0x0800;
+    const ETHERTYPE_IPV6: u16 = 0x86dd;
+    const ETHERTYPE_VLAN: [u16; 2] = [0x8100, 0x88a8];
+    const IPPROTO_UDP: u8 = 17;
+
+    fn be_u16(data: &[u8], pos: usize) -> Option<u16> {
+        return data.get(pos..pos + 2).map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]));
+    }
+
+    /// Extract UDP datagram from the frame. Non UDP packets and non-first IP fragments are skipped
+    /// # Arguments
+    ///
+    /// * `link_type`: link layer of the frame
+    /// * `frame`: captured bytes
+    /// * `timestamp`: capture time, seconds since unix epoch
+    ///
+    /// returns: Option<UdpDatagram>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn decode_frame(link_type: LinkType, frame: &[u8], timestamp: f64) -> Option<UdpDatagram> {
+        let (ethertype, offset) = match link_type {
+            LinkType::Null => {
+                let family = u32::from_le_bytes(frame.get(0..4)?.try_into().unwrap());
+                let family = if family > 0xffff { family.swap_bytes() } else { family };
+                (if family == 2 { ETHERTYPE_IPV4 } else { ETHERTYPE_IPV6 }, 4)
+            }
+            LinkType::Ethernet => {
+                let mut offset = 12;
+                let mut ethertype = be_u16(frame, offset)?;
+                while ETHERTYPE_VLAN.contains(&ethertype) {
+                    offset += 4;
+                    ethertype = be_u16(frame, offset)?;
+                }
+                (ethertype, offset + 2)
+            }
+            LinkType::RawIp => (if frame.first()? >> 4 == 4 { ETHERTYPE_IPV4 } else { ETHERTYPE_IPV6 }, 0),
+            LinkType::LinuxSll => (be_u16(frame, 14)?, 16),
+            LinkType::LinuxSll2 => (be_u16(frame, 0)?, 20),
+        };
+        let packet = frame.get(offset..)?;
+        return match ethertype {
+            ETHERTYPE_IPV4 => decode_ipv4(packet, timestamp),
+            ETHERTYPE_IPV6 => decode_ipv6(packet, timestamp),
+            _ => None
+        };
+    }
+
+    fn decode_ipv4(packet: &[u8], timestamp: f64) -> Option<UdpDatagram> {
+        let header_len = ((*packet.first()? & 0x0f) as usize) * 4;
+        let total_len = be_u16(packet, 2)? as usize;
+        let fragment_offset = be_u16(packet, 6)? & 0x1fff;
+        if *packet.get(9)? != IPPROTO_UDP || fragment_offset != 0 || header_len < 20 {
+            return None;
+        }
+        let source: [u8; 4] = packet.get(12..16)?.try_into().unwrap();
+        let destination: [u8; 4] = packet.get(16..20)?.try_into().unwrap();
+        // Captures may be truncated by snap length, total length may be zero with segmentation offload
+        let end = if total_len >= header_len { total_len.min(packet.len()) } else { packet.len() };
+        return decode_udp(packet.get(header_len..end)?, IpAddr::V4(Ipv4Addr::from(source)), IpAddr::V4(Ipv4Addr::from(destination)), timestamp);
+    }
+
+    fn decode_ipv6(packet: &[u8], timestamp: f64) -> Option<UdpDatagram> {
+        if *packet.first()? >> 4 != 6 {
+            return None;
+        }
+        let source: [u8; 16] = packet.get(8..24)?.try_into().unwrap();
+        let destination: [u8; 16] = packet.get(24..40)?.try_into().unwrap();
+        let mut next_header = *packet.get(6)?;
+        let mut offset = 40;
+        // Skip extension headers: hop-by-hop, routing, fragment and destination options
+        loop {
+            match next_header {
+                0 | 43 | 60 => {
+                    let len = (*packet.get(offset + 1)? as usize + 1) * 8;
+                    next_header = *packet.get(offset)?;
+                    offset += len;
+                }
+                44 => {
+                    if be_u16(packet, offset + 2)? & 0xfff8 != 0 {
+                        return None;
+                    }
+                    next_header = *packet.get(offset)?;
+                    offset += 8;
+                }
+                _ => break
+            }
+        }
+        if next_header != IPPROTO_UDP {
+            return None;
+        }
+        return decode_udp(packet.get(offset..)?, IpAddr::V6(Ipv6Addr::from(source)), IpAddr::V6(Ipv6Addr::from(destination)), timestamp);
+    }
+
+    fn decode_udp(segment: &[u8], source: IpAddr, destination: IpAddr, timestamp: f64) -> Option<UdpDatagram> {
+        let source_port = be_u16(segment, 0)?;
+        let destination_port = be_u16(segment, 2)?;
+        let length = be_u16(segment, 4)? as usize;
+        let end = if length >= 8 { length.min(segment.len()) } else { segment.len() };
+        return Some(UdpDatagram {
+            timestamp,
+            source: SocketAddr::new(source, source_port),
+            destination: SocketAddr::new(destination, destination_port),
+            payload: segment.get(8..end)?.to_vec(),
+        });
+    }
+
+    /// Reader of classic libpcap files
+    pub struct PcapReader<R: Read> {
+        reader: R,
+        big_endian: bool,
+        nanoseconds: bool,
+        pub link_type: LinkType,
+    }
+
+    impl<R: Read> PcapReader<R> {
+        pub fn new(mut reader: R) -> Result<PcapReader<R>, String> {
+            let mut header = [0u8; 24];
+            if let Err(e) = reader.read_exact(&mut header) {
+                return Err(format!("Unable to read pcap header: {}", e));
+            }
+            let (big_endian, nanoseconds) = match header[0..4] {
+                [0xd4, 0xc3, 0xb2, 0xa1] => (false, false),
+                [0xa1, 0xb2, 0xc3, 0xd4] => (true, false),
+                [0x4d, 0x3c, 0xb2, 0xa1] => (false, true),
+                [0xa1, 0xb2, 0x3c, 0x4d] => (true, true),
+                _ => return Err("Not a pcap file".to_string())
+            };
+            let raw_link_type = read_u32(&header[20..24], big_endian) & 0x0fff_ffff;
+            let link_type = match LinkType::from_pcap(raw_link_type) {
+                Some(link_type) => link_type,
+                None => return Err(format!("Unsupported pcap link type {}", raw_link_type))
+            };
+            return Ok(PcapReader { reader, big_endian, nanoseconds, link_type });
+        }
+
+        /// The next frame with its timestamp, None at the end of file
+        pub fn next_frame(&mut self) -> Result<Option<(f64, Vec<u8>)>, String> {
+            let mut header = [0u8; 16];
+            match self.reader.read_exact(&mut header) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(format!("Unable to read pcap record: {}", e))
+            }
+            let seconds = read_u32(&header[0..4], self.big_endian) as f64;
+            let fraction = read_u32(&header[4..8], self.big_endian) as f64;
+            let captured_len = read_u32(&header[8..12], self.big_endian) as usize;
+            if captured_len > 0x0400_0000 {
+                return Err(format!("Invalid pcap record length {}", captured_len));
+            }
+            let mut frame = vec![0u8; captured_len];
+            if let Err(e) = self.reader.read_exact(frame.as_mut_slice()) {
+                return Err(format!("Truncated pcap record: {}", e));
+            }
+            let timestamp = seconds + fraction / if self.nanoseconds { 1e9 } else { 1e6 };
+            return Ok(Some((timestamp, frame)));
+        }
+
+        /// The next UDP datagram, frames of other protocols are skipped
+        pub fn next_datagram(&mut self) -> Result<Option<UdpDatagram>, String> {
+            while let Some((timestamp, frame)) = self.next_frame()? {
+                if let Some(datagram) = decode_frame(self.link_type, frame.as_slice(), timestamp) {
+                    return Ok(Some(datagram));
+                }
+            }
+            return Ok(None);
+        }
+    }
+
+    fn read_u32(bytes: &[u8], big_endian: bool) -> u32 {
+        let bytes: [u8; 4] = bytes.try_into().unwrap();
+        return if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) };
+    }
+
+    const PCAPNG_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+    const PCAPNG_INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+    const PCAPNG_SIMPLE_PACKET: u32 = 0x0000_0003;
+    const PCAPNG_ENHANCED_PACKET: u32 = 0x0000_0006;
+    const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+    /// Option of interface description block with resolution of packet timestamps
+    const PCAPNG_IF_TSRESOL: u16 = 9;
+
+    /// Interface of pcapng section. Packets of interfaces with unsupported link types are skipped
+    struct PcapngInterface {
+        link_type: Option<LinkType>,
+        snap_len: u32,
+        /// Timestamp units per second
+        ticks_per_second: f64,
+    }
+
+    /// Reader of pcapng files. Sections may have different byte order, each section has its own interfaces
+    pub struct PcapngReader<R: Read> {
+        reader: R,
+        big_endian: bool,
+        interfaces: Vec<PcapngInterface>,
+    }
+
+    impl<R: Read> PcapngReader<R> {
+        pub fn new(mut reader: R) -> Result<PcapngReader<R>, String> {
+            let mut header = [0u8; 8];
+            if let Err(e) = reader.read_exact(&mut header) {
+                return Err(format!("Unable to read pcapng header: {}", e));
+            }
+            if read_u32(&header[0..4], false) != PCAPNG_SECTION_HEADER {
+                return Err("Not a pcapng file".to_string());
+            }
+            let mut pcapng = PcapngReader { reader, big_endian: false, interfaces: vec![] };
+            pcapng.read_section_header(&header[4..8])?;
+            return Ok(pcapng);
+        }
+
+        /// Rest of the section header after its type and length. Byte order magic defines endianness of the section
+        fn read_section_header(&mut self, length: &[u8]) -> Result<(), String> {
+            let mut magic = [0u8; 4];
+            if let Err(e) = self.reader.read_exact(&mut magic) {
+                return Err(format!("Truncated pcapng section header: {}", e));
+            }
+            self.big_endian = match read_u32(&magic, false) {
+                PCAPNG_BYTE_ORDER_MAGIC => false,
+                magic if magic.swap_bytes() == PCAPNG_BYTE_ORDER_MAGIC => true,
+                _ => return Err("Invalid pcapng byte order magic".to_string())
+            };
+            let total_len = read_u32(length, self.big_endian) as usize;
+            if total_len < 28 || total_len % 4 != 0 {
+                return Err(format!("Invalid pcapng section header length {}", total_len));
+            }
+            self.read_body(total_len - 12)?;
+            self.interfaces.clear();
+            return Ok(());
+        }
+
+        fn read_body(&mut self, len: usize) -> Result<Vec<u8>, String> {
+            if len > 0x0400_0000 {
+                return Err(format!("Invalid pcapng block length {}", len));
+            }
+            let mut body = vec![0u8; len];
+            if let Err(e) = self.reader.read_exact(body.as_mut_slice()) {
+                return Err(format!("Truncated pcapng block: {}", e));
+            }
+            return Ok(body);
+        }
+
+        fn interface_description(&self, body: &[u8]) -> PcapngInterface {
+            let link_type = u16::from_be_bytes(if self.big_endian { [body[0], body[1]] } else { [body[1], body[0]] });
+            let mut interface = PcapngInterface {
+                link_type: LinkType::from_pcap(link_type as u32),
+                snap_len: read_u32(&body[4..8], self.big_endian),
+                ticks_per_second: 1e6,
+            };
+            // Options are code, length and value padded to 4 bytes
+            let mut pos = 8;
+            while pos + 4 <= body.len() {
+                let code = u16::from_be_bytes(if self.big_endian { [body[pos], body[pos + 1]] } else { [body[pos + 1], body[pos]] });
+                let len = u16::from_be_bytes(if self.big_endian { [body[pos + 2], body[pos + 3]] } else { [body[pos + 3], body[pos + 2]] }) as usize;
+                if code == PCAPNG_IF_TSRESOL && len == 1 && pos + 4 < body.len() {
+                    // The most significant bit selects power of 2 instead of power of 10
+                    let resolution = body[pos + 4];
+                    interface.ticks_per_second = if resolution & 0x80 != 0 {
+                        2f64.powi((resolution & 0x7f) as i32)
+                    } else {
+                        10f64.powi(resolution as i32)
+                    };
+                }
+                pos += 4 + (len + 3) / 4 * 4;
+            }
+            return interface;
+        }
+
+        /// The next packet with its link type and timestamp, None at the end of file
+        pub fn next_frame(&mut self) -> Result<Option<(LinkType, f64, Vec<u8>)>, String> {
+            loop {
+                let mut header = [0u8; 8];
+                match self.reader.read_exact(&mut header) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+                    Err(e) => return Err(format!("Unable to read pcapng block: {}", e))
+                }
+                let block_type = read_u32(&header[0..4], self.big_endian);
+                if block_type == PCAPNG_SECTION_HEADER {
+                    self.read_section_header(&header[4..8])?;
+                    continue;
+                }
+                let total_len = read_u32(&header[4..8], self.big_endian) as usize;
+                if total_len < 12 || total_len % 4 != 0 {
+                    return Err(format!("Invalid pcapng block length {}", total_len));
+                }
+                // Body is followed by the repeated total length
+                let body = self.read_body(total_len - 8)?;
+                let body = &body[..body.len() - 4];
+                match block_type {
+                    PCAPNG_INTERFACE_DESCRIPTION if body.len() >= 8 => {
+                        let interface = self.interface_description(body);
+                        self.interfaces.push(interface);
+                    }
+                    PCAPNG_ENHANCED_PACKET if body.len() >= 20 => {
+                        let interface = match self.interfaces.get(read_u32(&body[0..4], self.big_endian) as usize) {
+                            Some(interface) => interface,
+                            None => continue
+                        };
+                        let link_type = match interface.link_type {
+                            Some(link_type) => link_type,
+                            None => continue
+                        };
+                        let ticks = ((read_u32(&body[4..8], self.big_endian) as u64) << 32) | read_u32(&body[8..12], self.big_endian) as u64;
+                        let captured_len = (read_u32(&body[12..16], self.big_endian) as usize).min(body.len() - 20);
+                        return Ok(Some((link_type, ticks as f64 / interface.ticks_per_second, body[20..20 + captured_len].to_vec())));
+                    }
+                    // Simple packets belong to the first interface and have no timestamp
+                    PCAPNG_SIMPLE_PACKET if body.len() >= 4 => {
+                        let (link_type, snap_len) = match self.interfaces.first() {
+                            Some(PcapngInterface { link_type: Some(link_type), snap_len, .. }) => (*link_type, *snap_len as usize),
+                            _ => continue
+                        };
+                        let original_len = read_u32(&body[0..4], self.big_endian) as usize;
+                        let mut captured_len = original_len.min(body.len() - 4);
+                        if snap_len > 0 {
+                            captured_len = captured_len.min(snap_len);
+                        }
+                        return Ok(Some((link_type, 0.0, body[4..4 + captured_len].to_vec())));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        /// The next UDP datagram, frames of other protocols are skipped
+        pub fn next_datagram(&mut self) -> Result<Option<UdpDatagram>, String> {
+            while let Some((link_type, timestamp, frame)) = self.next_frame()? {
+                if let Some(datagram) = decode_frame(link_type, frame.as_slice(), timestamp) {
+                    return Ok(Some(datagram));
+                }
+            }
+            return Ok(None);
+        }
+    }
+
+    /// Recorded capture in pcap or pcapng format, the format is detected by the file magic
+    pub enum CaptureFile {
+        Pcap(PcapReader<BufReader<File>>),
+        Pcapng(PcapngReader<BufReader<File>>),
+    }
+
+    impl CaptureFile {
+        pub fn open(path: &str) -> Result<CaptureFile, String> {
+            let mut file = match File::open(path) {
+                Ok(file) => file,
+                Err(e) => return Err(format!("Unable to open capture file {}: {}", path, e))
+            };
+            let mut magic = [0u8; 4];
+            let res = file.read_exact(&mut magic).and_then(|_| file.seek(SeekFrom::Start(0)));
+            if let Err(e) = res {
+                return Err(format!("Unable to read capture file {}: {}", path, e));
+            }
+            return if u32::from_le_bytes(magic) == PCAPNG_SECTION_HEADER {
+                Ok(CaptureFile::Pcapng(PcapngReader::new(BufReader::new(file))?))
+            } else {
+                Ok(CaptureFile::Pcap(PcapReader::new(BufReader::new(file))?))
+            };
+        }
+
+        pub fn next_datagram(&mut self) -> Result<Option<UdpDatagram>, String> {
+            return match self {
+                CaptureFile::Pcap(reader) => reader.next_datagram(),
+                CaptureFile::Pcapng(reader) => reader.next_datagram(),
+            };
+        }
+    }
+
+    /// Packet socket receiving IP packets of all protocols without link layer headers
+    pub struct RawSocket {
+        fd: libc::c_int,
+    }
+
+    impl RawSocket {
+        /// Open socket on the interface, `any` for all interfaces
+        pub fn open(interface: &str) -> Result<RawSocket, String> {
+            let protocol = (libc::ETH_P_ALL as u16).to_be() as libc::c_int;
+            let fd = unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_DGRAM, protocol) };
+            if fd < 0 {
+                return Err(format!("Unable to open raw socket: {}. CAP_NET_RAW is required", std::io::Error::last_os_error()));
+            }
+            let socket = RawSocket { fd };
+
+            // Receive timeout lets the capture loop flush activity when there is no traffic
+            let timeout = libc::timeval { tv_sec: 0, tv_usec: 200_000 };
+            unsafe {
+                libc::setsockopt(fd, libc::SOL_SOCKET, libc::SO_RCVTIMEO, &timeout as *const _ as *const libc::c_void,
+                                 std::mem::size_of::<libc::timeval>() as libc::socklen_t);
+            }
+
+            if interface != "any" {
+                let name = match std::ffi::CString::new(interface) {
+                    Ok(name) => name,
+                    Err(_) => return Err(format!("Invalid interface name {}", interface))
+                };
+                let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+                if index == 0 {
+                    return Err(format!("Interface {} not found", interface));
+                }
+                let mut address: libc::sockaddr_ll = unsafe { std::mem::zeroed() };
+                address.sll_family = libc::AF_PACKET as u16;
+                address.sll_protocol = protocol as u16;
+                address.sll_ifindex = index as i32;
+                let result = unsafe {
+                    libc::bind(fd, &address as *const _ as *const libc::sockaddr, std::mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t)
+                };
+                if result < 0 {
+                    return Err(format!("Unable to bind raw socket to {}: {}", interface, std::io::Error::last_os_error()));
+                }
+            }
+            return Ok(socket);
+        }
+
+        /// The next UDP datagram. None on receive timeout or for packets of other protocols
+        pub fn recv(&mut self, buffer: &mut [u8]) -> Result<Option<UdpDatagram>, String> {
+            let mut address: libc::sockaddr_ll = unsafe { std::mem::zeroed() };
+            let mut address_len = std::mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t;
+            let received = unsafe {
+                libc::recvfrom(self.fd, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len(), 0,
+                               &mut address as *mut _ as *mut libc::sockaddr, &mut address_len)
+            };
+            if received < 0 {
+                let e = std::io::Error::last_os_error();
+                return match e.kind() {
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut | std::io::ErrorKind::Interrupted => Ok(None),
+                    _ => Err(format!("Unable to receive packet: {}", e))
+                };
+            }
+            // Loopback delivers each packet twice, outgoing and incoming. It is seen on `lo` and `any`.
+            // Outgoing packets of other interfaces are seen once and are kept
+            if address.sll_pkttype == libc::PACKET_OUTGOING as u8 && address.sll_hatype == libc::ARPHRD_LOOPBACK {
+                return Ok(None);
+            }
+            return Ok(decode_frame(LinkType::RawIp, &buffer[..received as usize], now_secs()));
+        }
+    }
+
+    impl Drop for RawSocket {
+        fn drop(&mut self) {
+            unsafe {
+                libc::close(self.fd);
+            }
+        }
+    }
+
+    /// Traffic of the single GUID seen on the wire. Participant GUID (prefix only) counts all its messages,
+    /// endpoint GUID (`prefix|entity_id`) counts its submessages
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+    pub struct WireActivity {
+        pub guid: String,
+        /// The last source address, `ip:port`
+        pub source: String,
+        /// RTPS messages sent by the participant
+        pub messages: u64,
+        /// Bytes of RTPS messages sent by the participant
+        pub bytes: u64,
+        /// RTPS messages addressed to the participant by INFO_DST
+        #[serde(default)]
+        pub received_messages: u64,
+        #[serde(default)]
+        pub received_bytes: u64,
+        /// DATA and DATA_FRAG submessages of the writer
+        pub data: u64,
+        /// Bytes of serialized data of the writer
+        pub data_bytes: u64,
+        pub heartbeats: u64,
+        /// ACKNACK submessages of the reader
+        pub acknacks: u64,
+        pub gaps: u64,
+        /// Domain of the RTPS ports the activity was sent to, None if the ports aren't mapped to any domain
+        #[serde(default)]
+        pub port_domain_id: Option<u32>,
+        /// Seconds since unix epoch
+        pub first_seen: f64,
+        pub last_seen: f64,
+    }
+
+    impl WireActivity {
+        pub fn new(guid: String, timestamp: f64) -> WireActivity {
+            return WireActivity { guid, first_seen: timestamp, last_seen: timestamp, ..WireActivity::default() };
+        }
+
+        /// Add counters of the later activity of the same GUID
+        pub fn merge(&mut self, other: &WireActivity) {
+            if !other.source.is_empty() {
+                self.source = other.source.clone();
+            }
+            self.messages += other.messages;
+            self.bytes += other.bytes;
+            self.received_messages += other.received_messages;
+            self.received_bytes += other.received_bytes;
+            self.data += other.data;
+            self.data_bytes += other.data_bytes;
+            self.heartbeats += other.heartbeats;
+            self.acknacks += other.acknacks;
+            self.gaps += other.gaps;
+            if other.port_domain_id.is_some() {
+                self.port_domain_id = other.port_domain_id;
+            }
+            self.first_seen = self.first_seen.min(other.first_seen);
+            self.last_seen = self.last_seen.max(other.last_seen);
+        }
+    }
+
+    /// Accumulates activity of GUIDs from captured datagrams until it is taken
+    #[derive(Default)]
+    pub struct TrafficAttributor {
+        activity: HashMap<String, WireActivity>,
+        /// Port mapping of the monitored system, gives the domain of GUIDs which aren't discovered
+        port_params: PortParams,
+    }
+
+    impl TrafficAttributor {
+        pub fn new(port_params: PortParams) -> TrafficAttributor {
+            return TrafficAttributor { activity: HashMap::new(), port_params };
+        }
+
+        fn entry(&mut self, guid: String, timestamp: f64, port_domain_id: Option<u32>) -> &mut WireActivity {
+            let activity = self.activity.entry(guid.clone()).or_insert_with(|| WireActivity::new(guid, timestamp));
+            activity.first_seen = activity.first_seen.min(timestamp);
+            activity.last_seen = activity.last_seen.max(timestamp);
+            if port_domain_id.is_some() {
+                activity.port_domain_id = port_domain_id;
+            }
+            return activity;
+        }
+
+        /// Attribute the datagram to GUIDs of its sender. Returns false if it isn't an RTPS message
+        /// # Arguments
+        ///
+        /// * `datagram`: captured UDP datagram
+        ///
+        /// returns: bool
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn observe(&mut self, datagram: &UdpDatagram) -> bool {
+            if !is_rtps(datagram.payload.as_slice()) {
+                return false;
+            }
+            let message = match parse_message(datagram.payload.as_slice()) {
+                Ok(message) => message,
+                Err(_) => return false
+            };
+            let timestamp = datagram.timestamp;
+            let domain_id = self.port_params.domain(datagram.destination.port() as u32);
+            let participant = self.entry(hex_str_from_uc(message.header.guid_prefix), timestamp, domain_id);
+            participant.source = datagram.source.to_string();
+            participant.messages += 1;
+            participant.bytes += datagram.payload.len() as u64;
+
+            // INFO_SRC changes the sender of the following submessages
+            let mut source_prefix = message.header.guid_prefix;
+            for submessage in message.submessages.iter() {
+                match submessage {
+                    Submessage::InfoSrc { guid_prefix } => source_prefix = *guid_prefix,
+                    // Unknown destination prefix means all participants reached by the datagram
+                    Submessage::InfoDst { guid_prefix } if *guid_prefix != [0u8; 12] => {
+                        let destination = self.entry(hex_str_from_uc(*guid_prefix), timestamp, domain_id);
+                        destination.received_messages += 1;
+                        destination.received_bytes += datagram.payload.len() as u64;
+                    }
+                    Submessage::Data { writer_id, payload, .. } | Submessage::DataFrag { writer_id, payload, .. } => {
+                        let writer = self.entry(endpoint_guid(&source_prefix, writer_id), timestamp, domain_id);
+                        writer.data += 1;
+                        writer.data_bytes += payload.len() as u64;
+                    }
+                    Submessage::Heartbeat { writer_id, .. } => self.entry(endpoint_guid(&source_prefix, writer_id), timestamp, domain_id).heartbeats += 1,
+                    Submessage::Gap { writer_id, .. } => self.entry(endpoint_guid(&source_prefix, writer_id), timestamp, domain_id).gaps += 1,
+                    Submessage::AckNack { reader_id, .. } => self.entry(endpoint_guid(&source_prefix, reader_id), timestamp, domain_id).acknacks += 1,
+                    _ => {}
+                }
+            }
+            return true;
+        }
+
+        /// Activity accumulated since the previous call
+        pub fn take(&mut self) -> Vec<WireActivity> {
+            return self.activity.drain().map(|(_, activity)| activity).collect();
+        }
+    }
+
+    /// Capture packets until the source is exhausted or `on_activity` returns false. Accumulated activity is passed
+    /// to `on_activity` every `FLUSH_INTERVAL` and at the end of the file
+    /// # Arguments
+    ///
+    /// * `source`: interface or pcap file
+    /// * `port_params`: port mapping of the monitored system
+    /// * `on_activity`: receiver of activity batches, returns false to stop capture
+    ///
+    /// returns: Result<(), String>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn run<F: FnMut(Vec<WireActivity>) -> bool>(source: CaptureSource, port_params: PortParams, mut on_activity: F) -> Result<(), String> {
+        let mut attributor = TrafficAttributor::new(port_params);
+        let mut next_flush = Instant::now() + FLUSH_INTERVAL;
+        match source {
+            CaptureSource::File(path) => {
+                let mut reader = CaptureFile::open(path.as_str())?;
+                info!("Replaying capture file {}", path);
+                while let Some(datagram) = reader.next_datagram()? {
+                    attributor.observe(&datagram);
+                    if Instant::now() >= next_flush {
+                        if !on_activity(attributor.take()) {
+                            return Ok(());
+                        }
+                        next_flush = Instant::now() + FLUSH_INTERVAL;
+                    }
+                }
+                on_activity(attributor.take());
+                info!("Capture file {} is replayed", path);
+            }
+            CaptureSource::Interface(interface) => {
+                let mut socket = RawSocket::open(interface.as_str())?;
+                info!("Capturing RTPS traffic on {}", interface);
+                let mut buffer = vec![0u8; 65536];
+                loop {
+                    match socket.recv(buffer.as_mut_slice()) {
+                        Ok(Some(datagram)) => {
+                            attributor.observe(&datagram);
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            error!("{}", e);
+                            return Err(e);
+                        }
+                    }
+                    if Instant::now() >= next_flush {
+                        let activity = attributor.take();
+                        if !activity.is_empty() && !on_activity(activity) {
+                            warn!("Capture on {} is stopped", interface);
+                            return Ok(());
+                        }
+                        next_flush = Instant::now() + FLUSH_INTERVAL;
+                    }
+                }
+            }
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io::Cursor;
+    use std::net::SocketAddr;
+    use crate::capture::capture::{decode_frame, LinkType, PcapReader, PcapngReader, TrafficAttributor, UdpDatagram};
+    use crate::network::network::PortParams;
+    use crate::rtps::rtps::{ACKNACK, HEARTBEAT, INFO_DST};
+    use crate::rtps::tests::{data, header, submessage, PREFIX, READER, WRITER};
+
+    /// Ethernet frame with IPv4 and UDP headers
+    pub(crate) fn udp_frame(source: SocketAddr, destination: SocketAddr, payload: &[u8]) -> Vec<u8> {
+        let (source_ip, destination_ip) = match (source, destination) {
+            (SocketAddr::V4(source), SocketAddr::V4(destination)) => (source.ip().octets(), destination.ip().octets()),
+            _ => panic!("IPv4 addresses are expected")
+        };
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&[0x08, 0x00]);
+        let total_len = (20 + 8 + payload.len()) as u16;
+        frame.extend_from_slice(&[0x45, 0]);
+        frame.extend_from_slice(&total_len.to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0x40, 0, 64, 17, 0, 0]);
+        frame.extend_from_slice(&source_ip);
+        frame.extend_from_slice(&destination_ip);
+        frame.extend_from_slice(&source.port().to_be_bytes());
+        frame.extend_from_slice(&destination.port().to_be_bytes());
+        frame.extend_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(payload);
+        return frame;
+    }
+
+    /// Little endian pcap file with Ethernet link type
+    pub(crate) fn pcap_file(frames: &[(f64, Vec<u8>)]) -> Vec<u8> {
+        let mut file = vec![0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0];
+        file.extend_from_slice(&[0; 8]);
+        file.extend_from_slice(&65535u32.to_le_bytes());
+        file.extend_from_slice(&1u32.to_le_bytes());
+        for (timestamp, frame) in frames {
+            file.extend_from_slice(&(timestamp.trunc() as u32).to_le_bytes());
+            file.extend_from_slice(&((timestamp.fract() * 1e6).round() as u32).to_le_bytes());
+            file.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            file.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            file.extend_from_slice(frame.as_slice());
+        }
+        return file;
+    }
+
+    /// Little endian pcapng file with a single Ethernet interface and nanosecond timestamps
+    pub(crate) fn pcapng_file(frames: &[(f64, Vec<u8>)]) -> Vec<u8> {
+        let block = |file: &mut Vec<u8>, block_type: u32, body: &[u8]| {
+            let mut body = body.to_vec();
+            body.resize((body.len() + 3) / 4 * 4, 0);
+            let total_len = (body.len() + 12) as u32;
+            file.extend_from_slice(&block_type.to_le_bytes());
+            file.extend_from_slice(&total_len.to_le_bytes());
+            file.extend_from_slice(body.as_slice());
+            file.extend_from_slice(&total_len.to_le_bytes());
+        };
+        let mut file = Vec::new();
+        let mut section = 0x1a2b3c4du32.to_le_bytes().to_vec();
+        section.extend_from_slice(&[1, 0, 0, 0]);
+        section.extend_from_slice(&(-1i64).to_le_bytes());
+        block(&mut file, 0x0a0d0d0a, section.as_slice());
+        let mut interface = vec![1, 0, 0, 0];
+        interface.extend_from_slice(&65535u32.to_le_bytes());
+        interface.extend_from_slice(&[9, 0, 1, 0, 9, 0, 0, 0, 0, 0, 0, 0]);
+        block(&mut file, 1, interface.as_slice());
+        for (timestamp, frame) in frames {
+            let ticks = (timestamp * 1e9).round() as u64;
+            let mut packet = 0u32.to_le_bytes().to_vec();
+            packet.extend_from_slice(&((ticks >> 32) as u32).to_le_bytes());
+            packet.extend_from_slice(&(ticks as u32).to_le_bytes());
+            packet.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            packet.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            packet.extend_from_slice(frame.as_slice());
+            block(&mut file, 6, packet.as_slice());
+        }
+        return file;
+    }
+
+    #[test]
+    fn read_pcapng() {
+        let source: SocketAddr = "192.168.1.10:45000".parse().unwrap();
+        let destination: SocketAddr = "239.255.0.1:7400".parse().unwrap();
+        let file = pcapng_file(&[(1.25, udp_frame(source, destination, b"first")), (2.5, udp_frame(source, destination, b"second"))]);
+        let mut reader = PcapngReader::new(Cursor::new(file)).unwrap();
+        let first = reader.next_datagram().unwrap().unwrap();
+        assert_eq!((first.timestamp, first.payload), (1.25, b"first".to_vec()));
+        assert_eq!(reader.next_datagram().unwrap().unwrap().payload, b"second".to_vec());
+        assert_eq!(reader.next_datagram().unwrap(), None);
+        assert!(PcapngReader::new(Cursor::new(pcap_file(&[]))).is_err());
+    }
+
+    #[test]
+    fn decode_frames() {
+        let source: SocketAddr = "192.168.1.10:45000".parse().unwrap();
+        let destination: SocketAddr = "239.255.0.1:7401".parse().unwrap();
+        let frame = udp_frame(source, destination, b"payload");
+        assert_eq!(decode_frame(LinkType::Ethernet, frame.as_slice(), 1.5), Some(UdpDatagram {
+            timestamp: 1.5,
+            source,
+            destination,
+            payload: b"payload".to_vec(),
+        }));
+        // The same packet without link layer
+        assert_eq!(decode_frame(LinkType::RawIp, &frame[14..], 1.5).unwrap().payload, b"payload".to_vec());
+        // Not the first fragment
+        let mut fragment = frame.clone();
+        fragment[14 + 7] = 0x10;
+        assert_eq!(decode_frame(LinkType::Ethernet, fragment.as_slice(), 1.5), None);
+        // ARP
+        let mut arp = frame.clone();
+        arp[12..14].copy_from_slice(&[0x08, 0x06]);
+        assert_eq!(decode_frame(LinkType::Ethernet, arp.as_slice(), 1.5), None);
+    }
+
+    #[test]
+    fn attribute_pcap_traffic() {
+        let mut message = header(PREFIX);
+        data(&mut message, WRITER, 1, &[0x00, 0x01, 0x00, 0x00, 1, 2, 3, 4]);
+        let mut heartbeat = vec![0, 0, 0, 0];
+        heartbeat.extend_from_slice(&WRITER);
+        heartbeat.extend_from_slice(&[0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0]);
+        submessage(&mut message, HEARTBEAT, 0, heartbeat.as_slice());
+
+        let reader_prefix = [0x01, 0x0f, 0xcc, 0xdd, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00];
+        let mut reply = header(reader_prefix);
+        submessage(&mut reply, INFO_DST, 0, &PREFIX);
+        let mut acknack = READER.to_vec();
+        acknack.extend_from_slice(&WRITER);
+        acknack.extend_from_slice(&[0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0]);
+        submessage(&mut reply, ACKNACK, 0, acknack.as_slice());
+
+        // User unicast ports of participants 0 and 1 of domain 1
+        let writer_address: SocketAddr = "192.168.1.10:7661".parse().unwrap();
+        let reader_address: SocketAddr = "192.168.1.20:7663".parse().unwrap();
+        let file = pcap_file(&[
+            (10.0, udp_frame(writer_address, reader_address, message.as_slice())),
+            (10.5, udp_frame(reader_address, writer_address, reply.as_slice())),
+            (11.0, udp_frame(writer_address, reader_address, message.as_slice())),
+            (11.5, udp_frame(writer_address, reader_address, b"not rtps")),
+        ]);
+
+        let mut reader = PcapReader::new(Cursor::new(file)).unwrap();
+        let mut attributor = TrafficAttributor::new(PortParams::default());
+        let mut rtps_num = 0;
+        while let Some(datagram) = reader.next_datagram().unwrap() {
+            if attributor.observe(&datagram) {
+                rtps_num += 1;
+            }
+        }
+        assert_eq!(rtps_num, 3);
+
+        let mut activity = attributor.take();
+        activity.sort_by(|a, b| a.guid.cmp(&b.guid));
+        let guids: Vec<&str> = activity.iter().map(|activity| activity.guid.as_str()).collect();
+        assert_eq!(guids, vec![
+            "01.0f.aa.bb.39.30.00.00.01.00.00.00",
+            "01.0f.aa.bb.39.30.00.00.01.00.00.00|00.00.12.03",
+            "01.0f.cc.dd.01.00.00.00.01.00.00.00",
+            "01.0f.cc.dd.01.00.00.00.01.00.00.00|00.00.13.04",
+        ]);
+        let participant = &activity[0];
+        assert_eq!((participant.messages, participant.bytes), (2, 2 * message.len() as u64));
+        assert_eq!(participant.source, "192.168.1.10:7661");
+        assert_eq!(participant.port_domain_id, Some(1));
+        assert_eq!((participant.received_messages, participant.received_bytes), (1, reply.len() as u64));
+        assert_eq!((participant.first_seen, participant.last_seen), (10.0, 11.0));
+        let writer = &activity[1];
+        assert_eq!((writer.data, writer.data_bytes, writer.heartbeats), (2, 16, 2));
+        assert_eq!(activity[3].acknacks, 1);
+        assert!(attributor.take().is_empty());
+    }
+}
@@ -0,0 +1,722 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/// CDR serialization of samples without compiled type support. Layout of the sample is taken from the interface
+/// types, values are converted between CDR bytes and json: messages are objects, arrays and sequences are arrays.
+/// XCDR1 and XCDR2 in both byte orders are supported, XCDR2 structs may be delimited (appendable types).
+pub mod cdr {
+    use serde_json::{Map, Number, Value};
+    use crate::interfaces::interfaces::{Collection, FieldType, TypeInfo};
+
+    /// Representation identifiers of the encapsulation header
+    const CDR_BE: [u8; 2] = [0x00, 0x00];
+    const CDR_LE: [u8; 2] = [0x00, 0x01];
+    const CDR2_BE: [u8; 2] = [0x00, 0x06];
+    const CDR2_LE: [u8; 2] = [0x00, 0x07];
+    const D_CDR2_BE: [u8; 2] = [0x00, 0x08];
+    const D_CDR2_LE: [u8; 2] = [0x00, 0x09];
+
+    /// Size of the encapsulation header: representation identifier and options
+    pub const ENCAPSULATION_SIZE: usize = 4;
+    /// Nesting of messages deeper than this is rejected, e.g. of recursive types
+    const MAX_DEPTH: usize = 64;
+    /// Sequences longer than this are rejected, elements may take no bytes
+    const MAX_SEQUENCE_LENGTH: usize = 1 << 20;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum CdrVersion {
+        /// Classic CDR, primitives are aligned to their size
+        Xcdr1,
+        /// Primitives are aligned to 4 bytes at most, collections of non-primitive elements have their size (DHEADER)
+        Xcdr2,
+    }
+
+    /// Representation of the serialized sample
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct Encapsulation {
+        pub version: CdrVersion,
+        pub little_endian: bool,
+        /// Structs are preceded by their size (DHEADER), as appendable types of XCDR2
+        pub delimited: bool,
+    }
+
+    impl Encapsulation {
+        pub fn new(version: CdrVersion, little_endian: bool) -> Encapsulation {
+            return Encapsulation { version, little_endian, delimited: false };
+        }
+
+        /// Representation of the encapsulation header. Parameter lists of mutable types aren't supported
+        pub fn from_header(header: &[u8]) -> Result<Encapsulation, String> {
+            if header.len() < ENCAPSULATION_SIZE {
+                return Err("Serialized data is too short".to_string());
+            }
+            let representation = [header[0], header[1]];
+            let (version, little_endian, delimited) = match representation {
+                CDR_BE => (CdrVersion::Xcdr1, false, false),
+                CDR_LE => (CdrVersion::Xcdr1, true, false),
+                CDR2_BE => (CdrVersion::Xcdr2, false, false),
+                CDR2_LE => (CdrVersion::Xcdr2, true, false),
+                D_CDR2_BE => (CdrVersion::Xcdr2, false, true),
+                D_CDR2_LE => (CdrVersion::Xcdr2, true, true),
+                [a, b] => return Err(format!("Unsupported encapsulation {:02x}{:02x}", a, b))
+            };
+            return Ok(Encapsulation { version, little_endian, delimited });
+        }
+
+        /// Representation identifier of the encapsulation header
+        pub fn representation(&self) -> [u8; 2] {
+            return match (self.version, self.delimited, self.little_endian) {
+                (CdrVersion::Xcdr1, _, false) => CDR_BE,
+                (CdrVersion::Xcdr1, _, true) => CDR_LE,
+                (CdrVersion::Xcdr2, false, false) => CDR2_BE,
+                (CdrVersion::Xcdr2, false, true) => CDR2_LE,
+                (CdrVersion::Xcdr2, true, false) => D_CDR2_BE,
+                (CdrVersion::Xcdr2, true, true) => D_CDR2_LE,
+            };
+        }
+
+        fn max_align(&self) -> usize {
+            return match self.version {
+                CdrVersion::Xcdr1 => 8,
+                CdrVersion::Xcdr2 => 4,
+            };
+        }
+    }
+
+    /// Reader of CDR encoded values. Alignment is relative to the start of the data
+    pub struct CdrReader<'a> {
+        data: &'a [u8],
+        pos: usize,
+        little_endian: bool,
+        max_align: usize,
+    }
+
+    impl<'a> CdrReader<'a> {
+        /// Reader of XCDR1 data, e.g. of discovery parameters
+        pub fn new(data: &'a [u8], little_endian: bool) -> CdrReader<'a> {
+            return CdrReader { data, pos: 0, little_endian, max_align: 8 };
+        }
+
+        pub fn with_encapsulation(data: &'a [u8], encapsulation: &Encapsulation) -> CdrReader<'a> {
+            return CdrReader { data, pos: 0, little_endian: encapsulation.little_endian, max_align: encapsulation.max_align() };
+        }
+
+        fn align(&mut self, size: usize) {
+            let align = size.min(self.max_align);
+            self.pos = (self.pos + align - 1) / align * align;
+        }
+
+        pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+            if self.pos + len > self.data.len() {
+                return Err(format!("Value is truncated: {} bytes needed at {}, {} available", len, self.pos, self.data.len()));
+            }
+            let bytes = &self.data[self.pos..self.pos + len];
+            self.pos += len;
+            return Ok(bytes);
+        }
+
+        /// Aligned primitive in little endian order
+        fn primitive<const N: usize>(&mut self) -> Result<[u8; N], String> {
+            self.align(N);
+            let mut bytes: [u8; N] = self.bytes(N)?.try_into().unwrap();
+            if !self.little_endian {
+                bytes.reverse();
+            }
+            return Ok(bytes);
+        }
+
+        pub fn u8(&mut self) -> Result<u8, String> {
+            return Ok(self.bytes(1)?[0]);
+        }
+
+        pub fn u16(&mut self) -> Result<u16, String> {
+            return Ok(u16::from_le_bytes(self.primitive()?));
+        }
+
+        pub fn u32(&mut self) -> Result<u32, String> {
+            return Ok(u32::from_le_bytes(self.primitive()?));
+        }
+
+        pub fn i32(&mut self) -> Result<i32, String> {
+            return Ok(self.u32()? as i32);
+        }
+
+        pub fn u64(&mut self) -> Result<u64, String> {
+            return Ok(u64::from_le_bytes(self.primitive()?));
+        }
+
+        /// String with its length including the terminating zero
+        pub fn string(&mut self) -> Result<String, String> {
+            let len = self.u32()? as usize;
+            let bytes = self.bytes(len)?;
+            let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+            return Ok(String::from_utf8_lossy(bytes).to_string());
+        }
+
+        pub fn remaining(&self) -> usize {
+            return self.data.len().saturating_sub(self.pos);
+        }
+    }
+
+    /// Writer of CDR encoded values. Alignment is relative to the start of the data
+    struct CdrWriter {
+        data: Vec<u8>,
+        little_endian: bool,
+        max_align: usize,
+    }
+
+    impl CdrWriter {
+        fn align(&mut self, size: usize) {
+            let align = size.min(self.max_align);
+            let len = (self.data.len() + align - 1) / align * align;
+            self.data.resize(len, 0);
+        }
+
+        /// Aligned primitive given in little endian order
+        fn primitive<const N: usize>(&mut self, mut bytes: [u8; N]) {
+            self.align(N);
+            if !self.little_endian {
+                bytes.reverse();
+            }
+            self.data.extend_from_slice(&bytes);
+        }
+
+        fn u32(&mut self, value: u32) {
+            self.primitive(value.to_le_bytes());
+        }
+
+        /// Placeholder of DHEADER, returns its position
+        fn begin_delimited(&mut self) -> usize {
+            self.u32(0);
+            return self.data.len();
+        }
+
+        /// Set DHEADER to the size of the data written after it
+        fn end_delimited(&mut self, start: usize) {
+            let size = (self.data.len() - start) as u32;
+            let bytes = if self.little_endian { size.to_le_bytes() } else { size.to_be_bytes() };
+            self.data[start - 4..start].copy_from_slice(&bytes);
+        }
+    }
+
+    /// Strings and nested messages. Their arrays and sequences have DHEADER in XCDR2
+    fn is_primitive_element(field_type: &FieldType) -> bool {
+        return field_type.is_primitive() && field_type.base != "string" && field_type.base != "wstring";
+    }
+
+    /// NaN and infinities have no json representation, they are strings as in JavaScript
+    fn number<T: Into<f64>>(value: T) -> Value {
+        let value = value.into();
+        return match Number::from_f64(value) {
+            Some(number) => Value::Number(number),
+            None if value.is_nan() => Value::String("NaN".to_string()),
+            None if value > 0.0 => Value::String("Infinity".to_string()),
+            None => Value::String("-Infinity".to_string())
+        };
+    }
+
+    struct Decoder<'a> {
+        types: &'a TypeInfo,
+        reader: CdrReader<'a>,
+        encapsulation: Encapsulation,
+        /// Number of messages being decoded
+        depth: usize,
+    }
+
+    impl<'a> Decoder<'a> {
+        fn message(&mut self, name: &str) -> Result<Value, String> {
+            if self.depth >= MAX_DEPTH {
+                return Err(format!("{} is nested deeper than {}", name, MAX_DEPTH));
+            }
+            self.depth += 1;
+            let value = self.fields(name);
+            self.depth -= 1;
+            return value;
+        }
+
+        fn fields(&mut self, name: &str) -> Result<Value, String> {
+            let message = self.types.message(name).ok_or(format!("Type {} is unresolved", name))?;
+            let end = if self.encapsulation.delimited {
+                let size = self.reader.u32()? as usize;
+                if size > self.reader.remaining() {
+                    return Err(format!("Invalid size {} of {}", size, name));
+                }
+                Some(self.reader.pos + size)
+            } else {
+                None
+            };
+            let mut fields = Map::new();
+            for field in message.fields.iter() {
+                let value = self.field(&field.field_type).map_err(|e| format!("{}.{}: {}", name, field.name, e))?;
+                fields.insert(field.name.clone(), value);
+            }
+            // Members appended in newer versions of the type are skipped
+            if let Some(end) = end {
+                if self.reader.pos > end {
+                    return Err(format!("{} is longer than its size", name));
+                }
+                self.reader.pos = end;
+            }
+            return Ok(Value::Object(fields));
+        }
+
+        fn field(&mut self, field_type: &FieldType) -> Result<Value, String> {
+            let len = match field_type.collection {
+                Collection::Single => return self.single(field_type),
+                Collection::Array(size) => {
+                    self.collection_header(field_type)?;
+                    size
+                }
+                Collection::Sequence(bound) => {
+                    self.collection_header(field_type)?;
+                    let len = self.reader.u32()? as usize;
+                    if bound.map_or(false, |bound| len > bound) {
+                        return Err(format!("Sequence length {} exceeds its bound {}", len, bound.unwrap()));
+                    }
+                    // Primitives and strings take at least a byte, nested messages may be empty
+                    let min_size = if field_type.is_primitive() { 1 } else { 0 };
+                    if len > MAX_SEQUENCE_LENGTH || len * min_size > self.reader.remaining() {
+                        return Err(format!("Invalid sequence length {}", len));
+                    }
+                    len
+                }
+            };
+            let mut values = Vec::with_capacity(len);
+            for _ in 0..len {
+                values.push(self.single(field_type)?);
+            }
+            return Ok(Value::Array(values));
+        }
+
+        fn collection_header(&mut self, field_type: &FieldType) -> Result<(), String> {
+            if self.encapsulation.version == CdrVersion::Xcdr2 && !is_primitive_element(field_type) {
+                self.reader.u32()?;
+            }
+            return Ok(());
+        }
+
+        fn single(&mut self, field_type: &FieldType) -> Result<Value, String> {
+            let reader = &mut self.reader;
+            let value = match field_type.base.as_str() {
+                "bool" => Value::Bool(reader.u8()? != 0),
+                "byte" | "char" | "uint8" => Value::from(reader.u8()?),
+                "int8" => Value::from(reader.u8()? as i8),
+                "int16" => Value::from(reader.u16()? as i16),
+                "uint16" => Value::from(reader.u16()?),
+                "int32" => Value::from(reader.i32()?),
+                "uint32" => Value::from(reader.u32()?),
+                "int64" => Value::from(reader.u64()? as i64),
+                "uint64" => Value::from(reader.u64()?),
+                "float32" => number(f32::from_bits(reader.u32()?)),
+                "float64" => number(f64::from_bits(reader.u64()?)),
+                "string" => Value::String(reader.string()?),
+                "wchar" => Value::String(String::from_utf16_lossy(&[reader.u16()?])),
+                "wstring" => Value::String(self.wstring()?),
+                nested => return self.message(nested)
+            };
+            return Ok(value);
+        }
+
+        /// Wide string is UTF-16 with its length in bytes in XCDR2, and 4 byte characters with their count in XCDR1 as
+        /// Fast CDR serializes `wchar_t`
+        fn wstring(&mut self) -> Result<String, String> {
+            let len = self.reader.u32()? as usize;
+            if len > self.reader.remaining() {
+                return Err(format!("Invalid wstring length {}", len));
+            }
+            return match self.encapsulation.version {
+                CdrVersion::Xcdr1 => (0..len).map(|_| self.reader.u32().map(|c| char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER)))
+                    .collect(),
+                CdrVersion::Xcdr2 => {
+                    let units: Result<Vec<u16>, String> = (0..len / 2).map(|_| self.reader.u16()).collect();
+                    Ok(String::from_utf16_lossy(units?.as_slice()))
+                }
+            };
+        }
+    }
+
+    /// Decode serialized sample to json
+    /// # Arguments
+    ///
+    /// * `types`: the message type with its dependencies
+    /// * `message`: full name of the sample type, e.g. `example_interfaces/srv/AddTwoInts_Request`
+    /// * `data`: serialized sample with encapsulation header
+    ///
+    /// returns: Result<Value, String>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn decode(types: &TypeInfo, message: &str, data: &[u8]) -> Result<Value, String> {
+        let encapsulation = Encapsulation::from_header(data)?;
+        let reader = CdrReader::with_encapsulation(&data[ENCAPSULATION_SIZE..], &encapsulation);
+        let mut decoder = Decoder { types, reader, encapsulation, depth: 0 };
+        return decoder.message(message);
+    }
+
+    struct Encoder<'a> {
+        types: &'a TypeInfo,
+        writer: CdrWriter,
+        encapsulation: Encapsulation,
+        /// Number of messages being encoded
+        depth: usize,
+    }
+
+    impl<'a> Encoder<'a> {
+        fn message(&mut self, name: &str, value: &Value) -> Result<(), String> {
+            if self.depth >= MAX_DEPTH {
+                return Err(format!("{} is nested deeper than {}", name, MAX_DEPTH));
+            }
+            self.depth += 1;
+            let result = self.fields(name, value);
+            self.depth -= 1;
+            return result;
+        }
+
+        /// Fields missing in the object take default values of the definition, or zero values
+        fn fields(&mut self, name: &str, value: &Value) -> Result<(), String> {
+            let message = self.types.message(name).ok_or(format!("Type {} is unresolved", name))?;
+            let empty = Map::new();
+            let object = match value {
+                Value::Object(object) => object,
+                Value::Null => &empty,
+                _ => return Err(format!("{} must be an object", name))
+            };
+            if let Some(unknown) = object.keys().find(|key| !message.fields.iter().any(|field| &field.name == *key)) {
+                return Err(format!("Unknown field {} of {}", unknown, name));
+            }
+
+            let start = if self.encapsulation.delimited { Some(self.writer.begin_delimited()) } else { None };
+            for field in message.fields.iter() {
+                let error = |e: String| format!("{}.{}: {}", name, field.name, e);
+                let default = match (object.get(field.name.as_str()), field.default.as_deref()) {
+                    (Some(value), _) => value.clone(),
+                    (None, Some(default)) => default_value(&field.field_type, default).map_err(error)?,
+                    (None, None) => Value::Null
+                };
+                self.field(&field.field_type, &default).map_err(error)?;
+            }
+            if let Some(start) = start {
+                self.writer.end_delimited(start);
+            }
+            return Ok(());
+        }
+
+        fn field(&mut self, field_type: &FieldType, value: &Value) -> Result<(), String> {
+            let values = match (field_type.collection, value) {
+                (Collection::Single, _) => return self.single(field_type, value),
+                (_, Value::Array(values)) => values.as_slice(),
+                (_, Value::Null) => &[],
+                _ => return Err("Array is expected".to_string())
+            };
+            let start = if self.encapsulation.version == CdrVersion::Xcdr2 && !is_primitive_element(field_type) {
+                Some(self.writer.begin_delimited())
+            } else {
+                None
+            };
+            match field_type.collection {
+                Collection::Array(size) if values.is_empty() => {
+                    for _ in 0..size {
+                        self.single(field_type, &Value::Null)?;
+                    }
+                }
+                Collection::Array(size) if values.len() != size => {
+                    return Err(format!("Array must have {} elements, got {}", size, values.len()));
+                }
+                Collection::Sequence(Some(bound)) if values.len() > bound => {
+                    return Err(format!("Sequence length {} exceeds its bound {}", values.len(), bound));
+                }
+                Collection::Sequence(_) => self.writer.u32(values.len() as u32),
+                _ => {}
+            }
+            for value in values {
+                self.single(field_type, value)?;
+            }
+            if let Some(start) = start {
+                self.writer.end_delimited(start);
+            }
+            return Ok(());
+        }
+
+        /// Single value, null is the zero value of the type
+        fn single(&mut self, field_type: &FieldType, value: &Value) -> Result<(), String> {
+            let writer = &mut self.writer;
+            match field_type.base.as_str() {
+                "bool" => writer.primitive([match value {
+                    Value::Bool(value) => *value as u8,
+                    Value::Null => 0,
+                    _ => return Err(format!("Boolean is expected, got {}", value))
+                }]),
+                "byte" | "char" | "uint8" => writer.primitive([integer(value, 0, u8::MAX as i128)? as u8]),
+                "int8" => writer.primitive((integer(value, i8::MIN as i128, i8::MAX as i128)? as i8).to_le_bytes()),
+                "int16" => writer.primitive((integer(value, i16::MIN as i128, i16::MAX as i128)? as i16).to_le_bytes()),
+                "uint16" => writer.primitive((integer(value, 0, u16::MAX as i128)? as u16).to_le_bytes()),
+                "int32" => writer.primitive((integer(value, i32::MIN as i128, i32::MAX as i128)? as i32).to_le_bytes()),
+                "uint32" => writer.primitive((integer(value, 0, u32::MAX as i128)? as u32).to_le_bytes()),
+                "int64" => writer.primitive((integer(value, i64::MIN as i128, i64::MAX as i128)? as i64).to_le_bytes()),
+                "uint64" => writer.primitive((integer(value, 0, u64::MAX as i128)? as u64).to_le_bytes()),
+                "float32" => writer.primitive((float(value)? as f32).to_le_bytes()),
+                "float64" => writer.primitive(float(value)?.to_le_bytes()),
+                "string" => {
+                    let value = string(value, field_type.string_bound)?;
+                    writer.u32(value.len() as u32 + 1);
+                    writer.data.extend_from_slice(value.as_bytes());
+                    writer.data.push(0);
+                }
+                "wchar" => {
+                    let units: Vec<u16> = string(value, Some(1))?.encode_utf16().collect();
+                    if units.len() > 1 {
+                        return Err(format!("Character {} doesn't fit into wchar", value));
+                    }
+                    writer.primitive(units.first().copied().unwrap_or(0).to_le_bytes());
+                }
+                "wstring" => {
+                    let value = string(value, field_type.string_bound)?;
+                    match self.encapsulation.version {
+                        CdrVersion::Xcdr1 => {
+                            writer.u32(value.chars().count() as u32);
+                            value.chars().for_each(|c| writer.u32(c as u32));
+                        }
+                        CdrVersion::Xcdr2 => {
+                            let units: Vec<u16> = value.encode_utf16().collect();
+                            writer.u32(units.len() as u32 * 2);
+                            units.iter().for_each(|unit| writer.primitive(unit.to_le_bytes()));
+                        }
+                    }
+                }
+                nested => self.message(nested, value)?
+            }
+            return Ok(());
+        }
+    }
+
+    fn integer(value: &Value, min: i128, max: i128) -> Result<i128, String> {
+        let number = match value {
+            Value::Null => Some(0),
+            Value::Number(number) => number.as_i64().map(|n| n as i128).or(number.as_u64().map(|n| n as i128)),
+            _ => None
+        };
+        return match number {
+            Some(number) if number >= min && number <= max => Ok(number),
+            Some(number) => Err(format!("{} is out of range {}..{}", number, min, max)),
+            None => Err(format!("Integer is expected, got {}", value))
+        };
+    }
+
+    /// Number, or NaN and infinities as strings
+    fn float(value: &Value) -> Result<f64, String> {
+        return match value {
+            Value::Null => Ok(0.0),
+            Value::Number(number) => Ok(number.as_f64().unwrap_or(0.0)),
+            Value::String(text) if text == "NaN" => Ok(f64::NAN),
+            Value::String(text) if text == "Infinity" => Ok(f64::INFINITY),
+            Value::String(text) if text == "-Infinity" => Ok(f64::NEG_INFINITY),
+            _ => Err(format!("Number is expected, got {}", value))
+        };
+    }
+
+    fn string(value: &Value, bound: Option<usize>) -> Result<String, String> {
+        let value = match value {
+            Value::Null => "",
+            Value::String(value) => value.as_str(),
+            _ => return Err(format!("String is expected, got {}", value))
+        };
+        if bound.map_or(false, |bound| value.chars().count() > bound) {
+            return Err(format!("String {} exceeds its bound {}", value, bound.unwrap()));
+        }
+        return Ok(value.to_string());
+    }
+
+    /// Json value of the default written in the definition, e.g. `"text"`, `true` or `[1, 2]`
+    fn default_value(field_type: &FieldType, default: &str) -> Result<Value, String> {
+        let default = default.trim();
+        let unquoted = [('"', '"'), ('\'', '\'')].iter()
+            .find_map(|(open, close)| default.strip_prefix(*open).and_then(|value| value.strip_suffix(*close)));
+        let is_string = field_type.base == "string" || field_type.base == "wstring" || field_type.base == "wchar";
+        if is_string && field_type.collection == Collection::Single {
+            return Ok(Value::String(unquoted.unwrap_or(default).to_string()));
+        }
+        // Arrays of strings may be quoted with single quotes
+        let json = if is_string { default.replace('\'', "\"") } else { default.to_lowercase() };
+        return serde_json::from_str(json.as_str()).map_err(|_| format!("Invalid default value {}", default));
+    }
+
+    /// Encode json sample to CDR
+    /// # Arguments
+    ///
+    /// * `types`: the message type with its dependencies
+    /// * `message`: full name of the sample type
+    /// * `value`: json object, missing fields take default values of the definition
+    /// * `encapsulation`: representation of the result
+    ///
+    /// returns: Result<Vec<u8>, String>, serialized sample with encapsulation header
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn encode(types: &TypeInfo, message: &str, value: &Value, encapsulation: Encapsulation) -> Result<Vec<u8>, String> {
+        let writer = CdrWriter { data: Vec::new(), little_endian: encapsulation.little_endian, max_align: encapsulation.max_align() };
+        let mut encoder = Encoder { types, writer, encapsulation, depth: 0 };
+        encoder.message(message, value)?;
+
+        let mut data = encapsulation.representation().to_vec();
+        let body = encoder.writer.data;
+        // XCDR2 data is padded to 4 bytes, the number of padding bytes is in the options
+        let padding = match encapsulation.version {
+            CdrVersion::Xcdr1 => 0,
+            CdrVersion::Xcdr2 => (4 - body.len() % 4) % 4,
+        };
+        data.extend_from_slice(&[0x00, padding as u8]);
+        data.extend_from_slice(body.as_slice());
+        data.resize(data.len() + padding, 0);
+        return Ok(data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::cdr::cdr::{decode, encode, CdrVersion, Encapsulation};
+    use crate::interfaces::interfaces::{parse_message, InterfaceKind, InterfaceName, TypeInfo};
+
+    /// Type info of the first definition with the rest as its dependencies
+    fn type_info(definitions: &[(&str, &str)]) -> TypeInfo {
+        let mut messages: Vec<_> = definitions.iter()
+            .map(|(name, text)| parse_message(&InterfaceName::parse(name).unwrap(), text).unwrap())
+            .collect();
+        let dependencies = messages.split_off(1);
+        return TypeInfo {
+            name: messages[0].name.clone(),
+            kind: InterfaceKind::Message,
+            source: "test".to_string(),
+            messages,
+            dependencies,
+            unresolved: Vec::new(),
+        };
+    }
+
+    #[test]
+    fn string_message() {
+        let types = type_info(&[("std_msgs/msg/String", "string data")]);
+        let data = [0x00, 0x01, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, b'h', b'e', b'l', b'l', b'o', 0x00];
+        assert_eq!(decode(&types, "std_msgs/msg/String", &data).unwrap(), json!({"data": "hello"}));
+        let encapsulation = Encapsulation::new(CdrVersion::Xcdr1, true);
+        assert_eq!(encode(&types, "std_msgs/msg/String", &json!({"data": "hello"}), encapsulation).unwrap(), data.to_vec());
+
+        assert!(decode(&types, "std_msgs/msg/String", &data[..10]).is_err());
+        assert!(decode(&types, "std_msgs/msg/String", &[0x00, 0x03, 0x00, 0x00]).is_err());
+        assert!(encode(&types, "std_msgs/msg/String", &json!({"text": "hello"}), encapsulation).is_err());
+        assert!(encode(&types, "std_msgs/msg/String", &json!({"data": 5}), encapsulation).is_err());
+    }
+
+    #[test]
+    fn alignment() {
+        let types = type_info(&[("demo/msg/Aligned", "int8 a\nfloat64 b\nint16 c")]);
+        let value = json!({"a": -1, "b": 1.5, "c": 258});
+        let b = 1.5f64.to_le_bytes();
+
+        // XCDR1 aligns float64 to 8 bytes
+        let mut xcdr1 = vec![0x00, 0x01, 0x00, 0x00, 0xff, 0, 0, 0, 0, 0, 0, 0];
+        xcdr1.extend_from_slice(&b);
+        xcdr1.extend_from_slice(&[0x02, 0x01]);
+        assert_eq!(encode(&types, "demo/msg/Aligned", &value, Encapsulation::new(CdrVersion::Xcdr1, true)).unwrap(), xcdr1);
+        assert_eq!(decode(&types, "demo/msg/Aligned", &xcdr1).unwrap(), value);
+
+        // XCDR2 aligns it to 4 bytes and pads the data to 4 bytes
+        let mut xcdr2 = vec![0x00, 0x07, 0x00, 0x02, 0xff, 0, 0, 0];
+        xcdr2.extend_from_slice(&b);
+        xcdr2.extend_from_slice(&[0x02, 0x01, 0, 0]);
+        assert_eq!(encode(&types, "demo/msg/Aligned", &value, Encapsulation::new(CdrVersion::Xcdr2, true)).unwrap(), xcdr2);
+        assert_eq!(decode(&types, "demo/msg/Aligned", &xcdr2).unwrap(), value);
+
+        let mut big_endian = vec![0x00, 0x00, 0x00, 0x00, 0xff, 0, 0, 0, 0, 0, 0, 0];
+        big_endian.extend_from_slice(&1.5f64.to_be_bytes());
+        big_endian.extend_from_slice(&[0x01, 0x02]);
+        assert_eq!(encode(&types, "demo/msg/Aligned", &value, Encapsulation::new(CdrVersion::Xcdr1, false)).unwrap(), big_endian);
+        assert_eq!(decode(&types, "demo/msg/Aligned", &big_endian).unwrap(), value);
+    }
+
+    #[test]
+    fn delimited_xcdr2() {
+        let types = type_info(&[("demo/msg/Outer", "Inner inner\nstring[] names"), ("demo/msg/Inner", "uint16 x")]);
+        let value = json!({"inner": {"x": 7}, "names": ["a", "b"]});
+        let data = vec![0x00, 0x09, 0x00, 0x02,
+                        30, 0, 0, 0, // DHEADER of Outer
+                        2, 0, 0, 0, 7, 0, 0, 0, // DHEADER of Inner, x and padding
+                        18, 0, 0, 0, 2, 0, 0, 0, // DHEADER of names and their count
+                        2, 0, 0, 0, b'a', 0, 0, 0,
+                        2, 0, 0, 0, b'b', 0, 0, 0];
+        let encapsulation = Encapsulation { version: CdrVersion::Xcdr2, little_endian: true, delimited: true };
+        assert_eq!(encode(&types, "demo/msg/Outer", &value, encapsulation).unwrap(), data);
+        assert_eq!(decode(&types, "demo/msg/Outer", &data).unwrap(), value);
+
+        // Members appended to Inner by the newer version of the type are skipped
+        let mut appended = data.clone();
+        appended[4] = 34;
+        appended[8] = 6;
+        appended.splice(14..16, [0xaa, 0xbb, 0xcc, 0xdd, 0, 0]);
+        assert_eq!(decode(&types, "demo/msg/Outer", &appended).unwrap(), value);
+    }
+
+    #[test]
+    fn collections_and_defaults() {
+        let types = type_info(&[("demo/msg/Defaults", "int32[2] pair\nuint8[<=2] bytes\nstring<=3 name \"abc\"\nbool flag true\nfloat32 ratio 0.5")]);
+        let encapsulation = Encapsulation::new(CdrVersion::Xcdr1, true);
+        let data = encode(&types, "demo/msg/Defaults", &json!({"bytes": [1, 2]}), encapsulation).unwrap();
+        assert_eq!(decode(&types, "demo/msg/Defaults", &data).unwrap(),
+                   json!({"pair": [0, 0], "bytes": [1, 2], "name": "abc", "flag": true, "ratio": 0.5}));
+
+        assert!(encode(&types, "demo/msg/Defaults", &json!({"pair": [1]}), encapsulation).is_err());
+        assert!(encode(&types, "demo/msg/Defaults", &json!({"bytes": [1, 2, 3]}), encapsulation).is_err());
+        assert!(encode(&types, "demo/msg/Defaults", &json!({"bytes": [256]}), encapsulation).is_err());
+        assert!(encode(&types, "demo/msg/Defaults", &json!({"name": "abcd"}), encapsulation).is_err());
+
+        let wide = type_info(&[("demo/msg/Wide", "wchar c\nwstring text")]);
+        for version in [CdrVersion::Xcdr1, CdrVersion::Xcdr2] {
+            let value = json!({"c": "ж", "text": "añb"});
+            let data = encode(&wide, "demo/msg/Wide", &value, Encapsulation::new(version, false)).unwrap();
+            assert_eq!(decode(&wide, "demo/msg/Wide", &data).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn non_finite_floats() {
+        let types = type_info(&[("demo/msg/Floats", "float32 a\nfloat64 b\nfloat64 c")]);
+        let encapsulation = Encapsulation::new(CdrVersion::Xcdr1, true);
+        let value = json!({"a": "NaN", "b": "Infinity", "c": "-Infinity"});
+        let data = encode(&types, "demo/msg/Floats", &value, encapsulation).unwrap();
+        assert_eq!(decode(&types, "demo/msg/Floats", &data).unwrap(), value);
+        assert!(encode(&types, "demo/msg/Floats", &json!({"a": "inf"}), encapsulation).is_err());
+    }
+
+    #[test]
+    fn malicious_input() {
+        // Recursive type nested deeper than the limit
+        let recursive = type_info(&[("demo/msg/Tree", "Tree[] children")]);
+        let mut data = vec![0x00, 0x01, 0x00, 0x00];
+        for _ in 0..100 {
+            data.extend_from_slice(&[1, 0, 0, 0]);
+        }
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        assert!(decode(&recursive, "demo/msg/Tree", &data).is_err());
+        let mut value = json!({});
+        for _ in 0..100 {
+            value = json!({"children": [value]});
+        }
+        assert!(encode(&recursive, "demo/msg/Tree", &value, Encapsulation::new(CdrVersion::Xcdr1, true)).is_err());
+
+        // Empty messages take no bytes, their sequence length is capped anyway
+        let empty = type_info(&[("demo/msg/Items", "Empty[] items"), ("demo/msg/Empty", "")]);
+        let data = [0x00, 0x01, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff];
+        assert!(decode(&empty, "demo/msg/Items", &data).is_err());
+        let data = [0x00, 0x01, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00];
+        assert_eq!(decode(&empty, "demo/msg/Items", &data).unwrap(), json!({"items": [{}, {}, {}]}));
+    }
+}
@@ -0,0 +1,635 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/// Loading of daemon settings. Sources are applied in the following order, so every next source
+/// overrides the previous one: defaults, json config file, environment variables, command line arguments.
+/// Part of settings can be changed at runtime by api, see `SettingsManager`.
+pub mod config {
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+    use std::path::Path;
+    use std::str::FromStr;
+    use std::sync::Mutex;
+    use log::{info, warn};
+    use serde::Serialize;
+    use crate::discovery_server::discovery_server::DiscoveryControl;
+    use crate::fastdds_profiles::fastdds_profiles::{parse_guid_prefix, parse_locator, SERVER_ROLES};
+    use crate::network::network::PortParams;
+    use crate::ros2entites::ros2entities::{RemoteServer, Settings};
+
+    pub const ENV_PREFIX: &str = "ROS2MONITOR_";
+    pub const LOG_LEVELS: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+    pub const DISCOVERY_MODES: [&str; 2] = ["server", "simple"];
+    /// Config file used if no other is specified. Runtime settings changes are persisted into it
+    pub const DEFAULT_CONFIG_FILE: &str = "ros2monitor.json";
+    /// Names of all settings as in config file
+    pub const SETTING_NAMES: [&str; 22] = ["domain_ids", "include_internals", "dds_topic_type", "discovery_mode", "socket_path", "http_address",
+        "discovery_server", "fastdds_profiles_file", "server_role", "server_guid_prefix", "listening_locators", "remote_servers",
+        "log_level", "policy_file", "audit_log_file", "library_path",
+        "capture_interface", "capture_file", "analyze_file", "port_params", "dds_statistics", "type_lookup"];
+    /// Settings which can be changed by api without daemon restart
+    pub const RUNTIME_SETTINGS: [&str; 7] = ["domain_ids", "include_internals", "dds_topic_type", "discovery_mode", "log_level",
+        "dds_statistics", "type_lookup"];
+
+    /// Result of command line parsing
+    pub enum ConfigAction {
+        /// Run daemon with settings. Second value is the config file path for persisting runtime changes
+        Run(Settings, String),
+        /// Print the help and exit
+        Help,
+        /// Print resulting settings and exit
+        PrintConfig(Settings),
+    }
+
+    pub fn usage() -> String {
+        return format!(r#"Usage: ros2monitor [OPTIONS]
+
+Options:
+    --config <FILE>                 Json config file with settings (default {})
+    --domain-ids <IDS>              Comma separated ROS2 domain ids to monitor (--domain-id is an alias)
+                                    (default 0, the ROS2 default domain; it was 1 before)
+    --include-internals             Show internal nodes and topics
+    --dds-topic-type                Use DDS type names instead of ROS2 ones
+    --discovery-mode <MODE>         server (discovery server) or simple (passive multicast discovery)
+    --socket-path <PATH>            Unix socket for json protocol
+    --http-address <ADDR>           Address of http api, empty string disables it
+    --discovery-server <ADDR>       Value of ROS_DISCOVERY_SERVER
+    --fastdds-profiles-file <FILE>  Generated FastDDS profiles file, used by ros2 cli tools too
+    --server-role <ROLE>            server, backup or super_client
+    --server-guid-prefix <PREFIX>   GUID prefix of the discovery server, e.g. 44.53.00.5f.45.50.52.4f.53.49.4d.41
+    --listening-locators <ADDRS>    Comma separated ip:port locators of the discovery server
+    --remote-servers <SERVERS>      Semicolon separated servers to connect, e.g. <PREFIX>@10.0.0.2:11811,10.0.0.3:11811
+    --log-level <LEVEL>             One of {}
+    --policy-file <FILE>            Authorization policy file
+    --audit-log-file <FILE>         Audit log file, empty string disables it
+    --library-path <DIR>            Directory with nodegraph library
+    --capture-interface <IFACE>     Interface for passive RTPS capture, any for all, empty string disables it
+    --capture-file <FILE>           Pcap file replayed instead of live capture
+    --analyze-file <FILE>           Serve the graph reconstructed from pcap or pcapng file instead of live discovery
+    --port-params <PARAMS>          Comma separated RTPS port parameters, e.g. port_base=8400,participant_id_gain=4
+    --dds-statistics                Subscribe to FastDDS statistics topics of monitored participants
+    --type-lookup                   Request type objects of discovered endpoints
+    --print-config                  Print resulting settings and exit
+    -h, --help                      Print this help
+
+Every option can also be set by environment variable with {} prefix, e.g.
+{}DOMAIN_IDS=1,2. Config file can be set by {}CONFIG variable."#,
+                       DEFAULT_CONFIG_FILE, LOG_LEVELS.join(", "), ENV_PREFIX, ENV_PREFIX, ENV_PREFIX);
+    }
+
+    /// Apply single named option to settings. Name is in snake case, like in config file
+    fn apply_option(settings: &mut Settings, name: &str, value: &str) -> Result<(), String> {
+        let parse_bool = |value: &str| -> Result<bool, String> {
+            return match value.to_lowercase().as_str() {
+                "1" | "true" | "yes" | "on" => Ok(true),
+                "0" | "false" | "no" | "off" => Ok(false),
+                _ => Err(format!("Invalid boolean value {} for {}", value, name))
+            };
+        };
+
+        match name {
+            "domain_id" | "domain_ids" => {
+                let mut domain_ids = Vec::new();
+                for domain_id in value.split(',').map(|domain_id| domain_id.trim()) {
+                    match domain_id.parse() {
+                        Ok(domain_id) => domain_ids.push(domain_id),
+                        Err(_) => return Err(format!("Invalid domain id {}", domain_id))
+                    }
+                }
+                settings.domain_ids = domain_ids;
+            }
+            "include_internals" => settings.include_internals = parse_bool(value)?,
+            "dds_topic_type" => settings.dds_topic_type = parse_bool(value)?,
+            "discovery_mode" => settings.discovery_mode = value.to_lowercase(),
+            "socket_path" => settings.socket_path = value.to_string(),
+            "http_address" => settings.http_address = value.to_string(),
+            "discovery_server" => settings.discovery_server = value.to_string(),
+            "fastdds_profiles_file" => settings.fastdds_profiles_file = value.to_string(),
+            "server_role" => settings.server_role = value.to_lowercase(),
+            "server_guid_prefix" => settings.server_guid_prefix = value.to_lowercase(),
+            "listening_locators" => settings.listening_locators = split_list(value, ','),
+            "remote_servers" => {
+                let mut remote_servers = Vec::new();
+                for server in split_list(value, ';') {
+                    match server.split_once('@') {
+                        Some((guid_prefix, locators)) => remote_servers.push(RemoteServer {
+                            guid_prefix: guid_prefix.trim().to_lowercase(),
+                            locators: split_list(locators, ','),
+                        }),
+                        None => return Err(format!("Remote server must be in <guid prefix>@<locators> form, got {}", server))
+                    }
+                }
+                settings.remote_servers = remote_servers;
+            }
+            "log_level" => settings.log_level = value.to_lowercase(),
+            "policy_file" => settings.policy_file = value.to_string(),
+            "audit_log_file" => settings.audit_log_file = value.to_string(),
+            "library_path" => settings.library_path = value.to_string(),
+            "capture_interface" => settings.capture_interface = value.to_string(),
+            "capture_file" => settings.capture_file = value.to_string(),
+            "analyze_file" => settings.analyze_file = value.to_string(),
+            "port_params" => settings.port_params = parse_port_params(value)?,
+            "dds_statistics" => settings.dds_statistics = parse_bool(value)?,
+            "type_lookup" => settings.type_lookup = parse_bool(value)?,
+            _ => return Err(format!("Unknown option {}", name))
+        }
+
+        return Ok(());
+    }
+
+    /// Parse `name=value` pairs of port parameters, missing parameters have default values
+    fn parse_port_params(value: &str) -> Result<PortParams, String> {
+        let mut params = PortParams::default();
+        for pair in split_list(value, ',') {
+            let (name, number) = match pair.split_once('=') {
+                Some((name, number)) => (name.trim(), number.trim()),
+                None => return Err(format!("Port parameter must be in name=value form, got {}", pair))
+            };
+            let number: u32 = match number.parse() {
+                Ok(number) => number,
+                Err(_) => return Err(format!("Invalid value {} of port parameter {}", number, name))
+            };
+            match name {
+                "port_base" => params.port_base = number,
+                "domain_id_gain" => params.domain_id_gain = number,
+                "participant_id_gain" => params.participant_id_gain = number,
+                "offset_d0" => params.offset_d0 = number,
+                "offset_d1" => params.offset_d1 = number,
+                "offset_d2" => params.offset_d2 = number,
+                "offset_d3" => params.offset_d3 = number,
+                _ => return Err(format!("Unknown port parameter {}", name))
+            }
+        }
+        return Ok(params);
+    }
+
+    /// Split list option, empty items are skipped, so empty string is an empty list
+    fn split_list(value: &str, separator: char) -> Vec<String> {
+        return value.split(separator)
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect();
+    }
+
+    fn is_flag(name: &str) -> bool {
+        return name == "include_internals" || name == "dds_topic_type" || name == "dds_statistics" || name == "type_lookup";
+    }
+
+    pub fn load_file(path: &str) -> Result<Settings, String> {
+        let json = match std::fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) => return Err(format!("Unable to read config file {}: {}", path, e))
+        };
+        return match Settings::from_json(json.as_str()) {
+            Ok(settings) => Ok(settings),
+            Err(e) => Err(format!("{} in config file {}", e, path))
+        };
+    }
+
+    /// Build settings from command line arguments (without program name) and environment variables
+    pub fn load(args: &[String], env: &HashMap<String, String>) -> Result<ConfigAction, String> {
+        // Split arguments into (name, value) pairs first, because config file must be applied before others
+        let mut options: Vec<(String, String)> = Vec::new();
+        let mut config_file: Option<String> = env.get(format!("{}CONFIG", ENV_PREFIX).as_str()).cloned();
+        let mut print_config = false;
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if arg == "-h" || arg == "--help" {
+                return Ok(ConfigAction::Help);
+            }
+            if arg == "--print-config" {
+                print_config = true;
+                continue;
+            }
+
+            let option = match arg.strip_prefix("--") {
+                Some(option) => option,
+                None => return Err(format!("Unexpected argument {}", arg))
+            };
+            // Both --name=value and --name value forms are supported
+            let (name, inline_value) = match option.split_once('=') {
+                Some((name, value)) => (name.replace('-', "_"), Some(value.to_string())),
+                None => (option.replace('-', "_"), None)
+            };
+
+            let value = match inline_value {
+                Some(value) => value,
+                None if is_flag(name.as_str()) => "true".to_string(),
+                None => match iter.next() {
+                    Some(value) => value.clone(),
+                    None => return Err(format!("Option --{} requires a value", option))
+                }
+            };
+
+            if name == "config" {
+                config_file = Some(value);
+            } else {
+                options.push((name, value));
+            }
+        }
+
+        // Explicitly specified config file must exist, default one is optional
+        let mut settings = match config_file.as_ref() {
+            Some(path) => load_file(path.as_str())?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => load_file(DEFAULT_CONFIG_FILE)?,
+            None => Settings::default()
+        };
+        let config_file = config_file.unwrap_or(DEFAULT_CONFIG_FILE.to_string());
+
+        // Environment variables. Sorted to make error messages deterministic
+        let mut env_options: Vec<(&String, &String)> = env.iter()
+            .filter(|(name, _)| name.starts_with(ENV_PREFIX) && name.as_str() != format!("{}CONFIG", ENV_PREFIX))
+            .collect();
+        env_options.sort();
+        for (name, value) in env_options {
+            let option = name[ENV_PREFIX.len()..].to_lowercase();
+            if let Err(e) = apply_option(&mut settings, option.as_str(), value) {
+                return Err(format!("{} (environment variable {})", e, name));
+            }
+        }
+
+        for (name, value) in options.iter() {
+            if let Err(e) = apply_option(&mut settings, name.as_str(), value.as_str()) {
+                return Err(format!("{} (command line option --{})", e, name.replace('_', "-")));
+            }
+        }
+
+        validate(&settings)?;
+
+        return Ok(if print_config { ConfigAction::PrintConfig(settings) } else { ConfigAction::Run(settings, config_file) });
+    }
+
+    /// Check settings consistency. Returns all found problems at once
+    pub fn validate(settings: &Settings) -> Result<(), String> {
+        let mut errors: Vec<String> = Vec::new();
+
+        // Greater domain ids produce ports out of the valid range with default port mapping
+        if settings.domain_ids.is_empty() {
+            errors.push("domain_ids must contain at least one domain".to_string());
+        }
+        for domain_id in settings.domain_ids.iter().filter(|domain_id| **domain_id > 232) {
+            errors.push(format!("domain id must be in range 0..232, got {}", domain_id));
+        }
+        let mut unique_ids = settings.domain_ids.clone();
+        unique_ids.sort();
+        unique_ids.dedup();
+        if unique_ids.len() != settings.domain_ids.len() {
+            errors.push(format!("domain_ids must be unique, got {:?}", settings.domain_ids));
+        }
+
+        if !DISCOVERY_MODES.contains(&settings.discovery_mode.as_str()) {
+            errors.push(format!("discovery_mode must be one of {:?}, got {}", DISCOVERY_MODES, settings.discovery_mode));
+        }
+
+        if !LOG_LEVELS.contains(&settings.log_level.as_str()) {
+            errors.push(format!("log_level must be one of {:?}, got {}", LOG_LEVELS, settings.log_level));
+        }
+
+        if settings.socket_path.is_empty() {
+            errors.push("socket_path must not be empty".to_string());
+        } else if let Some(parent) = Path::new(settings.socket_path.as_str()).parent() {
+            if !parent.as_os_str().is_empty() && !parent.is_dir() {
+                errors.push(format!("Directory {} for socket_path doesn't exist", parent.display()));
+            }
+        }
+
+        if !settings.http_address.is_empty() && settings.http_address.parse::<SocketAddr>().is_err() {
+            errors.push(format!("http_address must be in ip:port form, got {}", settings.http_address));
+        }
+
+        // ROS_DISCOVERY_SERVER may contain several servers separated by semicolon
+        for server in settings.discovery_server.split(';').filter(|server| !server.is_empty()) {
+            let port_valid = match server.rsplit_once(':') {
+                Some((host, port)) => !host.is_empty() && port.parse::<u16>().is_ok(),
+                None => false
+            };
+            if !port_valid {
+                errors.push(format!("discovery_server must be in host:port form, got {}", server));
+            }
+        }
+
+        if settings.fastdds_profiles_file.is_empty() {
+            errors.push("fastdds_profiles_file must not be empty".to_string());
+        }
+
+        if !SERVER_ROLES.contains(&settings.server_role.as_str()) {
+            errors.push(format!("server_role must be one of {:?}, got {}", SERVER_ROLES, settings.server_role));
+        }
+        if let Err(e) = parse_guid_prefix(settings.server_guid_prefix.as_str()) {
+            errors.push(format!("server_guid_prefix: {}", e));
+        }
+        for locator in settings.listening_locators.iter() {
+            if let Err(e) = parse_locator(locator) {
+                errors.push(format!("listening_locators: {}", e));
+            }
+        }
+        for server in settings.remote_servers.iter() {
+            if let Err(e) = parse_guid_prefix(server.guid_prefix.as_str()) {
+                errors.push(format!("remote_servers: {}", e));
+            }
+            if server.locators.is_empty() {
+                errors.push(format!("remote_servers: server {} has no locators", server.guid_prefix));
+            }
+            for locator in server.locators.iter() {
+                if let Err(e) = parse_locator(locator) {
+                    errors.push(format!("remote_servers: {}", e));
+                }
+            }
+        }
+        if !settings.capture_file.is_empty() && !Path::new(settings.capture_file.as_str()).is_file() {
+            errors.push(format!("capture_file {} doesn't exist", settings.capture_file));
+        }
+        if !settings.analyze_file.is_empty() && !Path::new(settings.analyze_file.as_str()).is_file() {
+            errors.push(format!("analyze_file {} doesn't exist", settings.analyze_file));
+        }
+        match settings.port_params.validate() {
+            Ok(()) => {
+                let max_domain_id = settings.port_params.max_domain_id();
+                for domain_id in settings.domain_ids.iter().filter(|domain_id| **domain_id <= 232 && **domain_id > max_domain_id) {
+                    errors.push(format!("Ports of domain {} are out of range with port_params", domain_id));
+                }
+            }
+            Err(e) => errors.push(format!("port_params: {}", e))
+        }
+
+        // Server needs a locator to be reachable, super client needs a server to connect
+        if settings.discovery_mode == "server" {
+            if settings.server_role == "super_client" && settings.remote_servers.is_empty() {
+                errors.push("remote_servers must not be empty for super_client server_role".to_string());
+            }
+            if settings.server_role != "super_client" && settings.listening_locators.is_empty() {
+                errors.push(format!("listening_locators must not be empty for {} server_role", settings.server_role));
+            }
+        }
+
+        return if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Invalid configuration:\n  {}", errors.join("\n  ")))
+        };
+    }
+
+    /// Parse log level validated by `validate`
+    pub fn log_level_filter(log_level: &str) -> log::LevelFilter {
+        return log::LevelFilter::from_str(log_level).unwrap_or(log::LevelFilter::Debug);
+    }
+
+    /// Result of successful settings change
+    #[derive(Serialize, Debug)]
+    pub struct SettingsChange {
+        /// Names of changed settings
+        pub changed: Vec<String>,
+        /// Changed settings which take effect only after daemon restart
+        pub restart_required: Vec<String>,
+        /// Path of config file where settings were saved
+        pub saved_to: String,
+    }
+
+    /// Owner of the current daemon settings. Applies changes requested by api: log level is set
+    /// immediately, discovery related settings are passed to discovery server, and resulting settings
+    /// are saved into the config file.
+    pub struct SettingsManager {
+        settings: Mutex<Settings>,
+        config_file: String,
+        discovery_control: flume::Sender<DiscoveryControl>,
+    }
+
+    impl SettingsManager {
+        pub fn new(settings: Settings, config_file: String, discovery_control: flume::Sender<DiscoveryControl>) -> SettingsManager {
+            return SettingsManager {
+                settings: Mutex::new(settings),
+                config_file,
+                discovery_control,
+            };
+        }
+
+        pub fn settings(&self) -> Settings {
+            return self.settings.lock().unwrap().clone();
+        }
+
+        /// Change settings. Changes are validated as a whole, so either all or none of them are applied
+        /// # Arguments
+        ///
+        /// * `changes`: map from setting name (as in config file) to new value
+        ///
+        /// returns: Result<SettingsChange, String>
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn update(&self, changes: &HashMap<String, String>) -> Result<SettingsChange, String> {
+            if changes.is_empty() {
+                return Err("No settings to change".to_string());
+            }
+
+            let mut settings = self.settings.lock().unwrap();
+            let mut updated = settings.clone();
+            // Only requested changes are saved. Values from command line and environment stay out of the config file
+            let mut saved = if Path::new(self.config_file.as_str()).exists() {
+                load_file(self.config_file.as_str())?
+            } else {
+                Settings::default()
+            };
+            // Sorted to make error messages and response deterministic
+            let mut names: Vec<&String> = changes.keys().collect();
+            names.sort();
+            for name in names.iter() {
+                let value = changes.get(*name).unwrap().as_str();
+                apply_option(&mut updated, name.as_str(), value)?;
+                apply_option(&mut saved, name.as_str(), value)?;
+            }
+            validate(&updated)?;
+            validate(&saved)?;
+
+            let old_json = serde_json::to_value(&*settings).unwrap();
+            let new_json = serde_json::to_value(&updated).unwrap();
+            let changed: Vec<String> = names.iter()
+                .filter(|name| old_json.get(name.as_str()) != new_json.get(name.as_str()))
+                .map(|name| name.to_string())
+                .collect();
+            let restart_required: Vec<String> = changed.iter()
+                .filter(|name| !RUNTIME_SETTINGS.contains(&name.as_str()))
+                .cloned()
+                .collect();
+
+            // Settings are switched only if they are saved
+            saved.save(self.config_file.as_str())?;
+
+            if updated.log_level != settings.log_level {
+                log::set_max_level(log_level_filter(updated.log_level.as_str()));
+            }
+
+            if updated.domain_ids != settings.domain_ids || updated.include_internals != settings.include_internals
+                || updated.dds_topic_type != settings.dds_topic_type || updated.discovery_mode != settings.discovery_mode
+                || updated.dds_statistics != settings.dds_statistics || updated.type_lookup != settings.type_lookup {
+                if let Err(e) = self.discovery_control.send(DiscoveryControl::ApplySettings(updated.clone())) {
+                    return Err(format!("Unable to apply settings to discovery server: {}", e));
+                }
+            }
+
+            *settings = updated;
+            info!("Settings changed: {:?}", changed);
+            if !restart_required.is_empty() {
+                warn!("Settings {:?} will take effect after restart", restart_required);
+            }
+
+            return Ok(SettingsChange { changed, restart_required, saved_to: self.config_file.clone() });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::config::config::{ConfigAction, load, SettingsManager, validate};
+    use crate::discovery_server::discovery_server::DiscoveryControl;
+    use crate::ros2entites::ros2entities::Settings;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        return args.iter().map(|arg| arg.to_string()).collect();
+    }
+
+    fn settings(action: ConfigAction) -> Settings {
+        return match action {
+            ConfigAction::Run(settings, _) => settings,
+            _ => panic!("Expected run action")
+        };
+    }
+
+    #[test]
+    fn defaults() {
+        let settings = settings(load(&[], &HashMap::new()).unwrap());
+        assert_eq!(settings.domain_ids, vec![0]);
+        assert_eq!(settings.socket_path, "/tmp/ros2monitor.sock");
+        assert!(validate(&Settings::default()).is_ok());
+    }
+
+    #[test]
+    fn precedence() {
+        let path = std::env::temp_dir().join(format!("ros2monitor_config_test_{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"domain_id": 5, "log_level": "info", "include_internals": true}"#).unwrap();
+
+        let mut env = HashMap::new();
+        env.insert("ROS2MONITOR_DOMAIN_ID".to_string(), "7".to_string());
+        env.insert("ROS2MONITOR_LOG_LEVEL".to_string(), "warn".to_string());
+        env.insert("ROS2MONITOR_CONFIG".to_string(), path.to_str().unwrap().to_string());
+
+        let settings = settings(load(&args(&["--domain-id", "9", "--dds-topic-type"]), &env).unwrap());
+        assert_eq!(settings.domain_ids, vec![9]);
+        assert_eq!(settings.log_level, "warn");
+        assert!(settings.include_internals);
+        assert!(settings.dds_topic_type);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn invalid_values() {
+        let env = HashMap::new();
+        assert!(load(&args(&["--domain-id", "abc"]), &env).is_err());
+        assert!(load(&args(&["--domain-id=300"]), &env).is_err());
+        assert!(load(&args(&["--domain-ids", "1,1"]), &env).is_err());
+        assert!(load(&args(&["--log-level", "verbose"]), &env).is_err());
+        assert!(load(&args(&["--discovery-mode", "multicast"]), &env).is_err());
+        assert!(load(&args(&["--http-address", "localhost"]), &env).is_err());
+        assert!(load(&args(&["--unknown", "1"]), &env).is_err());
+        assert!(load(&args(&["--socket-path"]), &env).is_err());
+        assert!(load(&args(&["--config", "/not/existing/file.json"]), &env).is_err());
+        assert!(Settings::from_json(r#"{"domian_id": 1}"#).is_err());
+    }
+
+    #[test]
+    fn multiple_domains() {
+        let settings = settings(load(&args(&["--domain-ids", "0, 3,7"]), &HashMap::new()).unwrap());
+        assert_eq!(settings.domain_ids, vec![0, 3, 7]);
+
+        // Old config files have single domain id
+        assert_eq!(Settings::from_json(r#"{"domain_id": 4}"#).unwrap().domain_ids, vec![4]);
+        assert_eq!(Settings::from_json(r#"{"domain_ids": [4, 5]}"#).unwrap().domain_ids, vec![4, 5]);
+    }
+
+    #[test]
+    fn server_topology() {
+        let env = HashMap::new();
+        let settings = settings(load(&args(&["--server-role", "SERVER", "--listening-locators", "0.0.0.0:11811, [::]:11811",
+            "--remote-servers", "44.53.01.5f.45.50.52.4f.53.49.4d.41@10.0.0.2:11811,10.0.0.3:11811"]), &env).unwrap());
+        assert_eq!(settings.server_role, "server");
+        assert_eq!(settings.listening_locators, vec!["0.0.0.0:11811", "[::]:11811"]);
+        assert_eq!(settings.remote_servers.len(), 1);
+        assert_eq!(settings.remote_servers[0].locators, vec!["10.0.0.2:11811", "10.0.0.3:11811"]);
+
+        assert!(load(&args(&["--server-role", "client"]), &env).is_err());
+        assert!(load(&args(&["--server-role", "super_client"]), &env).is_err());
+        assert!(load(&args(&["--server-guid-prefix", "44.53"]), &env).is_err());
+        assert!(load(&args(&["--listening-locators", ""]), &env).is_err());
+        assert!(load(&args(&["--remote-servers", "10.0.0.2:11811"]), &env).is_err());
+        assert!(load(&args(&["--remote-servers", "44.53.01.5f.45.50.52.4f.53.49.4d.41@"]), &env).is_err());
+        // Topology isn't used in simple discovery mode
+        assert!(load(&args(&["--discovery-mode", "simple", "--listening-locators", ""]), &env).is_ok());
+    }
+
+    #[test]
+    fn port_params() {
+        let env = HashMap::new();
+        let settings = settings(load(&args(&["--port-params", "port_base=8400, participant_id_gain=4"]), &env).unwrap());
+        assert_eq!(settings.port_params.port_base, 8400);
+        assert_eq!(settings.port_params.participant_id_gain, 4);
+        assert_eq!(settings.port_params.domain_id_gain, 250);
+        assert_eq!(Settings::from_json(r#"{"port_params": {"offset_d3": 13}}"#).unwrap().port_params.offset_d3, 13);
+
+        assert!(load(&args(&["--port-params", "port_base"]), &env).is_err());
+        assert!(load(&args(&["--port-params", "gain=4"]), &env).is_err());
+        assert!(load(&args(&["--port-params", "participant_id_gain=1"]), &env).is_err());
+        // Domain 200 doesn't fit into port range with the larger base
+        assert!(load(&args(&["--port-params", "port_base=20000", "--domain-ids", "200"]), &env).is_err());
+    }
+
+    #[test]
+    fn help_and_print() {
+        assert!(matches!(load(&args(&["--help"]), &HashMap::new()).unwrap(), ConfigAction::Help));
+        assert!(matches!(load(&args(&["--print-config"]), &HashMap::new()).unwrap(), ConfigAction::PrintConfig(_)));
+    }
+
+    #[test]
+    fn runtime_update() {
+        let path = std::env::temp_dir().join(format!("ros2monitor_runtime_test_{}.json", std::process::id()));
+        let (tx, rx) = flume::unbounded();
+        // Log level comes from command line, it isn't in config file
+        let manager = SettingsManager::new(Settings { log_level: "trace".to_string(), ..Settings::default() }, path.to_str().unwrap().to_string(), tx);
+
+        let mut changes = HashMap::new();
+        changes.insert("include_internals".to_string(), "true".to_string());
+        changes.insert("socket_path".to_string(), "/tmp/other.sock".to_string());
+        let change = manager.update(&changes).unwrap();
+        assert_eq!(change.changed, vec!["include_internals", "socket_path"]);
+        assert_eq!(change.restart_required, vec!["socket_path"]);
+        assert!(matches!(rx.try_recv().unwrap(), DiscoveryControl::ApplySettings(settings) if settings.include_internals));
+
+        let saved = Settings::from_json(std::fs::read_to_string(&path).unwrap().as_str()).unwrap();
+        assert!(saved.include_internals);
+        // Settings which aren't changed keep the values of config file
+        assert_eq!(saved.log_level, Settings::default().log_level);
+        assert_eq!(manager.settings().log_level, "trace");
+
+        // Invalid change is rejected as a whole
+        let mut invalid = HashMap::new();
+        invalid.insert("dds_topic_type".to_string(), "true".to_string());
+        invalid.insert("domain_ids".to_string(), "1,1000".to_string());
+        assert!(manager.update(&invalid).is_err());
+        assert!(!manager.settings().dds_topic_type);
+        assert!(rx.try_recv().is_err());
+
+        // Settings aren't changed if they can't be saved
+        let manager = SettingsManager::new(Settings::default(), "/not/existing/dir/ros2monitor.json".to_string(), flume::unbounded().0);
+        assert!(manager.update(&changes).is_err());
+        assert!(!manager.settings().include_internals);
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -6,23 +6,33 @@
 
 pub mod discovery_server {
     use std::ffi::OsStr;
-    use std::sync::{Arc, mpsc, Mutex};
+    use std::path::PathBuf;
+    use std::sync::{Arc, mpsc, Mutex, RwLock};
     use std::sync::atomic::AtomicPtr;
     use std::sync::atomic::Ordering::Relaxed;
     use std::sync::mpsc::{Receiver, Sender};
     use std::thread;
+    use std::time::{Duration, Instant};
     use bitflags::{bitflags, Flags};
-    use dns_lookup::lookup_addr;
-    use log::{debug, warn};
+    use dns_lookup::{get_hostname, lookup_addr};
+    use log::{debug, error, info, warn};
     use serde::de::Unexpected::Option;
     use single_value_channel::channel_starting_with;
-    use crate::discovery_server_impl::{ParticipantData, ReaderData, stop_discovery_server_impl, WriterData};
-    use crate::fastdds_server::fastdds_server::{FastDDSDiscoverer, FastDDSEntity, FastDDSEvent, PartFunc, ReadFunc, WriteFunc};
-    use crate::network::network::{hostname_ip, parse_endpoint};
-    use crate::ros2_server::ros2_server::{Ros2Discoverer, Ros2DiscovererParams};
-    use crate::ros2entites::ros2entities::{Ros2Context, Ros2NodeState, Ros2Package, Ros2Publisher, Ros2State, Ros2Subscriber, Ros2Topic};
-
-    #[derive(Clone)]
+    use crate::capture::capture;
+    use crate::capture::capture::{CaptureSource, WireActivity};
+    use crate::discovery_server_impl::{ParticipantData, ReaderData, WriterData};
+    use crate::fastdds_server::fastdds_server::{DiscoveryMode, FastDDSDiscoverer, FastDDSDiscovererParams, FastDDSEntity, FastDDSEvent, FastDDSSender, PartFunc, ReadFunc, WriteFunc};
+    use crate::fastdds_profiles::fastdds_profiles;
+    use crate::interfaces::interfaces::TypeRegistry;
+    use crate::network::network::{demangle_type, hostname_ip, PortParams};
+    use crate::processes::processes::attach_local_process;
+    use crate::resources::resources::{HISTORY_SECS, ResourceMonitor, SAMPLE_INTERVAL};
+    use crate::traffic::traffic;
+    use crate::traffic::traffic::TrafficMonitor;
+    use crate::ros2_server::ros2_server::{Ros2Discoverer, Ros2DiscovererParams, SharedRos2Discoverer};
+    use crate::ros2entites::ros2entities::{split_node_fqn, DdsStatisticsSample, Ros2Context, Ros2NodeState, Ros2Package, Ros2Publisher, Ros2State, Ros2Subscriber, Ros2Topic, Settings};
+
+    #[derive(Clone, PartialEq, Debug)]
     pub struct DiscoveryFlags(u32);
 
     bitflags! {
@@ -31,9 +41,65 @@ pub mod discovery_server {
             const EnableROS2 = 0b00000010;
             const IncludeInternals = 0b00000100;
             const UseInternalNames = 0b00001000;
+            /// Observe default multicast discovery instead of running discovery server
+            const SimpleDiscovery = 0b00010000;
+            /// Subscribe to FastDDS statistics topics
+            const DdsStatistics = 0b00100000;
+            /// Request type objects of discovered endpoints
+            const TypeLookup = 0b01000000;
+        }
+    }
+
+    impl DiscoveryFlags {
+        /// Ros2 and FastDDS discovery flags with visibility options taken from settings
+        pub fn from_settings(settings: &Settings) -> DiscoveryFlags {
+            // Offline analysis serves the graph of the capture, nothing is discovered live
+            if !settings.analyze_file.is_empty() {
+                return DiscoveryFlags::empty();
+            }
+            let mut flags = DiscoveryFlags::EnableFastdds | DiscoveryFlags::EnableROS2;
+            if settings.include_internals {
+                flags |= DiscoveryFlags::IncludeInternals;
+            }
+            if settings.dds_topic_type {
+                flags |= DiscoveryFlags::UseInternalNames;
+            }
+            if settings.discovery_mode == "simple" {
+                flags |= DiscoveryFlags::SimpleDiscovery;
+            }
+            if settings.dds_statistics {
+                flags |= DiscoveryFlags::DdsStatistics;
+            }
+            if settings.type_lookup {
+                flags |= DiscoveryFlags::TypeLookup;
+            }
+            return flags;
+        }
+
+        pub fn discovery_mode(&self) -> DiscoveryMode {
+            return if self.contains(DiscoveryFlags::SimpleDiscovery) {
+                DiscoveryMode::Simple
+            } else {
+                DiscoveryMode::Server
+            };
         }
     }
 
+    /// Commands sent to running discovery server from other threads
+    pub enum DiscoveryControl {
+        /// Apply settings changed at runtime. Only affected discovery components are restarted
+        ApplySettings(Settings),
+        /// Packages explored in background, they are used to attribute local processes
+        PackagesExplored(Vec<Ros2Package>),
+        /// Traffic captured on the wire since the previous batch
+        WireActivity(Vec<WireActivity>),
+    }
+
+    /// Statistics samples are applied to the state in batches, each sample would clone the whole state otherwise
+    const STATISTICS_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+    /// Samples received above this number during the flush interval are dropped
+    const MAX_PENDING_STATISTICS: usize = 10000;
+
     fn default_handler_participant(_participant_data: ParticipantData) {}
 
     fn default_handler_reader(_reader_data: ReaderData) {}
@@ -68,42 +134,120 @@ pub mod discovery_server {
 
 
     pub struct DiscoveryServer {
-        pub domain_id: u32,
+        /// Monitored domains. Each domain has its own FastDDS discoverer
+        pub domain_ids: Vec<u32>,
         pub discovery_flags: DiscoveryFlags,
         pub fastdds_params: FastddsParams,
         pub on_state_update: fn(Arc<Ros2State>),
 
-        pub fastdds_discoverer: Box<FastDDSDiscoverer>,
-        pub ros2_discoverer: Box<Ros2Discoverer>,
+        pub fastdds_discoverers: Vec<Box<FastDDSDiscoverer>>,
+        pub ros2_discoverer: SharedRos2Discoverer,
         pub state: Arc<Mutex<Ros2State>>,
+        /// Resource usage of local node processes, shared with api
+        pub resource_monitor: Arc<ResourceMonitor>,
+        /// History of captured traffic, shared with api
+        pub traffic_monitor: Arc<TrafficMonitor>,
+        /// Interface types of installed packages and of type objects received in discovery, shared with api
+        pub type_registry: Arc<TypeRegistry>,
+        /// Source of passive RTPS capture, None if capture is disabled
+        pub capture_source: std::option::Option<CaptureSource>,
+        /// Port mapping of the monitored system, used to find the domain of captured traffic
+        port_params: PortParams,
+        /// Statistics samples received since the last flush
+        pending_statistics: Vec<DdsStatisticsSample>,
 
         state_tx: single_value_channel::Updater<std::option::Option<Ros2State>>,
+        // All fastdds discoverers send events into the same channel
+        fastdds_tx: FastDDSSender,
+        fastdds_rx: flume::Receiver<(FastDDSEvent, FastDDSEntity)>,
+        control_tx: flume::Sender<DiscoveryControl>,
+        control_rx: flume::Receiver<DiscoveryControl>,
+    }
+
+    /// One discoverer per domain. Server id is the index of the domain in the list
+    fn create_fastdds_discoverers(settings: &Settings, discovery_flags: &DiscoveryFlags, tx: &FastDDSSender) -> Vec<Box<FastDDSDiscoverer>> {
+        if !discovery_flags.contains(DiscoveryFlags::EnableFastdds) {
+            return vec![];
+        }
+        return settings.domain_ids.iter().enumerate()
+            .map(|(server_id, domain_id)| {
+                let server_id = server_id as u8;
+                // Super client has no listening locators of its own
+                let server_port = match fastdds_profiles::server_locators(&settings.listening_locators, server_id) {
+                    Ok(locators) if settings.server_role != "super_client" => locators.first().map(|locator| locator.port()).unwrap_or(0),
+                    _ => 0
+                };
+                let params = FastDDSDiscovererParams {
+                    domain_id: *domain_id,
+                    server_id,
+                    profiles_file: settings.fastdds_profiles_file.clone(),
+                    discovery_mode: discovery_flags.discovery_mode(),
+                    statistics: discovery_flags.contains(DiscoveryFlags::DdsStatistics),
+                    type_lookup: discovery_flags.contains(DiscoveryFlags::TypeLookup),
+                    server_port,
+                    port_params: settings.port_params.clone(),
+                };
+                return Box::new(FastDDSDiscoverer::new(params, tx.clone()));
+            })
+            .collect();
+    }
+
+    /// Ros2 cli tools work in the first monitored domain by default
+    fn ros2_discoverer_params(settings: &Settings) -> Ros2DiscovererParams {
+        return Ros2DiscovererParams {
+            domain_id: settings.domain_ids.first().cloned().unwrap_or(0),
+            fastdds_profiles_file: settings.fastdds_profiles_file.clone(),
+            discovery_server: fastdds_profiles::discovery_server_variable(settings, 0).unwrap_or_default(),
+            simple_discovery: DiscoveryFlags::from_settings(settings).contains(DiscoveryFlags::SimpleDiscovery),
+        };
     }
 
-    fn create_discovery_server(domain_id: u32, discovery_flags: DiscoveryFlags, state_tx: single_value_channel::Updater<std::option::Option<Ros2State>>) -> DiscoveryServer {
+    fn create_discovery_server(settings: &Settings, discovery_flags: DiscoveryFlags, state_tx: single_value_channel::Updater<std::option::Option<Ros2State>>) -> DiscoveryServer {
+        let (control_tx, control_rx) = flume::unbounded::<DiscoveryControl>();
+        let (fastdds_tx, fastdds_rx) = flume::unbounded::<(FastDDSEvent, FastDDSEntity)>();
         DiscoveryServer {
-            domain_id,
+            domain_ids: settings.domain_ids.clone(),
             discovery_flags: discovery_flags.clone(),
             fastdds_params: FastddsParams::new(),
             on_state_update: |state: Arc<Ros2State>| {},
-            fastdds_discoverer: Box::new(FastDDSDiscoverer::new(domain_id)),
-            ros2_discoverer: Box::new(Ros2Discoverer::new(Ros2DiscovererParams { domain_id })),
+            fastdds_discoverers: create_fastdds_discoverers(settings, &discovery_flags, &fastdds_tx),
+            ros2_discoverer: Arc::new(RwLock::new(Ros2Discoverer::new(ros2_discoverer_params(settings)))),
             state: Arc::new(Mutex::new(Ros2State::new(discovery_flags.contains(DiscoveryFlags::IncludeInternals)))),
+            resource_monitor: Arc::new(ResourceMonitor::new(HISTORY_SECS)),
+            traffic_monitor: Arc::new(TrafficMonitor::new(traffic::HISTORY_SECS)),
+            type_registry: Arc::new(TypeRegistry::from_env()),
+            capture_source: CaptureSource::from_settings(settings),
+            port_params: settings.port_params.clone(),
+            pending_statistics: Vec::new(),
             state_tx,
+            fastdds_tx,
+            fastdds_rx,
+            control_tx,
+            control_rx,
         }
     }
 
     impl Default for DiscoveryServer {
         fn default() -> DiscoveryServer {
             let (rx, tx) = single_value_channel::channel();
-            create_discovery_server(0, DiscoveryFlags::EnableROS2 | DiscoveryFlags::EnableFastdds, tx)
+            create_discovery_server(&Settings::default(), DiscoveryFlags::EnableROS2 | DiscoveryFlags::EnableFastdds, tx)
         }
     }
 
     impl DiscoveryServer {
-        pub fn new(domain_id: u32, discovery_flags: DiscoveryFlags) -> (single_value_channel::Receiver<std::option::Option<Ros2State>>, DiscoveryServer) {
+        pub fn new(settings: &Settings, discovery_flags: DiscoveryFlags) -> (single_value_channel::Receiver<std::option::Option<Ros2State>>, DiscoveryServer) {
             let (state_rx, state_tx) = single_value_channel::channel::<Ros2State>();
-            return (state_rx, create_discovery_server(domain_id, discovery_flags, state_tx));
+            return (state_rx, create_discovery_server(settings, discovery_flags, state_tx));
+        }
+
+        /// Sender for runtime commands, like settings changes. Commands are handled by `run` loop
+        pub fn control_sender(&self) -> flume::Sender<DiscoveryControl> {
+            return self.control_tx.clone();
+        }
+
+        /// Discoverer of the current settings
+        fn ros2_discoverer(&self) -> Ros2Discoverer {
+            return self.ros2_discoverer.read().unwrap().clone();
         }
 
         /// Handle discovered publisher
@@ -125,21 +269,19 @@ pub mod discovery_server {
             //if !self.discovery_flags.contains(DiscoveryFlags::IncludeInternals) && self.is_internal(publisher.clone().topic_name) {
             //    return;
             //}
-            publisher.node_name = match self.node_name_by_gid(publisher.topic_name.clone(), publisher.guid.clone()) {
+            publisher.node_name = match self.node_name_by_gid(publisher.domain_id, publisher.topic_name.clone(), publisher.guid.clone()) {
                 Ok(node_name) => node_name,
                 Err(_error_str) => "unknown".to_string()
             };
+            let (namespace, name) = split_node_fqn(publisher.node_name.as_str());
+            publisher.node_namespace = namespace;
+            publisher.node_name = name;
             if publisher.host.ip != "SHM" {
                 publisher.host.name = hostname_ip(publisher.clone().host.ip);
             }
-            publisher.topic_type = if self.discovery_flags.contains(DiscoveryFlags::UseInternalNames) {
-                publisher.topic_type
-            } else {
-                match self.topic_type_by_name(publisher.topic_name.clone()) {
-                    Ok(name) => name,
-                    Err(err) => publisher.topic_type.clone()
-                }
-            };
+            if !self.discovery_flags.contains(DiscoveryFlags::UseInternalNames) {
+                publisher.topic_type = demangle_type(publisher.topic_type.as_str());
+            }
             self.show_pub_info(publisher.clone(), false);
 
             if !publisher.node_name.is_empty() && publisher.node_name != "_NODE_NAME_UNKNOWN_" {
@@ -156,21 +298,19 @@ pub mod discovery_server {
             //if !self.discovery_flags.contains(DiscoveryFlags::IncludeInternals) && self.is_internal(subscriber.clone().topic_name) {
             //    return;
             //}
-            subscriber.node_name = match self.node_name_by_gid(subscriber.topic_name.clone(), subscriber.guid.clone()) {
+            subscriber.node_name = match self.node_name_by_gid(subscriber.domain_id, subscriber.topic_name.clone(), subscriber.guid.clone()) {
                 Ok(node_name) => node_name,
                 Err(_error_str) => "unknown".to_string()
             };
+            let (namespace, name) = split_node_fqn(subscriber.node_name.as_str());
+            subscriber.node_namespace = namespace;
+            subscriber.node_name = name;
             if subscriber.host.ip != "SHM" {
                 subscriber.host.name = hostname_ip(subscriber.clone().host.ip);
             }
-            subscriber.topic_type = if self.discovery_flags.contains(DiscoveryFlags::UseInternalNames) {
-                subscriber.topic_type
-            } else {
-                match self.topic_type_by_name(subscriber.topic_name.clone()) {
-                    Ok(name) => name,
-                    Err(err) => subscriber.topic_type.clone()
-                }
-            };
+            if !self.discovery_flags.contains(DiscoveryFlags::UseInternalNames) {
+                subscriber.topic_type = demangle_type(subscriber.topic_type.as_str());
+            }
             self.show_sub_info(subscriber.clone(), false);
             if !subscriber.node_name.is_empty() && subscriber.node_name != "_NODE_NAME_UNKNOWN_" {
                 self.state.lock().unwrap().add_subscriber(subscriber.clone());
@@ -182,7 +322,37 @@ pub mod discovery_server {
             }
         }
 
-        fn handle_discovered_context(&self, context: Ros2Context) {}
+        /// Handle discovered participant. Participant is stored even if it has no nodes,
+        /// e.g. plain DDS applications or ros2 daemon
+        /// # Arguments
+        ///
+        /// * `context`:
+        ///
+        /// returns: ()
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        fn handle_discovered_context(&self, mut context: Ros2Context) {
+            if context.host.ip == "SHM" {
+                // Shared memory is available only for participants of the same host
+                context.host.name = get_hostname().unwrap_or("unknown".to_string());
+            } else if !context.host.ip.is_empty() {
+                context.host.name = hostname_ip(context.host.ip.clone());
+            }
+            let packages = self.state.lock().unwrap().packages.clone();
+            if attach_local_process(&mut context, &packages) {
+                debug!("Participant {} is run by local process {} ({}/{})", context.guid, context.process.pid.unwrap_or_default(),
+                    context.process.package_name, context.process.executable);
+            }
+            debug!("Participant {} discovered in domain {}: vendor {}, host {}", context.guid, context.domain_id, context.vendor, context.host.ip);
+            self.state.lock().unwrap().add_context(context);
+            if let Err(_) = self.state_tx.update(Some(self.state.lock().unwrap().clone())) {
+                warn!("Unable to send state in handle_discovered_context");
+            }
+        }
 
         fn show_sub_info(&self, subscriber: Ros2Subscriber, removed: bool) {
             if removed {
@@ -190,7 +360,7 @@ pub mod discovery_server {
             } else {
                 debug!("------------------------On reader discovery-----------------------");
             }
-            debug!("Node name: {}", subscriber.node_name);
+            debug!("Node name: {}", subscriber.node_fqn());
             debug!("Topic name: {}", subscriber.topic_name);
             debug!("Topic type: {}", subscriber.topic_type);
             debug!("Topic guid: {}", subscriber.guid);
@@ -205,7 +375,7 @@ pub mod discovery_server {
             } else {
                 debug!("------------------------On writer discovery-----------------------");
             }
-            debug!("Node name: {}", publisher.node_name);
+            debug!("Node name: {}", publisher.node_fqn());
             debug!("Topic name: {}", publisher.topic_name);
             debug!("Topic type: {}", publisher.topic_type);
             debug!("Topic guid: {}", publisher.guid);
@@ -215,18 +385,16 @@ pub mod discovery_server {
         }
 
         fn handle_removed_publisher(&self, mut publisher: Ros2Publisher) {
-            publisher.node_name = match self.node_name_by_gid(publisher.topic_name.clone(), publisher.guid.clone()) {
+            publisher.node_name = match self.node_name_by_gid(publisher.domain_id, publisher.topic_name.clone(), publisher.guid.clone()) {
                 Ok(node_name) => node_name,
                 Err(_error_str) => "unknown".to_string()
             };
-            publisher.topic_type = if self.discovery_flags.contains(DiscoveryFlags::UseInternalNames) {
-                publisher.topic_type
-            } else {
-                match self.topic_type_by_name(publisher.topic_name.clone()) {
-                    Ok(name) => name,
-                    Err(err) => publisher.topic_type.clone()
-                }
-            };
+            let (namespace, name) = split_node_fqn(publisher.node_name.as_str());
+            publisher.node_namespace = namespace;
+            publisher.node_name = name;
+            if !self.discovery_flags.contains(DiscoveryFlags::UseInternalNames) {
+                publisher.topic_type = demangle_type(publisher.topic_type.as_str());
+            }
             if publisher.host.ip != "SHM" {
                 publisher.host.name = hostname_ip(publisher.clone().host.ip);
             }
@@ -242,21 +410,19 @@ pub mod discovery_server {
         }
 
         fn handle_removed_subscriber(&self, mut subscriber: Ros2Subscriber) {
-            subscriber.node_name = match self.node_name_by_gid(subscriber.topic_name.clone(), subscriber.guid.clone()) {
+            subscriber.node_name = match self.node_name_by_gid(subscriber.domain_id, subscriber.topic_name.clone(), subscriber.guid.clone()) {
                 Ok(node_name) => node_name,
                 Err(_error_str) => "unknown".to_string()
             };
+            let (namespace, name) = split_node_fqn(subscriber.node_name.as_str());
+            subscriber.node_namespace = namespace;
+            subscriber.node_name = name;
             if subscriber.host.ip != "SHM" {
                 subscriber.host.name = hostname_ip(subscriber.clone().host.ip);
             }
-            subscriber.topic_type = if self.discovery_flags.contains(DiscoveryFlags::UseInternalNames) {
-                subscriber.topic_type
-            } else {
-                match self.topic_type_by_name(subscriber.topic_name.clone()) {
-                    Ok(name) => name,
-                    Err(err) => subscriber.topic_type.clone()
-                }
-            };
+            if !self.discovery_flags.contains(DiscoveryFlags::UseInternalNames) {
+                subscriber.topic_type = demangle_type(subscriber.topic_type.as_str());
+            }
             self.show_sub_info(subscriber.clone(), true);
             if !subscriber.node_name.is_empty() && subscriber.node_name != "_NODE_NAME_UNKNOWN_" {
                 self.state.lock().unwrap().remove_subscriber(subscriber.clone());
@@ -268,64 +434,235 @@ pub mod discovery_server {
             }
         }
 
-        fn handle_removed_context(&self, context: Ros2Context) {}
+        /// Sample resources of local processes and attach them to nodes
+        fn sample_resources(&self) {
+            let pids = self.state.lock().unwrap().local_pids();
+            let samples = self.resource_monitor.sample(&pids);
+            if pids.is_empty() {
+                return;
+            }
+            let mut state = self.state.lock().unwrap();
+            state.set_resources(&samples);
+            if let Err(_) = self.state_tx.update(Some(state.clone())) {
+                warn!("Unable to send state in sample_resources");
+            }
+        }
+
+        /// Store explored packages and attribute already discovered local participants to them
+        fn handle_explored_packages(&self, packages: Vec<Ros2Package>) {
+            info!("{} packages explored", packages.len());
+            // Packages may be installed out of sourced prefixes, their interfaces are resolved too
+            self.type_registry.add_prefixes(packages.iter().filter(|package| !package.path.is_empty()).map(|package| PathBuf::from(package.path.as_str())).collect());
+            let mut state = self.state.lock().unwrap();
+            state.packages = packages;
+            let local_contexts: Vec<Ros2Context> = state.contexts().iter().filter(|context| context.process.pid.is_some()).cloned().collect();
+            for mut context in local_contexts {
+                if attach_local_process(&mut context, &state.packages) {
+                    state.add_context(context);
+                }
+            }
+            if let Err(_) = self.state_tx.update(Some(state.clone())) {
+                warn!("Unable to send state in handle_explored_packages");
+            }
+        }
+
+        /// Accumulate captured traffic in the traffic monitor. The graph doesn't change, so the state
+        /// isn't published; api resolves the accumulated traffic against the state on request
+        fn handle_wire_activity(&self, activity: Vec<WireActivity>) {
+            if activity.is_empty() {
+                return;
+            }
+            self.traffic_monitor.record(&activity);
+        }
+
+        /// Keep the sample reported by FastDDS statistics module until the next flush
+        fn handle_dds_statistics(&mut self, sample: DdsStatisticsSample) {
+            if self.pending_statistics.len() >= MAX_PENDING_STATISTICS {
+                debug!("Statistics sample of {} is dropped", sample.guid);
+                return;
+            }
+            self.pending_statistics.push(sample);
+        }
+
+        /// Update statistics of the entities by the samples received since the previous flush
+        fn flush_dds_statistics(&mut self) {
+            if self.pending_statistics.is_empty() {
+                return;
+            }
+            let mut state = self.state.lock().unwrap();
+            for sample in self.pending_statistics.drain(..) {
+                state.add_dds_statistics(sample);
+            }
+            if let Err(_) = self.state_tx.update(Some(state.clone())) {
+                warn!("Unable to send state in flush_dds_statistics");
+            }
+        }
+
+        /// Handle removed or dropped participant. All its nodes and endpoints are removed too
+        fn handle_removed_context(&self, context: Ros2Context) {
+            debug!("Participant {} removed from domain {}", context.guid, context.domain_id);
+            self.traffic_monitor.forget(context.guid.as_str());
+            self.state.lock().unwrap().remove_context(context);
+            if let Err(_) = self.state_tx.update(Some(self.state.lock().unwrap().clone())) {
+                warn!("Unable to send state in handle_removed_context");
+            }
+        }
 
         pub fn run(&mut self)
         {
             debug!("run_discovery_server");
 
-            // Discover packages
-            /*if self.discovery_flags.contains(DiscoveryFlags::EnableROS2) {
-                // We have to explore package once at booting or by request
-                let packages = self.ros2_discoverer.explore_packages();
-                for package in packages {
-                    self.state.lock().unwrap().add_package(package);
-                }
-            }*/
+            // Discover packages. Exploration takes a while, so it runs in background and doesn't delay discovery
+            if self.discovery_flags.contains(DiscoveryFlags::EnableROS2) {
+                let ros2_discoverer = self.ros2_discoverer();
+                let control_tx = self.control_tx.clone();
+                thread::spawn(move || {
+                    let packages = ros2_discoverer.explore_packages();
+                    if let Err(_) = control_tx.send(DiscoveryControl::PackagesExplored(packages)) {
+                        warn!("Unable to send explored packages");
+                    }
+                });
+            }
+
+            // Passive capture blocks on the socket, so it runs in its own thread and sends activity in batches
+            if let Some(source) = self.capture_source.clone() {
+                let control_tx = self.control_tx.clone();
+                let port_params = self.port_params.clone();
+                thread::spawn(move || {
+                    let res = capture::run(source, port_params, |activity| control_tx.send(DiscoveryControl::WireActivity(activity)).is_ok());
+                    if let Err(e) = res {
+                        error!("Capture stopped: {}", e);
+                    }
+                });
+            }
 
             // FastDDS callbacks
             // init fastdds discovery server
             if self.discovery_flags.contains(DiscoveryFlags::EnableFastdds) {
-                self.fastdds_discoverer.run();
+                // Servers of all domains are created in parallel
+                for fastdds_discoverer in self.fastdds_discoverers.iter_mut() {
+                    fastdds_discoverer.run();
+                }
+                for fastdds_discoverer in self.fastdds_discoverers.iter_mut() {
+                    fastdds_discoverer.wait_started();
+                }
             }
 
 
-            let handle_event_pub = |publisher: Ros2Publisher, event_type: FastDDSEvent| {
-                match event_type {
-                    FastDDSEvent::PublisherDiscovered => self.handle_discovered_publisher(publisher),
-                    FastDDSEvent::PublisherRemoved => self.handle_removed_publisher(publisher),
-                    _ => { panic!("It shouldn't be happened") }
-                };
-            };
-            let handle_event_sub = |subscriber: Ros2Subscriber, event_type: FastDDSEvent| {
-                match event_type {
-                    FastDDSEvent::SubscriberDiscovered => self.handle_discovered_subscriber(subscriber),
-                    FastDDSEvent::SubscriberRemoved => self.handle_removed_subscriber(subscriber),
-                    _ => { panic!("It shouldn't be happened") }
+            enum Event {
+                Fastdds((FastDDSEvent, FastDDSEntity)),
+                Control(DiscoveryControl),
+            }
+            // Resources are sampled and statistics are flushed between events, so busy discovery doesn't postpone them
+            let mut next_sample = Instant::now();
+            let mut next_flush = Instant::now() + STATISTICS_FLUSH_INTERVAL;
+            loop {
+                let event = flume::Selector::new()
+                    .recv(&self.fastdds_rx, |res| res.map(Event::Fastdds))
+                    .recv(&self.control_rx, |res| res.map(Event::Control))
+                    .wait_deadline(next_sample.min(next_flush));
+                match event {
+                    Ok(Ok(Event::Fastdds((event_type, data)))) => self.handle_fastdds_event(event_type, data),
+                    Ok(Ok(Event::Control(DiscoveryControl::ApplySettings(settings)))) => self.apply_settings(settings),
+                    Ok(Ok(Event::Control(DiscoveryControl::PackagesExplored(packages)))) => self.handle_explored_packages(packages),
+                    Ok(Ok(Event::Control(DiscoveryControl::WireActivity(activity)))) => self.handle_wire_activity(activity),
+                    Ok(Err(_)) => break,
+                    Err(_) => {}
                 }
-            };
-            let handle_event_context = |context: Ros2Context, event_type: FastDDSEvent| {
-                match event_type {
-                    FastDDSEvent::ContextDiscovered => self.handle_discovered_context(context),
-                    FastDDSEvent::ContextRemoved => self.handle_removed_context(context),
-                    _ => { panic!("It shouldn't be happened") }
+                if Instant::now() >= next_sample {
+                    self.sample_resources();
+                    next_sample = Instant::now() + SAMPLE_INTERVAL;
                 }
-            };
-            while let Ok((event_type, data)) = self.fastdds_discoverer.rx.recv() {
-                match data {
-                    FastDDSEntity::Publisher(publisher) => handle_event_pub(publisher, event_type),
-                    FastDDSEntity::Subscriber(subscriber) => handle_event_sub(subscriber, event_type),
-                    FastDDSEntity::Context(context) => handle_event_context(context, event_type),
-                    _ => { panic!("It shouldn't be happened") }
+                if Instant::now() >= next_flush {
+                    self.flush_dds_statistics();
+                    next_flush = Instant::now() + STATISTICS_FLUSH_INTERVAL;
                 }
             }
             panic!("AAAAAAAAAAAAAAAAAAAAA");
         }
 
-        pub fn stop(&self) {
+        fn handle_fastdds_event(&mut self, event_type: FastDDSEvent, data: FastDDSEntity) {
+            match (data, event_type) {
+                (FastDDSEntity::Publisher(publisher), FastDDSEvent::PublisherDiscovered) => self.handle_discovered_publisher(publisher),
+                (FastDDSEntity::Publisher(publisher), FastDDSEvent::PublisherRemoved) => self.handle_removed_publisher(publisher),
+                (FastDDSEntity::Subscriber(subscriber), FastDDSEvent::SubscriberDiscovered) => self.handle_discovered_subscriber(subscriber),
+                (FastDDSEntity::Subscriber(subscriber), FastDDSEvent::SubscriberRemoved) => self.handle_removed_subscriber(subscriber),
+                (FastDDSEntity::Context(context), FastDDSEvent::ContextDiscovered) => self.handle_discovered_context(context),
+                (FastDDSEntity::Context(context), FastDDSEvent::ContextRemoved) => self.handle_removed_context(context),
+                (FastDDSEntity::Statistics(sample), FastDDSEvent::StatisticsReceived) => self.handle_dds_statistics(sample),
+                (FastDDSEntity::Type(message), FastDDSEvent::TypeDiscovered) => {
+                    debug!("Type {} discovered", message.name);
+                    self.type_registry.add_discovered(message);
+                }
+                _ => { panic!("It shouldn't be happened") }
+            }
+        }
+
+        /// Apply settings changed at runtime.
+        /// Domain ids, discovery mode, visibility options (`include_internals`, `dds_topic_type`) and `dds_statistics`
+        /// are applied while entities are discovered, so FastDDS discoverer is restarted with empty state to rediscover the graph.
+        /// Ros2 discoverer just gets new parameters. Other settings aren't related to discovery.
+        /// # Arguments
+        ///
+        /// * `settings`: new settings
+        ///
+        /// returns: ()
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        fn apply_settings(&mut self, settings: Settings) {
+            let discovery_flags = DiscoveryFlags::from_settings(&settings);
+            if settings.domain_ids == self.domain_ids && discovery_flags == self.discovery_flags {
+                debug!("Settings don't affect discovery");
+                return;
+            }
+
+            info!("Restarting discovery: domain ids {:?} -> {:?}, flags {:?} -> {:?}",
+                self.domain_ids, settings.domain_ids, self.discovery_flags, discovery_flags);
+
+            self.stop();
+
+            self.domain_ids = settings.domain_ids.clone();
+            self.discovery_flags = discovery_flags;
+            *self.ros2_discoverer.write().unwrap() = Ros2Discoverer::new(ros2_discoverer_params(&settings));
+
+            // Entities of the previous run are rediscovered with new options, explored packages are kept
+            let mut state = Ros2State::new(self.discovery_flags.contains(DiscoveryFlags::IncludeInternals));
+            state.packages = self.state.lock().unwrap().packages.clone();
+            *self.state.lock().unwrap() = state.clone();
+            if let Err(_) = self.state_tx.update(Some(state)) {
+                warn!("Unable to send state in apply_settings");
+            }
+
+            // Drop events of the stopped discoverers
+            self.fastdds_rx.drain();
+            self.pending_statistics.clear();
+            // New domains need their server profiles
+            if self.discovery_flags.discovery_mode() == DiscoveryMode::Server {
+                if let Err(e) = fastdds_profiles::write(&settings) {
+                    error!("{}", e);
+                }
+            }
+            self.fastdds_discoverers = create_fastdds_discoverers(&settings, &self.discovery_flags, &self.fastdds_tx);
+            if self.discovery_flags.contains(DiscoveryFlags::EnableFastdds) {
+                // Servers of all domains are created in parallel
+                for fastdds_discoverer in self.fastdds_discoverers.iter_mut() {
+                    fastdds_discoverer.run();
+                }
+                for fastdds_discoverer in self.fastdds_discoverers.iter_mut() {
+                    fastdds_discoverer.wait_started();
+                }
+            }
+        }
+
+        /// Stop discovery servers of all running domains
+        pub fn stop(&mut self) {
             debug!("stop_discovery_server");
-            unsafe {
-                stop_discovery_server_impl(self.domain_id);
+            for fastdds_discoverer in self.fastdds_discoverers.iter_mut() {
+                fastdds_discoverer.stop();
             }
         }
 
@@ -335,7 +672,7 @@ pub mod discovery_server {
                 return true;
             }
 
-            if self.discovery_flags.contains(DiscoveryFlags::EnableFastdds) && self.fastdds_discoverer.running {
+            if self.discovery_flags.contains(DiscoveryFlags::EnableFastdds) && self.fastdds_discoverers.iter().any(|discoverer| discoverer.running) {
                 return true;
             }
 
@@ -347,7 +684,7 @@ pub mod discovery_server {
                 return Err("You must enable ros2 support and run discovery server to perform this action".to_string());
             }
 
-            return Ok(self.ros2_discoverer.lifecycle_state(node_name));
+            return Ok(self.ros2_discoverer().lifecycle_state(node_name));
         }
 
         pub fn lifecycled_node_names(&self) -> Result<Vec<String>, String> {
@@ -355,14 +692,14 @@ pub mod discovery_server {
                 return Err("You must enable ros2 support and run discovery server to perform this action".to_string());
             }
 
-            return Ok(self.ros2_discoverer.lifecycled_node_names());
+            return Ok(self.ros2_discoverer().lifecycled_node_names());
         }
 
         pub fn is_node_lifecycle(&self, node_name: String) -> Result<bool, String> {
             if !self.discovery_flags.contains(DiscoveryFlags::EnableROS2) {
                 return Err("You must enable ros2 support and run discovery server to perform this action".to_string());
             }
-            return Ok(self.ros2_discoverer.is_node_lifecycle(node_name));
+            return Ok(self.ros2_discoverer().is_node_lifecycle(node_name));
         }
 
         ///  This function may be well applied only for lifecycle nodes.
@@ -382,7 +719,7 @@ pub mod discovery_server {
             if !self.discovery_flags.contains(DiscoveryFlags::EnableROS2) {
                 return Err("You must enable ros2 support and run discovery server to perform this action".to_string());
             }
-            return Ok(self.ros2_discoverer.shutdown_node(node_name));
+            return Ok(self.ros2_discoverer().shutdown_node(node_name, None));
         }
 
         pub fn run_sample_node(&self) -> Result<String, String> {
@@ -390,7 +727,7 @@ pub mod discovery_server {
                 return Err("You must enable ros2 support and run discovery server to perform this action".to_string());
             }
 
-            return Ok(self.ros2_discoverer.run_sample_node());
+            return Ok(self.ros2_discoverer().run_sample_node());
         }
 
         pub fn is_node_running(&self, node_name: String) -> bool {
@@ -406,7 +743,7 @@ pub mod discovery_server {
                 return Err("You must enable ros2 support and run discovery server to perform this action".to_string());
             }
 
-            Ok(self.ros2_discoverer.run_node(node_name, args))
+            Ok(self.ros2_discoverer().run_node(node_name, args))
         }
 
         pub fn explore_packages(&self) -> Result<Vec<Ros2Package>, String> {
@@ -414,7 +751,7 @@ pub mod discovery_server {
                 return Err("You must enable ros2 support and run discovery server to perform this action".to_string());
             }
 
-            Ok(self.ros2_discoverer.explore_packages())
+            Ok(self.ros2_discoverer().explore_packages())
         }
 
         pub fn ros2_topic_names(&self) -> Result<Vec<String>, String> {
@@ -422,7 +759,7 @@ pub mod discovery_server {
                 return Err("You must enable ros2 support and run discovery server to perform this action".to_string());
             }
 
-            Ok(self.ros2_discoverer.ros2_topic_names())
+            Ok(self.ros2_discoverer().ros2_topic_names())
         }
 
         pub fn ros2_package_names(&self) -> Result<Vec<String>, String> {
@@ -430,7 +767,7 @@ pub mod discovery_server {
                 return Err("You must enable ros2 support and run discovery server to perform this action".to_string());
             }
 
-            Ok(self.ros2_discoverer.ros2_package_names())
+            Ok(self.ros2_discoverer().ros2_package_names())
         }
 
         pub fn explore_topics(&self) -> Result<Vec<Ros2Topic>, String> {
@@ -438,14 +775,14 @@ pub mod discovery_server {
                 return Err("You must enable ros2 support and run discovery server to perform this action".to_string());
             }
 
-            Ok(self.ros2_discoverer.explore_topics())
+            Ok(self.ros2_discoverer().explore_topics())
         }
 
         pub fn topic_info(&self, topic_name: String) -> Result<String, String> {
             if !self.discovery_flags.contains(DiscoveryFlags::EnableROS2) {
                 return Err("You must enable ros2 support and run discovery server to perform this action".to_string());
             }
-            Ok(self.ros2_discoverer.topic_info(topic_name))
+            Ok(self.ros2_discoverer().topic_info(topic_name))
         }
 
         pub fn package_path(&self, package_name: String) -> Result<String, String> {
@@ -453,7 +790,7 @@ pub mod discovery_server {
                 return Err("You must enable ros2 support and run discovery server to perform this action".to_string());
             }
 
-            Ok(self.ros2_discoverer.package_path(package_name))
+            Ok(self.ros2_discoverer().package_path(package_name))
         }
 
         /// Find package prefix from for specified package
@@ -474,7 +811,7 @@ pub mod discovery_server {
                 return Err("You must enable ros2 support and run discovery server to perform this action".to_string());
             }
 
-            Ok(self.ros2_discoverer.package_prefix(package_name))
+            Ok(self.ros2_discoverer().package_prefix(package_name))
         }
 
         pub fn ros2_node_names(&self) -> Result<Vec<String>, String> {
@@ -482,42 +819,35 @@ pub mod discovery_server {
                 return Err("You must enable ros2 support and run discovery server to perform this action".to_string());
             }
 
-            Ok(self.ros2_discoverer.ros2_node_names())
+            Ok(self.ros2_discoverer().ros2_node_names())
         }
 
         pub fn ros2_subscriber_names(&self) -> Result<Vec<String>, String> {
             if !self.discovery_flags.contains(DiscoveryFlags::EnableROS2) {
                 return Err("You must enable ros2 support and run discovery server to perform this action".to_string());
             }
-            Ok(self.ros2_discoverer.ros2_subscriber_names())
-        }
-
-        pub fn node_name_by_gid(&self, topic_name: String, gid_search: String) -> Result<String, String> {
-            if !self.discovery_flags.contains(DiscoveryFlags::EnableROS2) {
-                return Err("You must enable ros2 support and run discovery server to perform this action".to_string());
-            }
-            Ok(self.ros2_discoverer.node_name_by_gid(topic_name, gid_search))
+            Ok(self.ros2_discoverer().ros2_subscriber_names())
         }
 
-        pub fn topic_type_by_name(&self, topic_name: String) -> Result<String, String> {
+        pub fn node_name_by_gid(&self, domain_id: u32, topic_name: String, gid_search: String) -> Result<String, String> {
             if !self.discovery_flags.contains(DiscoveryFlags::EnableROS2) {
                 return Err("You must enable ros2 support and run discovery server to perform this action".to_string());
             }
-            self.ros2_discoverer.topic_type(topic_name)
+            Ok(self.ros2_discoverer().for_domain(domain_id).node_name_by_gid(topic_name, gid_search))
         }
 
         pub fn ros2_executable_names(&self, package_name: String) -> Result<Vec<String>, String> {
             if !self.discovery_flags.contains(DiscoveryFlags::EnableROS2) {
                 return Err("You must enable ros2 support and run discovery server to perform this action".to_string());
             }
-            Ok(self.ros2_discoverer.ros2_executable_names(package_name))
+            Ok(self.ros2_discoverer().ros2_executable_names(package_name))
         }
 
         pub fn node_info(&self, node_name: String) -> Result<String, String> {
             if !self.discovery_flags.contains(DiscoveryFlags::EnableROS2) {
                 return Err("You must enable ros2 support and run discovery server to perform this action".to_string());
             }
-            Ok(self.ros2_discoverer.node_info(node_name))
+            Ok(self.ros2_discoverer().node_info(node_name))
         }
     }
 }
\ No newline at end of file
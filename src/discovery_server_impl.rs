@@ -95,6 +95,7 @@ pub const SIG_ATOMIC_MAX: u32 = 2147483647;
 pub const SIZE_MAX: i32 = -1;
 pub const WINT_MIN: u32 = 0;
 pub const WINT_MAX: u32 = 4294967295;
+pub const MAX_LOCATORS: u32 = 4;
 pub const GUID_PREFIX_SIZE: u32 = 12;
 pub type size_t = ::std::os::raw::c_ulong;
 pub type wchar_t = ::std::os::raw::c_int;
@@ -257,6 +258,9 @@ pub const rmw_transport_UPDV6_TRANSPORT: rmw_transport = 3;
 pub const rmw_transport_TCPV4_TRANSPORT: rmw_transport = 4;
 pub const rmw_transport_TCPV6_TRANSPORT: rmw_transport = 5;
 pub type rmw_transport = ::std::os::raw::c_uint;
+pub const discovery_mode_SERVER_DISCOVERY_MODE: discovery_mode = 0;
+pub const discovery_mode_SIMPLE_DISCOVERY_MODE: discovery_mode = 1;
+pub type discovery_mode = ::std::os::raw::c_uint;
 #[repr(C)]
 #[derive(Copy, Clone)]
 pub struct FastDDSEndpoint {
@@ -344,16 +348,80 @@ fn bindgen_test_layout_FastDDSEndpoint() {
 }
 #[repr(C)]
 #[derive(Copy, Clone)]
+pub struct FastDDSLocators {
+    pub unicast: [FastDDSEndpoint; 4usize],
+    pub multicast: [FastDDSEndpoint; 4usize],
+    pub unicast_num: u8,
+    pub multicast_num: u8,
+}
+#[test]
+fn bindgen_test_layout_FastDDSLocators() {
+    assert_eq!(
+        ::std::mem::size_of::<FastDDSLocators>(),
+        196usize,
+        concat!("Size of: ", stringify!(FastDDSLocators))
+    );
+    assert_eq!(
+        ::std::mem::align_of::<FastDDSLocators>(),
+        4usize,
+        concat!("Alignment of ", stringify!(FastDDSLocators))
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<FastDDSLocators>())).unicast as *const _ as usize },
+        0usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(FastDDSLocators),
+            "::",
+            stringify!(unicast)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<FastDDSLocators>())).multicast as *const _ as usize },
+        96usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(FastDDSLocators),
+            "::",
+            stringify!(multicast)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<FastDDSLocators>())).unicast_num as *const _ as usize },
+        192usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(FastDDSLocators),
+            "::",
+            stringify!(unicast_num)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<FastDDSLocators>())).multicast_num as *const _ as usize },
+        193usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(FastDDSLocators),
+            "::",
+            stringify!(multicast_num)
+        )
+    );
+}
+#[repr(C)]
+#[derive(Copy, Clone)]
 pub struct ParticipantData {
     pub participant: *mut ::std::os::raw::c_void,
-    pub endpoint: FastDDSEndpoint,
+    pub locators: FastDDSLocators,
     pub guid: [::std::os::raw::c_uchar; 12usize],
+    pub vendor_id: [::std::os::raw::c_uchar; 2usize],
+    pub name: [::std::os::raw::c_char; 255usize],
+    pub user_data: [::std::os::raw::c_char; 255usize],
 }
 #[test]
 fn bindgen_test_layout_ParticipantData() {
     assert_eq!(
         ::std::mem::size_of::<ParticipantData>(),
-        48usize,
+        728usize,
         concat!("Size of: ", stringify!(ParticipantData))
     );
     assert_eq!(
@@ -372,18 +440,18 @@ fn bindgen_test_layout_ParticipantData() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<ParticipantData>())).endpoint as *const _ as usize },
+        unsafe { &(*(::std::ptr::null::<ParticipantData>())).locators as *const _ as usize },
         8usize,
         concat!(
             "Offset of field: ",
             stringify!(ParticipantData),
             "::",
-            stringify!(endpoint)
+            stringify!(locators)
         )
     );
     assert_eq!(
         unsafe { &(*(::std::ptr::null::<ParticipantData>())).guid as *const _ as usize },
-        32usize,
+        204usize,
         concat!(
             "Offset of field: ",
             stringify!(ParticipantData),
@@ -391,6 +459,36 @@ fn bindgen_test_layout_ParticipantData() {
             stringify!(guid)
         )
     );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<ParticipantData>())).vendor_id as *const _ as usize },
+        216usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(ParticipantData),
+            "::",
+            stringify!(vendor_id)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<ParticipantData>())).name as *const _ as usize },
+        218usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(ParticipantData),
+            "::",
+            stringify!(name)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<ParticipantData>())).user_data as *const _ as usize },
+        473usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(ParticipantData),
+            "::",
+            stringify!(user_data)
+        )
+    );
 }
 #[repr(C)]
 #[derive(Copy, Clone)]
@@ -398,13 +496,14 @@ pub struct WriterData {
     pub topic_name: [::std::os::raw::c_char; 255usize],
     pub type_name: [::std::os::raw::c_char; 255usize],
     pub guid_prefix: [::std::os::raw::c_uchar; 12usize],
-    pub endpoint: FastDDSEndpoint,
+    pub entity_id: [::std::os::raw::c_uchar; 4usize],
+    pub locators: FastDDSLocators,
 }
 #[test]
 fn bindgen_test_layout_WriterData() {
     assert_eq!(
         ::std::mem::size_of::<WriterData>(),
-        548usize,
+        724usize,
         concat!("Size of: ", stringify!(WriterData))
     );
     assert_eq!(
@@ -443,13 +542,23 @@ fn bindgen_test_layout_WriterData() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<WriterData>())).endpoint as *const _ as usize },
-        524usize,
+        unsafe { &(*(::std::ptr::null::<WriterData>())).entity_id as *const _ as usize },
+        522usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(WriterData),
+            "::",
+            stringify!(entity_id)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<WriterData>())).locators as *const _ as usize },
+        528usize,
         concat!(
             "Offset of field: ",
             stringify!(WriterData),
             "::",
-            stringify!(endpoint)
+            stringify!(locators)
         )
     );
 }
@@ -459,13 +568,14 @@ pub struct ReaderData {
     pub topic_name: [::std::os::raw::c_char; 255usize],
     pub type_name: [::std::os::raw::c_char; 255usize],
     pub guid_prefix: [::std::os::raw::c_uchar; 12usize],
-    pub endpoint: FastDDSEndpoint,
+    pub entity_id: [::std::os::raw::c_uchar; 4usize],
+    pub locators: FastDDSLocators,
 }
 #[test]
 fn bindgen_test_layout_ReaderData() {
     assert_eq!(
         ::std::mem::size_of::<ReaderData>(),
-        548usize,
+        724usize,
         concat!("Size of: ", stringify!(ReaderData))
     );
     assert_eq!(
@@ -504,13 +614,200 @@ fn bindgen_test_layout_ReaderData() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<ReaderData>())).endpoint as *const _ as usize },
-        524usize,
+        unsafe { &(*(::std::ptr::null::<ReaderData>())).entity_id as *const _ as usize },
+        522usize,
         concat!(
             "Offset of field: ",
             stringify!(ReaderData),
             "::",
-            stringify!(endpoint)
+            stringify!(entity_id)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<ReaderData>())).locators as *const _ as usize },
+        528usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(ReaderData),
+            "::",
+            stringify!(locators)
+        )
+    );
+}
+pub const statistics_kind_HISTORY_LATENCY_STATISTICS: statistics_kind = 0;
+pub const statistics_kind_NETWORK_LATENCY_STATISTICS: statistics_kind = 1;
+pub const statistics_kind_PUBLICATION_THROUGHPUT_STATISTICS: statistics_kind = 2;
+pub const statistics_kind_SUBSCRIPTION_THROUGHPUT_STATISTICS: statistics_kind = 3;
+pub const statistics_kind_RESENT_DATA_STATISTICS: statistics_kind = 4;
+pub const statistics_kind_HEARTBEAT_COUNT_STATISTICS: statistics_kind = 5;
+pub const statistics_kind_ACKNACK_COUNT_STATISTICS: statistics_kind = 6;
+pub const statistics_kind_RTPS_LOST_STATISTICS: statistics_kind = 7;
+pub type statistics_kind = ::std::os::raw::c_uint;
+#[doc = " Sample of FastDDS statistics topic. Fields which aren't used by the kind are zeroed"]
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct StatisticsData {
+    pub kind: statistics_kind,
+    pub guid_prefix: [::std::os::raw::c_uchar; 12usize],
+    pub entity_id: [::std::os::raw::c_uchar; 4usize],
+    pub remote_guid_prefix: [::std::os::raw::c_uchar; 12usize],
+    pub remote_entity_id: [::std::os::raw::c_uchar; 4usize],
+    pub src_locator: FastDDSEndpoint,
+    pub dst_locator: FastDDSEndpoint,
+    pub value: f64,
+    pub count: u64,
+    pub bytes: u64,
+}
+#[test]
+fn bindgen_test_layout_StatisticsData() {
+    assert_eq!(
+        ::std::mem::size_of::<StatisticsData>(),
+        112usize,
+        concat!("Size of: ", stringify!(StatisticsData))
+    );
+    assert_eq!(
+        ::std::mem::align_of::<StatisticsData>(),
+        8usize,
+        concat!("Alignment of ", stringify!(StatisticsData))
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<StatisticsData>())).kind as *const _ as usize },
+        0usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(StatisticsData),
+            "::",
+            stringify!(kind)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<StatisticsData>())).guid_prefix as *const _ as usize },
+        4usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(StatisticsData),
+            "::",
+            stringify!(guid_prefix)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<StatisticsData>())).entity_id as *const _ as usize },
+        16usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(StatisticsData),
+            "::",
+            stringify!(entity_id)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<StatisticsData>())).remote_guid_prefix as *const _ as usize },
+        20usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(StatisticsData),
+            "::",
+            stringify!(remote_guid_prefix)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<StatisticsData>())).remote_entity_id as *const _ as usize },
+        32usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(StatisticsData),
+            "::",
+            stringify!(remote_entity_id)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<StatisticsData>())).src_locator as *const _ as usize },
+        36usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(StatisticsData),
+            "::",
+            stringify!(src_locator)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<StatisticsData>())).dst_locator as *const _ as usize },
+        60usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(StatisticsData),
+            "::",
+            stringify!(dst_locator)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<StatisticsData>())).value as *const _ as usize },
+        88usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(StatisticsData),
+            "::",
+            stringify!(value)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<StatisticsData>())).count as *const _ as usize },
+        96usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(StatisticsData),
+            "::",
+            stringify!(count)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<StatisticsData>())).bytes as *const _ as usize },
+        104usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(StatisticsData),
+            "::",
+            stringify!(bytes)
+        )
+    );
+}
+#[doc = " Definition of the type received in discovery as XTypes type object"]
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct TypeData {
+    pub type_name: [::std::os::raw::c_char; 255usize],
+    pub definition: *const ::std::os::raw::c_char,
+}
+#[test]
+fn bindgen_test_layout_TypeData() {
+    assert_eq!(
+        ::std::mem::size_of::<TypeData>(),
+        264usize,
+        concat!("Size of: ", stringify!(TypeData))
+    );
+    assert_eq!(
+        ::std::mem::align_of::<TypeData>(),
+        8usize,
+        concat!("Alignment of ", stringify!(TypeData))
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<TypeData>())).type_name as *const _ as usize },
+        0usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(TypeData),
+            "::",
+            stringify!(type_name)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<TypeData>())).definition as *const _ as usize },
+        256usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(TypeData),
+            "::",
+            stringify!(definition)
         )
     );
 }
@@ -532,6 +829,107 @@ pub type on_reader_remove_callback_t = ::std::option::Option<
 pub type on_writer_remove_callback_t = ::std::option::Option<
     unsafe extern "C" fn(writer_data: WriterData, user_data: *mut ::std::os::raw::c_void),
 >;
+pub type on_statistics_callback_t = ::std::option::Option<
+    unsafe extern "C" fn(statistics_data: StatisticsData, user_data: *mut ::std::os::raw::c_void),
+>;
+pub type on_type_discovery_callback_t = ::std::option::Option<
+    unsafe extern "C" fn(type_data: TypeData, user_data: *mut ::std::os::raw::c_void),
+>;
+#[doc = " RTPS port mapping, the same as <port> element of FastDDS participant profile. Zero port base keeps defaults"]
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct RtpsPortParams {
+    pub port_base: u32,
+    pub domain_id_gain: u32,
+    pub participant_id_gain: u32,
+    pub offset_d0: u32,
+    pub offset_d1: u32,
+    pub offset_d2: u32,
+    pub offset_d3: u32,
+}
+#[test]
+fn bindgen_test_layout_RtpsPortParams() {
+    assert_eq!(
+        ::std::mem::size_of::<RtpsPortParams>(),
+        28usize,
+        concat!("Size of: ", stringify!(RtpsPortParams))
+    );
+    assert_eq!(
+        ::std::mem::align_of::<RtpsPortParams>(),
+        4usize,
+        concat!("Alignment of ", stringify!(RtpsPortParams))
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<RtpsPortParams>())).port_base as *const _ as usize },
+        0usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(RtpsPortParams),
+            "::",
+            stringify!(port_base)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<RtpsPortParams>())).domain_id_gain as *const _ as usize },
+        4usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(RtpsPortParams),
+            "::",
+            stringify!(domain_id_gain)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<RtpsPortParams>())).participant_id_gain as *const _ as usize },
+        8usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(RtpsPortParams),
+            "::",
+            stringify!(participant_id_gain)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<RtpsPortParams>())).offset_d0 as *const _ as usize },
+        12usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(RtpsPortParams),
+            "::",
+            stringify!(offset_d0)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<RtpsPortParams>())).offset_d1 as *const _ as usize },
+        16usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(RtpsPortParams),
+            "::",
+            stringify!(offset_d1)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<RtpsPortParams>())).offset_d2 as *const _ as usize },
+        20usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(RtpsPortParams),
+            "::",
+            stringify!(offset_d2)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<RtpsPortParams>())).offset_d3 as *const _ as usize },
+        24usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(RtpsPortParams),
+            "::",
+            stringify!(offset_d3)
+        )
+    );
+}
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct DiscoveryServerParams {
@@ -541,12 +939,19 @@ pub struct DiscoveryServerParams {
     pub participant_removed_callback: on_participant_remove_callback_t,
     pub reader_removed_callback: on_reader_remove_callback_t,
     pub writer_removed_callback: on_writer_remove_callback_t,
+    pub profiles_file: *const ::std::os::raw::c_char,
+    pub profile_name: *const ::std::os::raw::c_char,
+    pub discovery_mode: u8,
+    pub statistics_callback: on_statistics_callback_t,
+    pub type_discovery_callback: on_type_discovery_callback_t,
+    pub server_port: u16,
+    pub port_params: RtpsPortParams,
 }
 #[test]
 fn bindgen_test_layout_DiscoveryServerParams() {
     assert_eq!(
         ::std::mem::size_of::<DiscoveryServerParams>(),
-        48usize,
+        120usize,
         concat!("Size of: ", stringify!(DiscoveryServerParams))
     );
     assert_eq!(
@@ -632,6 +1037,92 @@ fn bindgen_test_layout_DiscoveryServerParams() {
             stringify!(writer_removed_callback)
         )
     );
+    assert_eq!(
+        unsafe {
+            &(*(::std::ptr::null::<DiscoveryServerParams>())).profiles_file as *const _ as usize
+        },
+        48usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(DiscoveryServerParams),
+            "::",
+            stringify!(profiles_file)
+        )
+    );
+    assert_eq!(
+        unsafe {
+            &(*(::std::ptr::null::<DiscoveryServerParams>())).profile_name as *const _ as usize
+        },
+        56usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(DiscoveryServerParams),
+            "::",
+            stringify!(profile_name)
+        )
+    );
+    assert_eq!(
+        unsafe {
+            &(*(::std::ptr::null::<DiscoveryServerParams>())).discovery_mode as *const _ as usize
+        },
+        64usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(DiscoveryServerParams),
+            "::",
+            stringify!(discovery_mode)
+        )
+    );
+    assert_eq!(
+        unsafe {
+            &(*(::std::ptr::null::<DiscoveryServerParams>())).statistics_callback as *const _
+                as usize
+        },
+        72usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(DiscoveryServerParams),
+            "::",
+            stringify!(statistics_callback)
+        )
+    );
+    assert_eq!(
+        unsafe {
+            &(*(::std::ptr::null::<DiscoveryServerParams>())).type_discovery_callback as *const _
+                as usize
+        },
+        80usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(DiscoveryServerParams),
+            "::",
+            stringify!(type_discovery_callback)
+        )
+    );
+    assert_eq!(
+        unsafe {
+            &(*(::std::ptr::null::<DiscoveryServerParams>())).server_port as *const _ as usize
+        },
+        88usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(DiscoveryServerParams),
+            "::",
+            stringify!(server_port)
+        )
+    );
+    assert_eq!(
+        unsafe {
+            &(*(::std::ptr::null::<DiscoveryServerParams>())).port_params as *const _ as usize
+        },
+        92usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(DiscoveryServerParams),
+            "::",
+            stringify!(port_params)
+        )
+    );
 }
 extern "C" {
     #[doc = " Run discovery server in separate thread."]
@@ -651,22 +1142,30 @@ extern "C" {
     pub fn is_discovery_running_impl(domain_id: u32) -> ::std::os::raw::c_int;
 }
 extern "C" {
-    pub fn register_on_participant_discovery_data(data: *mut ::std::os::raw::c_void);
+    #[doc = " Register user data passed to callbacks of the specified domain server."]
+    #[doc = " Must be called before run_discovery_server_impl for this domain."]
+    pub fn register_on_participant_discovery_data(domain_id: u32, data: *mut ::std::os::raw::c_void);
+}
+extern "C" {
+    pub fn register_on_reader_discovery_data(domain_id: u32, data: *mut ::std::os::raw::c_void);
+}
+extern "C" {
+    pub fn register_on_writer_discovery_data(domain_id: u32, data: *mut ::std::os::raw::c_void);
 }
 extern "C" {
-    pub fn register_on_reader_discovery_data(data: *mut ::std::os::raw::c_void);
+    pub fn register_on_participant_removed_data(domain_id: u32, data: *mut ::std::os::raw::c_void);
 }
 extern "C" {
-    pub fn register_on_writer_discovery_data(data: *mut ::std::os::raw::c_void);
+    pub fn register_on_reader_removed_data(domain_id: u32, data: *mut ::std::os::raw::c_void);
 }
 extern "C" {
-    pub fn register_on_participant_removed_data(data: *mut ::std::os::raw::c_void);
+    pub fn register_on_writer_removed_data(domain_id: u32, data: *mut ::std::os::raw::c_void);
 }
 extern "C" {
-    pub fn register_on_reader_removed_data(data: *mut ::std::os::raw::c_void);
+    pub fn register_on_statistics_data(domain_id: u32, data: *mut ::std::os::raw::c_void);
 }
 extern "C" {
-    pub fn register_on_writer_removed_data(data: *mut ::std::os::raw::c_void);
+    pub fn register_on_type_discovery_data(domain_id: u32, data: *mut ::std::os::raw::c_void);
 }
 extern "C" {
     pub fn rclcpp_init(argc: ::std::os::raw::c_int, argv: *const *const ::std::os::raw::c_char);
@@ -0,0 +1,330 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/// Generation of FastDDS XML profiles from settings. The generated file contains a participant profile
+/// of the monitor's discovery server for each monitored domain and the default super client profile,
+/// which is used by ros2 cli tools through FASTRTPS_DEFAULT_PROFILES_FILE.
+pub mod fastdds_profiles {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+    use crate::network::network::PortParams;
+    use crate::ros2entites::ros2entities::{RemoteServer, Settings};
+
+    pub const SERVER_ROLES: [&str; 3] = ["server", "backup", "super_client"];
+    /// Profile of ros2 cli tools. It is the default profile of the file
+    pub const CLI_PROFILE_NAME: &str = "ros2monitor_cli";
+
+    /// Name of the monitor's participant profile for the n-th monitored domain
+    pub fn server_profile_name(server_id: u8) -> String {
+        return format!("ros2monitor_server_{}", server_id);
+    }
+
+    /// Parse GUID prefix in FastDDS form, 12 hex bytes separated by dots
+    pub fn parse_guid_prefix(prefix: &str) -> Result<[u8; 12], String> {
+        let bytes: Vec<&str> = prefix.split('.').collect();
+        if bytes.len() != 12 {
+            return Err(format!("GUID prefix must contain 12 bytes, got {}", prefix));
+        }
+
+        let mut octets = [0u8; 12];
+        for (i, byte) in bytes.iter().enumerate() {
+            octets[i] = match u8::from_str_radix(byte, 16) {
+                Ok(octet) if byte.len() == 2 => octet,
+                _ => return Err(format!("Invalid byte {} in GUID prefix {}", byte, prefix))
+            };
+        }
+        return Ok(octets);
+    }
+
+    /// Parse locator in `ip:port` form, IPv6 address must be in brackets
+    pub fn parse_locator(locator: &str) -> Result<SocketAddr, String> {
+        return match locator.trim().parse::<SocketAddr>() {
+            Ok(address) => Ok(address),
+            Err(_) => Err(format!("Locator must be in ip:port form, got {}", locator))
+        };
+    }
+
+    /// GUID prefix of the n-th server. Server id is added to the third byte, as ROS_DISCOVERY_SERVER does
+    /// # Arguments
+    ///
+    /// * `prefix`: configured GUID prefix of the first server
+    /// * `server_id`: index of the monitored domain
+    ///
+    /// returns: Result<String, String>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn server_guid_prefix(prefix: &str, server_id: u8) -> Result<String, String> {
+        let mut octets = parse_guid_prefix(prefix)?;
+        octets[2] = octets[2].wrapping_add(server_id);
+        return Ok(octets.iter().map(|octet| format!("{:02x}", octet)).collect::<Vec<String>>().join("."));
+    }
+
+    /// Listening locators of the n-th server. Each next server uses ports greater by 2
+    pub fn server_locators(locators: &Vec<String>, server_id: u8) -> Result<Vec<SocketAddr>, String> {
+        let mut addresses = Vec::new();
+        for locator in locators.iter() {
+            let mut address = parse_locator(locator)?;
+            let port = address.port() as u32 + 2 * server_id as u32;
+            if port > u16::MAX as u32 {
+                return Err(format!("Port of locator {} for server {} is out of range", locator, server_id));
+            }
+            address.set_port(port as u16);
+            addresses.push(address);
+        }
+        return Ok(addresses);
+    }
+
+    fn locator_xml(address: &SocketAddr, indent: &str) -> String {
+        let transport = if address.is_ipv4() { "udpv4" } else { "udpv6" };
+        return format!("{indent}<locator>\n{indent}    <{transport}>\n{indent}        <address>{}</address>\n{indent}        <port>{}</port>\n{indent}    </{transport}>\n{indent}</locator>\n",
+                       address.ip(), address.port(), indent = indent, transport = transport);
+    }
+
+    fn locator_list_xml(addresses: &Vec<SocketAddr>, indent: &str) -> String {
+        let mut xml = format!("{}<metatrafficUnicastLocatorList>\n", indent);
+        for address in addresses.iter() {
+            xml += locator_xml(address, format!("{}    ", indent).as_str()).as_str();
+        }
+        xml += format!("{}</metatrafficUnicastLocatorList>\n", indent).as_str();
+        return xml;
+    }
+
+    fn servers_list_xml(servers: &Vec<(String, Vec<SocketAddr>)>, indent: &str) -> String {
+        let mut xml = format!("{}<discoveryServersList>\n", indent);
+        for (prefix, addresses) in servers.iter() {
+            xml += format!("{}    <RemoteServer prefix=\"{}\">\n", indent, prefix).as_str();
+            xml += locator_list_xml(addresses, format!("{}        ", indent).as_str()).as_str();
+            xml += format!("{}    </RemoteServer>\n", indent).as_str();
+        }
+        xml += format!("{}</discoveryServersList>\n", indent).as_str();
+        return xml;
+    }
+
+    fn remote_servers(remote_servers: &Vec<RemoteServer>) -> Result<Vec<(String, Vec<SocketAddr>)>, String> {
+        let mut servers = Vec::new();
+        for server in remote_servers.iter() {
+            parse_guid_prefix(server.guid_prefix.as_str())?;
+            let addresses: Result<Vec<SocketAddr>, String> = server.locators.iter().map(|locator| parse_locator(locator)).collect();
+            servers.push((server.guid_prefix.to_lowercase(), addresses?));
+        }
+        return Ok(servers);
+    }
+
+    /// Clients can't connect to the wildcard address, so the monitor is reached via loopback
+    fn connectable(address: &SocketAddr) -> SocketAddr {
+        let ip = match address.ip() {
+            IpAddr::V4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(ip) if ip.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            ip => ip
+        };
+        return SocketAddr::new(ip, address.port());
+    }
+
+    /// Port mapping element, omitted for the default mapping
+    fn port_xml(params: &PortParams, indent: &str) -> String {
+        if params.is_default() {
+            return "".to_string();
+        }
+        let values = [("portBase", params.port_base), ("domainIDGain", params.domain_id_gain), ("participantIDGain", params.participant_id_gain),
+            ("offsetd0", params.offset_d0), ("offsetd1", params.offset_d1), ("offsetd2", params.offset_d2), ("offsetd3", params.offset_d3)];
+        let mut xml = format!("{}<port>\n", indent);
+        for (name, value) in values {
+            xml += format!("{}    <{}>{}</{}>\n", indent, name, value, name).as_str();
+        }
+        xml += format!("{}</port>\n", indent).as_str();
+        return xml;
+    }
+
+    fn participant_xml(profile_name: &str, is_default: bool, protocol: &str, prefix: Option<&String>,
+                       servers: &Vec<(String, Vec<SocketAddr>)>, listening: &Vec<SocketAddr>, port_params: &PortParams) -> String {
+        let default_attribute = if is_default { " is_default_profile=\"true\"" } else { "" };
+        let mut xml = format!("    <participant profile_name=\"{}\"{}>\n        <rtps>\n", profile_name, default_attribute);
+        if let Some(prefix) = prefix {
+            xml += format!("            <prefix>{}</prefix>\n", prefix).as_str();
+        }
+        xml += port_xml(port_params, "            ").as_str();
+        xml += "            <builtin>\n                <discovery_config>\n";
+        xml += format!("                    <discoveryProtocol>{}</discoveryProtocol>\n", protocol).as_str();
+        if !servers.is_empty() {
+            xml += servers_list_xml(servers, "                    ").as_str();
+        }
+        xml += "                </discovery_config>\n";
+        if !listening.is_empty() {
+            xml += locator_list_xml(listening, "                ").as_str();
+        }
+        xml += "            </builtin>\n        </rtps>\n    </participant>\n";
+        return xml;
+    }
+
+    /// Generate profiles xml for all monitored domains
+    /// # Arguments
+    ///
+    /// * `settings`: validated settings
+    ///
+    /// returns: Result<String, String>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn generate(settings: &Settings) -> Result<String, String> {
+        let remote = remote_servers(&settings.remote_servers)?;
+        let is_server = settings.server_role != "super_client";
+        let protocol = match settings.server_role.as_str() {
+            "server" => "SERVER",
+            "backup" => "BACKUP",
+            "super_client" => "SUPER_CLIENT",
+            role => return Err(format!("server_role must be one of {:?}, got {}", SERVER_ROLES, role))
+        };
+
+        let mut xml = "<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n".to_string();
+        xml += "<!-- Generated by ros2monitor from its settings. Changes are overwritten on start -->\n";
+        xml += "<profiles xmlns=\"http://www.eprosima.com/XMLSchemas/fastRTPS_Profiles\">\n";
+
+        // Cli tools use the first domain, so they connect to the server of the first domain and to remote servers
+        let mut cli_servers = Vec::new();
+        for server_id in 0..settings.domain_ids.len() {
+            let server_id = server_id as u8;
+            let (prefix, listening) = if is_server {
+                (Some(server_guid_prefix(settings.server_guid_prefix.as_str(), server_id)?),
+                 server_locators(&settings.listening_locators, server_id)?)
+            } else {
+                (None, vec![])
+            };
+            if server_id == 0 {
+                if let Some(prefix) = prefix.as_ref() {
+                    cli_servers.push((prefix.clone(), listening.iter().map(|address| connectable(address)).collect()));
+                }
+            }
+            xml += participant_xml(server_profile_name(server_id).as_str(), false, protocol, prefix.as_ref(), &remote, &listening, &settings.port_params).as_str();
+        }
+
+        cli_servers.extend(remote.into_iter());
+        xml += participant_xml(CLI_PROFILE_NAME, true, "SUPER_CLIENT", None, &cli_servers, &vec![], &settings.port_params).as_str();
+        xml += "</profiles>\n";
+        return Ok(xml);
+    }
+
+    /// Value of ROS_DISCOVERY_SERVER for ros2 cli tools working in the domain of the n-th server. A non-empty
+    /// `discovery_server` setting is used as is, otherwise the list points at the first listening locator of the
+    /// monitor's server. The position in the list is the third byte of the server GUID prefix
+    /// # Arguments
+    ///
+    /// * `settings`: validated settings
+    /// * `server_id`: index of the monitored domain
+    ///
+    /// returns: Result<String, String>, empty string if the monitor has no own server
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn discovery_server_variable(settings: &Settings, server_id: u8) -> Result<String, String> {
+        if !settings.discovery_server.is_empty() {
+            return Ok(settings.discovery_server.clone());
+        }
+        if settings.server_role == "super_client" {
+            return Ok("".to_string());
+        }
+        let address = match server_locators(&settings.listening_locators, server_id)?.first() {
+            Some(address) => connectable(address),
+            None => return Ok("".to_string())
+        };
+        let position = parse_guid_prefix(settings.server_guid_prefix.as_str())?[2].wrapping_add(server_id) as usize;
+        let mut servers = vec!["".to_string(); position];
+        servers.push(address.to_string());
+        return Ok(servers.join(";"));
+    }
+
+    /// Generate profiles and write them into `fastdds_profiles_file`
+    pub fn write(settings: &Settings) -> Result<(), String> {
+        let xml = generate(settings)?;
+        return match std::fs::write(settings.fastdds_profiles_file.as_str(), xml) {
+            Ok(()) => Ok(()),
+            Err(e) => Err(format!("Unable to write FastDDS profiles into {}: {}", settings.fastdds_profiles_file, e))
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fastdds_profiles::fastdds_profiles::{discovery_server_variable, generate, parse_guid_prefix, server_guid_prefix, server_locators};
+    use crate::ros2entites::ros2entities::{RemoteServer, Settings};
+
+    #[test]
+    fn guid_and_locators() {
+        assert_eq!(server_guid_prefix("44.53.00.5f.45.50.52.4f.53.49.4d.41", 2).unwrap(), "44.53.02.5f.45.50.52.4f.53.49.4d.41");
+        assert!(parse_guid_prefix("44.53.00").is_err());
+        assert!(parse_guid_prefix("44.53.00.5f.45.50.52.4f.53.49.4d.zz").is_err());
+
+        let locators = server_locators(&vec!["0.0.0.0:11811".to_string(), "[::]:11811".to_string()], 1).unwrap();
+        assert_eq!(locators[0].to_string(), "0.0.0.0:11813");
+        assert_eq!(locators[1].to_string(), "[::]:11813");
+        assert!(server_locators(&vec!["localhost:11811".to_string()], 0).is_err());
+    }
+
+    #[test]
+    fn profiles() {
+        let mut settings = Settings::default();
+        settings.domain_ids = vec![1, 2];
+        settings.remote_servers = vec![RemoteServer {
+            guid_prefix: "44.53.01.5f.45.50.52.4f.53.49.4d.41".to_string(),
+            locators: vec!["192.168.1.10:11811".to_string()],
+        }];
+        let xml = generate(&settings).unwrap();
+        assert!(xml.contains("<participant profile_name=\"ros2monitor_server_0\">"));
+        assert!(xml.contains("<participant profile_name=\"ros2monitor_server_1\">"));
+        assert!(xml.contains("<prefix>44.53.01.5f.45.50.52.4f.53.49.4d.41</prefix>"));
+        assert!(xml.contains("<discoveryProtocol>BACKUP</discoveryProtocol>"));
+        assert!(xml.contains("<port>11813</port>"));
+        assert!(xml.contains("<address>192.168.1.10</address>"));
+        // Cli tools connect to the monitor via loopback
+        assert!(xml.contains("<participant profile_name=\"ros2monitor_cli\" is_default_profile=\"true\">"));
+        assert!(xml.contains("<address>127.0.0.1</address>"));
+
+        settings.server_role = "super_client".to_string();
+        let xml = generate(&settings).unwrap();
+        assert!(!xml.contains("<prefix>"));
+        assert!(!xml.contains("<address>127.0.0.1</address>"));
+    }
+
+    #[test]
+    fn discovery_server_variable_from_locators() {
+        let mut settings = Settings::default();
+        settings.listening_locators = vec!["0.0.0.0:12000".to_string()];
+        assert_eq!(discovery_server_variable(&settings, 0).unwrap(), "127.0.0.1:12000");
+        assert_eq!(discovery_server_variable(&settings, 1).unwrap(), ";127.0.0.1:12002");
+
+        settings.server_guid_prefix = "44.53.02.5f.45.50.52.4f.53.49.4d.41".to_string();
+        settings.listening_locators = vec!["[::]:11811".to_string()];
+        assert_eq!(discovery_server_variable(&settings, 0).unwrap(), ";;[::1]:11811");
+
+        settings.discovery_server = "10.0.0.1:11811".to_string();
+        assert_eq!(discovery_server_variable(&settings, 0).unwrap(), "10.0.0.1:11811");
+        settings.discovery_server = "".to_string();
+        settings.server_role = "super_client".to_string();
+        assert_eq!(discovery_server_variable(&settings, 0).unwrap(), "");
+    }
+
+    #[test]
+    fn port_params() {
+        let mut settings = Settings::default();
+        assert!(!generate(&settings).unwrap().contains("<portBase>"));
+
+        settings.port_params.port_base = 8400;
+        settings.port_params.participant_id_gain = 4;
+        let xml = generate(&settings).unwrap();
+        // Cli tools use the same mapping as the monitor
+        assert_eq!(xml.matches("<portBase>8400</portBase>").count(), 2);
+        assert!(xml.contains("<participantIDGain>4</participantIDGain>"));
+        assert!(xml.contains("<offsetd3>11</offsetd3>"));
+    }
+}
@@ -6,23 +6,31 @@
 
 pub mod fastdds_server {
     use ::std::os::raw::c_void;
+    use std::ffi::{CStr, CString};
     use std::mem::transmute;
     use flume::SendError;
     use std::thread;
-    use log::debug;
-    use crate::discovery_server_impl::{DiscoveryServerParams, ParticipantData, ReaderData, register_on_participant_discovery_data, register_on_participant_removed_data, register_on_reader_discovery_data, register_on_reader_removed_data, register_on_writer_discovery_data, register_on_writer_removed_data, run_discovery_server_impl, WriterData};
+    use log::{debug, error, warn};
+    use crate::discovery_server_impl::{discovery_mode_SERVER_DISCOVERY_MODE, discovery_mode_SIMPLE_DISCOVERY_MODE, DiscoveryServerParams, ParticipantData, RtpsPortParams, ReaderData, register_on_participant_discovery_data, register_on_participant_removed_data, register_on_reader_discovery_data, register_on_reader_removed_data, register_on_statistics_data, register_on_type_discovery_data, register_on_writer_discovery_data, register_on_writer_removed_data, run_discovery_server_impl, statistics_kind, stop_discovery_server_impl, statistics_kind_ACKNACK_COUNT_STATISTICS, statistics_kind_HEARTBEAT_COUNT_STATISTICS, statistics_kind_HISTORY_LATENCY_STATISTICS, statistics_kind_NETWORK_LATENCY_STATISTICS, statistics_kind_PUBLICATION_THROUGHPUT_STATISTICS, statistics_kind_RESENT_DATA_STATISTICS, statistics_kind_RTPS_LOST_STATISTICS, statistics_kind_SUBSCRIPTION_THROUGHPUT_STATISTICS, StatisticsData, TypeData, WriterData};
+    use crate::audit::audit::now_secs;
+    use crate::fastdds_profiles::fastdds_profiles::server_profile_name;
     use crate::fastdds_server::fastdds_server::FastDDSEntity::Context;
-    use crate::network::network::{fastdds_to_ros2, hex_str_from_uc, parse_endpoint, string_from_c};
-    use crate::ros2entites::ros2entities::{Host, Ros2Context, Ros2Publisher, Ros2Subscriber};
+    use crate::interfaces::interfaces::{parse_message, InterfaceName, MessageType};
+    use crate::network::network::{demangle_topic, hex_str_from_uc, parse_locators, string_from_c, Locator, PortParams};
+    use crate::ros2entites::ros2entities::{DdsStatisticsKind, DdsStatisticsSample, Host, Ros2Context, Ros2ProcessInfo, Ros2Publisher, Ros2Subscriber};
 
     pub type PartFunc = Box<dyn Fn(ParticipantData)>;
     pub type ReadFunc = Box<dyn Fn(ReaderData)>;
     pub type WriteFunc = Box<dyn Fn(WriterData)>;
+    pub type StatFunc = Box<dyn Fn(StatisticsData)>;
+    pub type TypeFunc = Box<dyn Fn(TypeData)>;
 
     pub enum FastDDSEntity {
         Publisher(Ros2Publisher),
         Subscriber(Ros2Subscriber),
         Context(Ros2Context),
+        Statistics(DdsStatisticsSample),
+        Type(MessageType),
     }
 
     pub enum FastDDSEvent {
@@ -32,32 +40,163 @@ pub mod fastdds_server {
         PublisherRemoved,
         SubscriberRemoved,
         ContextRemoved,
+        StatisticsReceived,
+        TypeDiscovered,
     }
 
-    pub struct FastDDSDiscoverer {
+    pub type FastDDSSender = flume::Sender<(FastDDSEvent, FastDDSEntity)>;
+
+    /// How monitor participates in DDS discovery
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    pub enum DiscoveryMode {
+        /// Monitor is a discovery server. Only participants which point ROS_DISCOVERY_SERVER at it are visible
+        Server,
+        /// Monitor is a plain participant and passively observes default multicast (SPDP) discovery
+        Simple,
+    }
+
+    /// Name of DDS implementation by RTPS vendor id
+    pub fn vendor_name(vendor_id: [u8; 2]) -> String {
+        return match vendor_id {
+            [0x01, 0x01] => "RTI Connext DDS".to_string(),
+            [0x01, 0x02] => "ADLink OpenSplice DDS".to_string(),
+            [0x01, 0x03] => "OCI OpenDDS".to_string(),
+            [0x01, 0x0f] => "eProsima Fast DDS".to_string(),
+            [0x01, 0x10] => "Eclipse Cyclone DDS".to_string(),
+            [0x01, 0x12] => "RTI Connext DDS Micro".to_string(),
+            [0x01, 0x13] => "Eclipse Zenoh".to_string(),
+            [major, minor] => format!("unknown ({:02x}.{:02x})", major, minor)
+        };
+    }
+
+    fn context_from_participant(participant_data: &ParticipantData, domain_id: u32) -> Ros2Context {
+        let (unicast_locators, multicast_locators) = parse_locators(&participant_data.locators);
+        let mut context = Ros2Context::new(hex_str_from_uc(participant_data.guid), Host::from_locators(unicast_locators, multicast_locators), domain_id);
+        context.vendor = vendor_name(participant_data.vendor_id);
+        context.name = string_from_c(participant_data.name);
+        context.process = Ros2ProcessInfo::from_user_data(string_from_c(participant_data.user_data).as_str());
+        context.discovered_at = now_secs();
+        return context;
+    }
+
+    /// Entity id of the participant itself
+    const PARTICIPANT_ENTITY_ID: [u8; 4] = [0x00, 0x00, 0x01, 0xc1];
+
+    fn statistics_kind_of(kind: statistics_kind) -> Option<DdsStatisticsKind> {
+        return match kind {
+            statistics_kind_HISTORY_LATENCY_STATISTICS => Some(DdsStatisticsKind::HistoryLatency),
+            statistics_kind_NETWORK_LATENCY_STATISTICS => Some(DdsStatisticsKind::NetworkLatency),
+            statistics_kind_PUBLICATION_THROUGHPUT_STATISTICS => Some(DdsStatisticsKind::PublicationThroughput),
+            statistics_kind_SUBSCRIPTION_THROUGHPUT_STATISTICS => Some(DdsStatisticsKind::SubscriptionThroughput),
+            statistics_kind_RESENT_DATA_STATISTICS => Some(DdsStatisticsKind::ResentData),
+            statistics_kind_HEARTBEAT_COUNT_STATISTICS => Some(DdsStatisticsKind::HeartbeatCount),
+            statistics_kind_ACKNACK_COUNT_STATISTICS => Some(DdsStatisticsKind::AcknackCount),
+            statistics_kind_RTPS_LOST_STATISTICS => Some(DdsStatisticsKind::RtpsLost),
+            _ => None
+        };
+    }
+
+    /// Participant GUID prefix or endpoint GUID `prefix|entity_id`, the same form as discovered entities have
+    fn statistics_guid(guid_prefix: [u8; 12], entity_id: [u8; 4]) -> String {
+        return if entity_id == PARTICIPANT_ENTITY_ID {
+            hex_str_from_uc(guid_prefix)
+        } else {
+            format!("{}|{}", hex_str_from_uc(guid_prefix), hex_str_from_uc(entity_id))
+        };
+    }
+
+    fn sample_from_statistics(statistics_data: &StatisticsData, domain_id: u32) -> Option<DdsStatisticsSample> {
+        let kind = statistics_kind_of(statistics_data.kind)?;
+        let (src_locator, dst_locator) = match kind {
+            DdsStatisticsKind::NetworkLatency => (Locator::from_fastdds(&statistics_data.src_locator), Locator::from_fastdds(&statistics_data.dst_locator)),
+            DdsStatisticsKind::RtpsLost => (None, Locator::from_fastdds(&statistics_data.dst_locator)),
+            _ => (None, None)
+        };
+        return Some(DdsStatisticsSample {
+            kind,
+            domain_id,
+            guid: statistics_guid(statistics_data.guid_prefix, statistics_data.entity_id),
+            remote_guid: match kind {
+                DdsStatisticsKind::HistoryLatency => Some(statistics_guid(statistics_data.remote_guid_prefix, statistics_data.remote_entity_id)),
+                _ => None
+            },
+            src_locator,
+            dst_locator,
+            value: statistics_data.value,
+            count: statistics_data.count,
+            bytes: statistics_data.bytes,
+        });
+    }
+
+    /// Message definition of the type object received in discovery
+    fn message_from_type(type_data: &TypeData) -> Result<MessageType, String> {
+        let name = InterfaceName::parse(string_from_c(type_data.type_name).as_str())?;
+        if type_data.definition.is_null() {
+            return Err(format!("Definition of {} is missing", name));
+        }
+        let definition = unsafe { CStr::from_ptr(type_data.definition) }.to_string_lossy();
+        return parse_message(&name, definition.as_ref());
+    }
+
+    /// Options of the single domain discoverer
+    #[derive(Clone, Debug)]
+    pub struct FastDDSDiscovererParams {
         pub domain_id: u32,
+        /// Index of the monitored domain, selects the server profile
+        pub server_id: u8,
+        /// Generated FastDDS profiles file with server profiles
+        pub profiles_file: String,
+        pub discovery_mode: DiscoveryMode,
+        /// Subscribe to FastDDS statistics topics
+        pub statistics: bool,
+        /// Request type objects of discovered endpoints
+        pub type_lookup: bool,
+        /// Listening port of the server, used if the profile can't be loaded. 0 if the monitor has no own server
+        pub server_port: u16,
+        /// Port mapping of the monitored system
+        pub port_params: PortParams,
+    }
+
+    /// Discoverer of the single DDS domain. FastDDS callbacks get pointers to the callback fields,
+    /// so the discoverer is boxed and stops its server before it is dropped
+    pub struct FastDDSDiscoverer {
+        pub params: FastDDSDiscovererParams,
         pub on_participant_discovery: PartFunc,
         pub on_reader_discovery: ReadFunc,
         pub on_writer_discovery: WriteFunc,
         pub on_participant_removed: PartFunc,
         pub on_reader_removed: ReadFunc,
         pub on_writer_removed: WriteFunc,
+        pub on_statistics: StatFunc,
+        pub on_type_discovery: TypeFunc,
+        /// The server participant is created, set once the run thread is joined
         pub running: bool,
-        pub rx: flume::Receiver<(FastDDSEvent, FastDDSEntity)>,
-        //pub tx: mpsc::Sender<FastDDSEntity>,
+        /// Thread which creates the server participant, it returns the result of the creation
+        run_thread: Option<thread::JoinHandle<i32>>,
     }
 
     impl FastDDSDiscoverer {
-        pub fn new(domain_id: u32) -> FastDDSDiscoverer {
-            let (tx, rx) = flume::unbounded::<(FastDDSEvent, FastDDSEntity)>();
+        /// Create discoverer for the domain. Events of all domains may be sent into the same channel,
+        /// entities are tagged by domain id
+        /// # Arguments
+        ///
+        /// * `params`: monitored domain, its server profile and discovery options
+        /// * `tx`: sender for discovered entities
+        ///
+        /// returns: FastDDSDiscoverer
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn new(params: FastDDSDiscovererParams, tx: FastDDSSender) -> FastDDSDiscoverer {
+            let domain_id = params.domain_id;
 
             let tx_participant_discovery = tx.clone();
             let on_participant_discovery = Box::new(move |participant_data: ParticipantData| {
                 // Called when new ROS2 context appears in network
-                let endpoint = parse_endpoint(participant_data.endpoint);
-                let guid = hex_str_from_uc(participant_data.guid);
-
-                let context: Ros2Context = Ros2Context::new(guid, Host::new(endpoint, "unknown".to_string()));
+                let context: Ros2Context = context_from_participant(&participant_data, domain_id);
                 let entity: FastDDSEntity = Context(context);
 
                 match tx_participant_discovery.send((FastDDSEvent::ContextDiscovered, entity)) {
@@ -68,20 +207,28 @@ pub mod fastdds_server {
 
             let tx_reader_discovery = tx.clone();
             let on_reader_discovery = Box::new(move |reader_data: ReaderData| {
-                let mut topic_name = fastdds_to_ros2(string_from_c(reader_data.topic_name));
+                let ros2_name = demangle_topic(string_from_c(reader_data.topic_name).as_str());
+                let mut topic_name = ros2_name.name;
                 let topic_type = string_from_c(reader_data.type_name);
                 if !topic_name.starts_with("/") {
                     topic_name = "/".to_string() + topic_name.as_str();
                 }
                 let guid = hex_str_from_uc(reader_data.guid_prefix);
-                let endpoint = parse_endpoint(reader_data.endpoint);
+                let entity_id = hex_str_from_uc(reader_data.entity_id);
+                let (unicast_locators, multicast_locators) = parse_locators(&reader_data.locators);
 
                 let subscriber = Ros2Subscriber {
                     topic_name,
                     guid,
                     node_name: "unknown".to_string(),
+                    node_namespace: "/".to_string(),
                     topic_type,
-                    host: Host::new(endpoint, "unknown".to_string()),
+                    host: Host::from_locators(unicast_locators, multicast_locators),
+                    domain_id,
+                    entity_id,
+                    qos: None,
+                    kind: ros2_name.kind,
+                    action: ros2_name.action,
                 };
                 let entity: FastDDSEntity = FastDDSEntity::Subscriber(subscriber);
                 match tx_reader_discovery.send((FastDDSEvent::SubscriberDiscovered, entity)) {
@@ -92,20 +239,28 @@ pub mod fastdds_server {
 
             let tx_writer_discovery = tx.clone();
             let on_writer_discovery = Box::new(move |writer_data: WriterData| {
-                let mut topic_name = fastdds_to_ros2(string_from_c(writer_data.topic_name));
+                let ros2_name = demangle_topic(string_from_c(writer_data.topic_name).as_str());
+                let mut topic_name = ros2_name.name;
                 let topic_type = string_from_c(writer_data.type_name);
                 if !topic_name.starts_with("/") {
                     topic_name = "/".to_string() + topic_name.as_str();
                 }
                 let guid = hex_str_from_uc(writer_data.guid_prefix);
-                let endpoint = parse_endpoint(writer_data.endpoint);
+                let entity_id = hex_str_from_uc(writer_data.entity_id);
+                let (unicast_locators, multicast_locators) = parse_locators(&writer_data.locators);
 
                 let publisher = Ros2Publisher {
                     topic_name: topic_name.clone(),
                     guid,
                     node_name: "unknown".to_string(),
+                    node_namespace: "/".to_string(),
                     topic_type,
-                    host: Host::new(endpoint, "unknown".to_string()),
+                    host: Host::from_locators(unicast_locators, multicast_locators),
+                    domain_id,
+                    entity_id,
+                    qos: None,
+                    kind: ros2_name.kind,
+                    action: ros2_name.action,
                 };
 
                 let entity: FastDDSEntity = FastDDSEntity::Publisher(publisher);
@@ -118,9 +273,7 @@ pub mod fastdds_server {
 
             let tx_participant_removed = tx.clone();
             let on_participant_removed = Box::new(move |participant_data: ParticipantData| {
-                let endpoint = parse_endpoint(participant_data.endpoint);
-                let guid = hex_str_from_uc(participant_data.guid);
-                let context: Ros2Context = Ros2Context::new(guid, Host::new(endpoint, "unknown".to_string()));
+                let context: Ros2Context = context_from_participant(&participant_data, domain_id);
                 let entity: FastDDSEntity = Context(context);
                 match tx_participant_removed.send((FastDDSEvent::ContextRemoved, entity)) {
                     Ok(()) => {}
@@ -129,20 +282,28 @@ pub mod fastdds_server {
             });
             let tx_reader_removed = tx.clone();
             let on_reader_removed = Box::new(move |reader_data: ReaderData| {
-                let mut topic_name = fastdds_to_ros2(string_from_c(reader_data.topic_name));
+                let ros2_name = demangle_topic(string_from_c(reader_data.topic_name).as_str());
+                let mut topic_name = ros2_name.name;
                 let topic_type = string_from_c(reader_data.type_name);
                 if !topic_name.starts_with("/") {
                     topic_name = "/".to_string() + topic_name.as_str();
                 }
                 let guid = hex_str_from_uc(reader_data.guid_prefix);
-                let endpoint = parse_endpoint(reader_data.endpoint);
+                let entity_id = hex_str_from_uc(reader_data.entity_id);
+                let (unicast_locators, multicast_locators) = parse_locators(&reader_data.locators);
 
                 let subscriber = Ros2Subscriber {
                     topic_name,
                     guid,
                     node_name: "unknown".to_string(),
+                    node_namespace: "/".to_string(),
                     topic_type,
-                    host: Host::new(endpoint, "unknown".to_string()),
+                    host: Host::from_locators(unicast_locators, multicast_locators),
+                    domain_id,
+                    entity_id,
+                    qos: None,
+                    kind: ros2_name.kind,
+                    action: ros2_name.action,
                 };
                 let entity: FastDDSEntity = FastDDSEntity::Subscriber(subscriber);
                 match tx_reader_removed.send((FastDDSEvent::SubscriberRemoved, entity)) {
@@ -152,20 +313,28 @@ pub mod fastdds_server {
             });
             let tx_writer_removed = tx.clone();
             let on_writer_removed = Box::new(move |writer_data: WriterData| {
-                let mut topic_name = fastdds_to_ros2(string_from_c(writer_data.topic_name));
+                let ros2_name = demangle_topic(string_from_c(writer_data.topic_name).as_str());
+                let mut topic_name = ros2_name.name;
                 let topic_type = string_from_c(writer_data.type_name);
                 if !topic_name.starts_with("/") {
                     topic_name = "/".to_string() + topic_name.as_str();
                 }
                 let guid = hex_str_from_uc(writer_data.guid_prefix);
-                let endpoint = parse_endpoint(writer_data.endpoint);
+                let entity_id = hex_str_from_uc(writer_data.entity_id);
+                let (unicast_locators, multicast_locators) = parse_locators(&writer_data.locators);
 
                 let publisher = Ros2Publisher {
                     topic_name,
                     guid,
                     node_name: "unknown".to_string(),
+                    node_namespace: "/".to_string(),
                     topic_type,
-                    host: Host::new(endpoint, "unknown".to_string()),
+                    host: Host::from_locators(unicast_locators, multicast_locators),
+                    domain_id,
+                    entity_id,
+                    qos: None,
+                    kind: ros2_name.kind,
+                    action: ros2_name.action,
                 };
                 let entity: FastDDSEntity = FastDDSEntity::Publisher(publisher);
                 match tx_writer_removed.send((FastDDSEvent::PublisherRemoved, entity)) {
@@ -174,16 +343,44 @@ pub mod fastdds_server {
                 };
             });
 
+            let tx_statistics = tx.clone();
+            let on_statistics = Box::new(move |statistics_data: StatisticsData| {
+                let sample = match sample_from_statistics(&statistics_data, domain_id) {
+                    Some(sample) => sample,
+                    None => return
+                };
+                // Statistics keep coming while discovery is restarted, so closed channel isn't an error
+                if let Err(_) = tx_statistics.send((FastDDSEvent::StatisticsReceived, FastDDSEntity::Statistics(sample))) {
+                    debug!("Unable to send statistics of domain {}", domain_id);
+                }
+            });
+
+            let tx_type_discovery = tx.clone();
+            let on_type_discovery = Box::new(move |type_data: TypeData| {
+                let message = match message_from_type(&type_data) {
+                    Ok(message) => message,
+                    Err(e) => {
+                        debug!("Skipping discovered type: {}", e);
+                        return;
+                    }
+                };
+                if let Err(_) = tx_type_discovery.send((FastDDSEvent::TypeDiscovered, FastDDSEntity::Type(message))) {
+                    debug!("Unable to send discovered type of domain {}", domain_id);
+                }
+            });
+
             FastDDSDiscoverer {
-                domain_id,
+                params,
                 on_participant_discovery,
                 on_reader_discovery,
                 on_writer_discovery,
                 on_participant_removed,
                 on_reader_removed,
                 on_writer_removed,
+                on_statistics,
+                on_type_discovery,
                 running: false,
-                rx,
+                run_thread: None,
             }
         }
 
@@ -213,44 +410,131 @@ pub mod fastdds_server {
                 let callback_raw: *mut Box<dyn Fn(WriterData)> = transmute(ctx);
                 (*(*callback_raw))(writer_data);
             }
+            unsafe extern "C" fn wrapper_statistics<F: Fn(StatisticsData)>(statistics_data: StatisticsData, ctx: *mut c_void) {
+                let callback_raw: *mut Box<dyn Fn(StatisticsData)> = transmute(ctx);
+                (*(*callback_raw))(statistics_data);
+            }
+            unsafe extern "C" fn wrapper_type_discovery<F: Fn(TypeData)>(type_data: TypeData, ctx: *mut c_void) {
+                let callback_raw: *mut Box<dyn Fn(TypeData)> = transmute(ctx);
+                (*(*callback_raw))(type_data);
+            }
+
+            // Pointers to the fields stay valid while the boxed discoverer is alive, it stops the server on drop
+            let ptr_participant_dsc = &mut self.on_participant_discovery as *mut PartFunc;
+            let ptr_writer_dsc = &mut self.on_writer_discovery as *mut WriteFunc;
+            let ptr_reader_dsc = &mut self.on_reader_discovery as *mut ReadFunc;
 
-            let ptr_participant_dsc = Box::into_raw(Box::new(self.on_participant_discovery.as_mut()));
-            let ptr_writer_dsc = Box::into_raw(Box::new(self.on_writer_discovery.as_mut()));
-            let ptr_reader_dsc = Box::into_raw(Box::new(self.on_reader_discovery.as_mut()));
+            let ptr_participant_rem = &mut self.on_participant_removed as *mut PartFunc;
+            let ptr_writer_rem = &mut self.on_writer_removed as *mut WriteFunc;
+            let ptr_reader_rem = &mut self.on_reader_removed as *mut ReadFunc;
 
-            let ptr_participant_rem = Box::into_raw(Box::new(self.on_participant_removed.as_mut()));
-            let ptr_writer_rem = Box::into_raw(Box::new(self.on_writer_removed.as_mut()));
-            let ptr_reader_rem = Box::into_raw(Box::new(self.on_reader_removed.as_mut()));
+            let ptr_statistics = &mut self.on_statistics as *mut StatFunc;
+            let ptr_type_dsc = &mut self.on_type_discovery as *mut TypeFunc;
 
             unsafe {
-                register_on_participant_discovery_data(ptr_participant_dsc as *mut c_void);
-                register_on_writer_discovery_data(ptr_writer_dsc as *mut c_void);
-                register_on_reader_discovery_data(ptr_reader_dsc as *mut c_void);
+                register_on_participant_discovery_data(self.params.domain_id, ptr_participant_dsc as *mut c_void);
+                register_on_writer_discovery_data(self.params.domain_id, ptr_writer_dsc as *mut c_void);
+                register_on_reader_discovery_data(self.params.domain_id, ptr_reader_dsc as *mut c_void);
+
+                register_on_participant_removed_data(self.params.domain_id, ptr_participant_rem as *mut c_void);
+                register_on_writer_removed_data(self.params.domain_id, ptr_writer_rem as *mut c_void);
+                register_on_reader_removed_data(self.params.domain_id, ptr_reader_rem as *mut c_void);
 
-                register_on_participant_removed_data(ptr_participant_rem as *mut c_void);
-                register_on_writer_removed_data(ptr_writer_rem as *mut c_void);
-                register_on_reader_removed_data(ptr_reader_rem as *mut c_void);
+                register_on_statistics_data(self.params.domain_id, ptr_statistics as *mut c_void);
+                register_on_type_discovery_data(self.params.domain_id, ptr_type_dsc as *mut c_void);
 
                 type ParticipantFunc = fn(ParticipantData);
                 type ReaderFunc = fn(ReaderData);
                 type WriterFunc = fn(WriterData);
+                type StatisticsFunc = fn(StatisticsData);
+                type TypeDiscoveryFunc = fn(TypeData);
+
+                let discovery_mode = match self.params.discovery_mode {
+                    DiscoveryMode::Server => discovery_mode_SERVER_DISCOVERY_MODE as u8,
+                    DiscoveryMode::Simple => discovery_mode_SIMPLE_DISCOVERY_MODE as u8,
+                };
+                let profiles_file = CString::new(self.params.profiles_file.clone()).unwrap();
+                let profile_name = CString::new(server_profile_name(self.params.server_id)).unwrap();
+
+                let statistics_callback = if self.params.statistics {
+                    Some(wrapper_statistics::<StatisticsFunc> as unsafe extern "C" fn(StatisticsData, *mut c_void))
+                } else {
+                    None
+                };
+                let type_discovery_callback = if self.params.type_lookup {
+                    Some(wrapper_type_discovery::<TypeDiscoveryFunc> as unsafe extern "C" fn(TypeData, *mut c_void))
+                } else {
+                    None
+                };
 
-                let discovery_params = DiscoveryServerParams {
-                    participant_discovery_callback: Some(wrapper_participant_discovery::<ParticipantFunc>),
-                    reader_discovery_callback: Some(wrapper_reader_discovery::<ReaderFunc>),
-                    writer_discovery_callback: Some(wrapper_writer_discovery::<WriterFunc>),
-                    participant_removed_callback: Some(wrapper_participant_removed::<ParticipantFunc>),
-                    reader_removed_callback: Some(wrapper_reader_removed::<ReaderFunc>),
-                    writer_removed_callback: Some(wrapper_writer_removed::<WriterFunc>),
+                let domain_id = self.params.domain_id;
+                let server_port = self.params.server_port;
+                let port_params = RtpsPortParams {
+                    port_base: self.params.port_params.port_base,
+                    domain_id_gain: self.params.port_params.domain_id_gain,
+                    participant_id_gain: self.params.port_params.participant_id_gain,
+                    offset_d0: self.params.port_params.offset_d0,
+                    offset_d1: self.params.port_params.offset_d1,
+                    offset_d2: self.params.port_params.offset_d2,
+                    offset_d3: self.params.port_params.offset_d3,
                 };
+                self.run_thread = Some(thread::spawn(move || {
+                    // Strings are owned by the thread, so pointers are valid during the call
+                    let discovery_params = DiscoveryServerParams {
+                        participant_discovery_callback: Some(wrapper_participant_discovery::<ParticipantFunc>),
+                        reader_discovery_callback: Some(wrapper_reader_discovery::<ReaderFunc>),
+                        writer_discovery_callback: Some(wrapper_writer_discovery::<WriterFunc>),
+                        participant_removed_callback: Some(wrapper_participant_removed::<ParticipantFunc>),
+                        reader_removed_callback: Some(wrapper_reader_removed::<ReaderFunc>),
+                        writer_removed_callback: Some(wrapper_writer_removed::<WriterFunc>),
+                        profiles_file: profiles_file.as_ptr(),
+                        profile_name: profile_name.as_ptr(),
+                        discovery_mode,
+                        statistics_callback,
+                        type_discovery_callback,
+                        server_port,
+                        port_params,
+                    };
+                    run_discovery_server_impl(domain_id, discovery_params)
+                }));
+            }
+        }
 
-                let domain_id = self.domain_id;
-                thread::spawn(move || {
-                    run_discovery_server_impl(domain_id, discovery_params);
-                });
+        /// Wait for the run thread. The discoverer is running only if its server participant was created
+        pub fn wait_started(&mut self) -> bool {
+            if let Some(run_thread) = self.run_thread.take() {
+                self.running = match run_thread.join() {
+                    Ok(0) => true,
+                    Ok(_) => {
+                        error!("Unable to run discovery server of domain {}", self.params.domain_id);
+                        false
+                    }
+                    Err(_) => {
+                        warn!("Discovery server thread of domain {} panicked", self.params.domain_id);
+                        false
+                    }
+                };
+            }
+            return self.running;
+        }
 
-                self.running = true;
+        /// Delete the server participant with its readers, so callbacks aren't called anymore.
+        /// Domains whose participant wasn't created have nothing to stop
+        pub fn stop(&mut self) {
+            // The participant is created by the run thread, it must exist before it is deleted
+            if !self.wait_started() {
+                return;
+            }
+            unsafe {
+                stop_discovery_server_impl(self.params.domain_id);
             }
+            self.running = false;
+        }
+    }
+
+    impl Drop for FastDDSDiscoverer {
+        fn drop(&mut self) {
+            self.stop();
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,360 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/// Embedded http server which exposes Api as REST routes. Every route is mapped onto the
+/// same Api methods as the control socket, so the json in responses is identical.
+pub mod http_server {
+    use std::collections::HashMap;
+    use std::error::Error;
+    use std::io;
+    use std::sync::Arc;
+    use log::{debug, error, info};
+    use serde_json::json;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::Mutex;
+    use crate::api::api::{Api, StateProvider};
+    use crate::auth::auth::ClientIdentity;
+    use crate::protocol::JsonProtocol;
+    use crate::ros2entites::ros2entities::Ros2State;
+
+    // Limits for incoming requests. Our requests are tiny, so everything bigger is a garbage
+    const MAX_HEADER_LINES: usize = 100;
+    const MAX_BODY_SIZE: usize = 64 * 1024;
+
+    pub struct HttpRequest {
+        pub method: String,
+        pub path: String,
+        pub query: HashMap<String, String>,
+        pub headers: HashMap<String, String>,
+        pub body: String,
+    }
+
+    pub struct HttpResponse {
+        pub status: u16,
+        pub body: String,
+    }
+
+    impl HttpResponse {
+        pub fn ok(body: String) -> HttpResponse {
+            return HttpResponse { status: 200, body };
+        }
+
+        pub fn error(status: u16, msg: &str) -> HttpResponse {
+            return HttpResponse { status, body: json!({"result": "failure", "msg": msg}).to_string() };
+        }
+
+        fn reason(&self) -> &'static str {
+            return match self.status {
+                200 => "OK",
+                400 => "Bad Request",
+                401 => "Unauthorized",
+                403 => "Forbidden",
+                404 => "Not Found",
+                405 => "Method Not Allowed",
+                413 => "Payload Too Large",
+                _ => "Internal Server Error"
+            };
+        }
+
+        pub fn to_bytes(&self) -> Vec<u8> {
+            let head = format!("HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                               self.status, self.reason(), self.body.as_bytes().len());
+            let mut bytes = head.into_bytes();
+            bytes.extend_from_slice(self.body.as_bytes());
+            return bytes;
+        }
+    }
+
+    pub struct HttpServer {
+        pub address: String,
+        api: Arc<Api>,
+        state_provider: StateProvider,
+    }
+
+    impl HttpServer {
+        pub fn new(address: String, api: Arc<Api>, state_provider: StateProvider) -> HttpServer {
+            return HttpServer { address, api, state_provider };
+        }
+
+        /// Accept http connections until error occurs. Each connection is handled in separate task
+        pub async fn run(&self) -> io::Result<()> {
+            let listener = TcpListener::bind(self.address.as_str()).await?;
+            info!("Http api is listening on {}", self.address);
+            loop {
+                let (stream, peer) = listener.accept().await?;
+                debug!("Http connection from {}", peer);
+                let api = self.api.clone();
+                let state_provider = self.state_provider.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, peer.to_string(), api, state_provider).await {
+                        error!("Unable to handle http request: {}", e);
+                    }
+                });
+            }
+        }
+    }
+
+    async fn handle_connection(mut stream: TcpStream, peer: String, api: Arc<Api>, state_provider: StateProvider) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+        let response = match read_request(&mut stream).await {
+            Ok(request) => {
+                let token = request.headers.get("authorization")
+                    .and_then(|value| value.strip_prefix("Bearer "))
+                    .map(|token| token.trim());
+                if api.policy.token.is_some() && !api.policy.check_token(token) {
+                    HttpResponse::error(401, "Authentication required: provide valid token in Authorization header")
+                } else {
+                    let identity = ClientIdentity::Tcp { peer, authenticated: api.policy.token.is_some() };
+                    route(&request, &identity, api, state_provider).await
+                }
+            }
+            Err(response) => response
+        };
+        stream.write_all(&response.to_bytes()).await?;
+        stream.shutdown().await?;
+        Ok(())
+    }
+
+    /// Read http request from stream. On malformed request returns response which must be sent back
+    pub async fn read_request(stream: &mut TcpStream) -> Result<HttpRequest, HttpResponse> {
+        let mut reader = BufReader::new(stream);
+
+        let mut request_line = String::new();
+        match reader.read_line(&mut request_line).await {
+            Ok(0) | Err(_) => return Err(HttpResponse::error(400, "Empty request")),
+            Ok(_) => ()
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or("").to_uppercase();
+        let target = parts.next().unwrap_or("").to_string();
+        if method.is_empty() || !target.starts_with('/') {
+            return Err(HttpResponse::error(400, "Malformed request line"));
+        }
+
+        let mut headers: HashMap<String, String> = HashMap::new();
+        for _ in 0..MAX_HEADER_LINES {
+            let mut line = String::new();
+            match reader.read_line(&mut line).await {
+                Ok(0) => break,
+                Ok(_) => (),
+                Err(_) => return Err(HttpResponse::error(400, "Unable to read headers"))
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+            }
+        }
+
+        let content_length: usize = match headers.get("content-length") {
+            Some(length) => match length.parse() {
+                Ok(length) => length,
+                Err(_) => return Err(HttpResponse::error(400, "Invalid Content-Length header"))
+            },
+            None => 0
+        };
+        if content_length > MAX_BODY_SIZE {
+            return Err(HttpResponse::error(413, "Request body is too large"));
+        }
+
+        let mut body = vec![0u8; content_length];
+        if content_length > 0 && reader.read_exact(&mut body).await.is_err() {
+            return Err(HttpResponse::error(400, "Unable to read request body"));
+        }
+
+        let (path, query_str) = match target.split_once('?') {
+            Some((path, query)) => (path.to_string(), query.to_string()),
+            None => (target.clone(), "".to_string())
+        };
+
+        let query: HashMap<String, String> = query_str.split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| match pair.split_once('=') {
+                Some((name, value)) => (percent_decode(name), percent_decode(value)),
+                None => (percent_decode(pair), "".to_string())
+            })
+            .collect();
+
+        return Ok(HttpRequest {
+            method,
+            path,
+            query,
+            headers,
+            body: String::from_utf8_lossy(&body).to_string(),
+        });
+    }
+
+    /// Map request onto Api method
+    pub async fn route(request: &HttpRequest, identity: &ClientIdentity, api: Arc<Api>, state_provider: StateProvider) -> HttpResponse {
+        let segments: Vec<String> = request.path.split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(percent_decode)
+            .collect();
+        let segments: Vec<&str> = segments.iter().map(|segment| segment.as_str()).collect();
+        // All read routes are views of the state command
+        if request.method == "GET" {
+            if let Err(msg) = api.policy.authorize(identity, "state") {
+                return HttpResponse::error(403, msg.as_str());
+            }
+        }
+
+        // Views of the single domain are requested by ?domain_id=<id>
+        let state = match api.domain_state(state_provider(), request.query.get("domain_id")).await {
+            Ok(state) => state,
+            Err(msg) => return HttpResponse::error(400, msg.as_str())
+        };
+
+        return match (request.method.as_str(), segments.as_slice()) {
+            ("GET", []) | ("GET", ["state"]) => HttpResponse::ok(api.ros2_state_json(state).await),
+            ("GET", ["nodes"]) => HttpResponse::ok(api.nodes_json(state, request.query.get("namespace")).await),
+            ("GET", ["nodes", node_name]) => match api.node_json(node_name.to_string(), request.query.get("namespace"), state).await {
+                Ok(node) => HttpResponse::ok(node),
+                Err(msg) => HttpResponse::error(404, msg.as_str())
+            },
+            ("GET", ["nodes", node_name, "resources"]) => {
+                let mut arguments = vec![("node_name", *node_name)];
+                for name in ["namespace", "minutes"] {
+                    if let Some(value) = request.query.get(name) {
+                        arguments.push((name, value.as_str()));
+                    }
+                }
+                command_response(&api, identity, "node_resources", arguments.as_slice(), state).await
+            }
+            ("GET", ["topics"]) => HttpResponse::ok(api.topics_json(state).await),
+            ("GET", ["topics", topic_name]) => match api.topic_json(topic_name.to_string(), state).await {
+                Some(topic) => HttpResponse::ok(topic),
+                None => HttpResponse::error(404, format!("Topic {} not found", topic_name).as_str())
+            },
+            ("GET", ["packages"]) => HttpResponse::ok(api.packages_json(state).await),
+            ("GET", ["hosts"]) => HttpResponse::ok(api.hosts_json(state).await),
+            ("GET", ["traffic"]) => {
+                let mut arguments = vec![];
+                for name in ["window", "limit", "domain_id"] {
+                    if let Some(value) = request.query.get(name) {
+                        arguments.push((name, value.as_str()));
+                    }
+                }
+                command_response(&api, identity, "traffic", arguments.as_slice(), state).await
+            }
+            ("GET", ["types", package, kind, name]) => {
+                let type_name = format!("{}/{}/{}", package, kind, name);
+                command_response(&api, identity, "type_info", &[("type_name", type_name.as_str())], state).await
+            }
+            ("POST", ["nodes", node_name, "shutdown"]) => {
+                let mut arguments = vec![("node_name", *node_name)];
+                for name in ["namespace", "domain_id"] {
+                    if let Some(value) = request.query.get(name) {
+                        arguments.push((name, value.as_str()));
+                    }
+                }
+                command_response(&api, identity, "shutdown", arguments.as_slice(), state).await
+            }
+            ("POST", ["nodes", node_name, "lifecycle", transition]) => {
+                let mut arguments = vec![("node_name", *node_name), ("transition", *transition)];
+                for name in ["namespace", "domain_id"] {
+                    if let Some(value) = request.query.get(name) {
+                        arguments.push((name, value.as_str()));
+                    }
+                }
+                command_response(&api, identity, "lifecycle", arguments.as_slice(), state).await
+            }
+            (_, ["nodes", ..]) | (_, ["topics", ..]) | (_, ["packages"]) | (_, ["hosts"]) | (_, ["traffic"]) | (_, ["types", ..]) | (_, ["state"]) => {
+                HttpResponse::error(405, format!("Method {} isn't allowed for {}", request.method, request.path).as_str())
+            }
+            _ => HttpResponse::error(404, format!("Unknown route {}", request.path).as_str())
+        };
+    }
+
+    /// Execute protocol command and wrap its json result into http response
+    async fn command_response(api: &Arc<Api>, identity: &ClientIdentity, command: &str, arguments: &[(&str, &str)], state: Arc<Mutex<Ros2State>>) -> HttpResponse {
+        let arguments: HashMap<String, String> = arguments.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        let request = match JsonProtocol::with_command(command, arguments) {
+            Ok(request) => request,
+            Err(msg) => return HttpResponse::error(400, msg.as_str())
+        };
+
+        if let Err(msg) = api.policy.authorize(identity, command) {
+            return HttpResponse::error(403, msg.as_str());
+        }
+
+        let body = api.dispatch(&request, identity, state).await;
+        let failed = match serde_json::from_str::<serde_json::Value>(body.as_str()) {
+            Ok(value) => value.get("result").and_then(|result| result.as_str()) == Some("failure"),
+            Err(_) => false
+        };
+
+        return HttpResponse { status: if failed { 400 } else { 200 }, body };
+    }
+
+    /// Decode percent-encoded url component. Node and topic names contain slashes, so clients
+    /// must pass them encoded, e.g. `/nodes/%2Frobot1%2Fcamera`
+    pub fn percent_decode(input: &str) -> String {
+        let bytes = input.as_bytes();
+        let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'%' && i + 3 <= bytes.len() && bytes[i + 1].is_ascii_hexdigit() && bytes[i + 2].is_ascii_hexdigit() {
+                let hex = [bytes[i + 1], bytes[i + 2]];
+                if let Ok(byte) = u8::from_str_radix(String::from_utf8_lossy(&hex).as_ref(), 16) {
+                    decoded.push(byte);
+                    i += 3;
+                    continue;
+                }
+            }
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+        return String::from_utf8_lossy(&decoded).to_string();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use crate::api::api::StateProvider;
+    use crate::api::tests::{test_api, test_state};
+    use crate::auth::auth::ClientIdentity;
+    use crate::http_server::http_server::{percent_decode, route, HttpRequest};
+
+    #[test]
+    fn percent_decoding() {
+        assert_eq!(percent_decode("%2Frobot1%2Fcamera"), "/robot1/camera");
+        // Escape at the end of the string
+        assert_eq!(percent_decode("a%20"), "a ");
+        assert_eq!(percent_decode("%41"), "A");
+        // Incomplete and invalid escapes are kept as is
+        assert_eq!(percent_decode("a%2"), "a%2");
+        assert_eq!(percent_decode("a%"), "a%");
+        assert_eq!(percent_decode("%+1"), "%+1");
+    }
+
+    #[tokio::test]
+    async fn node_commands_in_domain() {
+        let api = Arc::new(test_api());
+        let state_provider: StateProvider = Arc::new(test_state);
+        let root = ClientIdentity::Unix { uid: 0, gid: 0, pid: None };
+        let request = |query: &[(&str, &str)]| HttpRequest {
+            method: "POST".to_string(),
+            path: "/nodes/%2Frobot1%2Fcamera/lifecycle/activate".to_string(),
+            query: query.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+            headers: HashMap::new(),
+            body: "".to_string(),
+        };
+
+        // The node is discovered in domains 0 and 1
+        let response = route(&request(&[]), &root, api.clone(), state_provider.clone()).await;
+        assert_eq!(response.status, 400);
+        assert!(response.body.contains("use domain_id argument"), "{}", response.body);
+
+        // With the domain the command reaches ros2 cli, which doesn't know the node as a lifecycle one
+        let response = route(&request(&[("domain_id", "1")]), &root, api.clone(), state_provider.clone()).await;
+        assert!(response.body.contains("Node /robot1/camera isn't a lifecycle node"), "{}", response.body);
+    }
+}
@@ -0,0 +1,1151 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/// ROS2 interface types: parser of `.msg`, `.srv` and `.action` definitions and of their IDL form, and the registry which resolves
+/// types from installed package share directories or from type objects received in DDS discovery.
+pub mod interfaces {
+    use std::collections::{BTreeSet, HashMap};
+    use std::fmt::{Display, Formatter};
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+    use serde::{Deserialize, Serialize};
+
+    /// Primitive types of field definitions, strings included
+    pub const PRIMITIVE_TYPES: [&str; 16] = ["bool", "byte", "char", "wchar", "float32", "float64", "int8", "uint8", "int16",
+        "uint16", "int32", "uint32", "int64", "uint64", "string", "wstring"];
+
+    /// Source of definitions received in DDS discovery
+    pub const DISCOVERY_SOURCE: &str = "discovery";
+
+    #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    #[serde(rename_all = "snake_case")]
+    pub enum InterfaceKind {
+        Message,
+        Service,
+        Action,
+    }
+
+    impl InterfaceKind {
+        /// Subdirectory of the package share directory, the same as the middle part of the interface name
+        pub fn subfolder(&self) -> &'static str {
+            return match self {
+                InterfaceKind::Message => "msg",
+                InterfaceKind::Service => "srv",
+                InterfaceKind::Action => "action",
+            };
+        }
+
+        pub fn from_subfolder(subfolder: &str) -> Option<InterfaceKind> {
+            return [InterfaceKind::Message, InterfaceKind::Service, InterfaceKind::Action].into_iter()
+                .find(|kind| kind.subfolder() == subfolder);
+        }
+
+        /// Suffixes of the messages the interface consists of, separated by `---` in the definition file
+        pub fn parts(&self) -> &'static [&'static str] {
+            return match self {
+                InterfaceKind::Message => &[""],
+                InterfaceKind::Service => &["Request", "Response"],
+                InterfaceKind::Action => &["Goal", "Result", "Feedback"],
+            };
+        }
+    }
+
+    /// Full name of the interface `package/kind/Name`. Messages of services and actions have the part suffix,
+    /// e.g. `example_interfaces/srv/AddTwoInts_Request`
+    #[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    pub struct InterfaceName {
+        pub package: String,
+        pub kind: InterfaceKind,
+        pub name: String,
+    }
+
+    impl InterfaceName {
+        pub fn new(package: &str, kind: InterfaceKind, name: &str) -> InterfaceName {
+            return InterfaceName { package: package.to_string(), kind, name: name.to_string() };
+        }
+
+        /// Parse type name in any form ROS2 tools and DDS use
+        /// # Arguments
+        ///
+        /// * `type_name`: `pkg/msg/Type`, `pkg/Type` (message) or DDS name `pkg::msg::dds_::Type_`
+        ///
+        /// returns: Result<InterfaceName, String>
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn parse(type_name: &str) -> Result<InterfaceName, String> {
+            let type_name = type_name.trim();
+            let parts: Vec<&str> = if type_name.contains("::") {
+                type_name.split("::").filter(|part| *part != "dds_").collect()
+            } else {
+                type_name.split('/').collect()
+            };
+            let (package, kind, name) = match parts.as_slice() {
+                [package, name] => (*package, InterfaceKind::Message, *name),
+                [package, kind, name] => match InterfaceKind::from_subfolder(kind) {
+                    Some(kind) => (*package, kind, *name),
+                    None => return Err(format!("Unknown interface kind {} of {}", kind, type_name))
+                },
+                _ => return Err(format!("Invalid type name {}", type_name))
+            };
+            // DDS type names have trailing underscore
+            let name = if type_name.contains("::") { name.strip_suffix('_').unwrap_or(name) } else { name };
+            if !is_identifier(package) || !is_identifier(name) {
+                return Err(format!("Invalid type name {}", type_name));
+            }
+            return Ok(InterfaceName::new(package, kind, name));
+        }
+
+        /// Name of the part message, e.g. `AddTwoInts_Request`
+        pub fn part(&self, suffix: &str) -> InterfaceName {
+            return if suffix.is_empty() {
+                self.clone()
+            } else {
+                InterfaceName::new(self.package.as_str(), self.kind, format!("{}_{}", self.name, suffix).as_str())
+            };
+        }
+    }
+
+    impl Display for InterfaceName {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            return write!(f, "{}/{}/{}", self.package, self.kind.subfolder(), self.name);
+        }
+    }
+
+    fn is_identifier(name: &str) -> bool {
+        return !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !name.starts_with(|c: char| c.is_ascii_digit());
+    }
+
+    /// How many values the field holds
+    #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    pub enum Collection {
+        /// Single value
+        Single,
+        /// Fixed size array `T[N]`
+        Array(usize),
+        /// Sequence `T[]`, or bounded one `T[<=N]`
+        Sequence(Option<usize>),
+    }
+
+    /// Type of the field
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct FieldType {
+        /// Primitive type or full name of the nested message, e.g. `std_msgs/msg/Header`
+        pub base: String,
+        /// Maximum length of bounded string
+        pub string_bound: Option<usize>,
+        pub collection: Collection,
+    }
+
+    impl FieldType {
+        pub fn is_primitive(&self) -> bool {
+            return PRIMITIVE_TYPES.contains(&self.base.as_str());
+        }
+
+        /// Parse type of the field definition, e.g. `string<=10[<=5]`
+        /// # Arguments
+        ///
+        /// * `type_str`: type as it is written in the definition
+        /// * `package`: package of the definition, relative names of nested messages are resolved in it
+        ///
+        /// returns: Result<FieldType, String>
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn parse(type_str: &str, package: &str) -> Result<FieldType, String> {
+            let (base, collection) = match type_str.strip_suffix(']').and_then(|rest| rest.rsplit_once('[')) {
+                Some((base, "")) => (base, Collection::Sequence(None)),
+                Some((base, size)) => match size.strip_prefix("<=") {
+                    Some(bound) => (base, Collection::Sequence(Some(parse_size(bound, type_str)?))),
+                    None => (base, Collection::Array(parse_size(size, type_str)?))
+                },
+                None => (type_str, Collection::Single)
+            };
+
+            let (base, string_bound) = match base.split_once("<=") {
+                Some((string_type, bound)) if string_type == "string" || string_type == "wstring" => {
+                    (string_type.to_string(), Some(parse_size(bound, type_str)?))
+                }
+                Some(_) => return Err(format!("Only strings may be bounded, got {}", type_str)),
+                None if PRIMITIVE_TYPES.contains(&base) => (base.to_string(), None),
+                // Header is resolved without package for compatibility with ROS1 definitions
+                None if base == "Header" => ("std_msgs/msg/Header".to_string(), None),
+                None if is_identifier(base) => (InterfaceName::new(package, InterfaceKind::Message, base).to_string(), None),
+                None => (InterfaceName::parse(base)?.to_string(), None)
+            };
+            return Ok(FieldType { base, string_bound, collection });
+        }
+    }
+
+    fn parse_size(size: &str, type_str: &str) -> Result<usize, String> {
+        return size.trim().parse().map_err(|_| format!("Invalid size {} of {}", size, type_str));
+    }
+
+    impl Display for FieldType {
+        /// The same form as in definitions
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}", self.base)?;
+            if let Some(bound) = self.string_bound {
+                write!(f, "<={}", bound)?;
+            }
+            return match self.collection {
+                Collection::Single => Ok(()),
+                Collection::Array(size) => write!(f, "[{}]", size),
+                Collection::Sequence(None) => write!(f, "[]"),
+                Collection::Sequence(Some(bound)) => write!(f, "[<={}]", bound),
+            };
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct Field {
+        pub name: String,
+        #[serde(rename = "type")]
+        pub field_type: FieldType,
+        /// Default value as it is written in the definition
+        pub default: Option<String>,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct Constant {
+        pub name: String,
+        #[serde(rename = "type")]
+        pub type_name: String,
+        /// Value as it is written in the definition
+        pub value: String,
+    }
+
+    /// Structure of the message, or of the part of the service or action
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct MessageType {
+        /// Full name, e.g. `std_msgs/msg/String`
+        pub name: String,
+        pub fields: Vec<Field>,
+        pub constants: Vec<Constant>,
+    }
+
+    impl MessageType {
+        /// Full names of nested messages of the fields
+        pub fn nested_types(&self) -> Vec<String> {
+            return self.fields.iter()
+                .filter(|field| !field.field_type.is_primitive())
+                .map(|field| field.field_type.base.clone())
+                .collect();
+        }
+    }
+
+    /// Message, service or action with its messages
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct Interface {
+        pub name: String,
+        pub kind: InterfaceKind,
+        /// Message itself, request and response of the service, or goal, result and feedback of the action
+        pub messages: Vec<MessageType>,
+    }
+
+    /// Remove comment, `#` inside quoted default values isn't a comment
+    fn strip_comment(line: &str) -> &str {
+        let mut quote: Option<char> = None;
+        for (pos, c) in line.char_indices() {
+            match (quote, c) {
+                (None, '#') => return &line[..pos],
+                (None, '"') | (None, '\'') => quote = Some(c),
+                (Some(open), _) if open == c => quote = None,
+                _ => {}
+            }
+        }
+        return line;
+    }
+
+    /// Parse message definition in `.msg` format
+    /// # Arguments
+    ///
+    /// * `name`: full name of the message, relative names of nested messages are resolved in its package
+    /// * `text`: field and constant definitions, one per line
+    ///
+    /// returns: Result<MessageType, String>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn parse_message(name: &InterfaceName, text: &str) -> Result<MessageType, String> {
+        let mut message = MessageType { name: name.to_string(), fields: Vec::new(), constants: Vec::new() };
+        for (line_num, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |msg: String| format!("{} line {}: {}", name, line_num + 1, msg);
+            let (type_str, rest) = match line.split_once(char::is_whitespace) {
+                Some((type_str, rest)) => (type_str, rest.trim()),
+                None => return Err(error(format!("Field name is missing in {}", line)))
+            };
+            let field_type = FieldType::parse(type_str, name.package.as_str()).map_err(error)?;
+
+            // Constant is `TYPE NAME=value`, default value is separated from the field name by whitespace
+            let constant = rest.split_once('=').filter(|(constant_name, _)| is_identifier(constant_name.trim()));
+            if let Some((constant_name, value)) = constant {
+                if !field_type.is_primitive() || field_type.collection != Collection::Single {
+                    return Err(error(format!("Constant {} must be of primitive type", constant_name.trim())));
+                }
+                message.constants.push(Constant {
+                    name: constant_name.trim().to_string(),
+                    type_name: field_type.to_string(),
+                    value: value.trim().to_string(),
+                });
+                continue;
+            }
+
+            let (field_name, default) = match rest.split_once(char::is_whitespace) {
+                Some((field_name, default)) => (field_name, Some(default.trim().to_string())),
+                None => (rest, None)
+            };
+            if !is_identifier(field_name) {
+                return Err(error(format!("Invalid field name {}", field_name)));
+            }
+            message.fields.push(Field { name: field_name.to_string(), field_type, default });
+        }
+        return Ok(message);
+    }
+
+    /// Parse definition of the message, service or action. Parts of services and actions are separated by `---`
+    /// # Arguments
+    ///
+    /// * `name`: full name of the interface, its kind defines the number of parts
+    /// * `text`: content of `.msg`, `.srv` or `.action` file
+    ///
+    /// returns: Result<Interface, String>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn parse_interface(name: &InterfaceName, text: &str) -> Result<Interface, String> {
+        let mut sections: Vec<String> = vec![String::new()];
+        for line in text.lines() {
+            if strip_comment(line).trim() == "---" {
+                sections.push(String::new());
+            } else {
+                let section = sections.last_mut().unwrap();
+                section.push_str(line);
+                section.push('\n');
+            }
+        }
+        let parts = name.kind.parts();
+        if sections.len() != parts.len() {
+            return Err(format!("{} must have {} parts separated by ---, got {}", name, parts.len(), sections.len()));
+        }
+
+        let mut messages = Vec::new();
+        for (suffix, section) in parts.iter().zip(sections.iter()) {
+            messages.push(parse_message(&name.part(suffix), section.as_str())?);
+        }
+        return Ok(Interface { name: name.to_string(), kind: name.kind, messages });
+    }
+
+    /// Field types of OMG IDL which differ from `.msg` ones
+    fn idl_primitive(type_name: &str) -> Option<&'static str> {
+        return match type_name {
+            "boolean" => Some("bool"),
+            "octet" => Some("byte"),
+            "char" => Some("char"),
+            "wchar" => Some("wchar"),
+            "float" => Some("float32"),
+            "double" => Some("float64"),
+            "short" => Some("int16"),
+            "int8" => Some("int8"),
+            "uint8" => Some("uint8"),
+            "int16" => Some("int16"),
+            "uint16" => Some("uint16"),
+            "int32" => Some("int32"),
+            "uint32" => Some("uint32"),
+            "int64" => Some("int64"),
+            "uint64" => Some("uint64"),
+            _ => None
+        };
+    }
+
+    const IDL_PUNCTUATION: &str = "{}();<>[],=@\":";
+
+    /// Split IDL text into identifiers, literals and punctuation. Comments and preprocessor directives are dropped,
+    /// string literals keep their quotes
+    fn tokenize_idl(text: &str) -> Result<Vec<String>, String> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut tokens = Vec::new();
+        let mut pos = 0;
+        let mut line_start = true;
+        while pos < chars.len() {
+            let c = chars[pos];
+            let next = chars.get(pos + 1).copied();
+            if c == '\n' {
+                line_start = true;
+                pos += 1;
+                continue;
+            }
+            if c.is_whitespace() {
+                pos += 1;
+                continue;
+            }
+            let at_line_start = line_start;
+            line_start = false;
+            if (c == '#' && at_line_start) || (c == '/' && next == Some('/')) {
+                while pos < chars.len() && chars[pos] != '\n' {
+                    pos += 1;
+                }
+            } else if c == '/' && next == Some('*') {
+                let end = (pos + 2..chars.len().saturating_sub(1)).find(|end| chars[*end] == '*' && chars[*end + 1] == '/')
+                    .ok_or("Unterminated comment".to_string())?;
+                pos = end + 2;
+            } else if c == '"' || c == '\'' {
+                let start = pos;
+                pos += 1;
+                while pos < chars.len() && chars[pos] != c {
+                    pos += if chars[pos] == '\\' { 2 } else { 1 };
+                }
+                if pos >= chars.len() {
+                    return Err("Unterminated string literal".to_string());
+                }
+                pos += 1;
+                tokens.push(chars[start..pos].iter().collect());
+            } else if c == ':' && next == Some(':') {
+                tokens.push("::".to_string());
+                pos += 2;
+            } else if IDL_PUNCTUATION.contains(c) {
+                tokens.push(c.to_string());
+                pos += 1;
+            } else {
+                let start = pos;
+                while pos < chars.len() && !chars[pos].is_whitespace() && !IDL_PUNCTUATION.contains(chars[pos]) {
+                    pos += 1;
+                }
+                tokens.push(chars[start..pos].iter().collect());
+            }
+        }
+        return Ok(tokens);
+    }
+
+    fn interface_in(modules: &[String], name: &str) -> Result<InterfaceName, String> {
+        return match modules {
+            [package, kind] => match InterfaceKind::from_subfolder(kind) {
+                Some(kind) => Ok(InterfaceName::new(package, kind, name)),
+                None => Err(format!("Unknown interface kind {} of {}", kind, name))
+            },
+            _ => Err(format!("{} must be declared in <package>::<msg|srv|action> module", name))
+        };
+    }
+
+    /// Parser of the IDL subset rosidl generates: modules, structs, typedefs of arrays, constants and annotations
+    struct IdlParser {
+        tokens: Vec<String>,
+        pos: usize,
+        modules: Vec<String>,
+        /// Typedefs by name, rosidl declares them in the module of the struct
+        typedefs: HashMap<String, FieldType>,
+        messages: Vec<MessageType>,
+        /// Constants of `<Type>_Constants` modules by full name of the type
+        constants: HashMap<String, Vec<Constant>>,
+    }
+
+    impl IdlParser {
+        fn peek(&self) -> Option<&str> {
+            return self.tokens.get(self.pos).map(|token| token.as_str());
+        }
+
+        fn next(&mut self) -> Result<String, String> {
+            let token = self.tokens.get(self.pos).cloned().ok_or("Unexpected end of IDL".to_string())?;
+            self.pos += 1;
+            return Ok(token);
+        }
+
+        fn expect(&mut self, expected: &str) -> Result<(), String> {
+            let token = self.next()?;
+            if token != expected {
+                return Err(format!("Expected {}, got {}", expected, token));
+            }
+            return Ok(());
+        }
+
+        fn next_identifier(&mut self) -> Result<String, String> {
+            let token = self.next()?;
+            if !is_identifier(token.as_str()) {
+                return Err(format!("Expected identifier, got {}", token));
+            }
+            return Ok(token);
+        }
+
+        fn next_size(&mut self) -> Result<usize, String> {
+            let token = self.next()?;
+            return token.parse().map_err(|_| format!("Invalid size {}", token));
+        }
+
+        /// Full name of the type declared in the current module, e.g. `std_msgs::msg`
+        fn current_interface(&self, name: &str) -> Result<InterfaceName, String> {
+            return interface_in(self.modules.as_slice(), name);
+        }
+
+        fn parse_definitions(&mut self, nested: bool) -> Result<(), String> {
+            loop {
+                match self.peek() {
+                    None if nested => return Err("Unexpected end of IDL".to_string()),
+                    None => return Ok(()),
+                    Some("}") if nested => {
+                        self.next()?;
+                        return self.expect(";");
+                    }
+                    // Annotations of modules and structs, e.g. comments in @verbatim, aren't needed
+                    Some("@") => {
+                        self.parse_annotation()?;
+                    }
+                    Some("module") => {
+                        self.next()?;
+                        let name = self.next_identifier()?;
+                        self.expect("{")?;
+                        self.modules.push(name);
+                        self.parse_definitions(true)?;
+                        self.modules.pop();
+                    }
+                    Some("struct") => self.parse_struct()?,
+                    Some("typedef") => self.parse_typedef()?,
+                    Some("const") => self.parse_const()?,
+                    Some(token) => return Err(format!("Unsupported IDL definition {}", token))
+                }
+            }
+        }
+
+        /// Skip annotation with its parameters. Returns name and the value parameter, e.g. of `@default (value=5)`
+        fn parse_annotation(&mut self) -> Result<(String, Option<String>), String> {
+            self.expect("@")?;
+            let name = self.next_identifier()?;
+            let mut value = None;
+            if self.peek() == Some("(") {
+                self.next()?;
+                let mut depth = 1;
+                while depth > 0 {
+                    let token = self.next()?;
+                    match token.as_str() {
+                        "(" => depth += 1,
+                        ")" => depth -= 1,
+                        "value" if self.peek() == Some("=") => {
+                            self.next()?;
+                            value = Some(self.next()?);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            return Ok((name, value));
+        }
+
+        fn parse_type(&mut self) -> Result<FieldType, String> {
+            let token = self.next()?;
+            let single = |base: &str| FieldType { base: base.to_string(), string_bound: None, collection: Collection::Single };
+            let field_type = match token.as_str() {
+                "sequence" => {
+                    self.expect("<")?;
+                    let mut element = self.parse_type()?;
+                    if element.collection != Collection::Single {
+                        return Err("Nested collections aren't supported".to_string());
+                    }
+                    let bound = if self.peek() == Some(",") {
+                        self.next()?;
+                        Some(self.next_size()?)
+                    } else {
+                        None
+                    };
+                    self.expect(">")?;
+                    element.collection = Collection::Sequence(bound);
+                    element
+                }
+                "string" | "wstring" => {
+                    let mut field_type = single(token.as_str());
+                    if self.peek() == Some("<") {
+                        self.next()?;
+                        field_type.string_bound = Some(self.next_size()?);
+                        self.expect(">")?;
+                    }
+                    field_type
+                }
+                "unsigned" => match self.next()?.as_str() {
+                    "short" => single("uint16"),
+                    "long" if self.peek() == Some("long") => {
+                        self.next()?;
+                        single("uint64")
+                    }
+                    "long" => single("uint32"),
+                    other => return Err(format!("Unsupported type unsigned {}", other))
+                },
+                "long" => match self.peek() {
+                    Some("long") => {
+                        self.next()?;
+                        single("int64")
+                    }
+                    Some("double") => return Err("Unsupported type long double".to_string()),
+                    _ => single("int32")
+                },
+                _ => match idl_primitive(token.as_str()) {
+                    Some(base) => single(base),
+                    None => self.parse_scoped_type(token)?
+                }
+            };
+            return Ok(field_type);
+        }
+
+        /// Typedef or nested message, `pkg::msg::Type` or the name relative to the current module
+        fn parse_scoped_type(&mut self, first: String) -> Result<FieldType, String> {
+            // Leading `::` of the global scope
+            let mut parts = if first == "::" { vec![self.next_identifier()?] } else { vec![first] };
+            while self.peek() == Some("::") {
+                self.next()?;
+                parts.push(self.next_identifier()?);
+            }
+            if parts.len() == 1 {
+                if let Some(typedef) = self.typedefs.get(parts[0].as_str()) {
+                    return Ok(typedef.clone());
+                }
+            }
+            let name = match parts.as_slice() {
+                [name] if is_identifier(name) => self.current_interface(name)?,
+                [package, kind, name] => InterfaceName::parse(format!("{}/{}/{}", package, kind, name).as_str())?,
+                _ => return Err(format!("Unknown type {}", parts.join("::")))
+            };
+            return Ok(FieldType { base: name.to_string(), string_bound: None, collection: Collection::Single });
+        }
+
+        fn parse_typedef(&mut self) -> Result<(), String> {
+            self.expect("typedef")?;
+            let mut field_type = self.parse_type()?;
+            let name = self.next_identifier()?;
+            if self.peek() == Some("[") {
+                if field_type.collection != Collection::Single {
+                    return Err(format!("Nested collections aren't supported in {}", name));
+                }
+                self.next()?;
+                field_type.collection = Collection::Array(self.next_size()?);
+                self.expect("]")?;
+            }
+            self.expect(";")?;
+            self.typedefs.insert(name, field_type);
+            return Ok(());
+        }
+
+        /// Constants are declared in `<Type>_Constants` module next to the struct
+        fn parse_const(&mut self) -> Result<(), String> {
+            self.expect("const")?;
+            let field_type = self.parse_type()?;
+            let name = self.next_identifier()?;
+            self.expect("=")?;
+            let mut value = self.next()?;
+            while self.peek() != Some(";") {
+                value.push(' ');
+                value.push_str(self.next()?.as_str());
+            }
+            self.expect(";")?;
+
+            let owner = match self.modules.split_last() {
+                Some((module, parents)) if module.ends_with("_Constants") =>
+                    interface_in(parents, module.trim_end_matches("_Constants"))?,
+                _ => return Err(format!("Constant {} must be declared in <Type>_Constants module", name))
+            };
+            if field_type.base == "bool" {
+                value = value.to_lowercase();
+            }
+            self.constants.entry(owner.to_string()).or_default()
+                .push(Constant { name, type_name: field_type.to_string(), value });
+            return Ok(());
+        }
+
+        fn parse_struct(&mut self) -> Result<(), String> {
+            self.expect("struct")?;
+            let name = self.next_identifier()?;
+            let name = self.current_interface(name.as_str())?;
+            // Forward declaration
+            if self.peek() == Some(";") {
+                return self.expect(";");
+            }
+            self.expect("{")?;
+            let mut message = MessageType { name: name.to_string(), fields: Vec::new(), constants: Vec::new() };
+            while self.peek() != Some("}") {
+                let mut default = None;
+                while self.peek() == Some("@") {
+                    if let (annotation, Some(value)) = self.parse_annotation()? {
+                        if annotation == "default" {
+                            default = Some(value);
+                        }
+                    }
+                }
+                let mut field_type = self.parse_type()?;
+                let field_name = self.next_identifier()?;
+                if self.peek() == Some("[") {
+                    if field_type.collection != Collection::Single {
+                        return Err(format!("Nested collections aren't supported in {}.{}", name, field_name));
+                    }
+                    self.next()?;
+                    field_type.collection = Collection::Array(self.next_size()?);
+                    self.expect("]")?;
+                }
+                self.expect(";")?;
+                // rosidl adds dummy member to messages without fields, as IDL structs can't be empty
+                if field_name == "structure_needs_at_least_one_member" {
+                    continue;
+                }
+                message.fields.push(Field { name: field_name, default: default.map(|value| idl_default(&field_type, value)), field_type });
+            }
+            self.expect("}")?;
+            self.expect(";")?;
+            self.messages.push(message);
+            return Ok(());
+        }
+    }
+
+    /// Default value in the same form as in `.msg` definitions. Only string defaults are quoted
+    fn idl_default(field_type: &FieldType, value: String) -> String {
+        let is_string = (field_type.base == "string" || field_type.base == "wstring") && field_type.collection == Collection::Single;
+        let value = if is_string {
+            value
+        } else {
+            value.strip_prefix('"').and_then(|value| value.strip_suffix('"')).map(|value| value.to_string()).unwrap_or(value)
+        };
+        return if field_type.base == "bool" { value.to_lowercase() } else { value };
+    }
+
+    /// Parse all structs of the IDL file, in the subset of OMG IDL rosidl generates for `.msg`, `.srv` and `.action` files
+    /// # Arguments
+    ///
+    /// * `text`: content of `.idl` file
+    ///
+    /// returns: Result<Vec<MessageType>, String>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn parse_idl(text: &str) -> Result<Vec<MessageType>, String> {
+        let mut parser = IdlParser {
+            tokens: tokenize_idl(text)?,
+            pos: 0,
+            modules: Vec::new(),
+            typedefs: HashMap::new(),
+            messages: Vec::new(),
+            constants: HashMap::new(),
+        };
+        parser.parse_definitions(false)?;
+        for message in parser.messages.iter_mut() {
+            if let Some(constants) = parser.constants.remove(&message.name) {
+                message.constants = constants;
+            }
+        }
+        return Ok(parser.messages);
+    }
+
+    /// Parse IDL definition of the message, service or action, picking the structs of its parts
+    /// # Arguments
+    ///
+    /// * `name`: full name of the interface
+    /// * `text`: content of `.idl` file
+    ///
+    /// returns: Result<Interface, String>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn parse_interface_idl(name: &InterfaceName, text: &str) -> Result<Interface, String> {
+        let mut structs = parse_idl(text)?;
+        let mut messages = Vec::new();
+        for suffix in name.kind.parts() {
+            let part = name.part(suffix).to_string();
+            match structs.iter().position(|message| message.name == part) {
+                Some(index) => messages.push(structs.remove(index)),
+                None => return Err(format!("Struct {} not found in IDL of {}", part, name))
+            }
+        }
+        return Ok(Interface { name: name.to_string(), kind: name.kind, messages });
+    }
+
+    /// Structure of the type with all nested messages, result of `type_info` command
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct TypeInfo {
+        pub name: String,
+        pub kind: InterfaceKind,
+        /// Definition file or `discovery` for type objects received in DDS discovery
+        pub source: String,
+        pub messages: Vec<MessageType>,
+        /// Nested messages used by the fields, recursively
+        pub dependencies: Vec<MessageType>,
+        /// Nested messages which definitions aren't found
+        pub unresolved: Vec<String>,
+    }
+
+    impl TypeInfo {
+        /// Message of the type or of its dependencies by full name, e.g. to walk nested fields of the sample
+        pub fn message(&self, name: &str) -> Option<&MessageType> {
+            return self.messages.iter().chain(self.dependencies.iter()).find(|message| message.name == name);
+        }
+    }
+
+    /// Resolves interface types from package share directories of install prefixes, `<prefix>/share/<package>/msg/<Type>.msg`,
+    /// and from definitions received in DDS discovery. Installed files are preferred, they have constants and default values
+    pub struct TypeRegistry {
+        prefixes: Mutex<Vec<PathBuf>>,
+        /// Message definitions received in discovery by full name
+        discovered: Mutex<HashMap<String, MessageType>>,
+    }
+
+    impl TypeRegistry {
+        pub fn new(prefixes: Vec<PathBuf>) -> TypeRegistry {
+            return TypeRegistry { prefixes: Mutex::new(prefixes), discovered: Mutex::new(HashMap::new()) };
+        }
+
+        /// Registry with install prefixes of the sourced ROS2 environment
+        pub fn from_env() -> TypeRegistry {
+            let prefixes = match std::env::var("AMENT_PREFIX_PATH") {
+                Ok(path) => std::env::split_paths(path.as_str()).filter(|prefix| !prefix.as_os_str().is_empty()).collect(),
+                Err(_) => Vec::new()
+            };
+            return TypeRegistry::new(prefixes);
+        }
+
+        /// Add install prefixes, e.g. of explored packages. Known ones are skipped
+        pub fn add_prefixes(&self, prefixes: Vec<PathBuf>) {
+            let mut known = self.prefixes.lock().unwrap();
+            for prefix in prefixes {
+                if !known.contains(&prefix) {
+                    known.push(prefix);
+                }
+            }
+        }
+
+        /// Add message definition received in discovery
+        pub fn add_discovered(&self, message: MessageType) {
+            self.discovered.lock().unwrap().insert(message.name.clone(), message);
+        }
+
+        /// Definition file of the interface. Packages generated from IDL have only `.idl` files
+        fn find_file(&self, name: &InterfaceName) -> Option<PathBuf> {
+            let prefixes = self.prefixes.lock().unwrap();
+            return [name.kind.subfolder(), "idl"].iter()
+                .flat_map(|extension| prefixes.iter()
+                    .map(move |prefix| prefix.join("share").join(name.package.as_str()).join(name.kind.subfolder())
+                        .join(format!("{}.{}", name.name, extension))))
+                .find(|path| path.is_file());
+        }
+
+        /// Interface with its source, from the installed file or from discovered definitions of its parts
+        fn load_interface(&self, name: &InterfaceName) -> Result<(Interface, String), String> {
+            if let Some(path) = self.find_file(name) {
+                let text = std::fs::read_to_string(&path).map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+                let interface = if path.extension().map_or(false, |extension| extension == "idl") {
+                    parse_interface_idl(name, text.as_str()).map_err(|e| format!("{}: {}", path.display(), e))?
+                } else {
+                    parse_interface(name, text.as_str())?
+                };
+                return Ok((interface, path.display().to_string()));
+            }
+
+            let discovered = self.discovered.lock().unwrap();
+            let messages: Vec<MessageType> = name.kind.parts().iter()
+                .filter_map(|suffix| discovered.get(&name.part(suffix).to_string()).cloned())
+                .collect();
+            if messages.len() != name.kind.parts().len() {
+                return Err(format!("Type {} not found", name));
+            }
+            return Ok((Interface { name: name.to_string(), kind: name.kind, messages }, DISCOVERY_SOURCE.to_string()));
+        }
+
+        /// Single message. Messages of services and actions are taken from their interfaces by the part suffix
+        fn load_message(&self, name: &InterfaceName) -> Result<(MessageType, String), String> {
+            if name.kind != InterfaceKind::Message {
+                for suffix in name.kind.parts() {
+                    if let Some(interface_name) = name.name.strip_suffix(format!("_{}", suffix).as_str()) {
+                        let parent = InterfaceName::new(name.package.as_str(), name.kind, interface_name);
+                        let (interface, source) = self.load_interface(&parent)?;
+                        let message = interface.messages.into_iter().find(|message| message.name == name.to_string());
+                        return message.map(|message| (message, source)).ok_or(format!("Type {} not found", name));
+                    }
+                }
+            }
+            let (mut interface, source) = self.load_interface(name)?;
+            return Ok((interface.messages.remove(0), source));
+        }
+
+        /// Structure of the type with all nested messages
+        /// # Arguments
+        ///
+        /// * `type_name`: message, service or action name in ROS2 or DDS form, or the message of service or action,
+        /// e.g. `example_interfaces/srv/AddTwoInts_Request`
+        ///
+        /// returns: Result<TypeInfo, String>
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn type_info(&self, type_name: &str) -> Result<TypeInfo, String> {
+            let name = InterfaceName::parse(type_name)?;
+            let is_part = name.kind.parts().iter().any(|suffix| !suffix.is_empty() && name.name.ends_with(format!("_{}", suffix).as_str()));
+            let (interface, source) = if is_part {
+                let (message, source) = self.load_message(&name)?;
+                (Interface { name: name.to_string(), kind: InterfaceKind::Message, messages: vec![message] }, source)
+            } else {
+                self.load_interface(&name)?
+            };
+
+            let mut visited: BTreeSet<String> = interface.messages.iter().map(|message| message.name.clone()).collect();
+            let mut pending: Vec<String> = interface.messages.iter().flat_map(|message| message.nested_types()).collect();
+            let mut dependencies = Vec::new();
+            let mut unresolved = Vec::new();
+            while let Some(nested) = pending.pop() {
+                if !visited.insert(nested.clone()) {
+                    continue;
+                }
+                match InterfaceName::parse(nested.as_str()).and_then(|nested_name| self.load_message(&nested_name)) {
+                    Ok((message, _)) => {
+                        pending.extend(message.nested_types());
+                        dependencies.push(message);
+                    }
+                    Err(_) => unresolved.push(nested),
+                }
+            }
+            dependencies.sort_by(|a, b| a.name.cmp(&b.name));
+            unresolved.sort();
+
+            return Ok(TypeInfo {
+                name: interface.name,
+                kind: interface.kind,
+                source,
+                messages: interface.messages,
+                dependencies,
+                unresolved,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use crate::interfaces::interfaces::{parse_idl, parse_interface, parse_interface_idl, parse_message, Collection, FieldType, InterfaceKind,
+                                        InterfaceName, TypeRegistry, DISCOVERY_SOURCE};
+
+    fn fixtures() -> PathBuf {
+        return Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/interfaces");
+    }
+
+    #[test]
+    fn interface_names() {
+        let name = InterfaceName::parse("std_msgs/msg/String").unwrap();
+        assert_eq!(name, InterfaceName::new("std_msgs", InterfaceKind::Message, "String"));
+        assert_eq!(InterfaceName::parse("std_msgs/String").unwrap(), name);
+        assert_eq!(InterfaceName::parse("std_msgs::msg::dds_::String_").unwrap(), name);
+        assert_eq!(InterfaceName::parse("example_interfaces::srv::dds_::AddTwoInts_Request_").unwrap().to_string(),
+                   "example_interfaces/srv/AddTwoInts_Request");
+        assert_eq!(InterfaceName::parse("action_tutorials_interfaces/action/Fibonacci").unwrap().kind, InterfaceKind::Action);
+        assert!(InterfaceName::parse("std_msgs/foo/String").is_err());
+        assert!(InterfaceName::parse("String").is_err());
+    }
+
+    #[test]
+    fn field_types() {
+        let parse = |type_str: &str| FieldType::parse(type_str, "geometry_msgs").unwrap();
+        assert_eq!(parse("int32").collection, Collection::Single);
+        assert_eq!(parse("float64[9]").collection, Collection::Array(9));
+        assert_eq!(parse("uint8[]").collection, Collection::Sequence(None));
+        assert_eq!(parse("Point[<=3]").collection, Collection::Sequence(Some(3)));
+        assert_eq!(parse("Point[<=3]").base, "geometry_msgs/msg/Point");
+        assert_eq!(parse("std_msgs/Header").base, "std_msgs/msg/Header");
+        assert_eq!(parse("Header").base, "std_msgs/msg/Header");
+        assert_eq!(parse("string<=10[<=5]").string_bound, Some(10));
+        assert_eq!(parse("string<=10[<=5]").to_string(), "string<=10[<=5]");
+        assert!(FieldType::parse("int32<=5", "pkg").is_err());
+        assert!(FieldType::parse("int32[x]", "pkg").is_err());
+    }
+
+    #[test]
+    fn parse_msg() {
+        let text = r#"
+# Comment line
+int32 X=5 # constant
+string GREETING="hello # not a comment"
+std_msgs/Header header
+float64[3] position
+uint8[] data
+string<=8 name "unnamed"  # default
+bool enabled true
+"#;
+        let message = parse_message(&InterfaceName::new("demo", InterfaceKind::Message, "Demo"), text).unwrap();
+        assert_eq!(message.name, "demo/msg/Demo");
+        assert_eq!(message.constants.len(), 2);
+        assert_eq!(message.constants[0].value, "5");
+        assert_eq!(message.constants[1].value, "\"hello # not a comment\"");
+        let names: Vec<&str> = message.fields.iter().map(|field| field.name.as_str()).collect();
+        assert_eq!(names, vec!["header", "position", "data", "name", "enabled"]);
+        assert_eq!(message.fields[3].default.as_deref(), Some("\"unnamed\""));
+        assert_eq!(message.fields[4].default.as_deref(), Some("true"));
+        assert_eq!(message.nested_types(), vec!["std_msgs/msg/Header"]);
+
+        assert!(parse_message(&InterfaceName::new("demo", InterfaceKind::Message, "Bad"), "int32").is_err());
+        assert!(parse_message(&InterfaceName::new("demo", InterfaceKind::Message, "Bad"), "Point P=1").is_err());
+    }
+
+    #[test]
+    fn parse_srv_and_action() {
+        let service = parse_interface(&InterfaceName::new("example_interfaces", InterfaceKind::Service, "AddTwoInts"),
+                                      "int64 a\nint64 b\n---\nint64 sum\n").unwrap();
+        assert_eq!(service.messages.len(), 2);
+        assert_eq!(service.messages[0].name, "example_interfaces/srv/AddTwoInts_Request");
+        assert_eq!(service.messages[1].fields[0].name, "sum");
+
+        let action = parse_interface(&InterfaceName::new("demo", InterfaceKind::Action, "Fibonacci"),
+                                     "int32 order\n---\nint32[] sequence\n---\nint32[] partial_sequence\n").unwrap();
+        let names: Vec<&str> = action.messages.iter().map(|message| message.name.as_str()).collect();
+        assert_eq!(names, vec!["demo/action/Fibonacci_Goal", "demo/action/Fibonacci_Result", "demo/action/Fibonacci_Feedback"]);
+
+        assert!(parse_interface(&InterfaceName::new("demo", InterfaceKind::Service, "Bad"), "int64 a\n").is_err());
+    }
+
+    #[test]
+    fn type_registry() {
+        let prefix = std::env::temp_dir().join(format!("ros2monitor_interfaces_{}", std::process::id()));
+        let write = |package: &str, kind: &str, file: &str, text: &str| {
+            let dir = prefix.join("share").join(package).join(kind);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join(file), text).unwrap();
+        };
+        write("geometry_msgs", "msg", "Point.msg", "float64 x\nfloat64 y\nfloat64 z\n");
+        write("geometry_msgs", "msg", "Polygon.msg", "Point32[] points\n");
+        write("demo", "srv", "Nearest.srv", "geometry_msgs/Point target\ngeometry_msgs/Polygon area\n---\ngeometry_msgs/Point nearest\n");
+
+        let registry = TypeRegistry::new(vec![prefix.clone()]);
+        let info = registry.type_info("geometry_msgs/msg/Point").unwrap();
+        assert_eq!(info.kind, InterfaceKind::Message);
+        assert_eq!(info.messages[0].fields.len(), 3);
+        assert!(info.source.ends_with("Point.msg"));
+
+        let info = registry.type_info("demo/srv/Nearest").unwrap();
+        assert_eq!(info.kind, InterfaceKind::Service);
+        assert_eq!(info.messages.len(), 2);
+        let dependencies: Vec<&str> = info.dependencies.iter().map(|message| message.name.as_str()).collect();
+        assert_eq!(dependencies, vec!["geometry_msgs/msg/Point", "geometry_msgs/msg/Polygon"]);
+        assert_eq!(info.unresolved, vec!["geometry_msgs/msg/Point32"]);
+
+        let info = registry.type_info("demo::srv::dds_::Nearest_Response_").unwrap();
+        assert_eq!(info.messages[0].name, "demo/srv/Nearest_Response");
+
+        // Discovered definitions are used if there is no installed file
+        assert!(registry.type_info("geometry_msgs/msg/Point32").is_err());
+        let name = InterfaceName::parse("geometry_msgs::msg::dds_::Point32_").unwrap();
+        registry.add_discovered(parse_message(&name, "float32 x\nfloat32 y\nfloat32 z\n").unwrap());
+        let info = registry.type_info("geometry_msgs/msg/Point32").unwrap();
+        assert_eq!(info.source, DISCOVERY_SOURCE);
+        assert!(registry.type_info("demo/srv/Nearest").unwrap().unresolved.is_empty());
+        assert!(registry.type_info("demo/srv/Missing").is_err());
+
+        std::fs::remove_dir_all(&prefix).unwrap();
+    }
+
+    #[test]
+    fn parse_idl_structs() {
+        let text = r#"
+#include "std_msgs/msg/Header.idl"
+module demo {
+  module msg {
+    typedef double double__9[9];
+    module Demo_Constants {
+      const int32 X = 5;
+      const boolean ENABLED = TRUE; // comment
+    };
+    /* block
+       comment */
+    @verbatim (language="comment", text=
+      "Demo" "message")
+    struct Demo {
+      std_msgs::msg::Header header;
+      double__9 covariance;
+      sequence<Point, 3> points;
+      sequence<string<8>> names;
+      unsigned long count;
+      long long offset;
+      octet flags[4];
+      @default (value="(1, 2)")
+      sequence<int16> pair;
+    };
+    struct Point {
+      float x;
+    };
+  };
+};
+"#;
+        let messages = parse_idl(text).unwrap();
+        assert_eq!(messages.len(), 2);
+        let demo = &messages[0];
+        assert_eq!(demo.name, "demo/msg/Demo");
+        let types: Vec<String> = demo.fields.iter().map(|field| field.field_type.to_string()).collect();
+        assert_eq!(types, vec!["std_msgs/msg/Header", "float64[9]", "demo/msg/Point[<=3]", "string<=8[]", "uint32", "int64",
+                               "byte[4]", "int16[]"]);
+        assert_eq!(demo.fields[7].default.as_deref(), Some("(1, 2)"));
+        assert_eq!(demo.constants.len(), 2);
+        assert_eq!(demo.constants[1].value, "true");
+        assert_eq!(demo.constants[1].type_name, "bool");
+        assert_eq!(messages[1].fields[0].field_type.base, "float32");
+
+        assert!(parse_idl("module demo { struct Bad { int32 x; }; };").is_err());
+        assert!(parse_idl("module demo { module msg { struct Bad { int32 x; }; };").is_err());
+        assert!(parse_idl("module demo { module msg { struct Bad { long double x; }; }; };").is_err());
+        assert!(parse_idl("module demo { module msg { const int32 X = 1; }; };").is_err());
+    }
+
+    #[test]
+    fn idl_matches_msg() {
+        let dir = fixtures().join("share/demo_interfaces");
+        let read = |file: &str| std::fs::read_to_string(dir.join(file)).unwrap();
+        let reading = InterfaceName::new("demo_interfaces", InterfaceKind::Message, "Reading");
+        assert_eq!(parse_interface_idl(&reading, read("msg/Reading.idl").as_str()).unwrap(),
+                   parse_interface(&reading, read("msg/Reading.msg").as_str()).unwrap());
+        let scan = InterfaceName::new("demo_interfaces", InterfaceKind::Action, "Scan");
+        assert_eq!(parse_interface_idl(&scan, read("action/Scan.idl").as_str()).unwrap(),
+                   parse_interface(&scan, read("action/Scan.action").as_str()).unwrap());
+
+        let reset = InterfaceName::new("demo_interfaces", InterfaceKind::Service, "Reset");
+        assert!(parse_interface_idl(&reset, read("action/Scan.idl").as_str()).is_err());
+    }
+
+    #[test]
+    fn type_registry_fixtures() {
+        let registry = TypeRegistry::new(vec![fixtures()]);
+        let info = registry.type_info("demo_interfaces/action/Scan").unwrap();
+        assert!(info.source.ends_with("Scan.action"));
+        let dependencies: Vec<&str> = info.dependencies.iter().map(|message| message.name.as_str()).collect();
+        assert_eq!(dependencies, vec!["builtin_interfaces/msg/Time", "demo_interfaces/msg/Reading", "demo_interfaces/msg/Status",
+                                      "std_msgs/msg/Header"]);
+        assert!(info.unresolved.is_empty());
+
+        // Status is defined only in IDL
+        let status = info.message("demo_interfaces/msg/Status").unwrap();
+        assert_eq!(status.fields[1].default.as_deref(), Some("\"idle\""));
+        assert_eq!(status.fields[3].field_type.base, "uint64");
+        assert_eq!(status.fields[4].field_type.to_string(), "wstring<=32");
+        assert_eq!(status.constants[1].value, "-1");
+
+        let info = registry.type_info("demo_interfaces::srv::dds_::Reset_Request_").unwrap();
+        assert!(info.source.ends_with("Reset.idl"));
+        assert!(info.messages[0].fields.is_empty());
+        assert_eq!(registry.type_info("demo_interfaces/srv/Calibrate").unwrap().messages[0].fields[1].default.as_deref(), Some("0.01"));
+    }
+}
@@ -10,6 +10,7 @@ use std::env::args;
 use std::io::{self};
 
 use std::{env, fs, str};
+use std::collections::HashMap;
 use std::error::Error;
 use std::ops::Deref;
 
@@ -30,12 +31,16 @@ use crate::ros2entites::ros2entities::{Ros2State, Settings};
 
 use tokio::{time};
 use tokio::sync::{Mutex};
-use crate::api::api::Api;
+use crate::api::api::{Api, StateProvider};
+use crate::auth::auth::{AuthPolicy, ClientIdentity};
+use crate::audit::audit::AuditLog;
+use crate::config::config::{ConfigAction, SettingsManager};
 
 use crate::discovery_server::discovery_server::{DiscoveryFlags, DiscoveryServer};
 
 
 use crate::ros2_wrapper::ros2;
+use crate::http_server::http_server::HttpServer;
 
 
 mod ros2entites;
@@ -49,18 +54,35 @@ mod discovery_server;
 mod ros2_wrapper;
 mod fastdds_server;
 mod ros2_server;
+mod http_server;
+mod auth;
+mod audit;
+mod config;
+mod fastdds_profiles;
+mod processes;
+mod resources;
+mod rtps;
+mod capture;
+mod offline;
+mod traffic;
+mod interfaces;
+mod cdr;
 
 /**
 Handle client json request
  */
 async fn handle_client<'a>(mut stream: UnixStream, current_state: Arc<Mutex<Ros2State>>, api: Arc<Api>) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    // Identify client by its peer credentials
+    let cred = stream.peer_cred()?;
+    let identity = ClientIdentity::Unix { uid: cred.uid(), gid: cred.gid(), pid: cred.pid() };
+
     // Read request from stream
     let mut buffer = [0u8; 1024];
     let _nbytes = stream.read(&mut buffer[..]).await?;
     let request = str::from_utf8(&buffer).unwrap().trim().trim_matches(char::from(0));
 
     // Create response
-    let response: String = api.handle_request(request.to_string(), current_state).await;
+    let response: String = api.handle_request(request.to_string(), &identity, current_state).await;
     debug!("Json string for response: {}", response);
 
     // Write back response to client
@@ -71,24 +93,59 @@ async fn handle_client<'a>(mut stream: UnixStream, current_state: Arc<Mutex<Ros2
 }
 
 fn main() -> io::Result<()> {
-    let ld_library_path = env::var("LD_LIBRARY_PATH").unwrap();
-
-    env::set_var("LD_LIBRARY_PATH", format!("./src/c/lib/nodegraph:{ld_library_path}"));
-    env::set_var("ROS_DISCOVERY_SERVER", "0.0.0.0:11811");
-    env::set_var("FASTRTPS_DEFAULT_PROFILES_FILE", "super_client_configuration_file.xml");
-
+    let cli_args: Vec<String> = args().skip(1).collect();
+    let env_vars: HashMap<String, String> = env::vars().collect();
+    let (settings, config_file): (Settings, String) = match config::config::load(&cli_args, &env_vars) {
+        Ok(ConfigAction::Run(settings, config_file)) => (settings, config_file),
+        Ok(ConfigAction::Help) => {
+            println!("{}", config::config::usage());
+            return Ok(());
+        }
+        Ok(ConfigAction::PrintConfig(settings)) => {
+            println!("{}", settings.to_json());
+            return Ok(());
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, config::config::usage());
+            exit(2);
+        }
+    };
 
-    Command::new("ros2").arg("daemon").arg("stop").output().unwrap();
-    Command::new("ros2").arg("daemon").arg("start").output().unwrap();
-    sleep(Duration::from_secs(1));
+    let ld_library_path = env::var("LD_LIBRARY_PATH").unwrap_or_default();
+
+    env::set_var("LD_LIBRARY_PATH", format!("{}:{ld_library_path}", settings.library_path));
+    // Offline analysis doesn't discover anything, so neither profiles nor ros2 daemon are needed
+    let is_offline = !settings.analyze_file.is_empty();
+    if !is_offline {
+        // Ros2 daemon and cli tools have to use the same discovery as the monitor
+        if settings.discovery_mode == "simple" {
+            env::remove_var("ROS_DISCOVERY_SERVER");
+            env::remove_var("FASTRTPS_DEFAULT_PROFILES_FILE");
+        } else {
+            // Server profiles of the monitor and the profile of cli tools are generated from the same settings
+            if let Err(e) = fastdds_profiles::fastdds_profiles::write(&settings) {
+                eprintln!("{}", e);
+                exit(1);
+            }
+            match fastdds_profiles::fastdds_profiles::discovery_server_variable(&settings, 0) {
+                Ok(discovery_server) if discovery_server.is_empty() => env::remove_var("ROS_DISCOVERY_SERVER"),
+                Ok(discovery_server) => env::set_var("ROS_DISCOVERY_SERVER", discovery_server.as_str()),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    exit(1);
+                }
+            }
+            env::set_var("FASTRTPS_DEFAULT_PROFILES_FILE", settings.fastdds_profiles_file.as_str());
+        }
 
-    simple_logger::init_with_level(log::Level::Debug).unwrap();
+        Command::new("ros2").arg("daemon").arg("stop").output().unwrap();
+        Command::new("ros2").arg("daemon").arg("start").output().unwrap();
+        sleep(Duration::from_secs(1));
+    }
 
-    let settings: Settings = Settings {
-        domain_id: 1,
-        include_internals: false,
-        dds_topic_type: false,
-    };
+    // Logger passes everything, actual level is controlled by max level, so it can be changed at runtime
+    simple_logger::init_with_level(log::Level::Trace).unwrap();
+    log::set_max_level(config::config::log_level_filter(settings.log_level.as_str()));
 
     let ctrlc_pressed: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
     let ctrlc_pressed_setter = ctrlc_pressed.clone();
@@ -97,32 +154,67 @@ fn main() -> io::Result<()> {
         ctrlc_pressed_setter.store(true, Ordering::SeqCst);
     }).expect("Error setting Ctrl-C handler");
 
-    ros2::init(args());
-
-    let mut discovery_flags = DiscoveryFlags::EnableFastdds | DiscoveryFlags::EnableROS2;
-    if settings.include_internals {
-        discovery_flags |= DiscoveryFlags::IncludeInternals;
+    if !is_offline {
+        ros2::init(args());
     }
 
-    let (mut rx_state, mut discovery_server) = DiscoveryServer::new(settings.domain_id, discovery_flags);
+    let discovery_flags = DiscoveryFlags::from_settings(&settings);
+    let (rx_state, mut discovery_server) = DiscoveryServer::new(&settings, discovery_flags);
 
     let rt = Runtime::new().unwrap();
     let _guard = rt.enter();
 
-    let ros2discoverer = discovery_server.ros2_discoverer.clone();
+    let policy_file = settings.policy_file.as_str();
+    let policy = if Path::new(policy_file).exists() {
+        AuthPolicy::from_file(policy_file).unwrap_or_else(|e| {
+            error!("{}", e);
+            exit(1);
+        })
+    } else {
+        info!("Policy file {} not found. Using default policy", policy_file);
+        AuthPolicy::default()
+    };
+    // Analyzed capture can't be changed, so mutating commands are denied to everyone
+    let policy = AuthPolicy { read_only: is_offline, ..policy };
+
+    let audit_log = if settings.audit_log_file.is_empty() {
+        info!("Audit log is disabled by configuration");
+        None
+    } else {
+        match AuditLog::open(settings.audit_log_file.as_str()) {
+            Ok(audit_log) => Some(Arc::new(audit_log)),
+            Err(e) => {
+                error!("{}. Audit log is disabled", e);
+                None
+            }
+        }
+    };
 
-    let api: Arc<Api> = Arc::new(Api::new(ros2discoverer));
+    let settings_manager = Arc::new(SettingsManager::new(settings.clone(), config_file, discovery_server.control_sender()));
+    let api: Arc<Api> = Arc::new(Api::new(discovery_server.ros2_discoverer.clone(), policy, audit_log, settings_manager, discovery_server.resource_monitor.clone(),
+                                       discovery_server.traffic_monitor.clone(), discovery_server.type_registry.clone()));
 
-    let socket_name = "/tmp/ros2monitor.sock";
+    let socket_name = settings.socket_path.as_str();
     if Path::new(socket_name).exists() {
         fs::remove_file(socket_name).expect("Unable to release socket");
     }
 
     let listener = UnixListener::bind(socket_name)?;
-    // Set socket permissions for non root users. TODO: make it in more secure way
+    // Socket is available for all local users. Each request is authorized by peer credentials,
+    // so unprivileged users are limited to read-only commands
     fs::set_permissions(socket_name, fs::Permissions::from_mode(0o777))?;
 
-    let current_state = Arc::new(Mutex::<Ros2State>::new(Ros2State::new(false)));
+    // The graph of analyzed capture is served instead of the discovered one
+    let initial_state = if is_offline {
+        offline::offline::analyze_file(settings.analyze_file.as_str(), settings.include_internals, settings.dds_topic_type, &settings.port_params,
+                                       &discovery_server.traffic_monitor).unwrap_or_else(|e| {
+            error!("{}", e);
+            exit(1);
+        })
+    } else {
+        Ros2State::new(settings.include_internals)
+    };
+    let current_state = Arc::new(Mutex::<Ros2State>::new(initial_state));
 
     let ctrl_pressed_check = ctrlc_pressed.clone();
     let signal_handler = rt.spawn(async move {
@@ -130,10 +222,12 @@ fn main() -> io::Result<()> {
         let seconds = interval.period().as_secs();
         loop {
             if ctrl_pressed_check.load(Ordering::SeqCst) {
-                debug!("Shutting down ros2");
-                let res = ros2::shutdown();
-                if !res {
-                    error!("Unable to shutdown ros2");
+                if !is_offline {
+                    debug!("Shutting down ros2");
+                    let res = ros2::shutdown();
+                    if !res {
+                        error!("Unable to shutdown ros2");
+                    }
                 }
                 exit(0);
             }
@@ -146,18 +240,34 @@ fn main() -> io::Result<()> {
     rt.spawn(signal_handler);
 
 
+    // Both transports take the latest state published by discovery server. If nothing has
+    // been discovered yet, the initial empty state is used
+    let rx_state = Arc::new(std::sync::Mutex::new(rx_state));
+    let state_provider: StateProvider = Arc::new(move || {
+        return match rx_state.lock().unwrap().latest() {
+            Some(state) => Arc::new(Mutex::new(state.clone())),
+            None => current_state.clone()
+        };
+    });
+
+    // Http REST facade
+    if !settings.http_address.is_empty() {
+        let http_server = HttpServer::new(settings.http_address.clone(), api.clone(), state_provider.clone());
+        rt.spawn(async move {
+            if let Err(e) = http_server.run().await {
+                error!("Http server stopped: {}", e);
+            }
+        });
+    }
+
     // Accept connections from clients
     rt.spawn(async move {
         loop {
-            // Check state
-            let current_state = current_state.clone();
-            let state = match rx_state.latest() {
-                Some(state) => Arc::new(Mutex::new(state.clone())),
-                None => current_state.clone()
-            };
-
             let (stream, _) = listener.accept().await.unwrap();
 
+            // Check state
+            let state = state_provider();
+
             let api_clone = api.clone();
             tokio::spawn(async move {
                 let _res = handle_client(stream, state, api_clone).await;
@@ -165,7 +275,12 @@ fn main() -> io::Result<()> {
         }
     });
 
-    // Blocking operation
+    // Blocking operation. In offline mode the api serves the analyzed state until Ctrl+C
+    if is_offline {
+        loop {
+            sleep(Duration::from_secs(1));
+        }
+    }
     discovery_server.run();
     return Ok(());
 }
\ No newline at end of file
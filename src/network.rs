@@ -6,73 +6,348 @@
 
 pub mod network {
     use std::cell::RefCell;
-    use std::fmt::Write;
+    use std::fmt::{Display, Formatter, Write};
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
     use std::num::ParseIntError;
+    use std::str::FromStr;
     use dns_lookup::lookup_addr;
     use log::{error};
-    use serde::{Deserialize, Serialize};
-    use crate::discovery_server_impl::{FastDDSEndpoint, ParticipantData, rmw_transport_SHM_TRANSPORT, rmw_transport_TCPV4_TRANSPORT, rmw_transport_TCPV6_TRANSPORT, rmw_transport_UPDV4_TRANSPORT, rmw_transport_UPDV6_TRANSPORT};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use crate::discovery_server_impl::{FastDDSEndpoint, FastDDSLocators, ParticipantData, rmw_transport_SHM_TRANSPORT, rmw_transport_TCPV4_TRANSPORT, rmw_transport_TCPV6_TRANSPORT, rmw_transport_UPDV4_TRANSPORT, rmw_transport_UPDV6_TRANSPORT};
+    use crate::interfaces::interfaces::{InterfaceKind, InterfaceName};
     use crate::ros2entites::ros2entities::Host;
 
-    pub struct Port {
-        discovery_mcast_port: u32,
-        user_mcast_port: u32,
-        discovery_unicast_port: u32,
-        user_unicast_port: u32,
-    }
-
-    pub fn parse_endpoint(endpoint: FastDDSEndpoint) -> String {
-        if endpoint.transport == rmw_transport_SHM_TRANSPORT {
-            return "SHM".to_string();
-        } else if endpoint.transport == rmw_transport_UPDV4_TRANSPORT {
-            let endpoint_vec: Vec<String> = unsafe {
-                endpoint.__bindgen_anon_1.endpoint_v4.to_ascii_uppercase().iter().map(|&c| c.to_string()).collect::<Vec<String>>()
+    /// Transport kind of the locator, named as FastDDS prints it
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    pub enum Transport {
+        SHM,
+        UDPv4,
+        UDPv6,
+        TCPv4,
+        TCPv6,
+    }
+
+    impl Transport {
+        pub fn name(&self) -> &'static str {
+            return match self {
+                Transport::SHM => "SHM",
+                Transport::UDPv4 => "UDPv4",
+                Transport::UDPv6 => "UDPv6",
+                Transport::TCPv4 => "TCPv4",
+                Transport::TCPv6 => "TCPv6",
             };
-            return format!("{}", endpoint_vec.join("."));
-        } else if endpoint.transport == rmw_transport_UPDV6_TRANSPORT {
-            let endpoint_vec: Vec<String> = unsafe {
-                endpoint.__bindgen_anon_1.endpoint_v6.to_ascii_uppercase().iter().map(|&c| c.to_string()).collect::<Vec<String>>()
+        }
+
+        pub fn from_name(name: &str) -> Option<Transport> {
+            return [Transport::SHM, Transport::UDPv4, Transport::UDPv6, Transport::TCPv4, Transport::TCPv6].into_iter()
+                .find(|transport| transport.name() == name);
+        }
+    }
+
+    /// RTPS locator. Formatted the same way as FastDDS prints locators: `UDPv4:[192.168.1.10]:7412`,
+    /// `UDPv6:[fe80::1]:7412`, `TCPv4:[10.0.0.1]:1-5100` (logical and physical ports) and `SHM:[_]:7411`.
+    /// The same string form is used in json
+    #[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    pub struct Locator {
+        pub transport: Transport,
+        /// None for SHM locators
+        pub address: Option<IpAddr>,
+        /// TCP locators hold logical port in high 16 bits and physical port in low 16 bits
+        pub port: u32,
+    }
+
+    impl Locator {
+        pub fn new(transport: Transport, address: Option<IpAddr>, port: u32) -> Locator {
+            return Locator { transport, address, port };
+        }
+
+        /// Locator of FastDDS endpoint passed through FFI. None for unknown transports
+        pub fn from_fastdds(endpoint: &FastDDSEndpoint) -> Option<Locator> {
+            let (v4, v6) = unsafe { (endpoint.__bindgen_anon_1.endpoint_v4, endpoint.__bindgen_anon_1.endpoint_v6) };
+            let (transport, address) = match endpoint.transport {
+                rmw_transport_SHM_TRANSPORT => (Transport::SHM, None),
+                rmw_transport_UPDV4_TRANSPORT => (Transport::UDPv4, Some(IpAddr::V4(Ipv4Addr::from(v4)))),
+                rmw_transport_UPDV6_TRANSPORT => (Transport::UDPv6, Some(IpAddr::V6(Ipv6Addr::from(v6)))),
+                rmw_transport_TCPV4_TRANSPORT => (Transport::TCPv4, Some(IpAddr::V4(Ipv4Addr::from(v4)))),
+                rmw_transport_TCPV6_TRANSPORT => (Transport::TCPv6, Some(IpAddr::V6(Ipv6Addr::from(v6)))),
+                _ => return None
             };
-            return format!("{}", endpoint_vec.join("."));
-        } else if endpoint.transport == rmw_transport_TCPV4_TRANSPORT {
-            let endpoint_vec: Vec<String> = unsafe {
-                endpoint.__bindgen_anon_1.endpoint_v4.to_ascii_uppercase().iter().map(|&c| c.to_string()).collect::<Vec<String>>()
+            return Some(Locator::new(transport, address, endpoint.port));
+        }
+
+        /// Locator as it is serialized in RTPS discovery data: kind, port and 16 bytes of address.
+        /// IPv4 addresses take the last 4 bytes. None for unknown kinds
+        pub fn from_rtps(kind: i32, port: u32, address: [u8; 16]) -> Option<Locator> {
+            let v4: [u8; 4] = address[12..16].try_into().unwrap();
+            let (transport, address) = match kind {
+                1 => (Transport::UDPv4, Some(IpAddr::V4(Ipv4Addr::from(v4)))),
+                2 => (Transport::UDPv6, Some(IpAddr::V6(Ipv6Addr::from(address)))),
+                4 => (Transport::TCPv4, Some(IpAddr::V4(Ipv4Addr::from(v4)))),
+                8 => (Transport::TCPv6, Some(IpAddr::V6(Ipv6Addr::from(address)))),
+                // FastDDS shared memory transport
+                16 => (Transport::SHM, None),
+                _ => return None
             };
-            return format!("{}", endpoint_vec.join("."));
-        } else if endpoint.transport == rmw_transport_TCPV6_TRANSPORT {
-            let endpoint_vec: Vec<String> = unsafe {
-                endpoint.__bindgen_anon_1.endpoint_v6.to_ascii_uppercase().iter().map(|&c| c.to_string()).collect::<Vec<String>>()
+            return Some(Locator::new(transport, address, port));
+        }
+
+        pub fn is_tcp(&self) -> bool {
+            return self.transport == Transport::TCPv4 || self.transport == Transport::TCPv6;
+        }
+
+        /// Port the socket is bound to. TCP locators also carry the logical port
+        pub fn physical_port(&self) -> u32 {
+            return if self.is_tcp() { self.port & 0xffff } else { self.port };
+        }
+    }
+
+    impl Display for Locator {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            let address = match self.address {
+                Some(address) => address.to_string(),
+                None => "_".to_string()
+            };
+            let logical_port = self.port >> 16;
+            return if self.is_tcp() && logical_port != 0 {
+                write!(f, "{}:[{}]:{}-{}", self.transport.name(), address, logical_port, self.physical_port())
+            } else {
+                write!(f, "{}:[{}]:{}", self.transport.name(), address, self.port)
+            };
+        }
+    }
+
+    impl FromStr for Locator {
+        type Err = String;
+
+        fn from_str(locator: &str) -> Result<Self, Self::Err> {
+            let invalid = || format!("Invalid locator {}, expected <transport>:[<address>]:<port>", locator);
+            let (transport, rest) = locator.split_once(":[").ok_or_else(invalid)?;
+            let (address, port) = rest.rsplit_once("]:").ok_or_else(invalid)?;
+            let transport = Transport::from_name(transport).ok_or_else(invalid)?;
+            let address = match (transport, address) {
+                (Transport::SHM, _) => None,
+                (_, address) => Some(address.parse::<IpAddr>().map_err(|_| invalid())?)
             };
-            return format!("{}", endpoint_vec.join("."));
+            let port = match port.split_once('-') {
+                Some((logical, physical)) if transport == Transport::TCPv4 || transport == Transport::TCPv6 => {
+                    let logical: u32 = logical.parse().map_err(|_| invalid())?;
+                    let physical: u32 = physical.parse().map_err(|_| invalid())?;
+                    (logical << 16) | (physical & 0xffff)
+                }
+                _ => port.parse().map_err(|_| invalid())?
+            };
+            return Ok(Locator::new(transport, address, port));
         }
+    }
 
-        return "".to_string();
+    impl Serialize for Locator {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            return serializer.collect_str(self);
+        }
     }
 
-    pub fn domain_ports(domain_id: u32) -> Port {
-        // calculate function
-        let d0 = 0;
-        let d2 = 1;
-        let d1 = 10;
-        let d3 = 11;
-        let pb = 7400;
-        let dg = 250;
-        let pg = 2;
-
-        // TODO: temporary value. For the current moment, we assume that there is only one participant
-        let participant_id = 0;
-
-        /*
-        Theses formulas are taken from ros2 documentation website. They can be found in the javascript code.
-         */
-        let port: Port = Port {
-            discovery_mcast_port: pb + (dg * domain_id) + d0,
-            user_mcast_port: pb + (dg * domain_id) + d2,
-            discovery_unicast_port: pb + (dg * domain_id) + d1 + (pg * participant_id),
-            user_unicast_port: pb + (dg * domain_id) + d3 + (pg * participant_id),
-        };
+    impl<'de> Deserialize<'de> for Locator {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let locator = String::deserialize(deserializer)?;
+            return locator.parse().map_err(serde::de::Error::custom);
+        }
+    }
+
+    /// Unicast and multicast locators passed through FFI
+    /// # Arguments
+    ///
+    /// * `locators`: locator lists of FastDDS participant or endpoint
+    ///
+    /// returns: (Vec<Locator>, Vec<Locator>), unicast and multicast locators
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn parse_locators(locators: &FastDDSLocators) -> (Vec<Locator>, Vec<Locator>) {
+        let unicast_num = (locators.unicast_num as usize).min(locators.unicast.len());
+        let multicast_num = (locators.multicast_num as usize).min(locators.multicast.len());
+        let unicast = locators.unicast[..unicast_num].iter().filter_map(Locator::from_fastdds).collect();
+        let multicast = locators.multicast[..multicast_num].iter().filter_map(Locator::from_fastdds).collect();
+        return (unicast, multicast);
+    }
+
+    /// Ports of RTPS traffic of the single participant
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+    pub struct Port {
+        pub discovery_mcast_port: u32,
+        pub user_mcast_port: u32,
+        pub discovery_unicast_port: u32,
+        pub user_unicast_port: u32,
+    }
+
+    /// Kind of RTPS traffic sent to the port
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum PortKind {
+        DiscoveryMulticast,
+        UserMulticast,
+        DiscoveryUnicast,
+        UserUnicast,
+    }
+
+    /// Owner of the port found by the reverse port mapping. Multicast ports are shared by all participants of the domain
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+    pub struct PortUsage {
+        pub domain_id: u32,
+        pub participant_id: Option<u32>,
+        pub kind: PortKind,
+    }
+
+    /// Parameters of RTPS port mapping, the same as `<port>` element of FastDDS participant profile.
+    /// Defaults are the values of RTPS specification
+    #[derive(Deserialize, Clone, Serialize, Debug, PartialEq)]
+    #[serde(default, deny_unknown_fields)]
+    pub struct PortParams {
+        /// PB
+        pub port_base: u32,
+        /// DG
+        pub domain_id_gain: u32,
+        /// PG
+        pub participant_id_gain: u32,
+        /// d0, discovery multicast
+        pub offset_d0: u32,
+        /// d1, discovery unicast
+        pub offset_d1: u32,
+        /// d2, user multicast
+        pub offset_d2: u32,
+        /// d3, user unicast
+        pub offset_d3: u32,
+    }
+
+    impl Default for PortParams {
+        fn default() -> PortParams {
+            return PortParams {
+                port_base: 7400,
+                domain_id_gain: 250,
+                participant_id_gain: 2,
+                offset_d0: 0,
+                offset_d1: 10,
+                offset_d2: 1,
+                offset_d3: 11,
+            };
+        }
+    }
+
+    impl PortParams {
+        /// Ports of the participant. The formulas are from RTPS specification (9.6.1.1)
+        /// # Arguments
+        ///
+        /// * `domain_id`: DDS domain
+        /// * `participant_id`: id of the participant in the domain on its host, FastDDS assigns them from 0
+        ///
+        /// returns: Port
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn ports(&self, domain_id: u32, participant_id: u32) -> Port {
+            let domain_base = self.port_base + self.domain_id_gain * domain_id;
+            return Port {
+                discovery_mcast_port: domain_base + self.offset_d0,
+                user_mcast_port: domain_base + self.offset_d2,
+                discovery_unicast_port: domain_base + self.offset_d1 + self.participant_id_gain * participant_id,
+                user_unicast_port: domain_base + self.offset_d3 + self.participant_id_gain * participant_id,
+            };
+        }
+
+        /// The largest domain id whose ports fit into UDP port range
+        pub fn max_domain_id(&self) -> u32 {
+            let highest_offset = [self.offset_d0, self.offset_d1, self.offset_d2, self.offset_d3].into_iter().max().unwrap();
+            return (u16::MAX as u32).saturating_sub(self.port_base.saturating_add(highest_offset)) / self.domain_id_gain.max(1);
+        }
+
+        /// The largest participant id whose unicast ports don't fall into the next domain
+        pub fn max_participant_id(&self) -> u32 {
+            let highest_offset = self.offset_d1.max(self.offset_d3);
+            return self.domain_id_gain.saturating_sub(highest_offset + 1) / self.participant_id_gain.max(1);
+        }
+
+        /// Domain of the port, None if the port is out of the mapped range.
+        /// Unlike `resolve`, ports which aren't used by RTPS are attributed to the domain range they are in
+        pub fn domain(&self, port: u32) -> Option<u32> {
+            if port < self.port_base || self.domain_id_gain == 0 {
+                return None;
+            }
+            let domain_id = (port - self.port_base) / self.domain_id_gain;
+            return if domain_id <= self.max_domain_id() { Some(domain_id) } else { None };
+        }
+
+        /// Reverse port mapping, the domain, participant and kind of traffic of the port
+        /// # Arguments
+        ///
+        /// * `port`: observed UDP port
+        ///
+        /// returns: Option<PortUsage>, None if the port isn't used by RTPS with these parameters
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn resolve(&self, port: u32) -> Option<PortUsage> {
+            let domain_id = self.domain(port)?;
+            let offset = port - self.port_base - self.domain_id_gain * domain_id;
+            let usage = |participant_id: Option<u32>, kind: PortKind| Some(PortUsage { domain_id, participant_id, kind });
 
-        return port;
+            // Multicast ports are checked first, so a valid mapping never attributes them to a participant
+            if offset == self.offset_d0 {
+                return usage(None, PortKind::DiscoveryMulticast);
+            }
+            if offset == self.offset_d2 {
+                return usage(None, PortKind::UserMulticast);
+            }
+            if self.participant_id_gain > 0 {
+                if offset >= self.offset_d1 && (offset - self.offset_d1) % self.participant_id_gain == 0 {
+                    return usage(Some((offset - self.offset_d1) / self.participant_id_gain), PortKind::DiscoveryUnicast);
+                }
+                if offset >= self.offset_d3 && (offset - self.offset_d3) % self.participant_id_gain == 0 {
+                    return usage(Some((offset - self.offset_d3) / self.participant_id_gain), PortKind::UserUnicast);
+                }
+            }
+            return None;
+        }
+
+        /// Check that ports of different domains and participants don't overlap
+        pub fn validate(&self) -> Result<(), String> {
+            if self.domain_id_gain == 0 || self.participant_id_gain == 0 {
+                return Err("domain_id_gain and participant_id_gain must be positive".to_string());
+            }
+            let offsets = [self.offset_d0, self.offset_d1, self.offset_d2, self.offset_d3];
+            if offsets.iter().any(|offset| *offset >= self.domain_id_gain) {
+                return Err(format!("offsets must be less than domain_id_gain {}", self.domain_id_gain));
+            }
+            match self.port_base.checked_add(offsets.into_iter().max().unwrap()) {
+                Some(port) if port <= u16::MAX as u32 => {}
+                _ => return Err(format!("port_base {} is too large", self.port_base))
+            }
+            if self.offset_d0 == self.offset_d2 {
+                return Err("offset_d0 and offset_d2 must differ".to_string());
+            }
+            // Unicast ports of different participants interleave, so d1 and d3 must not fall on the same sequence
+            let unicast_distance = self.offset_d1.abs_diff(self.offset_d3);
+            if unicast_distance % self.participant_id_gain == 0 {
+                return Err(format!("offset_d1 and offset_d3 must differ by a value not divisible by participant_id_gain {}", self.participant_id_gain));
+            }
+            return Ok(());
+        }
+
+        pub fn is_default(&self) -> bool {
+            return *self == PortParams::default();
+        }
+    }
+
+    /// Ports of the first participant of the domain with the default port parameters
+    pub fn domain_ports(domain_id: u32) -> Port {
+        return PortParams::default().ports(domain_id, 0);
     }
 
     pub fn find_node_host(node_name: String, domain_id: u32) -> Host {
@@ -104,10 +379,7 @@ pub mod network {
         }
         debug!("###############################################################");*/
 
-        return Host {
-            name: "localhost".to_string(),
-            ip: "127.0.0.1".to_string(),
-        };
+        return Host::default();
     }
 
     pub fn decode_hex(s: &str) -> Result<Vec<u8>, ParseIntError> {
@@ -125,27 +397,113 @@ pub mod network {
         s
     }
 
-    /// This function is used for discarding fastdds prefixes for topic names
+    /// What the DDS topic is used for, by the prefix of its name
+    #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+    #[serde(rename_all = "snake_case")]
+    pub enum Ros2NameKind {
+        /// `rt/` topic
+        #[default]
+        Topic,
+        /// `rq/` requests of the service
+        ServiceRequest,
+        /// `rr/` replies of the service
+        ServiceReply,
+        /// `rs/` service
+        Service,
+        /// `ra/` action, or topic and service of the action under `_action/`
+        Action,
+        /// Plain DDS topic, e.g. `ros_discovery_info`
+        Dds,
+    }
+
+    impl Ros2NameKind {
+        /// Topics of ROS2 and plain DDS ones, as opposed to services and actions
+        pub fn is_topic(&self) -> bool {
+            return *self == Ros2NameKind::Topic || *self == Ros2NameKind::Dds;
+        }
+    }
+
+    /// ROS2 name of the DDS topic
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct Ros2Name {
+        /// Fully qualified name, e.g. `/add_two_ints`. DDS topics keep their name as is
+        pub name: String,
+        pub kind: Ros2NameKind,
+        /// Action which the topic or service belongs to, e.g. `/fibonacci` of `/fibonacci/_action/send_goal`
+        pub action: Option<String>,
+    }
+
+    const ACTION_INFIX: &str = "/_action/";
+
+    /// Demangle DDS topic name of ROS2 topic, service or action. Services have their request and reply suffixes
+    /// removed, so both directions have the name of the service
+    /// # Arguments
+    ///
+    /// * `topic_name`: DDS topic name, e.g. `rq/add_two_intsRequest`
+    ///
+    /// returns: Ros2Name
+    ///
+    /// # Examples
+    ///
+    /// ```
     ///
+    /// ```
+    pub fn demangle_topic(topic_name: &str) -> Ros2Name {
+        let (kind, name) = match topic_name.split_once('/') {
+            Some(("rt", name)) => (Ros2NameKind::Topic, name),
+            Some(("rq", name)) => (Ros2NameKind::ServiceRequest, name.strip_suffix("Request").unwrap_or(name)),
+            Some(("rr", name)) => (Ros2NameKind::ServiceReply, name.strip_suffix("Reply").unwrap_or(name)),
+            Some(("rs", name)) => (Ros2NameKind::Service, name),
+            Some(("ra", name)) => (Ros2NameKind::Action, name),
+            _ => return Ros2Name { name: topic_name.to_string(), kind: Ros2NameKind::Dds, action: None }
+        };
+        let name = format!("/{}", name.trim_start_matches('/'));
+        let action = match name.split_once(ACTION_INFIX) {
+            Some((action, _)) => Some(action.to_string()),
+            None if kind == Ros2NameKind::Action => Some(name.clone()),
+            None => None
+        };
+        return Ros2Name { name, kind, action };
+    }
+
+    /// Suffixes of DDS types of services and actions, longer ones first
+    const SERVICE_TYPE_SUFFIXES: [&str; 3] = ["_Request", "_Response", "_Reply"];
+    const ACTION_TYPE_SUFFIXES: [&str; 10] = ["_SendGoal_Request", "_SendGoal_Response", "_GetResult_Request", "_GetResult_Response",
+        "_FeedbackMessage", "_Goal", "_Result", "_Feedback", "_Request", "_Response"];
+
+    /// Demangle DDS type name to the name of ROS2 interface, e.g. `std_msgs::msg::dds_::String_` to `std_msgs/msg/String`.
+    /// Request and reply types are named by their service, types of action topics and services by their action.
+    /// Types which aren't ROS2 interfaces are returned as is
     /// # Arguments
     ///
-    /// * `topic_name`:
+    /// * `type_name`: DDS type name
     ///
     /// returns: String
     ///
     /// # Examples
     ///
-    /// let topic_fastdds: String = "rt/rosout".to_string();
-    /// let ros2_name = fastdds_to_ros2(topic_fastdds);
-    /// assert!(ros2_name.eq("rosout"));
-    pub fn fastdds_to_ros2(topic_name: String) -> String {
-        if topic_name.starts_with("rr/") || topic_name.starts_with("rt/") {
-            return topic_name[3..].to_string();
+    /// ```
+    ///
+    /// ```
+    pub fn demangle_type(type_name: &str) -> String {
+        if !type_name.contains("::dds_::") {
+            return type_name.to_string();
+        }
+        let mut name = match InterfaceName::parse(type_name) {
+            Ok(name) => name,
+            Err(_) => return type_name.to_string()
+        };
+        let suffixes: &[&str] = match name.kind {
+            InterfaceKind::Message => &[],
+            InterfaceKind::Service => &SERVICE_TYPE_SUFFIXES,
+            InterfaceKind::Action => &ACTION_TYPE_SUFFIXES,
+        };
+        if let Some(interface) = suffixes.iter().find_map(|suffix| name.name.strip_suffix(suffix)) {
+            name.name = interface.to_string();
         }
-        return topic_name;
+        return name.to_string();
     }
 
-
     pub fn string_from_c<const N: usize>(str: [::std::os::raw::c_char; N]) -> String {
         return String::from_iter(str.iter().take_while(|c| **c != 0).map(|c| *c as u8 as char));
     }
@@ -173,11 +531,137 @@ pub mod network {
     }
 
     pub fn hostname_ip(ip_str: String) -> String {
-        let ip: std::net::IpAddr = ip_str.parse().unwrap();
+        let ip: IpAddr = match ip_str.parse() {
+            Ok(ip) => ip,
+            Err(_) => return "unknown".to_string()
+        };
         let hostname = match lookup_addr(&ip) {
             Ok(name) => name,
-            Err(err) => "unknown".to_string()
+            Err(_) => "unknown".to_string()
         };
         return hostname;
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+    use crate::discovery_server_impl::{FastDDSEndpoint, FastDDSEndpoint__bindgen_ty_1, FastDDSLocators, rmw_transport_SHM_TRANSPORT, rmw_transport_TCPV4_TRANSPORT, rmw_transport_UPDV6_TRANSPORT};
+    use crate::network::network::{demangle_topic, demangle_type, domain_ports, parse_locators, Locator, PortKind, PortParams, PortUsage,
+                                  Ros2NameKind, Transport};
+
+    fn endpoint(transport: u32, v6: [u8; 16], port: u32) -> FastDDSEndpoint {
+        return FastDDSEndpoint { port, transport, __bindgen_anon_1: FastDDSEndpoint__bindgen_ty_1 { endpoint_v6: v6 } };
+    }
+
+    #[test]
+    fn locators() {
+        let mut v6 = [0u8; 16];
+        v6[0] = 0xfe;
+        v6[1] = 0x80;
+        v6[15] = 0x01;
+        let mut v4 = [0u8; 16];
+        v4[0..4].copy_from_slice(&[10, 0, 0, 1]);
+        let empty = endpoint(0, [0; 16], 0);
+        let mut locators = FastDDSLocators { unicast: [empty; 4], multicast: [empty; 4], unicast_num: 3, multicast_num: 0 };
+        locators.unicast[0] = endpoint(rmw_transport_SHM_TRANSPORT, [0; 16], 7411);
+        locators.unicast[1] = endpoint(rmw_transport_UPDV6_TRANSPORT, v6, 7412);
+        locators.unicast[2] = endpoint(rmw_transport_TCPV4_TRANSPORT, v4, (1 << 16) | 5100);
+
+        let (unicast, multicast) = parse_locators(&locators);
+        let formatted: Vec<String> = unicast.iter().map(|locator| locator.to_string()).collect();
+        assert_eq!(formatted, vec!["SHM:[_]:7411", "UDPv6:[fe80::1]:7412", "TCPv4:[10.0.0.1]:1-5100"]);
+        assert!(multicast.is_empty());
+        assert_eq!(unicast[2].physical_port(), 5100);
+
+        // String form is parsed back, json uses it too
+        for locator in unicast.iter() {
+            assert_eq!(locator.to_string().parse::<Locator>().unwrap(), *locator);
+        }
+        let locator = Locator::new(Transport::UDPv4, Some("239.255.0.1".parse::<IpAddr>().unwrap()), 7400);
+        assert_eq!(serde_json::to_string(&locator).unwrap(), "\"UDPv4:[239.255.0.1]:7400\"");
+        assert!("UDPv4:[_]:7400".parse::<Locator>().is_err());
+        assert!("192.168.1.10".parse::<Locator>().is_err());
+    }
+
+    #[test]
+    fn port_domains() {
+        let params = PortParams::default();
+        assert_eq!(params.domain(7400), Some(0));
+        assert_eq!(params.domain(7911), Some(2));
+        assert_eq!(params.domain(7649), Some(0));
+        assert_eq!(params.domain(7399), None);
+        assert_eq!(params.domain(80), None);
+        assert_eq!(params.max_domain_id(), 232);
+        assert_eq!(params.domain(65650), None);
+    }
+
+    #[test]
+    fn port_mapping() {
+        let params = PortParams::default();
+        let ports = params.ports(1, 3);
+        assert_eq!((ports.discovery_mcast_port, ports.user_mcast_port), (7650, 7651));
+        assert_eq!((ports.discovery_unicast_port, ports.user_unicast_port), (7666, 7667));
+        assert_eq!(domain_ports(0).discovery_unicast_port, 7410);
+
+        let usage = |domain_id, participant_id, kind| Some(PortUsage { domain_id, participant_id, kind });
+        assert_eq!(params.resolve(7650), usage(1, None, PortKind::DiscoveryMulticast));
+        assert_eq!(params.resolve(7651), usage(1, None, PortKind::UserMulticast));
+        assert_eq!(params.resolve(7666), usage(1, Some(3), PortKind::DiscoveryUnicast));
+        assert_eq!(params.resolve(7667), usage(1, Some(3), PortKind::UserUnicast));
+        assert_eq!(params.resolve(7405), None);
+        assert_eq!(params.resolve(80), None);
+        assert_eq!(params.max_participant_id(), 119);
+        assert_eq!(params.resolve(params.ports(0, 119).user_unicast_port), usage(0, Some(119), PortKind::UserUnicast));
+
+        // Every port of every participant maps back to it
+        let custom = PortParams { port_base: 9000, domain_id_gain: 100, participant_id_gain: 3, offset_d0: 5, offset_d1: 20, offset_d2: 6, offset_d3: 22 };
+        assert!(custom.validate().is_ok());
+        for participant_id in 0..20 {
+            let ports = custom.ports(4, participant_id);
+            assert_eq!(custom.resolve(ports.discovery_unicast_port), usage(4, Some(participant_id), PortKind::DiscoveryUnicast));
+            assert_eq!(custom.resolve(ports.user_unicast_port), usage(4, Some(participant_id), PortKind::UserUnicast));
+        }
+
+        assert!(PortParams { participant_id_gain: 1, ..PortParams::default() }.validate().is_err());
+        assert!(PortParams { offset_d3: 300, ..PortParams::default() }.validate().is_err());
+        assert!(PortParams { port_base: 65530, ..PortParams::default() }.validate().is_err());
+        assert!(PortParams { port_base: u32::MAX, ..PortParams::default() }.validate().is_err());
+        assert!(PortParams::default().validate().is_ok());
+    }
+
+    #[test]
+    fn demangling() {
+        let topic = demangle_topic("rt/chatter");
+        assert_eq!((topic.name.as_str(), topic.kind, topic.action), ("/chatter", Ros2NameKind::Topic, None));
+        assert_eq!(demangle_topic("rt/robot1/cmd_vel").name, "/robot1/cmd_vel");
+        let request = demangle_topic("rq/add_two_intsRequest");
+        assert_eq!((request.name.as_str(), request.kind), ("/add_two_ints", Ros2NameKind::ServiceRequest));
+        let reply = demangle_topic("rr/add_two_intsReply");
+        assert_eq!((reply.name.as_str(), reply.kind), ("/add_two_ints", Ros2NameKind::ServiceReply));
+        assert_eq!(demangle_topic("rs/add_two_ints").kind, Ros2NameKind::Service);
+        assert_eq!(demangle_topic("ra/fibonacci").action.as_deref(), Some("/fibonacci"));
+
+        let goal = demangle_topic("rq/fibonacci/_action/send_goalRequest");
+        assert_eq!(goal.name, "/fibonacci/_action/send_goal");
+        assert_eq!(goal.kind, Ros2NameKind::ServiceRequest);
+        assert_eq!(goal.action.as_deref(), Some("/fibonacci"));
+        assert_eq!(demangle_topic("rt/ns/fibonacci/_action/feedback").action.as_deref(), Some("/ns/fibonacci"));
+
+        let info = demangle_topic("ros_discovery_info");
+        assert_eq!((info.name.as_str(), info.kind), ("ros_discovery_info", Ros2NameKind::Dds));
+
+        assert_eq!(demangle_type("std_msgs::msg::dds_::String_"), "std_msgs/msg/String");
+        assert_eq!(demangle_type("example_interfaces::srv::dds_::AddTwoInts_Request_"), "example_interfaces/srv/AddTwoInts");
+        assert_eq!(demangle_type("example_interfaces::srv::dds_::AddTwoInts_Response_"), "example_interfaces/srv/AddTwoInts");
+        assert_eq!(demangle_type("demo::srv::dds_::Trigger_Reply_"), "demo/srv/Trigger");
+        assert_eq!(demangle_type("action_tutorials_interfaces::action::dds_::Fibonacci_SendGoal_Request_"),
+                   "action_tutorials_interfaces/action/Fibonacci");
+        assert_eq!(demangle_type("action_tutorials_interfaces::action::dds_::Fibonacci_FeedbackMessage_"),
+                   "action_tutorials_interfaces/action/Fibonacci");
+        assert_eq!(demangle_type("action_msgs::msg::dds_::GoalStatusArray_"), "action_msgs/msg/GoalStatusArray");
+        // Messages named like parts keep their names
+        assert_eq!(demangle_type("demo::msg::dds_::Scan_Request_"), "demo/msg/Scan_Request");
+        assert_eq!(demangle_type("eprosima::fastdds::statistics::EntityData"), "eprosima::fastdds::statistics::EntityData");
+        assert_eq!(demangle_type("std_msgs/msg/String"), "std_msgs/msg/String");
+    }
+}
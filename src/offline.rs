@@ -0,0 +1,893 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/// Offline analysis of recorded captures. The ROS2 graph is reconstructed from discovery traffic of the capture:
+/// participants from SPDP, endpoints with their QoS from SEDP and node names from `ros_discovery_info` topic.
+/// The result is a regular `Ros2State`, so it is served by the same api as the live state.
+pub mod offline {
+    use std::collections::{BTreeMap, BTreeSet, HashMap};
+    use log::{info, warn};
+    use crate::cdr::cdr::CdrReader;
+    use crate::capture::capture::{CaptureFile, TrafficAttributor, UdpDatagram, WireActivity, FLUSH_INTERVAL};
+    use crate::fastdds_server::fastdds_server::vendor_name;
+    use crate::network::network::{demangle_topic, demangle_type, hex_str_from_uc, Locator, PortParams};
+    use crate::ros2entites::ros2entities::{Host, Ros2Context, Ros2ProcessInfo, Ros2Publisher, Ros2Qos, Ros2State, Ros2Subscriber};
+    use crate::rtps::rtps::{parse_message, parse_parameter_list, EntityId, Parameter, Submessage};
+    use crate::traffic::traffic::TrafficMonitor;
+
+    /// Builtin writers of discovery data
+    const SPDP_PARTICIPANT_WRITER: EntityId = EntityId([0x00, 0x01, 0x00, 0xc2]);
+    const SEDP_PUBLICATIONS_WRITER: EntityId = EntityId([0x00, 0x00, 0x03, 0xc2]);
+    const SEDP_SUBSCRIPTIONS_WRITER: EntityId = EntityId([0x00, 0x00, 0x04, 0xc2]);
+
+    /// Topic where rmw announces nodes of the participant and GIDs of their endpoints
+    pub const DISCOVERY_INFO_TOPIC: &str = "ros_discovery_info";
+
+    const PID_TOPIC_NAME: u16 = 0x0005;
+    const PID_TYPE_NAME: u16 = 0x0007;
+    const PID_DOMAIN_ID: u16 = 0x000f;
+    const PID_VENDORID: u16 = 0x0016;
+    const PID_RELIABILITY: u16 = 0x001a;
+    const PID_LIVELINESS: u16 = 0x001b;
+    const PID_DURABILITY: u16 = 0x001d;
+    const PID_OWNERSHIP: u16 = 0x001f;
+    const PID_DEADLINE: u16 = 0x0023;
+    const PID_PARTITION: u16 = 0x0029;
+    const PID_LIFESPAN: u16 = 0x002b;
+    const PID_USER_DATA: u16 = 0x002c;
+    const PID_UNICAST_LOCATOR: u16 = 0x002f;
+    const PID_MULTICAST_LOCATOR: u16 = 0x0030;
+    const PID_DEFAULT_UNICAST_LOCATOR: u16 = 0x0031;
+    const PID_METATRAFFIC_UNICAST_LOCATOR: u16 = 0x0032;
+    const PID_HISTORY: u16 = 0x0040;
+    const PID_DEFAULT_MULTICAST_LOCATOR: u16 = 0x0048;
+    const PID_PARTICIPANT_GUID: u16 = 0x0050;
+    const PID_ENDPOINT_GUID: u16 = 0x005a;
+    const PID_ENTITY_NAME: u16 = 0x0062;
+    const PID_KEY_HASH: u16 = 0x0070;
+    const PID_STATUS_INFO: u16 = 0x0071;
+    /// Vendor specific parameters have the highest bit set, their meaning depends on the vendor
+    const PID_VENDOR_SPECIFIC: u16 = 0x8000;
+    /// Samples larger than this aren't reassembled from fragments
+    const MAX_SAMPLE_SIZE: u32 = 16 * 1024 * 1024;
+    /// Total size of samples being reassembled. The oldest samples are dropped to fit new ones
+    const MAX_REASSEMBLY_BYTES: usize = 64 * 1024 * 1024;
+    /// Number of samples being reassembled at once
+    const MAX_FRAGMENTED_SAMPLES: usize = 1024;
+    /// Incomplete samples are dropped when their fragments don't come for this number of seconds of the capture time
+    const FRAGMENT_TIMEOUT: f64 = 10.0;
+
+    /// Duration of seconds and nanoseconds, None if it is infinite
+    fn read_duration(reader: &mut CdrReader) -> Result<Option<f64>, String> {
+        let seconds = reader.i32()?;
+        let nanoseconds = reader.u32()?;
+        if seconds == i32::MAX {
+            return Ok(None);
+        }
+        return Ok(Some(seconds as f64 + nanoseconds as f64 / 1e9));
+    }
+
+    fn read_locator(reader: &mut CdrReader) -> Result<Option<Locator>, String> {
+        let kind = reader.i32()?;
+        let port = reader.u32()?;
+        let address: [u8; 16] = reader.bytes(16)?.try_into().unwrap();
+        return Ok(Locator::from_rtps(kind, port, address));
+    }
+
+    /// GUID in the same form as in `Ros2State`: participant prefix and `prefix|entity_id` for endpoints
+    fn guid_strings(guid: &[u8; 16]) -> (String, String) {
+        let prefix: [u8; 12] = guid[0..12].try_into().unwrap();
+        let entity_id: [u8; 4] = guid[12..16].try_into().unwrap();
+        let prefix = hex_str_from_uc(prefix);
+        let endpoint = format!("{}|{}", prefix, hex_str_from_uc(entity_id));
+        return (prefix, endpoint);
+    }
+
+    /// Endpoint announced in SEDP
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct DiscoveredEndpoint {
+        /// GUID prefix of the participant
+        pub participant: String,
+        /// Entity id inside the participant
+        pub entity_id: String,
+        pub is_writer: bool,
+        /// DDS topic name, e.g. `rt/chatter`
+        pub topic_name: String,
+        pub type_name: String,
+        pub qos: Ros2Qos,
+        pub unicast_locators: Vec<Locator>,
+        pub multicast_locators: Vec<Locator>,
+        pub domain_id: Option<u32>,
+    }
+
+    /// Node announced in `ros_discovery_info` with GUIDs of its endpoints
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct NodeEntities {
+        pub namespace: String,
+        pub name: String,
+        pub readers: Vec<String>,
+        pub writers: Vec<String>,
+    }
+
+    /// Decode `rmw_dds_common/msg/ParticipantEntitiesInfo`. GID is 24 bytes up to Humble and 16 bytes since Iron,
+    /// so both sizes are tried and the one which consumes the whole sample is taken
+    /// # Arguments
+    ///
+    /// * `payload`: serialized sample with CDR encapsulation header
+    ///
+    /// returns: Result<(String, Vec<NodeEntities>), String>, participant GUID prefix and its nodes
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn parse_participant_entities(payload: &[u8]) -> Result<(String, Vec<NodeEntities>), String> {
+        if payload.len() < 4 {
+            return Err("Serialized data is too short".to_string());
+        }
+        let little_endian = match [payload[0], payload[1]] {
+            [0x00, 0x00] => false,
+            [0x00, 0x01] => true,
+            [a, b] => return Err(format!("Unsupported encapsulation {:02x}{:02x}", a, b))
+        };
+        let mut last_error = String::new();
+        for gid_size in [24, 16] {
+            let mut reader = CdrReader::new(&payload[4..], little_endian);
+            match read_participant_entities(&mut reader, gid_size) {
+                Ok(entities) if reader.remaining() < 4 => return Ok(entities),
+                Ok(_) => last_error = format!("{} bytes left after ParticipantEntitiesInfo", reader.remaining()),
+                Err(e) => last_error = e
+            }
+        }
+        return Err(last_error);
+    }
+
+    fn read_participant_entities(reader: &mut CdrReader, gid_size: usize) -> Result<(String, Vec<NodeEntities>), String> {
+        let read_gid = |reader: &mut CdrReader| -> Result<[u8; 16], String> {
+            let gid = reader.bytes(gid_size)?;
+            return Ok(gid[0..16].try_into().unwrap());
+        };
+        let read_gids = |reader: &mut CdrReader| -> Result<Vec<String>, String> {
+            let num = reader.u32()? as usize;
+            if num * gid_size > reader.remaining() {
+                return Err(format!("Invalid GID sequence length {}", num));
+            }
+            let mut gids = Vec::with_capacity(num);
+            for _ in 0..num {
+                gids.push(guid_strings(&read_gid(reader)?).1);
+            }
+            return Ok(gids);
+        };
+
+        let (participant, _) = guid_strings(&read_gid(reader)?);
+        let nodes_num = reader.u32()? as usize;
+        if nodes_num > reader.remaining() {
+            return Err(format!("Invalid node sequence length {}", nodes_num));
+        }
+        let mut nodes = Vec::with_capacity(nodes_num);
+        for _ in 0..nodes_num {
+            let namespace = reader.string()?;
+            let name = reader.string()?;
+            let readers = read_gids(reader)?;
+            let writers = read_gids(reader)?;
+            nodes.push(NodeEntities { namespace, name, readers, writers });
+        }
+        return Ok((participant, nodes));
+    }
+
+    fn parameter<'a>(parameters: &'a [Parameter], id: u16) -> Option<&'a [u8]> {
+        return parameters.iter().find(|parameter| parameter.id == id).map(|parameter| parameter.value.as_slice());
+    }
+
+    fn locators(parameters: &[Parameter], id: u16, little_endian: bool) -> Vec<Locator> {
+        return parameters.iter()
+            .filter(|parameter| parameter.id == id)
+            .filter_map(|parameter| read_locator(&mut CdrReader::new(parameter.value.as_slice(), little_endian)).ok().flatten())
+            .collect();
+    }
+
+    /// QoS of SEDP data. Missing policies have their DDS default values
+    fn parse_qos(parameters: &[Parameter], is_writer: bool, little_endian: bool) -> Result<Ros2Qos, String> {
+        let mut qos = if is_writer { Ros2Qos::default_writer() } else { Ros2Qos::default_reader() };
+        for parameter in parameters.iter() {
+            let mut reader = CdrReader::new(parameter.value.as_slice(), little_endian);
+            match parameter.id {
+                PID_RELIABILITY => qos.reliability = (if reader.u32()? == 2 { "reliable" } else { "best_effort" }).to_string(),
+                PID_DURABILITY => qos.durability = match reader.u32()? {
+                    0 => "volatile",
+                    1 => "transient_local",
+                    2 => "transient",
+                    _ => "persistent"
+                }.to_string(),
+                PID_HISTORY => {
+                    qos.history = (if reader.u32()? == 1 { "keep_all" } else { "keep_last" }).to_string();
+                    qos.depth = reader.i32()?.max(0) as u32;
+                }
+                PID_DEADLINE => qos.deadline = read_duration(&mut reader)?,
+                PID_LIFESPAN => qos.lifespan = read_duration(&mut reader)?,
+                PID_LIVELINESS => {
+                    qos.liveliness = match reader.u32()? {
+                        0 => "automatic",
+                        1 => "manual_by_participant",
+                        _ => "manual_by_topic"
+                    }.to_string();
+                    qos.liveliness_lease_duration = read_duration(&mut reader)?;
+                }
+                PID_OWNERSHIP => qos.ownership = (if reader.u32()? == 1 { "exclusive" } else { "shared" }).to_string(),
+                PID_PARTITION => {
+                    let num = reader.u32()? as usize;
+                    for _ in 0..num.min(parameter.value.len()) {
+                        qos.partitions.push(reader.string()?);
+                    }
+                }
+                _ => {}
+            }
+        }
+        return Ok(qos);
+    }
+
+    /// Sample being reassembled from DATA_FRAG submessages
+    struct FragmentedSample {
+        data: Vec<u8>,
+        fragment_size: usize,
+        received: BTreeSet<u32>,
+        /// Capture time of the latest fragment
+        last_seen: f64,
+    }
+
+    /// Fragments of DATA_FRAG submessage
+    struct FragmentRange {
+        /// Number of the first fragment, starting from 1
+        start: u32,
+        num: u16,
+        size: u16,
+        sample_size: u32,
+    }
+
+    impl FragmentedSample {
+        fn is_complete(&self) -> bool {
+            let fragments_num = (self.data.len() + self.fragment_size - 1) / self.fragment_size;
+            return self.received.len() >= fragments_num;
+        }
+    }
+
+    /// Reconstructs the graph from datagrams of the capture. Discovery data is applied in capture order,
+    /// so participants and endpoints which left before the end of the capture aren't in the result
+    #[derive(Default)]
+    pub struct GraphBuilder {
+        participants: BTreeMap<String, Ros2Context>,
+        endpoints: BTreeMap<String, DiscoveredEndpoint>,
+        /// Nodes of each participant by its GUID prefix
+        nodes: BTreeMap<String, Vec<NodeEntities>>,
+        /// The latest sample of writers which may turn out to be `ros_discovery_info` writers by later SEDP data
+        pending_samples: HashMap<String, Vec<u8>>,
+        fragments: HashMap<(String, i64), FragmentedSample>,
+        /// Total size of samples in `fragments`
+        fragments_bytes: usize,
+        attributor: TrafficAttributor,
+        /// Port mapping of the captured system, gives the domain of participants which don't announce it
+        port_params: PortParams,
+        /// Captured activity split by `FLUSH_INTERVAL` of the capture time, as live capture does
+        traffic: Vec<Vec<WireActivity>>,
+        next_flush: f64,
+        pub datagrams_num: u64,
+        pub rtps_num: u64,
+    }
+
+    impl GraphBuilder {
+        pub fn new(port_params: PortParams) -> GraphBuilder {
+            return GraphBuilder { attributor: TrafficAttributor::new(port_params.clone()), port_params, ..GraphBuilder::default() };
+        }
+
+        /// Apply discovery data of the datagram and account its traffic
+        /// # Arguments
+        ///
+        /// * `datagram`: captured UDP datagram
+        ///
+        /// returns: ()
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn observe(&mut self, datagram: &UdpDatagram) {
+            self.datagrams_num += 1;
+            if datagram.timestamp >= self.next_flush {
+                self.flush_traffic();
+                self.next_flush = datagram.timestamp + FLUSH_INTERVAL.as_secs_f64();
+            }
+            if !self.attributor.observe(datagram) {
+                return;
+            }
+            self.rtps_num += 1;
+            let message = match parse_message(datagram.payload.as_slice()) {
+                Ok(message) => message,
+                Err(_) => return
+            };
+            let port_domain = self.port_params.domain(datagram.destination.port() as u32);
+            let mut source_prefix = message.header.guid_prefix;
+            for submessage in message.submessages {
+                match submessage {
+                    Submessage::InfoSrc { guid_prefix } => source_prefix = guid_prefix,
+                    Submessage::Data { writer_id, writer_sn: _, inline_qos, payload, is_key, .. } => {
+                        self.handle_sample(&source_prefix, writer_id, inline_qos.as_slice(), payload, is_key, port_domain, datagram.timestamp);
+                    }
+                    Submessage::DataFrag { writer_id, writer_sn, fragment_start, fragments_num, fragment_size, sample_size, payload, .. } => {
+                        let writer = hex_str_from_uc(source_prefix) + "|" + hex_str_from_uc(writer_id.0).as_str();
+                        if !writer_id.is_builtin() && !self.is_discovery_info_candidate(&writer) {
+                            continue;
+                        }
+                        let fragments = FragmentRange { start: fragment_start, num: fragments_num, size: fragment_size, sample_size };
+                        if let Some(data) = self.reassemble(writer, writer_sn, fragments, payload.as_slice(), datagram.timestamp) {
+                            self.handle_sample(&source_prefix, writer_id, &[], data, false, port_domain, datagram.timestamp);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        /// Add fragments of DATA_FRAG submessage to the sample. Submessage is dropped if its fragments
+        /// don't fit into the sample
+        ///
+        /// returns: Option<Vec<u8>>, the sample if it is complete
+        fn reassemble(&mut self, writer: String, writer_sn: i64, fragments: FragmentRange, payload: &[u8], timestamp: f64) -> Option<Vec<u8>> {
+            if fragments.sample_size > MAX_SAMPLE_SIZE || fragments.size == 0 || fragments.start == 0 {
+                return None;
+            }
+            // Fragment numbers start from 1
+            let last = fragments.start.checked_add(fragments.num as u32)?.checked_sub(1)?;
+            let key = (writer, writer_sn);
+            if !self.fragments.contains_key(&key) {
+                self.evict_fragments(fragments.sample_size as usize, timestamp);
+                self.fragments_bytes += fragments.sample_size as usize;
+                self.fragments.insert(key.clone(), FragmentedSample {
+                    data: vec![0u8; fragments.sample_size as usize],
+                    fragment_size: fragments.size as usize,
+                    received: BTreeSet::new(),
+                    last_seen: timestamp,
+                });
+            }
+            let sample = self.fragments.get_mut(&key).unwrap();
+            sample.last_seen = timestamp;
+            for index in fragments.start..=last {
+                let start = (index - 1) as usize * sample.fragment_size;
+                let offset = (index - fragments.start) as usize * sample.fragment_size;
+                if start >= sample.data.len() || offset >= payload.len() {
+                    break;
+                }
+                let len = sample.fragment_size.min(sample.data.len() - start).min(payload.len() - offset);
+                sample.data[start..start + len].copy_from_slice(&payload[offset..offset + len]);
+                sample.received.insert(index);
+            }
+            if !sample.is_complete() {
+                return None;
+            }
+            let sample = self.fragments.remove(&key).unwrap();
+            self.fragments_bytes -= sample.data.len();
+            return Some(sample.data);
+        }
+
+        /// Drop samples whose fragments stopped coming, then the oldest ones until the new sample fits
+        fn evict_fragments(&mut self, sample_size: usize, timestamp: f64) {
+            let expired: Vec<(String, i64)> = self.fragments.iter()
+                .filter(|(_, sample)| sample.last_seen < timestamp - FRAGMENT_TIMEOUT)
+                .map(|(key, _)| key.clone())
+                .collect();
+            for key in expired {
+                self.remove_fragments(&key);
+            }
+            while self.fragments.len() >= MAX_FRAGMENTED_SAMPLES || self.fragments_bytes + sample_size > MAX_REASSEMBLY_BYTES {
+                let oldest = match self.fragments.iter().min_by(|a, b| a.1.last_seen.total_cmp(&b.1.last_seen)) {
+                    Some((key, _)) => key.clone(),
+                    None => break
+                };
+                self.remove_fragments(&oldest);
+            }
+        }
+
+        fn remove_fragments(&mut self, key: &(String, i64)) {
+            if let Some(sample) = self.fragments.remove(key) {
+                self.fragments_bytes -= sample.data.len();
+            }
+        }
+
+        /// Writer isn't known yet or it is known as `ros_discovery_info` writer
+        fn is_discovery_info_candidate(&self, writer: &String) -> bool {
+            return match self.endpoints.get(writer) {
+                Some(endpoint) => endpoint.topic_name == DISCOVERY_INFO_TOPIC,
+                None => true
+            };
+        }
+
+        fn handle_sample(&mut self, prefix: &[u8; 12], writer_id: EntityId, inline_qos: &[Parameter], payload: Vec<u8>, is_key: bool,
+                         port_domain: Option<u32>, timestamp: f64) {
+            // Disposed or unregistered instance, its key is in the key hash or in the serialized key
+            let status = parameter(inline_qos, PID_STATUS_INFO).and_then(|value| value.get(3).cloned()).unwrap_or(0);
+            if status & 0x03 != 0 || is_key {
+                let key = match parameter(inline_qos, PID_KEY_HASH) {
+                    Some(key) if key.len() == 16 => Some(key.try_into().unwrap()),
+                    _ => self.key_from_payload(payload.as_slice())
+                };
+                if let Some(key) = key {
+                    self.handle_dispose(writer_id, &key);
+                }
+                return;
+            }
+            let result = match writer_id {
+                SPDP_PARTICIPANT_WRITER => self.handle_participant_data(payload.as_slice(), port_domain, timestamp),
+                SEDP_PUBLICATIONS_WRITER => self.handle_endpoint_data(payload.as_slice(), true, port_domain),
+                SEDP_SUBSCRIPTIONS_WRITER => self.handle_endpoint_data(payload.as_slice(), false, port_domain),
+                writer_id if writer_id.is_builtin() => Ok(()),
+                _ => {
+                    let writer = hex_str_from_uc(*prefix) + "|" + hex_str_from_uc(writer_id.0).as_str();
+                    self.handle_user_sample(writer, payload)
+                }
+            };
+            if let Err(e) = result {
+                warn!("Unable to decode discovery data: {}", e);
+            }
+        }
+
+        /// Key of disposed discovery data may be sent as parameter list with the GUID
+        fn key_from_payload(&self, payload: &[u8]) -> Option<[u8; 16]> {
+            let (parameters, _) = parse_parameter_list(payload).ok()?;
+            let guid = parameter(parameters.as_slice(), PID_PARTICIPANT_GUID).or(parameter(parameters.as_slice(), PID_ENDPOINT_GUID))?;
+            return guid.try_into().ok();
+        }
+
+        fn handle_dispose(&mut self, writer_id: EntityId, key: &[u8; 16]) {
+            let (prefix, endpoint) = guid_strings(key);
+            match writer_id {
+                SPDP_PARTICIPANT_WRITER => {
+                    self.participants.remove(&prefix);
+                    self.endpoints.retain(|_, endpoint| endpoint.participant != prefix);
+                    self.nodes.remove(&prefix);
+                }
+                SEDP_PUBLICATIONS_WRITER | SEDP_SUBSCRIPTIONS_WRITER => {
+                    self.endpoints.remove(&endpoint);
+                }
+                _ => {}
+            }
+        }
+
+        fn handle_participant_data(&mut self, payload: &[u8], port_domain: Option<u32>, timestamp: f64) -> Result<(), String> {
+            let (parameters, little_endian) = parse_parameter_list(payload)?;
+            let parameters: Vec<Parameter> = parameters.into_iter().filter(|parameter| parameter.id & PID_VENDOR_SPECIFIC == 0).collect();
+            let guid = match parameter(parameters.as_slice(), PID_PARTICIPANT_GUID) {
+                Some(guid) if guid.len() == 16 => guid_strings(guid.try_into().unwrap()).0,
+                _ => return Err("SPDP data without participant GUID".to_string())
+            };
+            let domain_id = match parameter(parameters.as_slice(), PID_DOMAIN_ID) {
+                Some(value) => Some(CdrReader::new(value, little_endian).u32()?),
+                None => port_domain
+            };
+
+            // Default locators are used by user traffic, metatraffic ones are taken if there are no default locators
+            let mut unicast = locators(parameters.as_slice(), PID_DEFAULT_UNICAST_LOCATOR, little_endian);
+            if unicast.is_empty() {
+                unicast = locators(parameters.as_slice(), PID_METATRAFFIC_UNICAST_LOCATOR, little_endian);
+            }
+            let multicast = locators(parameters.as_slice(), PID_DEFAULT_MULTICAST_LOCATOR, little_endian);
+            let mut context = Ros2Context::new(guid.clone(), Host::from_locators(unicast, multicast), domain_id.unwrap_or(0));
+            if let Some(vendor) = parameter(parameters.as_slice(), PID_VENDORID) {
+                if vendor.len() >= 2 {
+                    context.vendor = vendor_name([vendor[0], vendor[1]]);
+                }
+            }
+            if let Some(name) = parameter(parameters.as_slice(), PID_ENTITY_NAME) {
+                context.name = CdrReader::new(name, little_endian).string()?;
+            }
+            if let Some(user_data) = parameter(parameters.as_slice(), PID_USER_DATA) {
+                let mut reader = CdrReader::new(user_data, little_endian);
+                let len = reader.u32()? as usize;
+                let user_data = String::from_utf8_lossy(reader.bytes(len)?).to_string();
+                context.process = Ros2ProcessInfo::from_user_data(user_data.as_str());
+            }
+            // Participants announce themselves periodically, the first announcement is the discovery time
+            context.discovered_at = match self.participants.get(&guid) {
+                Some(known) => known.discovered_at,
+                None => timestamp
+            };
+            self.participants.insert(guid, context);
+            return Ok(());
+        }
+
+        fn handle_endpoint_data(&mut self, payload: &[u8], is_writer: bool, port_domain: Option<u32>) -> Result<(), String> {
+            let (parameters, little_endian) = parse_parameter_list(payload)?;
+            let parameters: Vec<Parameter> = parameters.into_iter().filter(|parameter| parameter.id & PID_VENDOR_SPECIFIC == 0).collect();
+            let guid: [u8; 16] = match parameter(parameters.as_slice(), PID_ENDPOINT_GUID) {
+                Some(guid) if guid.len() == 16 => guid.try_into().unwrap(),
+                _ => return Err("SEDP data without endpoint GUID".to_string())
+            };
+            let string = |id: u16| -> Result<String, String> {
+                return match parameter(parameters.as_slice(), id) {
+                    Some(value) => CdrReader::new(value, little_endian).string(),
+                    None => Ok("".to_string())
+                };
+            };
+            let (participant, endpoint_guid) = guid_strings(&guid);
+            let endpoint = DiscoveredEndpoint {
+                participant,
+                entity_id: hex_str_from_uc::<4>(guid[12..16].try_into().unwrap()),
+                is_writer,
+                topic_name: string(PID_TOPIC_NAME)?,
+                type_name: string(PID_TYPE_NAME)?,
+                qos: parse_qos(parameters.as_slice(), is_writer, little_endian)?,
+                unicast_locators: locators(parameters.as_slice(), PID_UNICAST_LOCATOR, little_endian),
+                multicast_locators: locators(parameters.as_slice(), PID_MULTICAST_LOCATOR, little_endian),
+                domain_id: port_domain,
+            };
+            if endpoint.topic_name == DISCOVERY_INFO_TOPIC {
+                if let Some(sample) = self.pending_samples.remove(&endpoint_guid) {
+                    self.handle_discovery_info(sample.as_slice())?;
+                }
+            }
+            self.pending_samples.remove(&endpoint_guid);
+            self.endpoints.insert(endpoint_guid, endpoint);
+            return Ok(());
+        }
+
+        /// Samples of unknown writers are kept until their SEDP data shows the topic
+        fn handle_user_sample(&mut self, writer: String, payload: Vec<u8>) -> Result<(), String> {
+            return match self.endpoints.get(&writer) {
+                Some(endpoint) if endpoint.topic_name == DISCOVERY_INFO_TOPIC => self.handle_discovery_info(payload.as_slice()),
+                Some(_) => Ok(()),
+                None => {
+                    self.pending_samples.insert(writer, payload);
+                    Ok(())
+                }
+            };
+        }
+
+        fn handle_discovery_info(&mut self, payload: &[u8]) -> Result<(), String> {
+            let (participant, nodes) = parse_participant_entities(payload)?;
+            self.nodes.insert(participant, nodes);
+            return Ok(());
+        }
+
+        /// Node which holds the endpoint, by announcements of its participant
+        fn endpoint_node(&self, endpoint_guid: &String, endpoint: &DiscoveredEndpoint) -> Option<&NodeEntities> {
+            return self.nodes.get(&endpoint.participant)?.iter().find(|node| {
+                let gids = if endpoint.is_writer { &node.writers } else { &node.readers };
+                return gids.contains(endpoint_guid);
+            });
+        }
+
+        /// Move activity accumulated since the previous flush into the next batch of `traffic`
+        fn flush_traffic(&mut self) {
+            let activity = self.attributor.take();
+            if !activity.is_empty() {
+                self.traffic.push(activity);
+            }
+        }
+
+        /// Batches of captured activity in the capture order
+        pub fn traffic(&self) -> &Vec<Vec<WireActivity>> {
+            return &self.traffic;
+        }
+
+        /// Graph at the end of the capture. Captured traffic is available by `traffic`
+        /// # Arguments
+        ///
+        /// * `include_internals`: show internal nodes and topics
+        /// * `dds_topic_type`: keep DDS type names instead of ROS2 ones
+        ///
+        /// returns: Ros2State
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn build(&mut self, include_internals: bool, dds_topic_type: bool) -> Ros2State {
+            let mut state = Ros2State::new(include_internals);
+            for context in self.participants.values() {
+                state.add_context(context.clone());
+            }
+            for (guid, endpoint) in self.endpoints.iter() {
+                // Endpoints usually don't announce locators, they use the ones of their participant
+                let context = self.participants.get(&endpoint.participant);
+                let host = match context {
+                    _ if !endpoint.unicast_locators.is_empty() => Host::from_locators(endpoint.unicast_locators.clone(), endpoint.multicast_locators.clone()),
+                    Some(context) => context.host.clone(),
+                    None => Host::default()
+                };
+                let domain_id = context.map(|context| context.domain_id).or(endpoint.domain_id).unwrap_or(0);
+                let (namespace, name) = match self.endpoint_node(guid, endpoint) {
+                    Some(node) => (node.namespace.clone(), node.name.clone()),
+                    None => ("/".to_string(), "unknown".to_string())
+                };
+                let ros2_name = demangle_topic(endpoint.topic_name.as_str());
+                let mut topic_name = ros2_name.name;
+                if !topic_name.starts_with('/') {
+                    topic_name = "/".to_string() + topic_name.as_str();
+                }
+                let topic_type = if dds_topic_type { endpoint.type_name.clone() } else { demangle_type(endpoint.type_name.as_str()) };
+                if endpoint.is_writer {
+                    state.add_publisher(Ros2Publisher {
+                        topic_name,
+                        guid: endpoint.participant.clone(),
+                        node_name: name,
+                        node_namespace: namespace,
+                        topic_type: topic_type.clone(),
+                        host,
+                        domain_id,
+                        entity_id: endpoint.entity_id.clone(),
+                        qos: Some(endpoint.qos.clone()),
+                        kind: ros2_name.kind,
+                        action: ros2_name.action.clone(),
+                    });
+                } else {
+                    state.add_subscriber(Ros2Subscriber {
+                        topic_name,
+                        guid: endpoint.participant.clone(),
+                        node_name: name,
+                        node_namespace: namespace,
+                        topic_type,
+                        host,
+                        domain_id,
+                        entity_id: endpoint.entity_id.clone(),
+                        qos: Some(endpoint.qos.clone()),
+                        kind: ros2_name.kind,
+                        action: ros2_name.action,
+                    });
+                }
+            }
+            self.flush_traffic();
+            return state;
+        }
+    }
+
+    /// Reconstruct the graph from pcap or pcapng file
+    /// # Arguments
+    ///
+    /// * `path`: capture file
+    /// * `include_internals`: show internal nodes and topics
+    /// * `dds_topic_type`: keep DDS type names instead of ROS2 ones
+    /// * `port_params`: port mapping of the captured system
+    /// * `traffic`: receives the captured traffic for time windows
+    ///
+    /// returns: Result<Ros2State, String>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn analyze_file(path: &str, include_internals: bool, dds_topic_type: bool, port_params: &PortParams, traffic: &TrafficMonitor) -> Result<Ros2State, String> {
+        let mut reader = CaptureFile::open(path)?;
+        let mut builder = GraphBuilder::new(port_params.clone());
+        while let Some(datagram) = reader.next_datagram()? {
+            builder.observe(&datagram);
+        }
+        let state = builder.build(include_internals, dds_topic_type);
+        for activity in builder.traffic().iter() {
+            traffic.record(activity);
+        }
+        info!("Capture {} analyzed: {} datagrams, {} RTPS messages, {} participants, {} endpoints",
+            path, builder.datagrams_num, builder.rtps_num, state.contexts().len(), state.endpoints_num());
+        return Ok(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use crate::capture::capture::UdpDatagram;
+    use crate::capture::tests::{pcapng_file, udp_frame};
+    use crate::offline::offline::{analyze_file, parse_participant_entities, GraphBuilder};
+    use crate::network::network::PortParams;
+    use crate::rtps::rtps::{DATA, DATA_FRAG};
+    use crate::traffic::traffic::{TrafficMonitor, HISTORY_SECS};
+    use crate::rtps::tests::{header, submessage, PREFIX, WRITER};
+
+    const OTHER_PREFIX: [u8; 12] = [0x01, 0x0f, 0xcc, 0xdd, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00];
+    const INFO_WRITER: [u8; 4] = [0x00, 0x00, 0x01, 0x03];
+    const SUBSCRIBER: [u8; 4] = [0x00, 0x00, 0x14, 0x04];
+
+    /// Little endian parameter list with PL_CDR_LE encapsulation
+    fn parameters(parameters: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let mut payload = vec![0x00, 0x03, 0x00, 0x00];
+        for (id, value) in parameters {
+            let mut value = value.clone();
+            value.resize((value.len() + 3) / 4 * 4, 0);
+            payload.extend_from_slice(&id.to_le_bytes());
+            payload.extend_from_slice(&(value.len() as u16).to_le_bytes());
+            payload.extend(value);
+        }
+        payload.extend_from_slice(&[0x01, 0x00, 0x00, 0x00]);
+        return payload;
+    }
+
+    fn string(value: &str) -> Vec<u8> {
+        let mut bytes = ((value.len() + 1) as u32).to_le_bytes().to_vec();
+        bytes.extend_from_slice(value.as_bytes());
+        bytes.push(0);
+        return bytes;
+    }
+
+    fn guid(prefix: [u8; 12], entity_id: [u8; 4]) -> Vec<u8> {
+        let mut guid = prefix.to_vec();
+        guid.extend_from_slice(&entity_id);
+        return guid;
+    }
+
+    fn u32s(values: &[u32]) -> Vec<u8> {
+        return values.iter().flat_map(|value| value.to_le_bytes()).collect();
+    }
+
+    /// RTPS message with DATA submessage of the writer
+    fn message(prefix: [u8; 12], writer: [u8; 4], inline_qos: &[(u16, Vec<u8>)], payload: &[u8]) -> Vec<u8> {
+        let mut body = vec![0, 0, 16, 0, 0, 0, 0, 0];
+        body.extend_from_slice(&writer);
+        body.extend(u32s(&[0, 1]));
+        let mut flags = 0;
+        if !inline_qos.is_empty() {
+            body.extend_from_slice(&parameters(inline_qos)[4..]);
+            flags |= 0x02;
+        }
+        if !payload.is_empty() {
+            body.extend_from_slice(payload);
+            flags |= 0x04;
+        }
+        let mut message = header(prefix);
+        submessage(&mut message, DATA, flags, body.as_slice());
+        return message;
+    }
+
+    /// RTPS message with DATA_FRAG submessage of the single fragment of the writer sample
+    fn fragment_message(prefix: [u8; 12], writer: [u8; 4], sn: u32, fragment_start: u32, fragment_size: u16, sample_size: u32, payload: &[u8]) -> Vec<u8> {
+        let mut body = vec![0, 0, 28, 0, 0, 0, 0, 0];
+        body.extend_from_slice(&writer);
+        body.extend(u32s(&[0, sn, fragment_start]));
+        body.extend_from_slice(&1u16.to_le_bytes());
+        body.extend_from_slice(&fragment_size.to_le_bytes());
+        body.extend(u32s(&[sample_size]));
+        body.extend_from_slice(payload);
+        let mut message = header(prefix);
+        submessage(&mut message, DATA_FRAG, 0, body.as_slice());
+        return message;
+    }
+
+    /// ParticipantEntitiesInfo with 16 byte GIDs
+    fn entities_info(prefix: [u8; 12], namespace: &str, name: &str, writers: &[[u8; 4]]) -> Vec<u8> {
+        let mut payload = vec![0x00, 0x01, 0x00, 0x00];
+        let mut body = guid(prefix, [0, 0, 1, 0xc1]);
+        body.extend(u32s(&[1]));
+        body.extend(string(namespace));
+        body.resize((body.len() + 3) / 4 * 4, 0);
+        body.extend(string(name));
+        body.resize((body.len() + 3) / 4 * 4, 0);
+        body.extend(u32s(&[0, writers.len() as u32]));
+        for writer in writers {
+            body.extend(guid(prefix, *writer));
+        }
+        payload.extend(body);
+        return payload;
+    }
+
+    #[test]
+    fn participant_entities() {
+        let payload = entities_info(PREFIX, "/robot", "talker", &[WRITER]);
+        let (participant, nodes) = parse_participant_entities(payload.as_slice()).unwrap();
+        assert_eq!(participant, "01.0f.aa.bb.39.30.00.00.01.00.00.00");
+        assert_eq!((nodes[0].namespace.as_str(), nodes[0].name.as_str()), ("/robot", "talker"));
+        assert_eq!(nodes[0].writers, vec!["01.0f.aa.bb.39.30.00.00.01.00.00.00|00.00.12.03"]);
+        assert!(nodes[0].readers.is_empty());
+        assert!(parse_participant_entities(&payload[..payload.len() - 8]).is_err());
+    }
+
+    #[test]
+    fn reassemble_fragments() {
+        let spdp = parameters(&[(0x0050, guid(PREFIX, [0, 0, 1, 0xc1])), (0x0016, vec![0x01, 0x0f])]);
+        let (half, size) = (spdp.len() / 2, spdp.len() as u32);
+        let spdp_writer = [0x00, 0x01, 0x00, 0xc2];
+        let datagram = |timestamp: f64, payload: Vec<u8>| UdpDatagram {
+            timestamp,
+            source: "192.168.1.10:7410".parse().unwrap(),
+            destination: "239.255.0.1:7400".parse().unwrap(),
+            payload,
+        };
+        let first = fragment_message(PREFIX, spdp_writer, 1, 1, half as u16, size, &spdp[..half]);
+        let second = fragment_message(PREFIX, spdp_writer, 1, 2, half as u16, size, &spdp[half..]);
+
+        // Fragment numbers which overflow are dropped
+        let mut builder = GraphBuilder::new(PortParams::default());
+        builder.observe(&datagram(100.0, fragment_message(PREFIX, spdp_writer, 1, u32::MAX, half as u16, size, &spdp[half..])));
+        builder.observe(&datagram(100.0, first.clone()));
+        assert!(builder.build(false, false).contexts().is_empty());
+        builder.observe(&datagram(101.0, second.clone()));
+        assert_eq!(builder.build(false, false).contexts().len(), 1);
+
+        // Incomplete sample is dropped when its fragments stop coming
+        let mut builder = GraphBuilder::new(PortParams::default());
+        builder.observe(&datagram(100.0, first));
+        builder.observe(&datagram(200.0, fragment_message(PREFIX, spdp_writer, 2, 1, half as u16, size, &spdp[..half])));
+        builder.observe(&datagram(200.0, second));
+        assert!(builder.build(false, false).contexts().is_empty());
+    }
+
+    #[test]
+    fn reconstruct_graph() {
+        let mut locator = u32s(&[1, 7913]);
+        locator.extend_from_slice(&[0; 12]);
+        locator.extend_from_slice(&[192, 168, 1, 10]);
+        let spdp = parameters(&[
+            (0x0050, guid(PREFIX, [0, 0, 1, 0xc1])),
+            (0x0016, vec![0x01, 0x0f]),
+            (0x0031, locator),
+            (0x002c, [u32s(&[10]), b"enclave=/;".to_vec()].concat()),
+        ]);
+        let sedp_writer = |entity_id: [u8; 4], topic: &str, topic_type: &str| parameters(&[
+            (0x005a, guid(PREFIX, entity_id)),
+            (0x0005, string(topic)),
+            (0x0007, string(topic_type)),
+            (0x001a, u32s(&[2, 0, 100_000_000])),
+            (0x001d, u32s(&[1])),
+            (0x0040, u32s(&[0, 10])),
+            (0x0023, u32s(&[0x7fff_ffff, 0xffff_ffff])),
+        ]);
+        let sedp_reader = parameters(&[
+            (0x005a, guid(OTHER_PREFIX, SUBSCRIBER)),
+            (0x0005, string("rt/chatter")),
+            (0x0007, string("std_msgs::msg::dds_::String_")),
+        ]);
+
+        let spdp_writer = [0x00, 0x01, 0x00, 0xc2];
+        let publications_writer = [0x00, 0x00, 0x03, 0xc2];
+        let subscriptions_writer = [0x00, 0x00, 0x04, 0xc2];
+        let multicast: SocketAddr = "239.255.0.1:7900".parse().unwrap();
+        let robot: SocketAddr = "192.168.1.10:7910".parse().unwrap();
+        let other: SocketAddr = "192.168.1.20:7912".parse().unwrap();
+        let dispose = [(0x0070, guid(OTHER_PREFIX, SUBSCRIBER)), (0x0071, vec![0, 0, 0, 3])];
+        let messages = vec![
+            (robot, multicast, message(PREFIX, spdp_writer, &[], spdp.as_slice())),
+            // Node announcement comes before SEDP data of its writer
+            (robot, other, message(PREFIX, INFO_WRITER, &[], entities_info(PREFIX, "/robot", "talker", &[WRITER]).as_slice())),
+            (robot, other, message(PREFIX, publications_writer, &[], sedp_writer(WRITER, "rt/chatter", "std_msgs::msg::dds_::String_").as_slice())),
+            (robot, other, message(PREFIX, publications_writer, &[], sedp_writer(INFO_WRITER, "ros_discovery_info", "rmw_dds_common::msg::dds_::ParticipantEntitiesInfo_").as_slice())),
+            (other, robot, message(OTHER_PREFIX, subscriptions_writer, &[], sedp_reader.as_slice())),
+        ];
+        let mut frames: Vec<(f64, Vec<u8>)> = messages.iter().enumerate()
+            .map(|(pos, (source, destination, message))| (100.0 + pos as f64, udp_frame(*source, *destination, message.as_slice())))
+            .collect();
+
+        let path = std::env::temp_dir().join(format!("ros2monitor_offline_{}.pcapng", std::process::id()));
+        std::fs::write(&path, pcapng_file(frames.as_slice())).unwrap();
+        let traffic = TrafficMonitor::new(HISTORY_SECS);
+        let state = analyze_file(path.to_str().unwrap(), false, false, &PortParams::default(), &traffic).unwrap();
+        assert_eq!(state.contexts().len(), 1);
+        let context = &state.contexts()[0];
+        assert_eq!((context.domain_id, context.vendor.as_str(), context.discovered_at), (2, "eProsima Fast DDS", 100.0));
+        assert_eq!(context.host.ip, "192.168.1.10");
+        assert_eq!(context.process.enclave, "/");
+
+        let node = state.node(2, "/robot/talker").unwrap();
+        let qos = node.publishers[0].qos.clone().unwrap();
+        assert_eq!((qos.reliability.as_str(), qos.durability.as_str(), qos.depth, qos.deadline), ("reliable", "transient_local", 10, None));
+        let topic = state.topic(2, "/chatter", "std_msgs/msg/String").unwrap();
+        assert_eq!((topic.publishers_num(), topic.subscribers_num()), (1, 1));
+        // Subscriber without SPDP data gets domain by the port and unknown node
+        let subscriber = &state.node(2, "/unknown").unwrap().subscribers[0];
+        assert_eq!(subscriber.qos.clone().unwrap().reliability, "best_effort");
+        assert!(!traffic.totals().is_empty());
+        // Windows are in the capture time, datagrams are a second apart
+        let report = traffic.report(&state, 2.0, 10, false);
+        assert_eq!(report.end, 104.0);
+        assert_eq!(report.top_talkers.iter().map(|talker| talker.messages).sum::<u64>(), 2);
+        assert_eq!(traffic.report(&state, 60.0, 10, false).top_talkers.len(), 2);
+
+        // Disposed subscriber is gone
+        frames.push((110.0, udp_frame(other, robot, message(OTHER_PREFIX, subscriptions_writer, &dispose, &[]).as_slice())));
+        std::fs::write(&path, pcapng_file(frames.as_slice())).unwrap();
+        let state = analyze_file(path.to_str().unwrap(), false, false, &PortParams::default(), &TrafficMonitor::new(HISTORY_SECS)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(state.topic(2, "/chatter", "std_msgs/msg/String").unwrap().subscribers_num(), 0);
+    }
+}
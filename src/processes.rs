@@ -0,0 +1,194 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/// Attribution of discovered participants to local processes. FastDDS puts the PID of the process into
+/// its GUID prefix, so the process of a local participant is found in `/proc` and its executable is mapped to
+/// the ROS2 package, which installs it.
+pub mod processes {
+    use std::net::IpAddr;
+    use std::path::Path;
+    use dns_lookup::{get_hostname, lookup_host};
+    use sysinfo::{Pid, PidExt, ProcessExt, System, SystemExt};
+    use crate::network::network::Transport;
+    use crate::ros2entites::ros2entities::{Ros2Context, Ros2Package};
+
+    /// PID of the FastDDS participant. Bytes 4..8 of eProsima GUID prefix hold the PID in little endian,
+    /// other vendors use their own layouts
+    /// # Arguments
+    ///
+    /// * `guid`: GUID prefix, e.g. `01.0f.aa.bb.39.30.00.00.01.00.00.00`
+    ///
+    /// returns: Option<u32>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn pid_from_guid_prefix(guid: &str) -> Option<u32> {
+        let octets: Vec<u8> = guid.split('.').filter_map(|octet| u8::from_str_radix(octet, 16).ok()).collect();
+        if octets.len() != 12 || octets[0..2] != [0x01, 0x0f] {
+            return None;
+        }
+        let pid = u32::from_le_bytes([octets[4], octets[5], octets[6], octets[7]]);
+        return if pid == 0 { None } else { Some(pid) };
+    }
+
+    /// Split content of `/proc/<pid>/cmdline` into arguments
+    pub fn parse_cmdline(raw: &[u8]) -> Vec<String> {
+        return raw.split(|byte| *byte == 0)
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| String::from_utf8_lossy(arg).to_string())
+            .collect();
+    }
+
+    pub fn read_cmdline(pid: u32) -> Option<Vec<String>> {
+        return std::fs::read(format!("/proc/{}/cmdline", pid)).ok().map(|raw| parse_cmdline(raw.as_slice()));
+    }
+
+    /// Package and executable of the path in ROS2 install layout `<prefix>/lib/<package>/<executable>`
+    pub fn ros_install_path(path: &str) -> Option<(String, String)> {
+        let components: Vec<&str> = path.split('/').filter(|component| !component.is_empty()).collect();
+        if components.len() < 3 || components[components.len() - 3] != "lib" {
+            return None;
+        }
+        return Some((components[components.len() - 2].to_string(), components[components.len() - 1].to_string()));
+    }
+
+    /// Find package and executable of the process. Executables of the explored packages are checked first,
+    /// then the install layout of the path is used. Interpreted nodes (e.g. python) are found by the script in cmdline
+    /// # Arguments
+    ///
+    /// * `exe`: path of the process executable
+    /// * `cmdline`: process arguments
+    /// * `packages`: explored packages
+    ///
+    /// returns: Option<(String, String)>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn attribute(exe: &str, cmdline: &Vec<String>, packages: &Vec<Ros2Package>) -> Option<(String, String)> {
+        let candidates: Vec<&str> = std::iter::once(exe)
+            .chain(cmdline.iter().map(|arg| arg.as_str()))
+            .filter(|arg| !arg.is_empty() && !arg.starts_with('-'))
+            .collect();
+
+        for candidate in candidates.iter() {
+            let found = packages.iter()
+                .flat_map(|package| package.executables.iter())
+                .find(|executable| executable.path == *candidate || same_file(executable.path.as_str(), candidate));
+            if let Some(executable) = found {
+                return Some((executable.package_name.clone(), executable.name.clone()));
+            }
+        }
+        return candidates.iter().find_map(|candidate| ros_install_path(candidate));
+    }
+
+    fn same_file(path: &str, other: &str) -> bool {
+        return match (Path::new(path).canonicalize(), Path::new(other).canonicalize()) {
+            (Ok(path), Ok(other)) => path == other,
+            _ => false
+        };
+    }
+
+    /// Addresses of the local host. Participants announcing them run on this host
+    fn is_local_address(ip: &str) -> bool {
+        if ip == "SHM" {
+            return true;
+        }
+        let address: IpAddr = match ip.parse() {
+            Ok(address) => address,
+            Err(_) => return false
+        };
+        if address.is_loopback() {
+            return true;
+        }
+        return match get_hostname().and_then(|hostname| lookup_host(hostname.as_str())) {
+            Ok(addresses) => addresses.contains(&address),
+            Err(_) => false
+        };
+    }
+
+    /// Fill process info of the local participant: PID, process name, cmdline, package and executable.
+    /// The participant is left as is if it runs on another host or its process isn't found
+    /// # Arguments
+    ///
+    /// * `context`: discovered participant
+    /// * `packages`: explored packages
+    ///
+    /// returns: bool, true if the process is found
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn attach_local_process(context: &mut Ros2Context, packages: &Vec<Ros2Package>) -> bool {
+        let is_local = is_local_address(context.host.ip.as_str()) || context.host.unicast_locators.iter().any(|locator| match locator.address {
+            Some(address) => is_local_address(address.to_string().as_str()),
+            None => locator.transport == Transport::SHM
+        });
+        let pid = match pid_from_guid_prefix(context.guid.as_str()) {
+            Some(pid) if is_local => pid,
+            _ => return false
+        };
+
+        let mut system = System::new();
+        if !system.refresh_process(Pid::from_u32(pid)) {
+            return false;
+        }
+        let process = match system.process(Pid::from_u32(pid)) {
+            Some(process) => process,
+            None => return false
+        };
+
+        let exe = process.exe().to_string_lossy().to_string();
+        context.process.pid = Some(pid);
+        context.process.process_name = process.name().to_string();
+        context.process.cmdline = read_cmdline(pid).unwrap_or_else(|| process.cmd().to_vec());
+        if let Some((package_name, executable)) = attribute(exe.as_str(), &context.process.cmdline, packages) {
+            context.process.package_name = package_name;
+            context.process.executable = executable;
+        }
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::processes::processes::{attribute, parse_cmdline, pid_from_guid_prefix, ros_install_path};
+    use crate::ros2entites::ros2entities::{Ros2Executable, Ros2Package};
+
+    #[test]
+    fn pid_from_guid() {
+        assert_eq!(pid_from_guid_prefix("01.0f.aa.bb.39.30.00.00.01.00.00.00"), Some(12345));
+        // Not eProsima prefix
+        assert_eq!(pid_from_guid_prefix("01.10.aa.bb.39.30.00.00.01.00.00.00"), None);
+        assert_eq!(pid_from_guid_prefix("01.0f.aa"), None);
+    }
+
+    #[test]
+    fn attribution() {
+        assert_eq!(parse_cmdline(b"/usr/bin/python3\0/opt/ros/humble/lib/demo_nodes_py/talker\0"),
+                   vec!["/usr/bin/python3".to_string(), "/opt/ros/humble/lib/demo_nodes_py/talker".to_string()]);
+        assert_eq!(ros_install_path("/opt/ros/humble/lib/demo_nodes_cpp/talker"), Some(("demo_nodes_cpp".to_string(), "talker".to_string())));
+        assert_eq!(ros_install_path("/usr/bin/python3"), None);
+
+        let packages = vec![Ros2Package {
+            name: "my_robot".to_string(),
+            path: "/ws/install/my_robot".to_string(),
+            executables: vec![Ros2Executable { name: "driver".to_string(), package_name: "my_robot".to_string(), path: "/ws/build/driver".to_string() }],
+        }];
+        assert_eq!(attribute("/ws/build/driver", &vec![], &packages), Some(("my_robot".to_string(), "driver".to_string())));
+        // Python node is found by its script
+        let cmdline = vec!["/usr/bin/python3".to_string(), "/opt/ros/humble/lib/demo_nodes_py/listener".to_string(), "--ros-args".to_string()];
+        assert_eq!(attribute("/usr/bin/python3.10", &cmdline, &packages), Some(("demo_nodes_py".to_string(), "listener".to_string())));
+        assert_eq!(attribute("/usr/bin/bash", &vec![], &packages), None);
+    }
+}
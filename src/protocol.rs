@@ -7,6 +7,7 @@
 use std::collections::HashMap;
 use log::debug;
 use serde_json::{Map, Value};
+use crate::config::config::SETTING_NAMES;
 
 pub struct JsonProtocol {
     // List of all possible commands
@@ -24,10 +25,39 @@ impl JsonProtocol {
         let mut arguments: HashMap<String, Vec<String>> = HashMap::new();
 
         commands.push("state".to_string());
-        arguments.insert("state".to_string(), Vec::new());
+        let state_args = ["domain_id".to_string()].to_vec();
+        arguments.insert("state".to_string(), state_args);
+
+        commands.push("nodes".to_string());
+        let nodes_args = ["namespace".to_string(), "domain_id".to_string()].to_vec();
+        arguments.insert("nodes".to_string(), nodes_args);
+
+        commands.push("node_resources".to_string());
+        let node_resources_args = ["node_name".to_string(), "namespace".to_string(), "domain_id".to_string(), "minutes".to_string()].to_vec();
+        arguments.insert("node_resources".to_string(), node_resources_args);
+
+        commands.push("hosts".to_string());
+        let hosts_args = ["domain_id".to_string()].to_vec();
+        arguments.insert("hosts".to_string(), hosts_args);
+
+        commands.push("ports".to_string());
+        let ports_args = ["domain_id".to_string(), "participant_id".to_string(), "port".to_string()].to_vec();
+        arguments.insert("ports".to_string(), ports_args);
+
+        commands.push("traffic".to_string());
+        let traffic_args = ["window".to_string(), "limit".to_string(), "domain_id".to_string()].to_vec();
+        arguments.insert("traffic".to_string(), traffic_args);
+
+        commands.push("dds_statistics".to_string());
+        let dds_statistics_args = ["domain_id".to_string(), "topic_name".to_string()].to_vec();
+        arguments.insert("dds_statistics".to_string(), dds_statistics_args);
+
+        commands.push("type_info".to_string());
+        let type_info_args = ["type_name".to_string(), "topic_name".to_string(), "domain_id".to_string()].to_vec();
+        arguments.insert("type_info".to_string(), type_info_args);
 
         commands.push("shutdown".to_string());
-        let kill_node_args = ["node_name".to_string()].to_vec();
+        let kill_node_args = ["node_name".to_string(), "namespace".to_string(), "domain_id".to_string()].to_vec();
         arguments.insert("shutdown".to_string(), kill_node_args);
 
         commands.push("rename_topic".to_string());
@@ -35,17 +65,32 @@ impl JsonProtocol {
         arguments.insert("rename_topic".to_string(), rename_topic_args);
 
         commands.push("launch".to_string());
-        let start_node_args = ["node_name".to_string()].to_vec();
+        let start_node_args = ["package_name".to_string(), "executable".to_string(), "node_name".to_string(), "namespace".to_string(), "domain_id".to_string()].to_vec();
         arguments.insert("launch".to_string(), start_node_args);
 
         commands.push("cleanup".to_string());
-        let cleanup_node_args = ["node_name".to_string()].to_vec();
+        let cleanup_node_args = ["node_name".to_string(), "namespace".to_string(), "domain_id".to_string()].to_vec();
         arguments.insert("cleanup".to_string(), cleanup_node_args);
 
         commands.push("configure".to_string());
-        let configure_node_args = ["node_name".to_string()].to_vec();
+        let configure_node_args = ["node_name".to_string(), "namespace".to_string(), "domain_id".to_string()].to_vec();
         arguments.insert("configure".to_string(), configure_node_args);
 
+        commands.push("lifecycle".to_string());
+        let lifecycle_args = ["node_name".to_string(), "namespace".to_string(), "transition".to_string(), "domain_id".to_string()].to_vec();
+        arguments.insert("lifecycle".to_string(), lifecycle_args);
+
+        commands.push("audit_log".to_string());
+        let audit_log_args = ["from".to_string(), "to".to_string(), "command".to_string(), "limit".to_string()].to_vec();
+        arguments.insert("audit_log".to_string(), audit_log_args);
+
+        commands.push("get_settings".to_string());
+        arguments.insert("get_settings".to_string(), Vec::new());
+
+        commands.push("set_settings".to_string());
+        let set_settings_args = SETTING_NAMES.iter().map(|name| name.to_string()).collect();
+        arguments.insert("set_settings".to_string(), set_settings_args);
+
         return JsonProtocol {
             allowed_commands: commands,
             allowed_arguments: arguments,
@@ -54,6 +99,28 @@ impl JsonProtocol {
         };
     }
 
+    /// Create already parsed request for the specified command. Used by transports which
+    /// don't speak json framing, like http facade
+    pub fn with_command(command: &str, arguments: HashMap<String, String>) -> Result<JsonProtocol, String> {
+        let mut request = JsonProtocol::new();
+        if !request.allowed_commands.contains(&command.to_string()) {
+            let msg = format!("You must use one of the following supported commands: {:?}. Command {} is not supported", request.allowed_commands, command);
+            return Err(msg);
+        }
+
+        let allowed_arguments = request.allowed_arguments.get(command).unwrap();
+        for arg_name in arguments.keys() {
+            if !allowed_arguments.contains(arg_name) {
+                let msg = format!("Argument {} is not allowed for command {}. Allowed arguments for this command: {:?}", arg_name, command, allowed_arguments);
+                return Err(msg);
+            }
+        }
+
+        request.command = command.to_string();
+        request.arguments = arguments;
+        return Ok(request);
+    }
+
     /// Parse json formatted request string. Return nothing on success, error message - on error
     pub fn parse_request(&mut self, json_request: &str) -> Result<(), String> {
         debug!("Parsing json request: {}", json_request);
@@ -91,12 +158,16 @@ impl JsonProtocol {
             return Err(msg);
         }
 
-        for argument in request.get("arguments") {
-            if argument.as_array().is_none() || argument.as_array().unwrap().len() == 0 {
-                continue;
-            }
+        let arguments = match request.get("arguments").unwrap().as_array() {
+            Some(arguments) => arguments.clone(),
+            None => Vec::new()
+        };
 
-            let arg_obj = argument.as_array().unwrap()[0].as_object().unwrap();
+        for argument in arguments.iter() {
+            let arg_obj = match argument.as_object() {
+                Some(arg_obj) => arg_obj,
+                None => return Err("Each argument in request must be an object".to_string())
+            };
             if !arg_obj.contains_key("name") {
                 let msg = "Each argument object in request must have a name field";
                 return Err(msg.to_string());
@@ -0,0 +1,168 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/// Resource usage of local node processes. Processes are sampled periodically by discovery server,
+/// the latest sample is attached to nodes and the history is kept for `node_resources` command.
+pub mod resources {
+    use std::collections::{HashMap, VecDeque};
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use serde::{Deserialize, Serialize};
+    use sysinfo::{Pid, PidExt, ProcessExt, System, SystemExt};
+    use crate::audit::audit::now_secs;
+
+    /// Period of sampling
+    pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+    /// Samples older than this are dropped
+    pub const HISTORY_SECS: f64 = 3600.0;
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct ResourceSample {
+        /// Seconds since unix epoch
+        pub timestamp: f64,
+        /// CPU usage in percents of the single core, may exceed 100 for multithreaded processes
+        pub cpu_usage: f32,
+        /// Resident memory in bytes
+        pub memory: u64,
+        pub threads: u64,
+        pub open_fds: u64,
+        /// Seconds since the process start
+        pub uptime: u64,
+    }
+
+    /// Number of entries in the directory of `/proc/<pid>`, like `task` or `fd`. Zero if the directory isn't readable,
+    /// e.g. file descriptors of processes of other users
+    fn proc_entries(pid: u32, dir: &str) -> u64 {
+        return match std::fs::read_dir(format!("/proc/{}/{}", pid, dir)) {
+            Ok(entries) => entries.count() as u64,
+            Err(_) => 0
+        };
+    }
+
+    pub struct ResourceMonitor {
+        /// System of each monitored PID keeps previous CPU times, so usage is computed between consecutive samples.
+        /// Systems of PIDs which aren't monitored anymore are dropped
+        systems: Mutex<HashMap<u32, System>>,
+        /// Samples by (pid, process start time), since PIDs are reused by new processes
+        history: Mutex<HashMap<(u32, u64), VecDeque<ResourceSample>>>,
+        history_secs: f64,
+    }
+
+    impl ResourceMonitor {
+        pub fn new(history_secs: f64) -> ResourceMonitor {
+            return ResourceMonitor {
+                systems: Mutex::new(HashMap::new()),
+                history: Mutex::new(HashMap::new()),
+                history_secs,
+            };
+        }
+
+        /// Sample processes and store samples in history. History of processes which aren't in the list is dropped
+        /// # Arguments
+        ///
+        /// * `pids`: processes of local nodes
+        ///
+        /// returns: HashMap<u32, ResourceSample>, samples of running processes
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn sample(&self, pids: &Vec<u32>) -> HashMap<u32, ResourceSample> {
+            let mut samples = HashMap::new();
+            let mut started = HashMap::new();
+            let mut systems = self.systems.lock().unwrap();
+            systems.retain(|pid, _| pids.contains(pid));
+            for pid in pids.iter() {
+                let system = systems.entry(*pid).or_insert_with(System::new);
+                if !system.refresh_process(Pid::from_u32(*pid)) {
+                    systems.remove(pid);
+                    continue;
+                }
+                if let Some(process) = system.process(Pid::from_u32(*pid)) {
+                    started.insert(*pid, process.start_time());
+                    samples.insert(*pid, ResourceSample {
+                        timestamp: now_secs(),
+                        cpu_usage: process.cpu_usage(),
+                        memory: process.memory(),
+                        threads: proc_entries(*pid, "task"),
+                        open_fds: proc_entries(*pid, "fd"),
+                        uptime: process.run_time(),
+                    });
+                }
+            }
+
+            // History of exited processes, including the previous processes of reused PIDs, is dropped
+            self.history.lock().unwrap().retain(|(pid, start_time), _| started.get(pid) == Some(start_time));
+            for (pid, sample) in samples.iter() {
+                self.record(*pid, started[pid], sample.clone());
+            }
+            return samples;
+        }
+
+        pub(crate) fn record(&self, pid: u32, start_time: u64, sample: ResourceSample) {
+            let mut history = self.history.lock().unwrap();
+            // The process which used the PID before has exited
+            history.retain(|(known_pid, known_start_time), _| *known_pid != pid || *known_start_time == start_time);
+            let samples = history.entry((pid, start_time)).or_insert_with(VecDeque::new);
+            let oldest = sample.timestamp - self.history_secs;
+            samples.push_back(sample);
+            while samples.front().map_or(false, |sample| sample.timestamp < oldest) {
+                samples.pop_front();
+            }
+        }
+
+        /// Samples of the process currently running with the PID since the timestamp, oldest first
+        pub fn history(&self, pid: u32, since: f64) -> Vec<ResourceSample> {
+            return match self.history.lock().unwrap().iter().find(|((known_pid, _), _)| *known_pid == pid) {
+                Some((_, samples)) => samples.iter().filter(|sample| sample.timestamp >= since).cloned().collect(),
+                None => vec![]
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::resources::resources::{ResourceMonitor, ResourceSample};
+
+    fn sample_at(timestamp: f64) -> ResourceSample {
+        return ResourceSample { timestamp, cpu_usage: 1.0, memory: 1024, threads: 2, open_fds: 3, uptime: 10 };
+    }
+
+    #[test]
+    fn history() {
+        let monitor = ResourceMonitor::new(60.0);
+        monitor.record(10, 1, sample_at(100.0));
+        monitor.record(10, 1, sample_at(150.0));
+        monitor.record(10, 1, sample_at(200.0));
+        // Samples older than 60 seconds are dropped
+        assert_eq!(monitor.history(10, 0.0), vec![sample_at(150.0), sample_at(200.0)]);
+        assert_eq!(monitor.history(10, 160.0), vec![sample_at(200.0)]);
+        assert!(monitor.history(11, 0.0).is_empty());
+
+        // New process with the reused PID doesn't inherit the history
+        monitor.record(10, 2, sample_at(210.0));
+        assert_eq!(monitor.history(10, 0.0), vec![sample_at(210.0)]);
+    }
+
+    #[test]
+    fn sample_own_process() {
+        let monitor = ResourceMonitor::new(60.0);
+        let pid = std::process::id();
+        let samples = monitor.sample(&vec![pid]);
+        let sample = samples.get(&pid).unwrap();
+        assert!(sample.memory > 0);
+        assert!(sample.threads >= 1);
+        assert!(sample.open_fds >= 1);
+        assert_eq!(monitor.history(pid, 0.0).len(), 1);
+
+        // History of the process which isn't monitored anymore is dropped
+        monitor.sample(&vec![]);
+        assert!(monitor.history(pid, 0.0).is_empty());
+    }
+}
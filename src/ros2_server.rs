@@ -8,6 +8,7 @@ pub mod ros2_server {
     use std::ffi::OsStr;
     use std::io;
     use std::process::Command;
+    use std::sync::{Arc, RwLock};
     use std::time::Duration;
     use grep_matcher::Matcher;
     use grep_regex::RegexMatcher;
@@ -16,14 +17,23 @@ pub mod ros2_server {
     use tokio::runtime::Runtime;
     use tokio::task::JoinHandle;
     use tokio::time;
-    use crate::ros2entites::ros2entities::{Host, Ros2ActionClient, Ros2ActionServer, Ros2Executable, Ros2Node, Ros2NodeState, Ros2Package, Ros2Publisher, Ros2ServiceClient, Ros2ServiceServer, Ros2Subscriber, Ros2Topic, Settings};
+    use crate::network::network::Ros2NameKind;
+    use crate::ros2entites::ros2entities::{contains_node, node_fqn, split_node_fqn, Host, Ros2ActionClient, Ros2ActionServer, Ros2Executable, Ros2Node, Ros2NodeState, Ros2Package, Ros2Publisher, Ros2ServiceClient, Ros2ServiceServer, Ros2Subscriber, Ros2Topic, ROOT_NAMESPACE, Settings};
     use grep_searcher::{Searcher, SearcherBuilder, Sink, SinkContext, SinkMatch};
     use grep_searcher::sinks::UTF8;
+    use crate::processes::processes::ros_install_path;
     use log::{debug, warn};
+    use serde_json::json;
 
     #[derive(Clone)]
     pub struct Ros2DiscovererParams {
         pub domain_id: u32,
+        /// FastDDS profiles file passed to ros2 cli tools
+        pub fastdds_profiles_file: String,
+        /// Value of ROS_DISCOVERY_SERVER for ros2 cli tools
+        pub discovery_server: String,
+        /// Ros2 cli tools use default multicast discovery instead of discovery server
+        pub simple_discovery: bool,
     }
 
     #[derive(Clone)]
@@ -31,6 +41,9 @@ pub mod ros2_server {
         params: Ros2DiscovererParams,
     }
 
+    /// Discoverer shared by discovery server and api. Discovery server replaces it when settings change
+    pub type SharedRos2Discoverer = Arc<RwLock<Ros2Discoverer>>;
+
     struct StringSink<W>(W);
 
     impl<W: io::Write> Sink for StringSink<W> {
@@ -68,6 +81,30 @@ pub mod ros2_server {
             return Ros2Discoverer { params };
         }
 
+        /// Ros2 cli command with environment of the monitored domain and discovery mode
+        fn ros2_command(&self) -> Command {
+            let mut command = Command::new("ros2");
+            command.env("ROS_DOMAIN_ID", self.params.domain_id.to_string());
+            if self.params.simple_discovery {
+                command.env_remove("ROS_DISCOVERY_SERVER").env_remove("FASTRTPS_DEFAULT_PROFILES_FILE");
+            } else {
+                if self.params.discovery_server.is_empty() {
+                    command.env_remove("ROS_DISCOVERY_SERVER");
+                } else {
+                    command.env("ROS_DISCOVERY_SERVER", self.params.discovery_server.as_str());
+                }
+                command.env("FASTRTPS_DEFAULT_PROFILES_FILE", self.params.fastdds_profiles_file.as_str());
+            }
+            return command;
+        }
+
+        /// The same discoverer, but ros2 cli tools are run in the specified domain
+        pub fn for_domain(&self, domain_id: u32) -> Ros2Discoverer {
+            let mut params = self.params.clone();
+            params.domain_id = domain_id;
+            return Ros2Discoverer { params };
+        }
+
         pub fn lifecycle_state(&self, node_name: String) -> Ros2NodeState {
             /*let mut lifecycle_get_command = Command::new("ros2")
                 .arg("lifecycle")
@@ -104,28 +141,29 @@ pub mod ros2_server {
         }
 
         pub fn lifecycled_node_names(&self) -> Vec<String> {
-            let data_bytes = Command::new("ros2")
-                .arg("lifecycle")
-                .arg("nodes")
-                .output()
-                .expect("failed to execute process");
-            let lifecycle_nodes_str: String = match String::from_utf8(data_bytes.stdout) {
-                Ok(v) => v.to_string(),
-                Err(e) => panic!("Invalid UTF-8 sequence: {}", e),
+            let data_bytes = match self.ros2_command().arg("lifecycle").arg("nodes").output() {
+                Ok(output) => output,
+                Err(e) => {
+                    warn!("Unable to list lifecycle nodes: {}", e);
+                    return Vec::new();
+                }
             };
+            let lifecycle_nodes_str = String::from_utf8_lossy(&data_bytes.stdout);
             let lifecycle_nodes: Vec<String> = lifecycle_nodes_str.lines().map(|node| node.to_string()).collect();
             return lifecycle_nodes;
         }
 
+        /// Check if the node with the fully qualified or bare name is a lifecycle node
         pub fn is_node_lifecycle(&self, node_name: String) -> bool {
-            return self.lifecycled_node_names().contains(&node_name);
+            return contains_node(&self.lifecycled_node_names(), node_name.as_str());
         }
 
         ///  This function may be well applied only for lifecycle nodes.
-        ///  If node doesn't support it, `killall` command will be used without any guarantee of success
+        ///  If node doesn't support it, its process is killed by PID, or by `killall` without any guarantee of success
         /// # Arguments
         ///
-        /// * `node_name`: Name of node
+        /// * `node_name`: Fully qualified name of node
+        /// * `pid`: PID of the node process if it's known
         ///
         /// returns: String
         ///
@@ -134,31 +172,81 @@ pub mod ros2_server {
         /// ```
         ///
         /// ```
-        pub fn shutdown_node(&self, node_name: String) -> String {
+        pub fn shutdown_node(&self, node_name: String, pid: Option<u32>) -> String {
+            let (namespace, name) = split_node_fqn(node_name.as_str());
+            let node_name = node_fqn(namespace.as_str(), name.as_str());
             return if self.is_node_lifecycle(node_name.clone()) { // Perform lifecycle scenario
                 let err_msg_on_shutdown = format!("Unable to set shutdown state for node {}", node_name);
                 // Set shutdown state
-                let output = Command::new("ros2").arg("lifecycle").arg("set").arg(node_name).arg("shutdown").output().expect(err_msg_on_shutdown.as_str());
+                let output = self.ros2_command().arg("lifecycle").arg("set").arg(node_name).arg("shutdown").output().expect(err_msg_on_shutdown.as_str());
 
                 let response = if output.status.success() {
-                    r#"{"result": "failure", "msg": "#.to_string() + err_msg_on_shutdown.as_str() + r#"}"#
+                    json!({"result": "success"}).to_string()
                 } else {
-                    r#"{"result": "success"}"#.to_string()
+                    json!({"result": "failure", "msg": err_msg_on_shutdown}).to_string()
                 };
 
                 response
-            } else { // Perform 'killall' scenario
+            } else { // Perform 'kill' scenario
                 let err_msg = format!("Unable to kill node {}", node_name);
-                let output = Command::new("killall").arg(node_name.clone()).output().unwrap();
+                // Process is usually named after the node, namespace is set by remapping, so nodes of different
+                // namespaces may share the process name
+                let output = match pid {
+                    Some(pid) => Command::new("kill").arg(pid.to_string()).output(),
+                    None => Command::new("killall").arg(name).output()
+                };
+                let output = match output {
+                    Ok(output) => output,
+                    Err(e) => return json!({"result": "failure", "msg": format!("{}: {}", err_msg, e)}).to_string()
+                };
                 let response = if output.status.success() {
-                    r#"{"result": "failure", "msg": "#.to_string() + err_msg.as_str() + r#"}"#
+                    json!({"result": "success"}).to_string()
                 } else {
-                    r#"{"result": "success"}"#.to_string()
+                    json!({"result": "failure", "msg": err_msg}).to_string()
                 };
                 response
             };
         }
 
+        /// Trigger lifecycle transition for the node via `ros2 lifecycle set`.
+        /// Returns json string with the result of the transition
+        /// # Arguments
+        ///
+        /// * `node_name`: Fully qualified name of node
+        /// * `transition`: transition label, like `configure`, `activate`, `deactivate`, `cleanup` or `shutdown`
+        ///
+        /// returns: String
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn lifecycle_transition(&self, node_name: String, transition: String) -> String {
+            let allowed_transitions = ["configure", "activate", "deactivate", "cleanup", "shutdown"];
+            if !allowed_transitions.contains(&transition.as_str()) {
+                return json!({"result": "failure", "msg": format!("Unknown lifecycle transition {}. Allowed transitions: {:?}", transition, allowed_transitions)}).to_string();
+            }
+
+            let (namespace, name) = split_node_fqn(node_name.as_str());
+            let node_name = node_fqn(namespace.as_str(), name.as_str());
+            if !self.is_node_lifecycle(node_name.clone()) {
+                return json!({"result": "failure", "msg": format!("Node {} isn't a lifecycle node", node_name)}).to_string();
+            }
+
+            let output = match self.ros2_command().arg("lifecycle").arg("set").arg(node_name.clone()).arg(transition.clone()).output() {
+                Ok(output) => output,
+                Err(e) => return json!({"result": "failure", "msg": format!("Unable to run ros2 lifecycle: {}", e)}).to_string()
+            };
+
+            return if output.status.success() {
+                json!({"result": "success", "msg": ""}).to_string()
+            } else {
+                let err_msg = String::from_utf8_lossy(&output.stderr).trim().to_string();
+                json!({"result": "failure", "msg": format!("Unable to {} node {}: {}", transition, node_name, err_msg)}).to_string()
+            };
+        }
+
         pub fn run_sample_node(&self) -> String {
             let node_name = "turtle_teleop_key";
             let _output = Command::new("ros2")
@@ -170,8 +258,42 @@ pub mod ros2_server {
             return node_name.to_string();
         }
 
+        /// Check if the node with the fully qualified or bare name is running
         pub fn is_node_running(&self, node_name: String) -> bool {
-            return self.ros2_node_names().contains(&node_name);
+            return contains_node(&self.ros2_node_names(), node_name.as_str());
+        }
+
+        /// Run the executable of the package via `ros2 run`. The node is remapped to the namespace and the name if they are given
+        /// # Arguments
+        ///
+        /// * `package_name`: package of the executable
+        /// * `executable`: executable of the node
+        /// * `namespace`: namespace of the node, the default one of the executable if None
+        /// * `node_name`: name of the node, the default one of the executable if None
+        ///
+        /// returns: String
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn launch_node(&self, package_name: &str, executable: &str, namespace: Option<&str>, node_name: Option<&str>) -> String {
+            let mut command = self.ros2_command();
+            command.arg("run").arg(package_name).arg(executable);
+            if namespace.is_some() || node_name.is_some() {
+                command.arg("--ros-args");
+            }
+            if let Some(namespace) = namespace {
+                command.arg("-r").arg(format!("__ns:={}", namespace));
+            }
+            if let Some(node_name) = node_name {
+                command.arg("-r").arg(format!("__node:={}", node_name));
+            }
+            return match command.spawn() {
+                Ok(child) => json!({"result": "success", "pid": child.id()}).to_string(),
+                Err(e) => json!({"result": "failure", "msg": format!("Unable to run {} {}: {}", package_name, executable, e)}).to_string()
+            };
         }
 
         pub fn run_node<I, S>(&self, node_name: String, args: I) -> String
@@ -188,15 +310,15 @@ pub mod ros2_server {
             return node_name.to_string();
         }
 
+        /// Packages with their executables. Package of the executable and its install prefix are taken from
+        /// the install layout `<prefix>/lib/<package>/<executable>`, so only one ros2 cli call is needed
         pub fn explore_packages(&self) -> Vec<Ros2Package> {
-            let package_names = self.ros2_package_names();
             let mut packages: Vec<Ros2Package> = Vec::new();
 
-            let executables_info_bytes = Command::new("ros2")
+            let executables_info_bytes = self.ros2_command()
                 .arg("pkg")
                 .arg("executables")
                 .arg("--full-path")
-                .env("FASTRTPS_DEFAULT_PROFILES_FILE", "super_client_configuration_file.xml")
                 .output()
                 .expect("failed to execute process");
             let executables_info: Vec<String> = match String::from_utf8(executables_info_bytes.stdout) {
@@ -204,32 +326,29 @@ pub mod ros2_server {
                 Err(e) => panic!("Invalid UTF-8 sequence: {}", e),
             };
 
-            for package_name in package_names.iter() {
-                // Find executables for current package
-                let executable_info_lines: Vec<&str> = executables_info.iter().filter(|line| line.contains(package_name)).map(|line| line.as_str()).collect();
-                if executable_info_lines.is_empty() {
-                    continue;
-                }
-
-                let mut executables: Vec<Ros2Executable> = Vec::new();
-                for executable_line in executable_info_lines {
-                    let name = executable_line.split('/').collect::<Vec<&str>>().last().unwrap().to_string();
-                    let path = executable_line.to_string();
-                    executables.push(Ros2Executable { name, package_name: package_name.to_string(), path });
+            for executable_line in executables_info.iter().map(|line| line.trim()) {
+                let (package_name, name) = match ros_install_path(executable_line) {
+                    Some(attribution) => attribution,
+                    None => continue
+                };
+                let executable = Ros2Executable { name: name.clone(), package_name: package_name.clone(), path: executable_line.to_string() };
+                match packages.iter_mut().find(|package| package.name == package_name) {
+                    Some(package) => package.executables.push(executable),
+                    None => {
+                        let suffix = format!("/lib/{}/{}", package_name, name);
+                        let path = executable_line.strip_suffix(suffix.as_str()).unwrap_or("").to_string();
+                        packages.push(Ros2Package { name: package_name, path, executables: vec![executable] });
+                    }
                 }
-
-                let package = Ros2Package { name: package_name.to_string(), path: self.package_path(package_name.to_string()), executables };
-                packages.push(package);
-            };
+            }
 
             return packages;
         }
 
         pub fn ros2_topic_names(&self) -> Vec<String> {
-            let topics_bytes_str = Command::new("ros2")
+            let topics_bytes_str = self.ros2_command()
                 .arg("topic")
                 .arg("list")
-                .env("FASTRTPS_DEFAULT_PROFILES_FILE", "super_client_configuration_file.xml")
                 .output()
                 .expect("Failed to retrieve topic list from ros2");
 
@@ -243,10 +362,9 @@ pub mod ros2_server {
         }
 
         pub fn ros2_package_names(&self) -> Vec<String> {
-            let node_bytes_str = Command::new("ros2")
+            let node_bytes_str = self.ros2_command()
                 .arg("pkg")
                 .arg("list")
-                .env("FASTRTPS_DEFAULT_PROFILES_FILE", "super_client_configuration_file.xml")
                 .output()
                 .expect("failed to execute process");
 
@@ -278,30 +396,18 @@ pub mod ros2_server {
                 let type_line = info.lines().find(|line| line.contains(topic_type_pattern)).unwrap();
                 let topic_type = type_line.split(": ").collect::<Vec<&str>>()[1];
 
-                topics.push(Ros2Topic { name, node_name: node_name.to_string(), topic_type: topic_type.to_string(), subscribers_num: 0, publishers_num: 0 });
+                topics.push(Ros2Topic::new(name, node_name.to_string(), topic_type.to_string(), self.params.domain_id));
             }
 
             return topics;
         }
 
-        pub fn topic_type(&self, topic_name: String) -> Result<String, String> {
-            let info = self.topic_info(topic_name.clone());
-            let topic_type_pattern = "Topic type";
-            if !info.contains(topic_type_pattern) {
-                return Err("Unable to find topic info".to_string());
-            }
-            let type_line = info.lines().find(|line| line.contains(topic_type_pattern)).unwrap();
-            let topic_type = type_line.split(": ").collect::<Vec<&str>>()[1];
-            return Ok(topic_type.to_string());
-        }
-
         pub fn topic_info(&self, topic_name: String) -> String {
-            let data_bytes = Command::new("ros2")
+            let data_bytes = self.ros2_command()
                 .arg("topic")
                 .arg("info")
                 .arg(topic_name.clone())
                 .arg("--verbose")
-                .env("FASTRTPS_DEFAULT_PROFILES_FILE", "super_client_configuration_file.xml")
                 .output()
                 .expect(format!("Failed to obtain info for topic {}", topic_name).as_str());
             let info: String = match String::from_utf8(data_bytes.stdout) {
@@ -332,12 +438,11 @@ pub mod ros2_server {
         ///
         /// ```
         pub fn package_prefix(&self, package_name: String) -> String {
-            let data_bytes = Command::new("ros2")
+            let data_bytes = self.ros2_command()
                 .arg("pkg")
                 .arg("prefix")
                 .arg(package_name)
                 .arg("--share")
-                .env("FASTRTPS_DEFAULT_PROFILES_FILE", "super_client_configuration_file.xml")
                 .output()
                 .expect("failed to execute process");
             //String::from_utf8(node_bytes_str.stdout);
@@ -349,10 +454,9 @@ pub mod ros2_server {
         }
 
         pub fn ros2_node_names(&self) -> Vec<String> {
-            let node_bytes_str = Command::new("ros2")
+            let node_bytes_str = self.ros2_command()
                 .arg("node")
                 .arg("list")
-                .env("FASTRTPS_DEFAULT_PROFILES_FILE", "super_client_configuration_file.xml")
                 .output()
                 .expect("failed to execute process");
 
@@ -366,10 +470,9 @@ pub mod ros2_server {
         }
 
         pub fn ros2_subscriber_names(&self) -> Vec<String> {
-            let node_bytes_str = Command::new("ros2")
+            let node_bytes_str = self.ros2_command()
                 .arg("pkg")
                 .arg("list")
-                .env("FASTRTPS_DEFAULT_PROFILES_FILE", "super_client_configuration_file.xml")
                 .output()
                 .expect("failed to execute process");
 
@@ -386,12 +489,11 @@ pub mod ros2_server {
             if !topic_name.starts_with('/') {
                 topic_name = "/".to_string() + topic_name.as_str();
             }
-            let data_bytes = Command::new("ros2")
+            let data_bytes = self.ros2_command()
                 .arg("topic")
                 .arg("info")
                 .arg(topic_name.clone())
                 .arg("--verbose")
-                .env("FASTRTPS_DEFAULT_PROFILES_FILE", "super_client_configuration_file.xml")
                 .output()
                 .expect("failed to execute process");
 
@@ -409,6 +511,7 @@ pub mod ros2_server {
             };
 
             let mut node_name: String = "".to_string();
+            let mut node_namespace: String = ROOT_NAMESPACE.to_string();
             for line in data_str.lines() {
                 if line.starts_with("Node name:") {
                     let mut iter = line.split(": ");
@@ -416,13 +519,19 @@ pub mod ros2_server {
                     node_name = iter.next().unwrap().to_string();
                 }
 
+                if line.starts_with("Node namespace:") {
+                    let mut iter = line.split(": ");
+                    iter.next();
+                    node_namespace = iter.next().unwrap_or(ROOT_NAMESPACE).to_string();
+                }
+
                 if line.starts_with("GID") {
                     let mut iter = line.split(": ");
                     iter.next();
                     let gid = iter.next().unwrap();
                     let four_octets = guid[0..11].to_string();
                     if gid.starts_with(four_octets.as_str()) {
-                        return node_name;
+                        return node_fqn(node_namespace.as_str(), node_name.as_str());
                     }
                 }
             }
@@ -431,11 +540,10 @@ pub mod ros2_server {
         }
 
         pub fn ros2_executable_names(&self, package_name: String) -> Vec<String> {
-            let node_bytes_str = Command::new("ros2")
+            let node_bytes_str = self.ros2_command()
                 .arg("pkg")
                 .arg("executables")
                 .arg(package_name)
-                .env("FASTRTPS_DEFAULT_PROFILES_FILE", "super_client_configuration_file.xml")
                 .output()
                 .expect("failed to execute process");
 
@@ -449,11 +557,10 @@ pub mod ros2_server {
         }
 
         pub fn node_info(&self, node_name: String) -> String {
-            let data_bytes = Command::new("ros2")
+            let data_bytes = self.ros2_command()
                 .arg("node")
                 .arg("info")
                 .arg(node_name)
-                .env("FASTRTPS_DEFAULT_PROFILES_FILE", "super_client_configuration_file.xml")
                 .output()
                 .expect("failed to execute process");
             let info: String = match String::from_utf8(data_bytes.stdout) {
@@ -463,8 +570,10 @@ pub mod ros2_server {
             return info;
         }
 
+        /// Explore node by its fully qualified name, as `ros2 node list` prints it
         pub fn explore_node(&self, node_name: String) -> Result<Ros2Node, String> {
             let node_info: String = self.node_info(node_name.clone());
+            let (namespace, name) = split_node_fqn(node_name.as_str());
             if node_info.starts_with("Unable") {
                 return Err(node_info);
             }
@@ -488,12 +597,12 @@ pub mod ros2_server {
 
             for subscriber_info in subscribers_info {
                 let infos: Vec<String> = subscriber_info.split(':').map(|entry| entry.trim().to_string()).collect();
-                subscribers.push(Ros2Subscriber { topic_name: infos[0].clone(), topic_type: infos[0].clone(), node_name: node_name.clone(), guid: "unknown".to_string(), host: Host::default() });
+                subscribers.push(Ros2Subscriber { topic_name: infos[0].clone(), topic_type: infos[0].clone(), node_name: name.clone(), node_namespace: namespace.clone(), guid: "unknown".to_string(), host: Host::default(), domain_id: self.params.domain_id, entity_id: "".to_string(), qos: None, kind: Ros2NameKind::Topic, action: None });
             }
 
             for publisher_info in publishers_info {
                 let infos: Vec<String> = publisher_info.split(':').map(|entry| entry.trim().to_string()).collect();
-                publishers.push(Ros2Publisher { topic_name: infos[0].clone(), topic_type: infos[0].clone(), node_name: node_name.clone(), guid: "unknown".to_string(), host: Host::default() });
+                publishers.push(Ros2Publisher { topic_name: infos[0].clone(), topic_type: infos[0].clone(), node_name: name.clone(), node_namespace: namespace.clone(), guid: "unknown".to_string(), host: Host::default(), domain_id: self.params.domain_id, entity_id: "".to_string(), qos: None, kind: Ros2NameKind::Topic, action: None });
             }
 
             for service_server_info in service_servers_info {
@@ -524,11 +633,13 @@ pub mod ros2_server {
 
             //let host = self.find_node_host(node_name.clone(), self.params.domain_id);
             let host = Host::default();
-            // TODO: to know package name of each node
 
             return Ok(Ros2Node {
-                name: node_name.clone(),
-                package_name: "package".to_string(),
+                name,
+                namespace,
+                // Package is attributed by the process of the node participant
+                package_name: "".to_string(),
+                executable: "".to_string(),
                 subscribers,
                 publishers,
                 action_clients,
@@ -538,6 +649,10 @@ pub mod ros2_server {
                 host,
                 is_lifecycle,
                 state: lifecycle_state,
+                domain_id: self.params.domain_id,
+                // Ros2 cli doesn't show GUIDs, the node is linked to its participant by FastDDS discovery
+                context_guid: "".to_string(),
+                resources: None,
             });
         }
 
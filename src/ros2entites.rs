@@ -5,45 +5,229 @@
  */
 
 pub mod ros2entities {
-    use std::cmp::min;
+    use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+    use std::net::IpAddr;
     use std::string::String;
-    use serde::{Deserialize, Serialize};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use serde::ser::SerializeStruct;
+    use crate::audit::audit::now_secs;
+    use crate::capture::capture::WireActivity;
+    use crate::network::network::{Locator, PortParams, Ros2NameKind, Transport};
+    use crate::resources::resources::ResourceSample;
 
+    /// Accept both single domain id (as in old config files) and list of domain ids
+    fn one_or_many_domains<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u32>, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum OneOrMany {
+            One(u32),
+            Many(Vec<u32>),
+        }
+
+        return Ok(match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::One(domain_id) => vec![domain_id],
+            OneOrMany::Many(domain_ids) => domain_ids,
+        });
+    }
+
+    /// Discovery server the monitor connects to
+    #[derive(Deserialize, Clone, Serialize, Debug, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    pub struct RemoteServer {
+        /// GUID prefix of the remote server, 12 hex bytes separated by dots
+        pub guid_prefix: String,
+        /// Listening locators of the remote server, `ip:port`
+        pub locators: Vec<String>,
+    }
+
+    /// Daemon settings. Every field has a default value, so config file may contain only a part of them
     #[derive(Deserialize, Clone, Serialize)]
+    #[serde(default, deny_unknown_fields)]
     pub struct Settings {
-        pub domain_id: u32,
+        /// Monitored domains. Each domain has its own discovery server. The first one is used by ros2 cli tools
+        #[serde(alias = "domain_id", deserialize_with = "one_or_many_domains")]
+        pub domain_ids: Vec<u32>,
         pub include_internals: bool,
-        pub dds_topic_type: bool
+        pub dds_topic_type: bool,
+        /// `server` - monitor is a discovery server for ROS_DISCOVERY_SERVER clients,
+        /// `simple` - monitor passively observes default multicast discovery
+        pub discovery_mode: String,
+        /// Path of the unix socket for json protocol
+        pub socket_path: String,
+        /// Address of http api. Empty string disables http api
+        pub http_address: String,
+        /// Value for ROS_DISCOVERY_SERVER environment variable. Derived from `listening_locators` if empty.
+        /// Not used in simple discovery mode
+        pub discovery_server: String,
+        /// FastDDS profiles file generated on start. Contains discovery server profiles of the monitor
+        /// and the default super client profile used by ros2 cli tools
+        pub fastdds_profiles_file: String,
+        /// Role of the monitor participant in server discovery mode: `server`, `backup` or `super_client`
+        pub server_role: String,
+        /// GUID prefix of the discovery server. The third byte is incremented for each next domain
+        pub server_guid_prefix: String,
+        /// Listening locators of the discovery server, `ip:port`. The port is incremented by 2 for each next domain
+        pub listening_locators: Vec<String>,
+        /// Discovery servers the monitor connects to
+        pub remote_servers: Vec<RemoteServer>,
+        /// One of error, warn, info, debug, trace
+        pub log_level: String,
+        /// Authorization policy file. Default policy is used if file doesn't exist
+        pub policy_file: String,
+        /// Audit log file. Empty string disables audit log
+        pub audit_log_file: String,
+        /// Directory with nodegraph library
+        pub library_path: String,
+        /// Interface for passive RTPS capture, `any` for all interfaces. Empty string disables capture
+        pub capture_interface: String,
+        /// Pcap file replayed instead of live capture. Has priority over capture_interface
+        pub capture_file: String,
+        /// Pcap or pcapng file to analyze. The graph is reconstructed from the file instead of live discovery.
+        /// Empty string means live monitoring
+        pub analyze_file: String,
+        /// RTPS port mapping of the monitored system, written into FastDDS profiles if it isn't the default one
+        pub port_params: PortParams,
+        /// Subscribe to FastDDS statistics topics. Only participants with enabled statistics module publish them
+        pub dds_statistics: bool,
+        /// Request XTypes type objects of discovered endpoints to resolve types which aren't installed locally
+        pub type_lookup: bool,
+    }
+
+    impl Default for Settings {
+        fn default() -> Settings {
+            return Settings {
+                domain_ids: vec![0],
+                include_internals: false,
+                dds_topic_type: false,
+                discovery_mode: "server".to_string(),
+                socket_path: "/tmp/ros2monitor.sock".to_string(),
+                http_address: "127.0.0.1:8080".to_string(),
+                discovery_server: "".to_string(),
+                fastdds_profiles_file: "ros2monitor_profiles.xml".to_string(),
+                server_role: "backup".to_string(),
+                server_guid_prefix: "44.53.00.5f.45.50.52.4f.53.49.4d.41".to_string(),
+                listening_locators: vec!["0.0.0.0:11811".to_string()],
+                remote_servers: vec![],
+                log_level: "debug".to_string(),
+                policy_file: "ros2monitor_policy.json".to_string(),
+                audit_log_file: "ros2monitor_audit.jsonl".to_string(),
+                library_path: "./src/c/lib/nodegraph".to_string(),
+                capture_interface: "".to_string(),
+                capture_file: "".to_string(),
+                analyze_file: "".to_string(),
+                port_params: PortParams::default(),
+                dds_statistics: false,
+                type_lookup: false,
+            };
+        }
     }
 
     impl Settings {
         pub fn new() -> Settings {
-            return Settings { domain_id: 0, include_internals: false, dds_topic_type: false };
+            return Settings::default();
         }
 
-        pub fn from_json(json: &str) -> Settings {
-            let settings: Settings = serde_json::from_str(json).unwrap();
-            return settings;
+        pub fn from_json(json: &str) -> Result<Settings, String> {
+            return match serde_json::from_str(json) {
+                Ok(settings) => Ok(settings),
+                Err(e) => Err(format!("Invalid settings: {}", e))
+            };
         }
 
         pub fn to_json(&self) -> String {
-            let json = serde_json::to_string(&self).unwrap();
+            let json = serde_json::to_string_pretty(&self).unwrap();
             return json;
         }
 
-        pub fn save(&self, path: &str) {
+        pub fn save(&self, path: &str) -> Result<(), String> {
             let json = self.to_json();
-            std::fs::write(path, json).expect("Unable to write file");
+            return match std::fs::write(path, json) {
+                Ok(()) => Ok(()),
+                Err(e) => Err(format!("Unable to write settings into {}: {}", path, e))
+            };
         }
     }
 
 
-    #[derive(Deserialize, Clone)]
+    /// Check entity kind, the last byte of endpoint GUID
+    fn is_builtin_entity(endpoint_guid: &str) -> bool {
+        return match endpoint_guid.rsplit_once('.').map(|(_, kind)| u8::from_str_radix(kind, 16)) {
+            Some(Ok(kind)) => kind & 0xc0 == 0xc0,
+            _ => false
+        };
+    }
+
+    /// Location of the discovered endpoint in the graph
+    #[derive(Clone)]
+    struct EndpointEntry {
+        is_publisher: bool,
+        /// Fully qualified name of the node which holds the endpoint. None if the endpoint is hidden, e.g. internal topic
+        node_fqn: Option<String>,
+        topic_name: String,
+        topic_type: String,
+        context_guid: String,
+    }
+
+    /// Hash indices of `Ros2State`. Keys start with domain id, because the same names exist in several domains
+    #[derive(Clone, Default)]
+    struct Ros2StateIndex {
+        /// (domain id, fully qualified node name) -> position in `nodes`
+        nodes: HashMap<(u32, String), usize>,
+        /// (domain id, topic name, topic type) -> position in `topics`
+        topics: HashMap<(u32, String, String), usize>,
+        /// (domain id, topic name) -> types of topics with this name
+        topic_types: HashMap<(u32, String), BTreeSet<String>>,
+        /// (domain id, guid prefix) -> position in `contexts`
+        contexts: HashMap<(u32, String), usize>,
+        /// (domain id, endpoint guid) -> endpoint location
+        endpoints: HashMap<(u32, String), EndpointEntry>,
+        /// (domain id, guid prefix) -> endpoint guids of the participant
+        context_endpoints: HashMap<(u32, String), HashSet<String>>,
+        /// (domain id, reader guid) -> guids of writers which report history latency to the reader
+        latency_writers: HashMap<(u32, String), HashSet<String>>,
+    }
+
+    impl Ros2StateIndex {
+        /// Number of entries in all indices
+        fn len(&self) -> usize {
+            return self.nodes.len() + self.topics.len() + self.topic_types.len() + self.contexts.len() + self.endpoints.len()
+                + self.context_endpoints.len() + self.latency_writers.len();
+        }
+    }
+
+    /// GUID prefix of the endpoint guid `prefix|entity_id`
+    fn context_guid_of(endpoint_guid: &String) -> String {
+        return match endpoint_guid.split_once('|') {
+            Some((prefix, _)) => prefix.to_string(),
+            None => endpoint_guid.clone()
+        };
+    }
+
+    /// Statistics of GUIDs whose participants aren't discovered are kept up to this number of entities
+    pub const MAX_UNKNOWN_STATISTICS: usize = 1024;
+    /// Network latency is kept for up to this number of locator pairs
+    pub const MAX_NETWORK_LATENCIES: usize = 4096;
+
+    /// Graph of discovered entities. Entities are kept in vectors, so json output stays the same,
+    /// and hash indices give O(1) lookups by endpoint GUID, node name and topic name on every discovery event.
+    /// Vectors are private to keep indices valid, all changes go through methods.
+    #[derive(Clone)]
     pub struct Ros2State {
         pub packages: Vec<Ros2Package>,
-        pub nodes: Vec<Ros2Node>,
-        pub topics: Vec<Ros2Topic>,
+        nodes: Vec<Ros2Node>,
+        topics: Vec<Ros2Topic>,
+        /// Discovered DDS participants. Nodes and endpoints are linked to them by GUID prefix
+        contexts: Vec<Ros2Context>,
+        /// Machines which run the participants. Hosts are kept after their participants leave
+        hosts: Vec<Ros2Host>,
+        /// Traffic captured on the wire by GUID. Activity is kept while the monitor runs, even for unknown GUIDs
+        wire: BTreeMap<String, WireActivity>,
+        /// FastDDS statistics by (domain id, participant or endpoint GUID)
+        statistics: BTreeMap<(u32, String), Ros2DdsStatistics>,
+        /// FastDDS network latency by (domain id, source locator, destination locator)
+        network_latency: BTreeMap<(u32, Locator, Locator), Ros2NetworkLatency>,
         pub include_internals: bool,
+        index: Ros2StateIndex,
     }
 
     impl Ros2State {
@@ -52,8 +236,265 @@ pub mod ros2entities {
                 packages: Vec::new(),
                 nodes: Vec::new(),
                 topics: Vec::new(),
+                contexts: Vec::new(),
+                hosts: Vec::new(),
+                wire: BTreeMap::new(),
+                statistics: BTreeMap::new(),
+                network_latency: BTreeMap::new(),
                 include_internals: filter_internal,
+                index: Ros2StateIndex::default(),
+            };
+        }
+
+        pub fn nodes(&self) -> &Vec<Ros2Node> {
+            return &self.nodes;
+        }
+
+        pub fn topics(&self) -> &Vec<Ros2Topic> {
+            return &self.topics;
+        }
+
+        pub fn contexts(&self) -> &Vec<Ros2Context> {
+            return &self.contexts;
+        }
+
+        /// Node by its fully qualified name. Bare name is looked up in the root namespace
+        pub fn node(&self, domain_id: u32, fqn: &str) -> Option<&Ros2Node> {
+            let (namespace, name) = split_node_fqn(fqn);
+            return self.index.nodes.get(&(domain_id, node_fqn(namespace.as_str(), name.as_str()))).map(|pos| &self.nodes[*pos]);
+        }
+
+        /// Nodes of all domains matching the name. Name with namespace (e.g. `/robot1/camera` or `robot1/camera`) matches
+        /// the single node per domain, bare name without namespace matches nodes with this name in any namespace
+        /// # Arguments
+        ///
+        /// * `node_name`: fully qualified or bare node name
+        /// * `namespace`: namespace of the bare name, if known
+        ///
+        /// returns: Vec<&Ros2Node>
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn find_nodes(&self, node_name: &str, namespace: Option<&str>) -> Vec<&Ros2Node> {
+            let fqn = match namespace {
+                Some(namespace) => Some(node_fqn(namespace, node_name)),
+                None if node_name.contains('/') => {
+                    let (namespace, name) = split_node_fqn(node_name);
+                    Some(node_fqn(namespace.as_str(), name.as_str()))
+                }
+                None => None
+            };
+            return match fqn {
+                Some(fqn) => self.nodes.iter().filter(|node| node.fqn() == fqn).collect(),
+                None => self.nodes.iter().filter(|node| node.name == node_name).collect()
+            };
+        }
+
+        /// Nodes in the namespace and all nested namespaces, e.g. `/robot1` contains `/robot1/arm/controller`
+        pub fn nodes_in_namespace(&self, namespace: &str) -> Vec<&Ros2Node> {
+            return self.nodes.iter().filter(|node| is_in_namespace(node.namespace.as_str(), namespace)).collect();
+        }
+
+        pub fn topic(&self, domain_id: u32, topic_name: &str, topic_type: &str) -> Option<&Ros2Topic> {
+            return self.index.topics.get(&(domain_id, topic_name.to_string(), topic_type.to_string())).map(|pos| &self.topics[*pos]);
+        }
+
+        /// Topics with the name in the domain. Usually there is one topic, but publishers may use different types
+        pub fn topics_by_name(&self, domain_id: u32, topic_name: &str) -> Vec<&Ros2Topic> {
+            return match self.index.topic_types.get(&(domain_id, topic_name.to_string())) {
+                Some(types) => types.iter().filter_map(|topic_type| self.topic(domain_id, topic_name, topic_type)).collect(),
+                None => vec![]
+            };
+        }
+
+        /// Hosts with fully qualified names of nodes, which run there now
+        pub fn hosts(&self) -> Vec<Ros2Host> {
+            let mut hosts = self.hosts.clone();
+            for node in self.nodes.iter() {
+                let host = hosts.iter_mut().find(|host| host.contexts.contains(&node.context_guid));
+                if let Some(host) = host {
+                    host.nodes.insert(node.fqn());
+                }
+            }
+            return hosts;
+        }
+
+        /// Add captured activity to the accumulated one
+        pub fn add_wire_activity(&mut self, activity: Vec<WireActivity>) {
+            for activity in activity {
+                match self.wire.get_mut(&activity.guid) {
+                    Some(known) => known.merge(&activity),
+                    None => {
+                        self.wire.insert(activity.guid.clone(), activity);
+                    }
+                }
+            }
+        }
+
+        /// Captured activity with GUIDs resolved to discovered participants and endpoints
+        ///
+        /// returns: Vec<Ros2WireEntity>
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn wire_activity(&self) -> Vec<Ros2WireEntity> {
+            return self.wire.values().map(|activity| self.resolve_wire(activity)).collect();
+        }
+
+        /// Resolve GUID of the captured activity to the discovered participant or endpoint
+        pub fn resolve_wire(&self, activity: &WireActivity) -> Ros2WireEntity {
+            let domain_id = self.wire_domain(activity);
+            let mut entity = Ros2WireEntity {
+                activity: activity.clone(),
+                kind: WireEntityKind::Unknown,
+                domain_id,
+                node: None,
+                topic_name: None,
+                topic_type: None,
             };
+            if let Some(domain_id) = domain_id {
+                let (kind, entry) = self.resolve_guid(domain_id, &activity.guid);
+                entity.kind = kind;
+                if let Some(entry) = entry {
+                    entity.node = entry.node_fqn.clone();
+                    entity.topic_name = Some(entry.topic_name.clone());
+                    entity.topic_type = Some(entry.topic_type.clone());
+                }
+            }
+            return entity;
+        }
+
+        /// Domain of the discovered participant of the activity, or the domain of its RTPS ports if it isn't discovered
+        fn wire_domain(&self, activity: &WireActivity) -> Option<u32> {
+            let prefix = context_guid_of(&activity.guid);
+            return self.contexts.iter().find(|context| context.guid == prefix).map(|context| context.domain_id)
+                .or(activity.port_domain_id);
+        }
+
+        /// Kind of the participant or endpoint GUID of the domain with the endpoint location if it is discovered
+        fn resolve_guid(&self, domain_id: u32, guid: &String) -> (WireEntityKind, Option<&EndpointEntry>) {
+            let prefix = context_guid_of(guid);
+            if !self.index.contexts.contains_key(&(domain_id, prefix.clone())) {
+                return (WireEntityKind::Unknown, None);
+            }
+            if prefix == *guid {
+                return (WireEntityKind::Participant, None);
+            }
+            return match self.index.endpoints.get(&(domain_id, guid.clone())) {
+                Some(entry) => (if entry.is_publisher { WireEntityKind::Writer } else { WireEntityKind::Reader }, Some(entry)),
+                // Builtin entities have both upper bits of the entity kind set, they serve discovery and liveliness
+                None if is_builtin_entity(guid.as_str()) => (WireEntityKind::Builtin, None),
+                None => (WireEntityKind::Unknown, None)
+            };
+        }
+
+        /// Update statistics of the entity by the sample of FastDDS statistics topic
+        pub fn add_dds_statistics(&mut self, sample: DdsStatisticsSample) {
+            if sample.kind == DdsStatisticsKind::NetworkLatency {
+                if let (Some(src_locator), Some(dst_locator)) = (sample.src_locator, sample.dst_locator) {
+                    let key = (sample.domain_id, src_locator.clone(), dst_locator.clone());
+                    if !self.network_latency.contains_key(&key) && self.network_latency.len() >= MAX_NETWORK_LATENCIES {
+                        return;
+                    }
+                    let latency = Ros2NetworkLatency {
+                        domain_id: sample.domain_id,
+                        src_locator,
+                        dst_locator,
+                        latency: sample.value,
+                        updated_at: now_secs(),
+                    };
+                    self.network_latency.insert(key, latency);
+                }
+                return;
+            }
+
+            // Samples of GUIDs which aren't discovered are never pruned by removal, so their number is limited
+            let key = (sample.domain_id, sample.guid.clone());
+            if !self.statistics.contains_key(&key) && !self.has_context(context_guid_of(&sample.guid).as_str(), sample.domain_id) {
+                let unknown = self.statistics.keys()
+                    .filter(|(domain_id, guid)| !self.has_context(context_guid_of(guid).as_str(), *domain_id))
+                    .count();
+                if unknown >= MAX_UNKNOWN_STATISTICS {
+                    return;
+                }
+            }
+
+            let statistics = self.statistics.entry(key)
+                .or_insert_with(|| Ros2DdsStatistics::new(sample.guid.clone(), sample.domain_id));
+            statistics.updated_at = now_secs();
+            match sample.kind {
+                DdsStatisticsKind::HistoryLatency => {
+                    let reader_guid = sample.remote_guid.unwrap_or_default();
+                    statistics.history_latency.insert(reader_guid.clone(), sample.value);
+                    self.index.latency_writers.entry((sample.domain_id, reader_guid)).or_default().insert(sample.guid);
+                }
+                DdsStatisticsKind::PublicationThroughput => statistics.publication_throughput = Some(sample.value),
+                DdsStatisticsKind::SubscriptionThroughput => statistics.subscription_throughput = Some(sample.value),
+                DdsStatisticsKind::ResentData => statistics.resent_data = Some(sample.count),
+                DdsStatisticsKind::HeartbeatCount => statistics.heartbeats = Some(sample.count),
+                DdsStatisticsKind::AcknackCount => statistics.acknacks = Some(sample.count),
+                DdsStatisticsKind::RtpsLost => {
+                    let locator = match sample.dst_locator {
+                        Some(locator) => locator,
+                        None => return
+                    };
+                    // Counters are cumulative per destination, so the last sample replaces the previous one
+                    let lost = Ros2LostTraffic { locator, packets: sample.count, bytes: sample.bytes };
+                    match statistics.lost.iter_mut().find(|known| known.locator == lost.locator) {
+                        Some(known) => *known = lost,
+                        None => statistics.lost.push(lost),
+                    }
+                }
+                DdsStatisticsKind::NetworkLatency => {}
+            }
+        }
+
+        /// FastDDS statistics with GUIDs resolved to discovered participants and endpoints
+        ///
+        /// returns: Vec<Ros2DdsStatistics>
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn dds_statistics(&self) -> Vec<Ros2DdsStatistics> {
+            return self.statistics.values().map(|statistics| {
+                let mut statistics = statistics.clone();
+                let (kind, entry) = self.resolve_guid(statistics.domain_id, &statistics.guid);
+                statistics.kind = kind;
+                if let Some(entry) = entry {
+                    statistics.node = entry.node_fqn.clone();
+                    statistics.topic_name = Some(entry.topic_name.clone());
+                    statistics.topic_type = Some(entry.topic_type.clone());
+                }
+                return statistics;
+            }).collect();
+        }
+
+        /// Latency between locators reported by FastDDS statistics
+        pub fn network_latency(&self) -> Vec<Ros2NetworkLatency> {
+            return self.network_latency.values().cloned().collect();
+        }
+
+        pub fn context(&self, domain_id: u32, guid: &str) -> Option<&Ros2Context> {
+            return self.index.contexts.get(&(domain_id, guid.to_string())).map(|pos| &self.contexts[*pos]);
+        }
+
+        /// Number of endpoints known by their GUIDs, including hidden ones
+        pub fn endpoints_num(&self) -> usize {
+            return self.index.endpoints.len();
+        }
+
+        /// Number of entries in all indices, they are pruned together with entities
+        pub fn index_len(&self) -> usize {
+            return self.index.len();
         }
 
         pub fn add_package(&mut self, package_: Ros2Package) {
@@ -66,18 +507,132 @@ pub mod ros2entities {
             return self.packages.iter().any(|package| package.name == package_name);
         }
 
+        /// Check node presence in any domain. Node name is either fully qualified or bare name in any namespace
         pub fn has_node(&self, node_name: String) -> bool {
-            return self.nodes.iter().any(|node| node.name == node_name);
+            return !self.find_nodes(node_name.as_str(), None).is_empty();
         }
 
         pub fn has_publisher(&self, publisher_: Ros2Publisher) -> bool {
-            let pred = |publisher: &&Ros2Publisher| { return publisher.topic_type == publisher_.topic_type && publisher.node_name == publisher_.node_name; };
-            return !self.nodes.iter().position(|node| !node.publishers.iter().find(pred).is_none()).is_none();
+            return match self.index.endpoints.get(&(publisher_.domain_id, publisher_.endpoint_guid())) {
+                Some(entry) => entry.is_publisher && entry.node_fqn.is_some(),
+                None => false
+            };
         }
 
         pub fn has_subscriber(&self, subscriber_: Ros2Subscriber) -> bool {
-            let pred = |subscriber: &&Ros2Subscriber| { return subscriber.topic_type == subscriber_.topic_type && subscriber.node_name == subscriber_.node_name; };
-            return !self.nodes.iter().position(|node| !node.subscribers.iter().find(pred).is_none()).is_none();
+            return match self.index.endpoints.get(&(subscriber_.domain_id, subscriber_.endpoint_guid())) {
+                Some(entry) => !entry.is_publisher && entry.node_fqn.is_some(),
+                None => false
+            };
+        }
+
+        /// Copy of the state which contains only entities of the specified domain
+        /// # Arguments
+        ///
+        /// * `domain_id`: domain to keep
+        ///
+        /// returns: Ros2State
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn filter_domain(&self, domain_id: u32) -> Ros2State {
+            let mut state = Ros2State {
+                packages: self.packages.clone(),
+                nodes: self.nodes.iter().filter(|node| node.domain_id == domain_id).cloned().collect(),
+                topics: self.topics.iter().filter(|topic| topic.domain_id == domain_id).cloned().collect(),
+                contexts: self.contexts.iter().filter(|context| context.domain_id == domain_id).cloned().collect(),
+                hosts: self.hosts.iter()
+                    .filter(|host| host.domains.contains(&domain_id))
+                    .map(|host| Ros2Host {
+                        contexts: host.contexts.iter().filter(|guid| self.has_context(guid, domain_id)).cloned().collect(),
+                        domains: BTreeSet::from([domain_id]),
+                        ..host.clone()
+                    })
+                    .collect(),
+                wire: self.wire.iter()
+                    .filter(|(_, activity)| self.wire_domain(activity) == Some(domain_id))
+                    .map(|(guid, activity)| (guid.clone(), activity.clone()))
+                    .collect(),
+                statistics: self.statistics.iter()
+                    .filter(|((statistics_domain_id, _), _)| *statistics_domain_id == domain_id)
+                    .map(|(key, statistics)| (key.clone(), statistics.clone()))
+                    .collect(),
+                network_latency: self.network_latency.iter()
+                    .filter(|((latency_domain_id, _, _), _)| *latency_domain_id == domain_id)
+                    .map(|(key, latency)| (key.clone(), latency.clone()))
+                    .collect(),
+                include_internals: self.include_internals,
+                index: Ros2StateIndex::default(),
+            };
+            state.reindex();
+            return state;
+        }
+
+        /// Rebuild all indices from entities
+        fn reindex(&mut self) {
+            let mut index = Ros2StateIndex::default();
+            for (pos, context) in self.contexts.iter().enumerate() {
+                index.contexts.insert((context.domain_id, context.guid.clone()), pos);
+            }
+            for ((domain_id, writer), statistics) in self.statistics.iter() {
+                for reader_guid in statistics.history_latency.keys() {
+                    index.latency_writers.entry((*domain_id, reader_guid.clone())).or_default().insert(writer.clone());
+                }
+            }
+            for (pos, topic) in self.topics.iter().enumerate() {
+                index.topics.insert((topic.domain_id, topic.name.clone(), topic.topic_type.clone()), pos);
+                index.topic_types.entry((topic.domain_id, topic.name.clone())).or_default().insert(topic.topic_type.clone());
+                let endpoints = topic.publishers.iter().map(|guid| (true, guid)).chain(topic.subscribers.iter().map(|guid| (false, guid)));
+                for (is_publisher, guid) in endpoints {
+                    let entry = EndpointEntry {
+                        is_publisher,
+                        node_fqn: None,
+                        topic_name: topic.name.clone(),
+                        topic_type: topic.topic_type.clone(),
+                        context_guid: context_guid_of(guid),
+                    };
+                    index.context_endpoints.entry((topic.domain_id, entry.context_guid.clone())).or_default().insert(guid.clone());
+                    index.endpoints.insert((topic.domain_id, guid.clone()), entry);
+                }
+            }
+            self.index = index;
+            for pos in 0..self.nodes.len() {
+                self.index_node(pos);
+            }
+        }
+
+        /// Add node and its endpoints into indices
+        fn index_node(&mut self, pos: usize) {
+            let node = &self.nodes[pos];
+            let fqn = node.fqn();
+            self.index.nodes.insert((node.domain_id, fqn.clone()), pos);
+            let endpoints = node.publishers.iter().map(|publisher| (true, publisher.endpoint_guid(), &publisher.topic_name, &publisher.topic_type))
+                .chain(node.subscribers.iter().map(|subscriber| (false, subscriber.endpoint_guid(), &subscriber.topic_name, &subscriber.topic_type)));
+            for (is_publisher, guid, topic_name, topic_type) in endpoints {
+                let entry = EndpointEntry {
+                    is_publisher,
+                    node_fqn: Some(fqn.clone()),
+                    topic_name: topic_name.clone(),
+                    topic_type: topic_type.clone(),
+                    context_guid: context_guid_of(&guid),
+                };
+                self.index.context_endpoints.entry((node.domain_id, entry.context_guid.clone())).or_default().insert(guid.clone());
+                self.index.endpoints.insert((node.domain_id, guid), entry);
+            }
+        }
+
+        /// Sorted ids of domains which have at least one node or topic
+        pub fn domain_ids(&self) -> Vec<u32> {
+            let mut domain_ids: Vec<u32> = self.nodes.iter().map(|node| node.domain_id)
+                .chain(self.topics.iter().map(|topic| topic.domain_id))
+                .chain(self.contexts.iter().map(|context| context.domain_id))
+                .collect();
+            domain_ids.sort();
+            domain_ids.dedup();
+            return domain_ids;
         }
 
         /// Push node into store if it doesn't exists yet.
@@ -94,20 +649,22 @@ pub mod ros2entities {
         ///
         /// ```
         pub fn add_node(&mut self, node_: Ros2Node) -> (bool, &Ros2Node) {
-            let is_present = self.nodes.iter().any(|node| node.name == node_.name);
-            let pushed_node: &Ros2Node;
+            let key = (node_.domain_id, node_.fqn());
+            let is_present = self.index.nodes.contains_key(&key);
             if !is_present {
-                self.nodes.push(node_.clone());
+                self.nodes.push(node_);
+                self.index_node(self.nodes.len() - 1);
             }
-            pushed_node = self.nodes.iter().find(|&node_found| node_found.name == node_.clone().name).unwrap();
+            let pos = *self.index.nodes.get(&key).unwrap();
 
-            return (!is_present, pushed_node);
+            return (!is_present, &self.nodes[pos]);
         }
 
+        /// Add topic, endpoints of already existing topic are merged
         pub fn add_topic(&mut self, topic_: Ros2Topic) {
-            //if self.topics.iter().find(|&topic| topic.name == topic_.name) == None {
-            self.topics.push(topic_);
-            //}
+            let topic = self.topic_entry(&topic_.name, &topic_.topic_type, &topic_.node_name, topic_.domain_id);
+            topic.publishers.extend(topic_.publishers.iter().cloned());
+            topic.subscribers.extend(topic_.subscribers.iter().cloned());
         }
 
         pub fn remove_package(&mut self, package_: Ros2Package) {
@@ -115,16 +672,294 @@ pub mod ros2entities {
             self.packages.remove(index);
         }
 
+        /// Remove node with its endpoints. Topics without endpoints are removed too
         pub fn remove_node(&mut self, node_: Ros2Node) {
-            let index = self.nodes.iter().position(|node| node.name == node_.name).unwrap();
-            let node = self.nodes.get(index).unwrap();
-            // Remove topics with beholds this node
-            self.topics.retain(|topic| topic.node_name != node.name);
-            // Remove node itself
-            self.nodes.remove(index);
+            let pos = match self.index.nodes.get(&(node_.domain_id, node_.fqn())) {
+                Some(pos) => *pos,
+                None => return
+            };
+            let node = &self.nodes[pos];
+            let fqn = node.fqn();
+            // Endpoints explored by ros2 cli have no GUIDs, so only endpoints indexed for this node are removed
+            let guids: Vec<String> = node.publishers.iter().map(|publisher| publisher.endpoint_guid())
+                .chain(node.subscribers.iter().map(|subscriber| subscriber.endpoint_guid()))
+                .filter(|guid| match self.index.endpoints.get(&(node.domain_id, guid.clone())) {
+                    Some(entry) => entry.node_fqn.as_ref() == Some(&fqn),
+                    None => false
+                })
+                .collect();
+            for guid in guids {
+                self.remove_endpoint(node_.domain_id, &guid, None);
+            }
+            self.remove_node_at(pos);
+        }
+
+        /// Remove topic with all its endpoints
+        pub fn remove_topic(&mut self, topic_: Ros2Topic) {
+            let topic = match self.topic(topic_.domain_id, topic_.name.as_str(), topic_.topic_type.as_str()) {
+                Some(topic) => topic,
+                None => return
+            };
+            let guids: Vec<String> = topic.publishers.iter().chain(topic.subscribers.iter()).cloned().collect();
+            for guid in guids {
+                self.remove_endpoint(topic_.domain_id, &guid, None);
+            }
+        }
+
+        /// Vector element is replaced by the last one, so only index of the moved element is updated
+        fn remove_node_at(&mut self, pos: usize) {
+            let node = self.nodes.swap_remove(pos);
+            self.index.nodes.remove(&(node.domain_id, node.fqn()));
+            if let Some(moved) = self.nodes.get(pos) {
+                self.index.nodes.insert((moved.domain_id, moved.fqn()), pos);
+            }
+        }
+
+        fn remove_topic_at(&mut self, pos: usize) {
+            let topic = self.topics.swap_remove(pos);
+            self.index.topics.remove(&(topic.domain_id, topic.name.clone(), topic.topic_type.clone()));
+            let name_key = (topic.domain_id, topic.name);
+            if let Some(types) = self.index.topic_types.get_mut(&name_key) {
+                types.remove(&topic.topic_type);
+                if types.is_empty() {
+                    self.index.topic_types.remove(&name_key);
+                }
+            }
+            if let Some(moved) = self.topics.get(pos) {
+                self.index.topics.insert((moved.domain_id, moved.name.clone(), moved.topic_type.clone()), pos);
+            }
+        }
+
+        fn remove_context_at(&mut self, pos: usize) {
+            let context = self.contexts.swap_remove(pos);
+            self.index.contexts.remove(&(context.domain_id, context.guid));
+            if let Some(moved) = self.contexts.get(pos) {
+                self.index.contexts.insert((moved.domain_id, moved.guid.clone()), pos);
+            }
+        }
+
+        /// Remove endpoint from its node, topic and indices. Kind of endpoint is checked if specified
+        fn remove_endpoint(&mut self, domain_id: u32, guid: &String, is_publisher: Option<bool>) {
+            let key = (domain_id, guid.clone());
+            let entry = match self.index.endpoints.get(&key) {
+                Some(entry) if is_publisher.is_none() || is_publisher == Some(entry.is_publisher) => entry.clone(),
+                _ => return
+            };
+            self.index.endpoints.remove(&key);
+
+            if let Some(fqn) = entry.node_fqn.as_ref() {
+                if let Some(pos) = self.index.nodes.get(&(domain_id, fqn.clone())) {
+                    let node = &mut self.nodes[*pos];
+                    if entry.is_publisher {
+                        node.publishers.retain(|publisher| publisher.endpoint_guid() != *guid);
+                    } else {
+                        node.subscribers.retain(|subscriber| subscriber.endpoint_guid() != *guid);
+                    }
+                }
+            }
+
+            if let Some(pos) = self.index.topics.get(&(domain_id, entry.topic_name.clone(), entry.topic_type.clone())).cloned() {
+                let topic = &mut self.topics[pos];
+                if entry.is_publisher {
+                    topic.publishers.remove(guid);
+                } else {
+                    topic.subscribers.remove(guid);
+                }
+                if topic.is_empty() {
+                    self.remove_topic_at(pos);
+                }
+            }
+
+            let context_key = (domain_id, entry.context_guid);
+            if let Some(guids) = self.index.context_endpoints.get_mut(&context_key) {
+                guids.remove(guid);
+                if guids.is_empty() {
+                    self.index.context_endpoints.remove(&context_key);
+                }
+            }
+
+            // Statistics of the endpoint aren't updated anymore
+            self.remove_statistics(domain_id, guid);
+        }
+
+        /// Remove statistics of the entity and history latency reported to it by writers
+        fn remove_statistics(&mut self, domain_id: u32, guid: &String) {
+            let key = (domain_id, guid.clone());
+            if let Some(statistics) = self.statistics.remove(&key) {
+                for reader_guid in statistics.history_latency.keys() {
+                    let reader_key = (domain_id, reader_guid.clone());
+                    if let Some(writers) = self.index.latency_writers.get_mut(&reader_key) {
+                        writers.remove(guid);
+                        if writers.is_empty() {
+                            self.index.latency_writers.remove(&reader_key);
+                        }
+                    }
+                }
+            }
+            if let Some(writers) = self.index.latency_writers.remove(&key) {
+                for writer in writers {
+                    if let Some(statistics) = self.statistics.get_mut(&(domain_id, writer)) {
+                        statistics.history_latency.remove(guid);
+                    }
+                }
+            }
+        }
+
+        pub fn has_context(&self, guid: &str, domain_id: u32) -> bool {
+            return self.index.contexts.contains_key(&(domain_id, guid.to_string()));
+        }
+
+        /// Add discovered participant. Repeated discovery of the same participant replaces the previous
+        /// data, but keeps the time of the first discovery
+        /// # Arguments
+        ///
+        /// * `context_`: discovered participant
+        ///
+        /// returns: ()
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn add_context(&mut self, mut context_: Ros2Context) {
+            let key = (context_.domain_id, context_.guid.clone());
+            self.update_host(&context_);
+            match self.index.contexts.get(&key) {
+                Some(pos) => {
+                    let context = &mut self.contexts[*pos];
+                    context_.discovered_at = context.discovered_at;
+                    *context = context_;
+                }
+                None => {
+                    self.contexts.push(context_);
+                    self.index.contexts.insert(key.clone(), self.contexts.len() - 1);
+                }
+            }
+            self.attribute_nodes(key.0, key.1.as_str());
+        }
+
+        /// Add participant to the host it runs on. Host is found by participant addresses or FastDDS host id,
+        /// hosts which turn out to be the same machine are merged
+        fn update_host(&mut self, context: &Ros2Context) {
+            let mut host = Ros2Host::from_context(context);
+            let mut positions: Vec<usize> = self.hosts.iter().enumerate()
+                .filter(|(_, known)| known.is_same(&host))
+                .map(|(pos, _)| pos)
+                .collect();
+            // Descending order, so swap_remove doesn't move hosts which are still to be merged
+            positions.reverse();
+            for pos in positions {
+                let known = self.hosts.swap_remove(pos);
+                host.merge(known);
+            }
+            host.last_seen = now_secs();
+            self.hosts.push(host);
+        }
+
+        /// Set package and executable of the participant's process to its nodes
+        fn attribute_nodes(&mut self, domain_id: u32, guid: &str) {
+            let process = match self.context(domain_id, guid) {
+                Some(context) if !context.process.executable.is_empty() => context.process.clone(),
+                _ => return
+            };
+            for node in self.nodes.iter_mut().filter(|node| node.context_guid == guid && node.domain_id == domain_id) {
+                node.package_name = process.package_name.clone();
+                node.executable = process.executable.clone();
+            }
+        }
+
+        /// Remove participant with all its nodes, publishers and subscribers.
+        /// FastDDS doesn't report removal of endpoints of the dropped participant, so they are removed here
+        /// # Arguments
+        ///
+        /// * `context_`: removed participant, only guid and domain id are used
+        ///
+        /// returns: ()
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn remove_context(&mut self, context_: Ros2Context) {
+            let key = (context_.domain_id, context_.guid.clone());
+            let locators = match self.context(context_.domain_id, context_.guid.as_str()) {
+                Some(context) => context.host.unicast_locators.clone(),
+                None => vec![]
+            };
+            if let Some(guids) = self.index.context_endpoints.remove(&key) {
+                for guid in guids.iter() {
+                    self.remove_endpoint(context_.domain_id, guid, None);
+                }
+            }
+
+            // Positions in descending order, so swap_remove doesn't move nodes which are still to be removed
+            let mut positions: Vec<usize> = self.nodes.iter().enumerate()
+                .filter(|(_, node)| node.context_guid == context_.guid && node.domain_id == context_.domain_id)
+                .map(|(pos, _)| pos)
+                .collect();
+            positions.reverse();
+            for pos in positions {
+                self.remove_node_at(pos);
+            }
+
+            if let Some(pos) = self.index.contexts.get(&key).cloned() {
+                self.remove_context_at(pos);
+            }
+
+            if let Some(host) = self.hosts.iter_mut().find(|host| host.contexts.contains(&context_.guid)) {
+                host.contexts.remove(&context_.guid);
+                host.last_seen = now_secs();
+            }
+
+            // Traffic and statistics of the participant aren't updated anymore
+            self.wire.retain(|guid, activity| context_guid_of(guid) != context_.guid || activity.port_domain_id.map_or(false, |domain_id| domain_id != context_.domain_id));
+            let guids: BTreeSet<String> = self.statistics.keys().chain(self.index.latency_writers.keys())
+                .filter(|(domain_id, guid)| *domain_id == context_.domain_id && context_guid_of(guid) == context_.guid)
+                .map(|(_, guid)| guid.clone())
+                .collect();
+            for guid in guids.iter() {
+                self.remove_statistics(context_.domain_id, guid);
+            }
+            // Latency to locators of the participant, unless other participants of the domain listen on them too
+            let used: Vec<&Locator> = self.contexts.iter()
+                .filter(|context| context.domain_id == context_.domain_id)
+                .flat_map(|context| context.host.unicast_locators.iter())
+                .collect();
+            let removed: Vec<&Locator> = locators.iter().filter(|locator| !used.contains(locator)).collect();
+            self.network_latency.retain(|(domain_id, src_locator, dst_locator), _| {
+                *domain_id != context_.domain_id || !(removed.contains(&src_locator) || removed.contains(&dst_locator))
+            });
         }
 
-        pub fn remove_topic(&mut self, topic_: Ros2Topic) {}
+        /// PIDs of local processes which run discovered participants
+        pub fn local_pids(&self) -> Vec<u32> {
+            let mut pids: Vec<u32> = self.contexts.iter().filter_map(|context| context.process.pid).collect();
+            pids.sort();
+            pids.dedup();
+            return pids;
+        }
+
+        /// PID of the local process which runs the node
+        pub fn node_pid(&self, node: &Ros2Node) -> Option<u32> {
+            return self.context(node.domain_id, node.context_guid.as_str()).and_then(|context| context.process.pid);
+        }
+
+        /// Attach the latest resource samples to nodes of local processes
+        pub fn set_resources(&mut self, samples: &HashMap<u32, ResourceSample>) {
+            let pids: HashMap<(u32, String), u32> = self.contexts.iter()
+                .filter_map(|context| context.process.pid.map(|pid| ((context.domain_id, context.guid.clone()), pid)))
+                .collect();
+            for node in self.nodes.iter_mut() {
+                node.resources = pids.get(&(node.domain_id, node.context_guid.clone())).and_then(|pid| samples.get(pid)).cloned();
+            }
+        }
+
+        /// Nodes which belong to the participant
+        pub fn context_nodes(&self, guid: &str, domain_id: u32) -> Vec<&Ros2Node> {
+            return self.nodes.iter().filter(|node| node.context_guid == guid && node.domain_id == domain_id).collect();
+        }
 
         fn is_internal(&self, mut topic_name: String) -> bool {
             let names: Vec<&str> = vec!["parameter_events", "rosout"];
@@ -134,88 +969,141 @@ pub mod ros2entities {
             return names.contains(&topic_name.as_str());
         }
 
-        pub fn add_publisher(&mut self, publisher: Ros2Publisher) {
-            let node_name = publisher.node_name.clone();
-            let is_internal = self.is_internal(publisher.topic_name.clone());
-            let publishers = if (is_internal && self.include_internals) || !is_internal {
-                vec![publisher.clone()]
-            } else {
-                vec![]
+        /// Find topic or create the empty one
+        fn topic_entry(&mut self, name: &String, topic_type: &String, node_name: &String, domain_id: u32) -> &mut Ros2Topic {
+            let key = (domain_id, name.clone(), topic_type.clone());
+            let pos = match self.index.topics.get(&key) {
+                Some(pos) => *pos,
+                None => {
+                    self.topics.push(Ros2Topic::new(name.clone(), node_name.clone(), topic_type.clone(), domain_id));
+                    self.index.topics.insert(key, self.topics.len() - 1);
+                    self.index.topic_types.entry((domain_id, name.clone())).or_default().insert(topic_type.clone());
+                    self.topics.len() - 1
+                }
             };
-            if !node_name.starts_with("_") || self.include_internals {
-                let mut node = self.nodes.iter_mut().find(|node| node.name == node_name);
-                if node.is_none() {
-                    let new_node = Ros2Node {
-                        name: node_name.clone(),
-                        package_name: "".to_string(),
-                        subscribers: vec![],
-                        publishers,
-                        service_servers: vec![],
-                        service_clients: vec![],
-                        action_servers: vec![],
-                        action_clients: vec![],
-                        host: publisher.clone().host,
-                        is_lifecycle: false,
-                        state: Ros2NodeState::Unconfigured,
-                    };
-                    self.add_node(new_node);
+            return self.topics.get_mut(pos).unwrap();
+        }
+
+        fn new_node(name: String, namespace: String, host: Host, domain_id: u32, context_guid: String) -> Ros2Node {
+            return Ros2Node {
+                name,
+                namespace,
+                package_name: "".to_string(),
+                executable: "".to_string(),
+                subscribers: vec![],
+                publishers: vec![],
+                service_servers: vec![],
+                service_clients: vec![],
+                action_servers: vec![],
+                action_clients: vec![],
+                host,
+                is_lifecycle: false,
+                state: Ros2NodeState::Unconfigured,
+                domain_id,
+                context_guid,
+                resources: None,
+            };
+        }
+
+        /// Position of the node which holds the endpoint, the node is created if needed.
+        /// None if nodes with this name are hidden
+        fn endpoint_node(&mut self, node_name: &String, namespace: &String, host: &Host, domain_id: u32, context_guid: &String) -> Option<usize> {
+            if node_name.starts_with("_") && !self.include_internals {
+                return None;
+            }
+            let fqn = node_fqn(namespace.as_str(), node_name.as_str());
+            if !self.index.nodes.contains_key(&(domain_id, fqn.clone())) {
+                self.add_node(Ros2State::new_node(node_name.clone(), normalize_namespace(namespace.as_str()), host.clone(), domain_id, context_guid.clone()));
+                self.attribute_nodes(domain_id, context_guid.as_str());
+            }
+            return self.index.nodes.get(&(domain_id, fqn)).cloned();
+        }
+
+        /// Add endpoint into topic and indices
+        fn index_endpoint(&mut self, domain_id: u32, guid: String, entry: EndpointEntry, node_fqn: &String, kind: Ros2NameKind) {
+            // Services and actions aren't topics, their endpoints are only indexed
+            if kind.is_topic() {
+                let topic = self.topic_entry(&entry.topic_name, &entry.topic_type, node_fqn, domain_id);
+                if entry.is_publisher {
+                    topic.publishers.insert(guid.clone());
                 } else {
-                    if !publishers.is_empty() {
-                        node.as_mut().unwrap().publishers.push(publisher.clone());
-                    }
+                    topic.subscribers.insert(guid.clone());
                 }
             }
+            self.index.context_endpoints.entry((domain_id, entry.context_guid.clone())).or_default().insert(guid.clone());
+            self.index.endpoints.insert((domain_id, guid), entry);
+        }
+
+        /// Add discovered publisher into its node and topic. Repeated discovery of the same endpoint is ignored
+        /// # Arguments
+        ///
+        /// * `publisher`:
+        ///
+        /// returns: ()
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn add_publisher(&mut self, publisher: Ros2Publisher) {
+            let guid = publisher.endpoint_guid();
+            if self.index.endpoints.contains_key(&(publisher.domain_id, guid.clone())) {
+                return;
+            }
+            let is_internal = self.is_internal(publisher.topic_name.clone());
+            let is_visible = !is_internal || self.include_internals;
 
-            // Check for corresponding topic presence. If it does exists, just increase publishers count
-            let topic: Option<&mut Ros2Topic> = self.topics.iter_mut().find(|topic| publisher.clone().topic_name == topic.name && publisher.clone().topic_type == topic.topic_type);
-            if topic.is_none() {
-                self.topics.push(Ros2Topic { name: publisher.clone().topic_name.clone(), node_name, topic_type: publisher.topic_type, subscribers_num: 0, publishers_num: 1 });
-            } else {
-                topic.unwrap().publishers_num += 1;
+            let fqn = publisher.node_fqn();
+            let mut node_fqn = None;
+            if let Some(pos) = self.endpoint_node(&publisher.node_name, &publisher.node_namespace, &publisher.host, publisher.domain_id, &publisher.guid) {
+                if is_visible {
+                    self.nodes[pos].publishers.push(publisher.clone());
+                    node_fqn = Some(fqn.clone());
+                }
             }
+
+            let entry = EndpointEntry {
+                is_publisher: true,
+                node_fqn,
+                topic_name: publisher.topic_name.clone(),
+                topic_type: publisher.topic_type.clone(),
+                context_guid: publisher.guid.clone(),
+            };
+            self.index_endpoint(publisher.domain_id, guid, entry, &fqn, publisher.kind);
         }
 
+        /// The same as add_publisher but for subscriber
         pub fn add_subscriber(&mut self, subscriber: Ros2Subscriber) {
-            let node_name = subscriber.node_name.clone();
+            let guid = subscriber.endpoint_guid();
+            if self.index.endpoints.contains_key(&(subscriber.domain_id, guid.clone())) {
+                return;
+            }
             let is_internal = self.is_internal(subscriber.topic_name.clone());
-            let subscribers: Vec<Ros2Subscriber> = if (is_internal && self.include_internals) || !is_internal {
-                vec![subscriber.clone()]
-            } else {
-                vec![]
-            };
+            let is_visible = !is_internal || self.include_internals;
 
-            if !node_name.starts_with("_") || self.include_internals {
-                let mut node = self.nodes.iter_mut().find(|node| node.name == node_name);
-                if node.is_none() {
-                    let new_node = Ros2Node {
-                        name: node_name.clone(),
-                        package_name: "".to_string(),
-                        subscribers,
-                        publishers: vec![],
-                        service_servers: vec![],
-                        service_clients: vec![],
-                        action_servers: vec![],
-                        action_clients: vec![],
-                        host: subscriber.host,
-                        is_lifecycle: false,
-                        state: Ros2NodeState::Unconfigured,
-                    };
-                    self.add_node(new_node);
-                } else {
-                    node.as_mut().unwrap().subscribers.push(subscriber.clone());
+            let fqn = subscriber.node_fqn();
+            let mut node_fqn = None;
+            if let Some(pos) = self.endpoint_node(&subscriber.node_name, &subscriber.node_namespace, &subscriber.host, subscriber.domain_id, &subscriber.guid) {
+                if is_visible {
+                    self.nodes[pos].subscribers.push(subscriber.clone());
+                    node_fqn = Some(fqn.clone());
                 }
             }
-            // Check for corresponding topic presence. If it does exists, just increase subscribers count
-            let topic: Option<&mut Ros2Topic> = self.topics.iter_mut().find(|topic| subscriber.topic_name == topic.name && subscriber.topic_type == topic.topic_type);
-            if topic.is_none() {
-                self.topics.push(Ros2Topic { name: subscriber.topic_name.clone(), node_name, topic_type: subscriber.topic_type, subscribers_num: 0, publishers_num: 1 });
-            } else {
-                topic.unwrap().publishers_num += 1;
-            }
+
+            let entry = EndpointEntry {
+                is_publisher: false,
+                node_fqn,
+                topic_name: subscriber.topic_name.clone(),
+                topic_type: subscriber.topic_type.clone(),
+                context_guid: subscriber.guid.clone(),
+            };
+            self.index_endpoint(subscriber.domain_id, guid, entry, &fqn, subscriber.kind);
         }
 
         /// Deletes publisher from state
-        /// To delete publisher we have to find the node, which contains this publisher and remove the publisher from this node
+        /// The publisher is found by its GUID, so node name and topic type of removed endpoint don't matter.
+        /// Topic is deleted as it has no more publishers and subscribers
         /// # Arguments
         ///
         /// * `publisher_`:
@@ -228,28 +1116,7 @@ pub mod ros2entities {
         ///
         /// ```
         pub fn remove_publisher(&mut self, publisher_: Ros2Publisher) {
-            let pred = |publisher: &&Ros2Publisher| { return publisher.topic_type == publisher_.topic_type && publisher.node_name == publisher_.node_name; };
-            let node_idx = match self.nodes.iter().position(|node| !node.publishers.iter().find(pred).is_none()) {
-                Some(idx) => idx,
-                None => return
-            };
-            let node: &mut Ros2Node = self.nodes.get_mut(node_idx).unwrap();
-
-            let pub_pred = |publisher: &Ros2Publisher| { return publisher.topic_type == publisher_.topic_type && publisher.node_name == publisher_.node_name; };
-            let pub_idx = node.publishers.iter().position(pub_pred).unwrap();
-            node.publishers.remove(pub_idx);
-
-            // Check for corresponding topic presence. If it does exists, decrease publishers count.
-            // As it goes to zero, delete topic
-            let topic_pos = self.topics.iter_mut().position(|topic| publisher_.topic_name == topic.name && publisher_.topic_type == topic.topic_type);
-            if !topic_pos.is_none() {
-                let topic: &mut Ros2Topic = self.topics.get_mut(topic_pos.unwrap()).unwrap();
-                if topic.publishers_num == 0 && topic.subscribers_num == 0 {
-                    self.topics.remove(topic_pos.unwrap());
-                } else {
-                    topic.publishers_num = min(topic.publishers_num, 0);
-                }
-            }
+            self.remove_endpoint(publisher_.domain_id, &publisher_.endpoint_guid(), Some(true));
         }
 
         /// The same as remove_publisher but for subscriber
@@ -266,28 +1133,7 @@ pub mod ros2entities {
         ///
         /// ```
         pub fn remove_subscriber(&mut self, subscriber_: Ros2Subscriber) {
-            let pred = |&subscriber: &&Ros2Subscriber| { return subscriber.topic_type == subscriber_.topic_type && subscriber.node_name == subscriber_.node_name; };
-            let node_idx = match self.nodes.iter().position(|node| !node.subscribers.iter().find(pred).is_none()) {
-                Some(idx) => idx,
-                None => return /* TODO: Temporary solution. This case isn't appropriate because there is a change of broken graph state*/
-            };
-            let node = self.nodes.get_mut(node_idx).unwrap();
-
-            let sub_pred = |subscriber: &Ros2Subscriber| { return subscriber.topic_type == subscriber_.topic_type && subscriber.node_name == subscriber_.node_name; };
-            let sub_idx = node.subscribers.iter().position(sub_pred).unwrap();
-            node.subscribers.remove(sub_idx);
-
-            // Check for corresponding topic presence. If it does exists, decrease publishers count.
-            // As it goes to zero, delete topic
-            let topic_pos = self.topics.iter_mut().position(|topic| subscriber_.topic_name == topic.name && subscriber_.topic_type == topic.topic_type);
-            if !topic_pos.is_none() {
-                let topic: &mut Ros2Topic = self.topics.get_mut(topic_pos.unwrap()).unwrap();
-                if topic.publishers_num == 0 && topic.subscribers_num == 0 {
-                    self.topics.remove(topic_pos.unwrap());
-                } else {
-                    topic.subscribers_num = min(topic.subscribers_num, 0);
-                }
-            }
+            self.remove_endpoint(subscriber_.domain_id, &subscriber_.endpoint_guid(), Some(false));
         }
 
         pub fn contains_node(&self, node_name: String) -> bool {
@@ -313,16 +1159,48 @@ pub mod ros2entities {
 
     #[derive(Serialize, Deserialize, Clone)]
     pub struct Host {
+        /// Address of the first IP unicast locator, `SHM` if the entity is reachable by shared memory only
         pub ip: String,
         pub name: String,
+        #[serde(default)]
+        pub unicast_locators: Vec<Locator>,
+        #[serde(default)]
+        pub multicast_locators: Vec<Locator>,
     }
 
     impl Host {
         pub fn default() -> Host {
-            return Host { ip: "127.0.0.1".to_string(), name: "localhost".to_string() };
+            return Host::new("127.0.0.1".to_string(), "localhost".to_string());
         }
         pub fn new(ip: String, name: String) -> Host {
-            return Host { ip, name };
+            return Host { ip, name, unicast_locators: vec![], multicast_locators: vec![] };
+        }
+
+        /// Host of the discovered entity by its locators. Name is resolved later
+        /// # Arguments
+        ///
+        /// * `unicast_locators`: unicast locators of the entity
+        /// * `multicast_locators`: multicast locators of the entity
+        ///
+        /// returns: Host
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn from_locators(unicast_locators: Vec<Locator>, multicast_locators: Vec<Locator>) -> Host {
+            let ip = match unicast_locators.iter().find_map(|locator| locator.address) {
+                Some(address) => address.to_string(),
+                None if unicast_locators.iter().any(|locator| locator.transport == Transport::SHM) => "SHM".to_string(),
+                None => "".to_string()
+            };
+            return Host { ip, name: "unknown".to_string(), unicast_locators, multicast_locators };
+        }
+
+        /// All locators, unicast first
+        pub fn locators(&self) -> impl Iterator<Item=&Locator> {
+            return self.unicast_locators.iter().chain(self.multicast_locators.iter());
         }
     }
 
@@ -336,11 +1214,66 @@ pub mod ros2entities {
         NonLifecycle,
     }
 
+    pub const ROOT_NAMESPACE: &str = "/";
+
+    fn root_namespace() -> String {
+        return ROOT_NAMESPACE.to_string();
+    }
+
+    /// Namespace with leading slash and without trailing one, e.g. `robot1/` -> `/robot1`
+    pub fn normalize_namespace(namespace: &str) -> String {
+        let trimmed = namespace.trim().trim_matches('/');
+        return format!("/{}", trimmed);
+    }
+
+    /// Fully qualified node name, e.g. `/robot1` and `camera` -> `/robot1/camera`
+    pub fn node_fqn(namespace: &str, name: &str) -> String {
+        let namespace = normalize_namespace(namespace);
+        return if namespace == ROOT_NAMESPACE { format!("/{}", name) } else { format!("{}/{}", namespace, name) };
+    }
+
+    /// Split fully qualified node name into namespace and name. Bare name belongs to the root namespace
+    /// # Arguments
+    ///
+    /// * `fqn`: fully qualified or bare node name
+    ///
+    /// returns: (String, String)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn split_node_fqn(fqn: &str) -> (String, String) {
+        return match fqn.rsplit_once('/') {
+            Some((namespace, name)) => (normalize_namespace(namespace), name.to_string()),
+            None => (root_namespace(), fqn.to_string())
+        };
+    }
+
+    /// Check if the fully qualified names contain the node. Namespace and name are compared, bare name is in the root namespace
+    pub fn contains_node(node_names: &[String], node_name: &str) -> bool {
+        let node = split_node_fqn(node_name);
+        return node_names.iter().any(|listed| split_node_fqn(listed.trim()) == node);
+    }
+
+    /// Check if namespace is the same as `parent` or nested into it
+    pub fn is_in_namespace(namespace: &str, parent: &str) -> bool {
+        let parent = normalize_namespace(parent);
+        return parent == ROOT_NAMESPACE || namespace == parent || namespace.starts_with(format!("{}/", parent).as_str());
+    }
+
     /// Ros2Node is the main struct that contains almost all information about the node and its publishers and subscribers
     #[derive(Serialize, Deserialize, Clone)]
     pub struct Ros2Node {
+        /// Node name without namespace
         pub name: String,
+        #[serde(default = "root_namespace")]
+        pub namespace: String,
         pub package_name: String,
+        /// Executable of the node process, empty if unknown
+        #[serde(default)]
+        pub executable: String,
         pub subscribers: Vec<Ros2Subscriber>,
         pub publishers: Vec<Ros2Publisher>,
         pub service_servers: Vec<Ros2ServiceServer>,
@@ -350,13 +1283,25 @@ pub mod ros2entities {
         pub host: Host,
         pub is_lifecycle: bool,
         pub state: Ros2NodeState,
+        #[serde(default)]
+        pub domain_id: u32,
+        /// GUID prefix of the participant (context) which runs the node
+        #[serde(default)]
+        pub context_guid: String,
+        /// The latest resource usage of the node process, only for local processes
+        #[serde(default)]
+        pub resources: Option<ResourceSample>,
     }
 
     impl Ros2Node {
+        /// Create node by its bare or fully qualified name
         pub fn create(name: String) -> Ros2Node {
+            let (namespace, name) = split_node_fqn(name.as_str());
             return Ros2Node {
                 name,
+                namespace,
                 package_name: "".to_string(),
+                executable: "".to_string(),
                 subscribers: vec![],
                 publishers: vec![],
                 service_servers: vec![],
@@ -366,38 +1311,428 @@ pub mod ros2entities {
                 host: Host::default(),
                 is_lifecycle: false,
                 state: Ros2NodeState::Inactive,
+                domain_id: 0,
+                context_guid: "".to_string(),
+                resources: None,
+            };
+        }
+
+        /// Fully qualified name, the node is identified by it in its domain
+        pub fn fqn(&self) -> String {
+            return node_fqn(self.namespace.as_str(), self.name.as_str());
+        }
+    }
+
+    /// Process information announced by the participant in its user data. Process of the local participant
+    /// is also found by PID, so its command line, package and executable are known
+    #[derive(Clone, Serialize, Deserialize, Default, Debug, PartialEq)]
+    pub struct Ros2ProcessInfo {
+        /// Security enclave of ROS2 context, `/` by default
+        pub enclave: String,
+        /// All `key=value;` pairs of user data, including enclave
+        pub properties: BTreeMap<String, String>,
+        /// PID of the local process, None for remote participants
+        #[serde(default)]
+        pub pid: Option<u32>,
+        #[serde(default)]
+        pub process_name: String,
+        #[serde(default)]
+        pub cmdline: Vec<String>,
+        /// Package which installs the executable, empty if unknown
+        #[serde(default)]
+        pub package_name: String,
+        #[serde(default)]
+        pub executable: String,
+    }
+
+    impl Ros2ProcessInfo {
+        /// Parse participant user data. ROS2 contexts announce `enclave=/;`, older distributions announce
+        /// node name and namespace, other tools may add their own keys
+        /// # Arguments
+        ///
+        /// * `user_data`: user data as string
+        ///
+        /// returns: Ros2ProcessInfo
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn from_user_data(user_data: &str) -> Ros2ProcessInfo {
+            let properties: BTreeMap<String, String> = user_data.split(';')
+                .filter_map(|pair| pair.split_once('='))
+                .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+                .filter(|(key, _)| !key.is_empty())
+                .collect();
+            return Ros2ProcessInfo {
+                enclave: properties.get("enclave").cloned().unwrap_or_default(),
+                properties,
+                ..Ros2ProcessInfo::default()
             };
         }
     }
 
+    /// DDS participant. Since Foxy each ROS2 context (usually a process) has a single participant for all its nodes
     #[derive(Clone, Serialize, Deserialize)]
     pub struct Ros2Context {
+        /// GUID prefix of the participant
         pub guid: String,
         pub host: Host,
+        #[serde(default)]
+        pub domain_id: u32,
+        #[serde(default)]
+        pub vendor: String,
+        /// Participant name, empty for most ROS2 contexts
+        #[serde(default)]
+        pub name: String,
+        #[serde(default)]
+        pub process: Ros2ProcessInfo,
+        /// Seconds since unix epoch of the first discovery
+        #[serde(default)]
+        pub discovered_at: f64,
     }
 
     impl Ros2Context {
-        pub fn new(guid: String, host: Host) -> Ros2Context {
-            return Ros2Context { guid, host };
+        pub fn new(guid: String, host: Host, domain_id: u32) -> Ros2Context {
+            return Ros2Context {
+                guid,
+                host,
+                domain_id,
+                vendor: "".to_string(),
+                name: "".to_string(),
+                process: Ros2ProcessInfo::default(),
+                discovered_at: 0.0,
+            };
+        }
+    }
+
+    /// Host id of eProsima GUID prefix, bytes 2..4. Participants of the same host share it
+    fn fastdds_host_id(guid: &str) -> Option<String> {
+        let octets: Vec<&str> = guid.split('.').collect();
+        if octets.len() != 12 || octets[0..2] != ["01", "0f"] {
+            return None;
+        }
+        return Some(format!("{}.{}", octets[2], octets[3]));
+    }
+
+    /// Loopback addresses and addresses of the default docker bridge 172.17.0.0/16 are the same on many hosts
+    fn identifies_host(ip: &str) -> bool {
+        return match ip.parse::<IpAddr>() {
+            Ok(IpAddr::V4(address)) => !address.is_loopback() && address.octets()[0..2] != [172, 17],
+            Ok(IpAddr::V6(address)) => !address.is_loopback(),
+            Err(_) => false
+        };
+    }
+
+    /// Machine which runs discovered participants
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct Ros2Host {
+        /// Host name resolved by the participant address, `unknown` if it isn't resolved
+        pub name: String,
+        /// Addresses announced by participants. Loopback addresses identify the monitor's host
+        pub ips: BTreeSet<String>,
+        /// Transports of participant locators: SHM, UDPv4, UDPv6, TCPv4, TCPv6
+        pub transports: BTreeSet<String>,
+        /// Host ids of FastDDS GUID prefixes
+        pub host_ids: BTreeSet<String>,
+        /// GUID prefixes of participants running there now
+        pub contexts: BTreeSet<String>,
+        pub domains: BTreeSet<u32>,
+        /// Fully qualified names of nodes running there now
+        pub nodes: BTreeSet<String>,
+        /// Seconds since unix epoch of the first discovered participant
+        pub first_seen: f64,
+        /// Seconds since unix epoch of the last discovery or removal of its participants
+        pub last_seen: f64,
+    }
+
+    impl Ros2Host {
+        /// Host of the single participant
+        fn from_context(context: &Ros2Context) -> Ros2Host {
+            let mut host = Ros2Host {
+                name: context.host.name.clone(),
+                ips: BTreeSet::new(),
+                transports: BTreeSet::new(),
+                host_ids: fastdds_host_id(context.guid.as_str()).into_iter().collect(),
+                contexts: BTreeSet::from([context.guid.clone()]),
+                domains: BTreeSet::from([context.domain_id]),
+                nodes: BTreeSet::new(),
+                first_seen: if context.discovered_at > 0.0 { context.discovered_at } else { now_secs() },
+                last_seen: 0.0,
+            };
+            for locator in context.host.unicast_locators.iter() {
+                host.transports.insert(locator.transport.name().to_string());
+            }
+            let addresses = context.host.unicast_locators.iter().filter_map(|locator| locator.address)
+                .chain(context.host.ip.parse::<IpAddr>().ok());
+            for address in addresses.filter(|address| !address.is_unspecified()) {
+                host.ips.insert(address.to_string());
+            }
+            return host;
+        }
+
+        fn is_same(&self, other: &Ros2Host) -> bool {
+            return self.ips.iter().any(|ip| identifies_host(ip) && other.ips.contains(ip))
+                || !self.host_ids.is_disjoint(&other.host_ids) || !self.contexts.is_disjoint(&other.contexts);
+        }
+
+        /// Merge the previously known host into this one. Resolved name is preferred over `localhost` and `unknown`
+        fn merge(&mut self, other: Ros2Host) {
+            let is_resolved = |name: &str| !name.is_empty() && name != "unknown" && name != "localhost";
+            if !is_resolved(self.name.as_str()) && !other.name.is_empty() && other.name != "unknown" {
+                self.name = other.name;
+            }
+            self.ips.extend(other.ips);
+            self.transports.extend(other.transports);
+            self.host_ids.extend(other.host_ids);
+            self.contexts.extend(other.contexts);
+            self.domains.extend(other.domains);
+            self.first_seen = self.first_seen.min(other.first_seen);
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    pub enum WireEntityKind {
+        Participant,
+        Writer,
+        Reader,
+        /// Builtin endpoint of discovery or liveliness
+        Builtin,
+        /// GUID of the participant which isn't discovered, e.g. it uses another discovery server
+        Unknown,
+    }
+
+    /// Captured traffic of the GUID with the entity it belongs to
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct Ros2WireEntity {
+        #[serde(flatten)]
+        pub activity: WireActivity,
+        pub kind: WireEntityKind,
+        pub domain_id: Option<u32>,
+        /// Fully qualified name of the node of the endpoint
+        pub node: Option<String>,
+        pub topic_name: Option<String>,
+        pub topic_type: Option<String>,
+    }
+
+    /// Kind of FastDDS statistics topic
+    #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    pub enum DdsStatisticsKind {
+        HistoryLatency,
+        NetworkLatency,
+        PublicationThroughput,
+        SubscriptionThroughput,
+        ResentData,
+        HeartbeatCount,
+        AcknackCount,
+        RtpsLost,
+    }
+
+    /// Sample of FastDDS statistics topic. Fields which aren't used by the kind are zero or None
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct DdsStatisticsSample {
+        pub kind: DdsStatisticsKind,
+        pub domain_id: u32,
+        /// Participant or endpoint GUID which reports the statistics, writer of history latency
+        pub guid: String,
+        /// Reader of history latency
+        pub remote_guid: Option<String>,
+        /// Source of network latency
+        pub src_locator: Option<Locator>,
+        /// Destination of network latency and lost traffic
+        pub dst_locator: Option<Locator>,
+        /// Latency in nanoseconds or throughput in bytes per second
+        pub value: f64,
+        /// Submessages or lost packets
+        pub count: u64,
+        /// Lost bytes
+        pub bytes: u64,
+    }
+
+    /// Traffic lost on the way to the destination locator
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct Ros2LostTraffic {
+        pub locator: Locator,
+        pub packets: u64,
+        pub bytes: u64,
+    }
+
+    /// FastDDS statistics of the participant or endpoint. Values are the latest reported ones, counters are cumulative
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct Ros2DdsStatistics {
+        pub guid: String,
+        pub domain_id: u32,
+        pub kind: WireEntityKind,
+        /// Fully qualified name of the node of the endpoint
+        pub node: Option<String>,
+        pub topic_name: Option<String>,
+        pub topic_type: Option<String>,
+        /// Latency from the writer history to the reader history in nanoseconds by reader GUID
+        pub history_latency: BTreeMap<String, f64>,
+        /// Bytes per second
+        pub publication_throughput: Option<f64>,
+        /// Bytes per second
+        pub subscription_throughput: Option<f64>,
+        pub resent_data: Option<u64>,
+        pub heartbeats: Option<u64>,
+        pub acknacks: Option<u64>,
+        pub lost: Vec<Ros2LostTraffic>,
+        pub updated_at: f64,
+    }
+
+    impl Ros2DdsStatistics {
+        pub fn new(guid: String, domain_id: u32) -> Ros2DdsStatistics {
+            return Ros2DdsStatistics {
+                guid,
+                domain_id,
+                kind: WireEntityKind::Unknown,
+                node: None,
+                topic_name: None,
+                topic_type: None,
+                history_latency: BTreeMap::new(),
+                publication_throughput: None,
+                subscription_throughput: None,
+                resent_data: None,
+                heartbeats: None,
+                acknacks: None,
+                lost: Vec::new(),
+                updated_at: 0.0,
+            };
+        }
+    }
+
+    /// Latency between locators in nanoseconds
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct Ros2NetworkLatency {
+        pub domain_id: u32,
+        pub src_locator: Locator,
+        pub dst_locator: Locator,
+        pub latency: f64,
+        pub updated_at: f64,
+    }
+
+    /// QoS policies of the endpoint. Durations are in seconds, None for infinite ones
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct Ros2Qos {
+        /// `reliable` or `best_effort`
+        pub reliability: String,
+        /// `volatile`, `transient_local`, `transient` or `persistent`
+        pub durability: String,
+        /// `keep_last` or `keep_all`
+        pub history: String,
+        /// History depth for `keep_last`
+        pub depth: u32,
+        pub deadline: Option<f64>,
+        pub lifespan: Option<f64>,
+        /// `automatic`, `manual_by_participant` or `manual_by_topic`
+        pub liveliness: String,
+        pub liveliness_lease_duration: Option<f64>,
+        /// `shared` or `exclusive`
+        pub ownership: String,
+        pub partitions: Vec<String>,
+    }
+
+    impl Ros2Qos {
+        /// Default QoS of DDS writers. Readers are best effort by default
+        pub fn default_writer() -> Ros2Qos {
+            return Ros2Qos {
+                reliability: "reliable".to_string(),
+                durability: "volatile".to_string(),
+                history: "keep_last".to_string(),
+                depth: 1,
+                deadline: None,
+                lifespan: None,
+                liveliness: "automatic".to_string(),
+                liveliness_lease_duration: None,
+                ownership: "shared".to_string(),
+                partitions: vec![],
+            };
+        }
+
+        pub fn default_reader() -> Ros2Qos {
+            return Ros2Qos { reliability: "best_effort".to_string(), ..Ros2Qos::default_writer() };
         }
     }
 
+    /// GUID of the endpoint in FastDDS form `prefix|entity_id`. Endpoints discovered without entity id
+    /// (e.g. by ros2 cli) are identified by their prefix only
+    fn endpoint_guid(guid: &String, entity_id: &String) -> String {
+        return if entity_id.is_empty() { guid.clone() } else { format!("{}|{}", guid, entity_id) };
+    }
+
     #[derive(Serialize, Deserialize, Clone)]
     pub struct Ros2Subscriber {
         pub topic_name: String,
         pub node_name: String,
+        #[serde(default = "root_namespace")]
+        pub node_namespace: String,
+        /// GUID prefix of the participant
         pub guid: String,
         pub topic_type: String,
         pub host: Host,
+        #[serde(default)]
+        pub domain_id: u32,
+        /// Entity id of the reader inside the participant
+        #[serde(default)]
+        pub entity_id: String,
+        /// QoS announced in SEDP, None if discovery doesn't provide it
+        #[serde(default)]
+        pub qos: Option<Ros2Qos>,
+        /// Topic, service or action by the prefix of DDS topic name
+        #[serde(default)]
+        pub kind: Ros2NameKind,
+        /// Action which the topic or service belongs to
+        #[serde(default)]
+        pub action: Option<String>,
+    }
+
+    impl Ros2Subscriber {
+        pub fn endpoint_guid(&self) -> String {
+            return endpoint_guid(&self.guid, &self.entity_id);
+        }
+
+        pub fn node_fqn(&self) -> String {
+            return node_fqn(self.node_namespace.as_str(), self.node_name.as_str());
+        }
     }
 
     #[derive(Serialize, Deserialize, Clone)]
     pub struct Ros2Publisher {
         pub topic_name: String,
+        /// GUID prefix of the participant
         pub guid: String,
         pub node_name: String,
+        #[serde(default = "root_namespace")]
+        pub node_namespace: String,
         pub topic_type: String,
         pub host: Host,
+        #[serde(default)]
+        pub domain_id: u32,
+        /// Entity id of the writer inside the participant
+        #[serde(default)]
+        pub entity_id: String,
+        /// QoS announced in SEDP, None if discovery doesn't provide it
+        #[serde(default)]
+        pub qos: Option<Ros2Qos>,
+        /// Topic, service or action by the prefix of DDS topic name
+        #[serde(default)]
+        pub kind: Ros2NameKind,
+        /// Action which the topic or service belongs to
+        #[serde(default)]
+        pub action: Option<String>,
+    }
+
+    impl Ros2Publisher {
+        pub fn endpoint_guid(&self) -> String {
+            return endpoint_guid(&self.guid, &self.entity_id);
+        }
+
+        pub fn node_fqn(&self) -> String {
+            return node_fqn(self.node_namespace.as_str(), self.node_name.as_str());
+        }
     }
 
     #[derive(Serialize, Deserialize, Clone)]
@@ -436,23 +1771,67 @@ pub mod ros2entities {
         pub node_name: String,
     }
 
-    #[derive(Serialize, Deserialize, Clone)]
+    /// Topic with GUIDs of its endpoints. Publishers and subscribers numbers are derived from GUID sets,
+    /// so repeated discovery of the same endpoint doesn't change them
+    #[derive(Deserialize, Clone)]
     pub struct Ros2Topic {
         pub name: String,
         pub node_name: String,
         pub topic_type: String,
-        pub subscribers_num: u64,
-        pub publishers_num: u64,
+        #[serde(default)]
+        pub publishers: BTreeSet<String>,
+        #[serde(default)]
+        pub subscribers: BTreeSet<String>,
+        #[serde(default)]
+        pub domain_id: u32,
+    }
+
+    impl Ros2Topic {
+        pub fn new(name: String, node_name: String, topic_type: String, domain_id: u32) -> Ros2Topic {
+            return Ros2Topic { name, node_name, topic_type, publishers: BTreeSet::new(), subscribers: BTreeSet::new(), domain_id };
+        }
+
+        pub fn publishers_num(&self) -> u64 {
+            return self.publishers.len() as u64;
+        }
+
+        pub fn subscribers_num(&self) -> u64 {
+            return self.subscribers.len() as u64;
+        }
+
+        pub fn is_empty(&self) -> bool {
+            return self.publishers.is_empty() && self.subscribers.is_empty();
+        }
+    }
+
+    /// Numbers of endpoints are kept in json for clients which don't need GUIDs
+    impl Serialize for Ros2Topic {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut topic = serializer.serialize_struct("Ros2Topic", 8)?;
+            topic.serialize_field("name", &self.name)?;
+            topic.serialize_field("node_name", &self.node_name)?;
+            topic.serialize_field("topic_type", &self.topic_type)?;
+            topic.serialize_field("subscribers_num", &self.subscribers_num())?;
+            topic.serialize_field("publishers_num", &self.publishers_num())?;
+            topic.serialize_field("publishers", &self.publishers)?;
+            topic.serialize_field("subscribers", &self.subscribers)?;
+            topic.serialize_field("domain_id", &self.domain_id)?;
+            return topic.end();
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::ros2entites::ros2entities::{Host, Ros2Node, Ros2Publisher, Ros2State, Ros2Subscriber};
+    use std::collections::HashSet;
+    use proptest::prelude::*;
+    use crate::capture::capture::WireActivity;
+    use crate::network::network::{Locator, Ros2NameKind};
+    use crate::ros2entites::ros2entities::{contains_node, is_in_namespace, node_fqn, MAX_UNKNOWN_STATISTICS, split_node_fqn, DdsStatisticsKind, DdsStatisticsSample, Host, Ros2Context, Ros2Node, Ros2ProcessInfo, Ros2Publisher, Ros2State, Ros2Subscriber, WireEntityKind};
 
     #[test]
     fn add_node() {
-        let mut state = Ros2State::new();
+        let mut state = Ros2State::new(false);
         assert_eq!(state.has_node("test_node".to_string()), false);
         let test_node = Ros2Node::create("test_node".to_string());
         state.add_node(test_node);
@@ -461,13 +1840,19 @@ mod tests {
 
     #[test]
     fn add_publisher() {
-        let mut state = Ros2State::new();
+        let mut state = Ros2State::new(false);
         let test_publisher: Ros2Publisher = Ros2Publisher {
             topic_name: "test_name".to_string(),
             guid: "test_guid".to_string(),
             node_name: "test_node".to_string(),
+            node_namespace: "/".to_string(),
             topic_type: "test_type".to_string(),
             host: Host::default(),
+            domain_id: 0,
+            entity_id: "".to_string(),
+            qos: None,
+            kind: Ros2NameKind::Topic,
+            action: None,
         };
         assert_eq!(state.has_publisher(test_publisher.clone()), false);
         state.add_publisher(test_publisher.clone());
@@ -476,13 +1861,19 @@ mod tests {
 
     #[test]
     fn add_subscriber() {
-        let mut state = Ros2State::new();
+        let mut state = Ros2State::new(false);
         let test_subscriber: Ros2Subscriber = Ros2Subscriber {
             topic_name: "test_name".to_string(),
             guid: "test_guid".to_string(),
             node_name: "test_node".to_string(),
+            node_namespace: "/".to_string(),
             topic_type: "test_type".to_string(),
             host: Host::default(),
+            domain_id: 0,
+            entity_id: "".to_string(),
+            qos: None,
+            kind: Ros2NameKind::Topic,
+            action: None,
         };
         assert_eq!(state.has_subscriber(test_subscriber.clone()), false);
         state.add_subscriber(test_subscriber.clone());
@@ -491,13 +1882,19 @@ mod tests {
 
     #[test]
     fn remove_publisher() {
-        let mut state = Ros2State::new();
+        let mut state = Ros2State::new(false);
         let test_publisher: Ros2Publisher = Ros2Publisher {
             topic_name: "test_name".to_string(),
             guid: "test_guid".to_string(),
             node_name: "test_node".to_string(),
+            node_namespace: "/".to_string(),
             topic_type: "test_type".to_string(),
             host: Host::default(),
+            domain_id: 0,
+            entity_id: "".to_string(),
+            qos: None,
+            kind: Ros2NameKind::Topic,
+            action: None,
         };
         assert_eq!(state.has_publisher(test_publisher.clone()), false);
         state.add_publisher(test_publisher.clone());
@@ -508,13 +1905,19 @@ mod tests {
 
     #[test]
     fn remove_subscriber() {
-        let mut state = Ros2State::new();
+        let mut state = Ros2State::new(false);
         let test_subscriber: Ros2Subscriber = Ros2Subscriber {
             topic_name: "test_name".to_string(),
             guid: "test_guid".to_string(),
             node_name: "test_node".to_string(),
+            node_namespace: "/".to_string(),
             topic_type: "test_type".to_string(),
             host: Host::default(),
+            domain_id: 0,
+            entity_id: "".to_string(),
+            qos: None,
+            kind: Ros2NameKind::Topic,
+            action: None,
         };
         assert_eq!(state.has_subscriber(test_subscriber.clone()), false);
         state.add_subscriber(test_subscriber.clone());
@@ -522,4 +1925,566 @@ mod tests {
         state.remove_subscriber(test_subscriber.clone());
         assert_eq!(state.has_subscriber(test_subscriber.clone()), false);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn services_arent_topics() {
+        let mut state = Ros2State::new(false);
+        let publisher = |topic_name: &str, entity_id: &str, kind: Ros2NameKind| Ros2Publisher {
+            topic_name: topic_name.to_string(),
+            guid: "01.0f.aa".to_string(),
+            node_name: "server".to_string(),
+            node_namespace: "/".to_string(),
+            topic_type: "example_interfaces/srv/AddTwoInts".to_string(),
+            host: Host::default(),
+            domain_id: 0,
+            entity_id: entity_id.to_string(),
+            qos: None,
+            kind,
+            action: None,
+        };
+        state.add_publisher(publisher("/add_two_ints", "00.00.12.03", Ros2NameKind::ServiceReply));
+        state.add_publisher(publisher("/add_two_ints", "00.00.13.03", Ros2NameKind::Topic));
+        assert_eq!(state.topics().len(), 1);
+        assert_eq!(state.topics()[0].publishers_num(), 1);
+        assert_eq!(state.node(0, "/server").unwrap().publishers.len(), 2);
+        assert_eq!(state.node(0, "/server").unwrap().publishers[0].kind, Ros2NameKind::ServiceReply);
+
+        state.remove_publisher(publisher("/add_two_ints", "00.00.12.03", Ros2NameKind::ServiceReply));
+        assert_eq!(state.topics()[0].publishers_num(), 1);
+        assert_eq!(state.node(0, "/server").unwrap().publishers.len(), 1);
+    }
+
+    #[test]
+    fn domains() {
+        let mut state = Ros2State::new(false);
+        let publisher = |domain_id: u32| Ros2Publisher {
+            topic_name: "test_name".to_string(),
+            guid: "test_guid".to_string(),
+            node_name: "test_node".to_string(),
+            node_namespace: "/".to_string(),
+            topic_type: "test_type".to_string(),
+            host: Host::default(),
+            domain_id,
+            entity_id: "".to_string(),
+            qos: None,
+            kind: Ros2NameKind::Topic,
+            action: None,
+        };
+        state.add_publisher(publisher(1));
+        state.add_publisher(publisher(2));
+        // The same node in different domains are different nodes
+        assert_eq!(state.nodes().len(), 2);
+        assert_eq!(state.topics().len(), 2);
+        assert_eq!(state.domain_ids(), vec![1, 2]);
+
+        state.remove_publisher(publisher(2));
+        assert!(state.has_publisher(publisher(1)));
+        assert!(!state.has_publisher(publisher(2)));
+
+        let filtered = state.filter_domain(1);
+        assert_eq!(filtered.nodes().len(), 1);
+        assert_eq!(filtered.nodes()[0].domain_id, 1);
+        assert!(state.filter_domain(3).nodes().is_empty());
+    }
+
+    #[test]
+    fn contexts() {
+        let mut state = Ros2State::new(false);
+        let mut context = Ros2Context::new("01.0f.aa".to_string(), Host::default(), 0);
+        context.discovered_at = 10.0;
+        state.add_context(context.clone());
+        context.discovered_at = 20.0;
+        context.vendor = "eProsima Fast DDS".to_string();
+        state.add_context(context.clone());
+        assert_eq!(state.contexts().len(), 1);
+        assert_eq!(state.contexts()[0].discovered_at, 10.0);
+        assert_eq!(state.contexts()[0].vendor, "eProsima Fast DDS");
+
+        let publisher = |guid: &str, node_name: &str| Ros2Publisher {
+            topic_name: "test_name".to_string(),
+            guid: guid.to_string(),
+            node_name: node_name.to_string(),
+            node_namespace: "/".to_string(),
+            topic_type: "test_type".to_string(),
+            host: Host::default(),
+            domain_id: 0,
+            entity_id: "".to_string(),
+            qos: None,
+            kind: Ros2NameKind::Topic,
+            action: None,
+        };
+        state.add_publisher(publisher("01.0f.aa", "owned_node"));
+        state.add_publisher(publisher("01.0f.bb", "other_node"));
+        assert_eq!(state.context_nodes("01.0f.aa", 0).len(), 1);
+
+        // Removal of participant removes its nodes and endpoints
+        state.remove_context(context);
+        assert!(!state.has_context("01.0f.aa", 0));
+        assert!(!state.has_node("owned_node".to_string()));
+        assert!(state.has_node("other_node".to_string()));
+        assert!(!state.has_publisher(publisher("01.0f.aa", "owned_node")));
+    }
+
+    #[test]
+    fn process_info() {
+        let process = Ros2ProcessInfo::from_user_data("enclave=/robot;name=talker;");
+        assert_eq!(process.enclave, "/robot");
+        assert_eq!(process.properties.get("name").unwrap(), "talker");
+        assert_eq!(Ros2ProcessInfo::from_user_data(""), Ros2ProcessInfo::default());
+    }
+
+    #[test]
+    fn hosts() {
+        let mut state = Ros2State::new(false);
+        let context = |guid: &str, locator: &str, name: &str, domain_id: u32| {
+            let mut host = Host::from_locators(vec![locator.parse().unwrap()], vec![]);
+            host.name = name.to_string();
+            let mut context = Ros2Context::new(guid.to_string(), host, domain_id);
+            context.discovered_at = 10.0;
+            return context;
+        };
+        let robot = context("01.0f.aa.bb.01.00.00.00.01.00.00.00", "UDPv4:[192.168.1.10]:7412", "robot", 0);
+        state.add_context(robot.clone());
+        // Same FastDDS host id
+        state.add_context(context("01.0f.aa.bb.02.00.00.00.01.00.00.00", "SHM:[_]:7411", "unknown", 1));
+        // Same address, other vendor
+        state.add_context(context("01.10.11.22.33.44.55.66.77.88.99.00", "UDPv4:[192.168.1.10]:7410", "unknown", 0));
+        state.add_context(context("01.0f.cc.dd.01.00.00.00.01.00.00.00", "UDPv6:[fe80::1]:7412", "unknown", 0));
+        state.add_publisher(Ros2Publisher {
+            topic_name: "chatter".to_string(),
+            guid: robot.guid.clone(),
+            node_name: "talker".to_string(),
+            node_namespace: "/robot1".to_string(),
+            topic_type: "std_msgs::msg::dds_::String_".to_string(),
+            host: Host::default(),
+            domain_id: 0,
+            entity_id: "00.00.12.03".to_string(),
+            qos: None,
+            kind: Ros2NameKind::Topic,
+            action: None,
+        });
+
+        let hosts = state.hosts();
+        assert_eq!(hosts.len(), 2);
+        let host = hosts.iter().find(|host| host.name == "robot").unwrap();
+        assert_eq!(host.ips.iter().collect::<Vec<_>>(), vec!["192.168.1.10"]);
+        assert_eq!(host.transports.iter().collect::<Vec<_>>(), vec!["SHM", "UDPv4"]);
+        assert_eq!(host.contexts.len(), 3);
+        assert_eq!(host.domains.iter().collect::<Vec<_>>(), vec![&0, &1]);
+        assert_eq!(host.nodes.iter().collect::<Vec<_>>(), vec!["/robot1/talker"]);
+        assert_eq!(host.first_seen, 10.0);
+        let other = hosts.iter().find(|host| host.name == "unknown").unwrap();
+        assert_eq!(other.ips.iter().collect::<Vec<_>>(), vec!["fe80::1"]);
+        assert_eq!(state.filter_domain(1).hosts().len(), 1);
+        // Participants of other domains aren't listed in the domain
+        let host = state.filter_domain(1).hosts()[0].clone();
+        assert_eq!(host.contexts.iter().collect::<Vec<_>>(), vec!["01.0f.aa.bb.02.00.00.00.01.00.00.00"]);
+        assert_eq!(host.domains.iter().collect::<Vec<_>>(), vec![&1]);
+
+        // Loopback and docker bridge addresses are the same on other hosts
+        state.add_context(context("01.0f.ee.ff.01.00.00.00.01.00.00.00", "UDPv4:[172.17.0.1]:7412", "unknown", 2));
+        state.add_context(context("01.0f.ee.fe.01.00.00.00.01.00.00.00", "UDPv4:[172.17.0.1]:7414", "unknown", 2));
+        state.add_context(context("01.0f.ee.fd.01.00.00.00.01.00.00.00", "UDPv4:[127.0.0.1]:7416", "unknown", 2));
+        state.add_context(context("01.0f.ee.fc.01.00.00.00.01.00.00.00", "UDPv4:[127.0.0.1]:7418", "unknown", 2));
+        assert_eq!(state.filter_domain(2).hosts().len(), 4);
+
+        // Host is kept without its participants
+        state.remove_context(robot);
+        let hosts = state.hosts();
+        let host = hosts.iter().find(|host| host.name == "robot").unwrap();
+        assert_eq!(host.contexts.len(), 2);
+        assert!(host.nodes.is_empty());
+    }
+
+    #[test]
+    fn wire_activity() {
+        let mut state = Ros2State::new(false);
+        let prefix = "01.0f.aa.bb.01.00.00.00.01.00.00.00";
+        state.add_context(Ros2Context::new(prefix.to_string(), Host::default(), 3));
+        state.add_publisher(Ros2Publisher {
+            topic_name: "chatter".to_string(),
+            guid: prefix.to_string(),
+            node_name: "talker".to_string(),
+            node_namespace: "/".to_string(),
+            topic_type: "std_msgs::msg::dds_::String_".to_string(),
+            host: Host::default(),
+            domain_id: 3,
+            entity_id: "00.00.12.03".to_string(),
+            qos: None,
+            kind: Ros2NameKind::Topic,
+            action: None,
+        });
+        let activity = |guid: &str, data: u64| WireActivity { data, ..WireActivity::new(guid.to_string(), 10.0) };
+        state.add_wire_activity(vec![
+            activity(prefix, 0),
+            activity(format!("{}|00.00.12.03", prefix).as_str(), 2),
+            activity(format!("{}|00.01.00.c2", prefix).as_str(), 0),
+            activity("01.0f.cc.dd.01.00.00.00.01.00.00.00", 0),
+        ]);
+        state.add_wire_activity(vec![activity(format!("{}|00.00.12.03", prefix).as_str(), 3)]);
+
+        let wire = state.wire_activity();
+        let kinds: Vec<WireEntityKind> = wire.iter().map(|entity| entity.kind).collect();
+        assert_eq!(kinds, vec![WireEntityKind::Participant, WireEntityKind::Writer, WireEntityKind::Builtin, WireEntityKind::Unknown]);
+        assert_eq!(wire[1].activity.data, 5);
+        assert_eq!(wire[1].node.as_deref(), Some("/talker"));
+        assert_eq!(wire[1].topic_name.as_deref(), Some("chatter"));
+        assert_eq!(wire[3].domain_id, None);
+        assert_eq!(state.filter_domain(3).wire_activity().len(), 3);
+
+        // Participants which aren't discovered get the domain of their ports
+        state.add_wire_activity(vec![WireActivity { port_domain_id: Some(3), ..activity("01.0f.ee.ff.01.00.00.00.01.00.00.00", 1) }]);
+        assert_eq!(state.wire_activity()[4].domain_id, Some(3));
+        assert_eq!(state.wire_activity()[4].kind, WireEntityKind::Unknown);
+        assert_eq!(state.filter_domain(3).wire_activity().len(), 4);
+
+        // Activity of the removed participant and its endpoints is dropped
+        state.remove_context(Ros2Context::new(prefix.to_string(), Host::default(), 3));
+        assert_eq!(state.wire_activity().len(), 2);
+    }
+
+    #[test]
+    fn dds_statistics() {
+        let mut state = Ros2State::new(false);
+        let prefix = "01.0f.aa.bb.01.00.00.00.01.00.00.00";
+        let reader_prefix = "01.0f.cc.dd.01.00.00.00.01.00.00.00";
+        let locator: Locator = "UDPv4:[10.0.0.2]:7411".parse().unwrap();
+        let mut host = Host::default();
+        host.unicast_locators = vec![locator.clone()];
+        state.add_context(Ros2Context::new(prefix.to_string(), host, 3));
+        state.add_context(Ros2Context::new(reader_prefix.to_string(), Host::default(), 3));
+        let publisher = Ros2Publisher {
+            topic_name: "chatter".to_string(),
+            guid: prefix.to_string(),
+            node_name: "talker".to_string(),
+            node_namespace: "/".to_string(),
+            topic_type: "std_msgs::msg::dds_::String_".to_string(),
+            host: Host::default(),
+            domain_id: 3,
+            entity_id: "00.00.12.03".to_string(),
+            qos: None,
+            kind: Ros2NameKind::Topic,
+            action: None,
+        };
+        state.add_publisher(publisher.clone());
+        let writer = format!("{}|00.00.12.03", prefix);
+        let reader = format!("{}|00.00.12.04", reader_prefix);
+        let sample = |kind: DdsStatisticsKind, guid: &str| DdsStatisticsSample {
+            kind,
+            domain_id: 3,
+            guid: guid.to_string(),
+            remote_guid: None,
+            src_locator: None,
+            dst_locator: None,
+            value: 0.0,
+            count: 0,
+            bytes: 0,
+        };
+        state.add_dds_statistics(DdsStatisticsSample { remote_guid: Some(reader.clone()), value: 250000.0, ..sample(DdsStatisticsKind::HistoryLatency, writer.as_str()) });
+        state.add_dds_statistics(DdsStatisticsSample { value: 1024.0, ..sample(DdsStatisticsKind::PublicationThroughput, writer.as_str()) });
+        state.add_dds_statistics(DdsStatisticsSample { count: 4, ..sample(DdsStatisticsKind::HeartbeatCount, writer.as_str()) });
+        state.add_dds_statistics(DdsStatisticsSample { count: 7, ..sample(DdsStatisticsKind::HeartbeatCount, writer.as_str()) });
+        state.add_dds_statistics(DdsStatisticsSample { dst_locator: Some(locator.clone()), count: 2, bytes: 100, ..sample(DdsStatisticsKind::RtpsLost, prefix) });
+        state.add_dds_statistics(DdsStatisticsSample { dst_locator: Some(locator.clone()), count: 3, bytes: 150, ..sample(DdsStatisticsKind::RtpsLost, prefix) });
+        state.add_dds_statistics(DdsStatisticsSample { src_locator: Some(locator.clone()), dst_locator: Some(locator.clone()), value: 1000.0, ..sample(DdsStatisticsKind::NetworkLatency, prefix) });
+
+        let statistics = state.dds_statistics();
+        assert_eq!(statistics.len(), 2);
+        assert_eq!(statistics[0].kind, WireEntityKind::Participant);
+        assert_eq!(statistics[0].lost.len(), 1);
+        assert_eq!(statistics[0].lost[0].packets, 3);
+        assert_eq!(statistics[0].lost[0].bytes, 150);
+        assert_eq!(statistics[1].kind, WireEntityKind::Writer);
+        assert_eq!(statistics[1].node.as_deref(), Some("/talker"));
+        assert_eq!(statistics[1].topic_name.as_deref(), Some("chatter"));
+        assert_eq!(statistics[1].history_latency.get(&reader), Some(&250000.0));
+        assert_eq!(statistics[1].publication_throughput, Some(1024.0));
+        assert_eq!(statistics[1].heartbeats, Some(7));
+        assert_eq!(state.network_latency().len(), 1);
+        assert!(state.filter_domain(4).dds_statistics().is_empty());
+        assert_eq!(state.filter_domain(3).dds_statistics().len(), 2);
+
+        // Latency to the reader of the removed participant is dropped with it
+        state.remove_context(Ros2Context::new(reader_prefix.to_string(), Host::default(), 3));
+        assert!(state.dds_statistics()[1].history_latency.is_empty());
+        // Statistics of the removed endpoint too
+        state.remove_publisher(publisher);
+        assert_eq!(state.dds_statistics().len(), 1);
+        state.remove_context(Ros2Context::new(prefix.to_string(), Host::default(), 3));
+        assert!(state.dds_statistics().is_empty());
+        assert!(state.network_latency().is_empty());
+
+        // Statistics of participants which aren't discovered are limited
+        for i in 0..MAX_UNKNOWN_STATISTICS + 10 {
+            let guid = format!("01.0f.ee.ff.01.00.00.00.01.00.00.00|{:02x}.{:02x}.00.03", i / 256, i % 256);
+            state.add_dds_statistics(DdsStatisticsSample { count: 1, ..sample(DdsStatisticsKind::HeartbeatCount, guid.as_str()) });
+        }
+        assert_eq!(state.dds_statistics().len(), MAX_UNKNOWN_STATISTICS);
+    }
+
+    #[test]
+    fn topic_counts() {
+        let mut state = Ros2State::new(false);
+        let publisher = |entity_id: &str| Ros2Publisher {
+            topic_name: "/chatter".to_string(),
+            guid: "01.0f.aa".to_string(),
+            node_name: "talker".to_string(),
+            node_namespace: "/".to_string(),
+            topic_type: "std_msgs/msg/String".to_string(),
+            host: Host::default(),
+            domain_id: 0,
+            entity_id: entity_id.to_string(),
+            qos: None,
+            kind: Ros2NameKind::Topic,
+            action: None,
+        };
+        // Two publishers of the same type on the same node are different endpoints
+        state.add_publisher(publisher("00.00.01.03"));
+        state.add_publisher(publisher("00.00.02.03"));
+        state.add_publisher(publisher("00.00.02.03"));
+        assert_eq!(state.topics().len(), 1);
+        assert_eq!(state.topics()[0].publishers_num(), 2);
+        assert_eq!(state.nodes()[0].publishers.len(), 2);
+
+        state.remove_publisher(publisher("00.00.01.03"));
+        assert_eq!(state.topics()[0].publishers_num(), 1);
+        assert!(state.has_publisher(publisher("00.00.02.03")));
+        state.remove_publisher(publisher("00.00.02.03"));
+        assert!(state.topics().is_empty());
+    }
+
+    #[test]
+    fn indexed_lookups() {
+        let mut state = Ros2State::new(false);
+        // Several publishers and subscribers of the same node on different topics
+        for index in 0..6 {
+            state.add_publisher(test_publisher(index));
+            state.add_subscriber(test_subscriber(index));
+        }
+        assert_eq!(state.endpoints_num(), 12);
+        assert_eq!(state.node(0, "node_0").unwrap().publishers.len(), 2);
+        assert_eq!(state.topics_by_name(0, "/topic_1").len(), 1);
+        assert_eq!(state.topic(0, "/topic_1", "test_type").unwrap().publishers_num(), 3);
+        assert!(state.node(1, "node_0").is_none());
+
+        state.remove_node(state.node(0, "node_0").unwrap().clone());
+        assert!(state.node(0, "node_0").is_none());
+        assert_eq!(state.endpoints_num(), 8);
+        assert_eq!(state.topic(0, "/topic_0", "test_type").unwrap().publishers_num(), 2);
+        // Other nodes are still reachable after their positions changed
+        assert_eq!(state.node(0, "node_2").unwrap().subscribers.len(), 2);
+
+        state.add_context(Ros2Context::new("01.0f.01".to_string(), Host::default(), 0));
+        state.remove_context(Ros2Context::new("01.0f.01".to_string(), Host::default(), 0));
+        assert!(state.context(0, "01.0f.01").is_none());
+        assert!(state.node(0, "node_1").is_none());
+        assert_eq!(state.endpoints_num(), 4);
+        assert!(state.has_publisher(test_publisher(2)));
+        assert!(!state.has_publisher(test_publisher(1)));
+    }
+
+    #[test]
+    fn namespaces() {
+        assert_eq!(split_node_fqn("/robot1/arm/controller"), ("/robot1/arm".to_string(), "controller".to_string()));
+        assert_eq!(split_node_fqn("/talker"), ("/".to_string(), "talker".to_string()));
+        assert_eq!(split_node_fqn("talker"), ("/".to_string(), "talker".to_string()));
+        assert_eq!(node_fqn("robot1/", "camera"), "/robot1/camera");
+        assert_eq!(node_fqn("/", "camera"), "/camera");
+        assert!(is_in_namespace("/robot1/arm", "/robot1"));
+        assert!(!is_in_namespace("/robot10", "/robot1"));
+        let listed = vec!["/robot1/camera".to_string(), "/talker".to_string()];
+        assert!(contains_node(&listed, "/robot1/camera"));
+        assert!(contains_node(&listed, "talker"));
+        assert!(!contains_node(&listed, "/robot2/camera"));
+        assert!(!contains_node(&listed, "camera"));
+
+        let mut state = Ros2State::new(false);
+        let publisher = |namespace: &str, entity_id: &str| Ros2Publisher {
+            topic_name: "/image".to_string(),
+            guid: "01.0f.aa".to_string(),
+            node_name: "camera".to_string(),
+            node_namespace: namespace.to_string(),
+            topic_type: "sensor_msgs/msg/Image".to_string(),
+            host: Host::default(),
+            domain_id: 0,
+            entity_id: entity_id.to_string(),
+            qos: None,
+            kind: Ros2NameKind::Topic,
+            action: None,
+        };
+        // Nodes with the same name in different namespaces don't collide
+        state.add_publisher(publisher("/robot1", "00.00.01.03"));
+        state.add_publisher(publisher("/robot2", "00.00.02.03"));
+        state.add_node(Ros2Node::create("/robot1/arm/controller".to_string()));
+        assert_eq!(state.nodes().len(), 3);
+        assert_eq!(state.node(0, "/robot1/camera").unwrap().publishers.len(), 1);
+        assert_eq!(state.topics()[0].node_name, "/robot1/camera");
+
+        assert_eq!(state.find_nodes("camera", None).len(), 2);
+        assert_eq!(state.find_nodes("camera", Some("/robot2"))[0].fqn(), "/robot2/camera");
+        assert_eq!(state.find_nodes("/robot2/camera", None).len(), 1);
+        assert_eq!(state.find_nodes("robot2/camera", None)[0].fqn(), "/robot2/camera");
+        assert!(state.find_nodes("/camera", None).is_empty());
+        assert!(state.has_node("/robot1/arm/controller".to_string()));
+        assert!(!state.has_node("/controller".to_string()));
+
+        assert_eq!(state.nodes_in_namespace("/robot1").len(), 2);
+        assert_eq!(state.nodes_in_namespace("/robot1/arm").len(), 1);
+        assert_eq!(state.nodes_in_namespace("/").len(), 3);
+
+        state.remove_publisher(publisher("/robot1", "00.00.01.03"));
+        assert!(state.has_publisher(publisher("/robot2", "00.00.02.03")));
+    }
+
+    /// Bulk add and removal of a large graph with statistics leaves no entries behind. Removal of an endpoint
+    /// only touches entries of this endpoint, statistics of the others stay
+    #[test]
+    fn large_graph_indices() {
+        let count: u32 = 20000;
+        let prefix = |index: u32| format!("01.0f.{:04x}", index % 1000);
+        let endpoint = |index: u32| Ros2Publisher {
+            topic_name: format!("/topic_{}", index % 500),
+            guid: prefix(index),
+            node_name: format!("node_{}", index % 1000),
+            node_namespace: "/".to_string(),
+            topic_type: "test_type".to_string(),
+            host: Host::default(),
+            domain_id: 0,
+            entity_id: format!("{:08x}", index),
+            qos: None,
+            kind: Ros2NameKind::Topic,
+            action: None,
+        };
+        // Each endpoint reports history latency to the next one
+        let latency = |index: u32| DdsStatisticsSample {
+            kind: DdsStatisticsKind::HistoryLatency,
+            domain_id: 0,
+            guid: endpoint(index).endpoint_guid(),
+            remote_guid: Some(endpoint((index + 1) % count).endpoint_guid()),
+            src_locator: None,
+            dst_locator: None,
+            value: 1.0,
+            count: 0,
+            bytes: 0,
+        };
+
+        let mut state = Ros2State::new(false);
+        for index in 0..1000 {
+            state.add_context(Ros2Context::new(prefix(index), Host::default(), 0));
+        }
+        let contexts_len = state.index_len();
+        for index in 0..count {
+            state.add_publisher(endpoint(index));
+            state.add_dds_statistics(latency(index));
+        }
+        assert_eq!(state.endpoints_num(), count as usize);
+        assert_eq!(state.topics().len(), 500);
+        assert_eq!(state.dds_statistics().len(), count as usize);
+
+        state.remove_publisher(endpoint(1));
+        let statistics = state.dds_statistics();
+        assert_eq!(statistics.len(), count as usize - 1);
+        // Latency reported to the removed endpoint is gone, latency of the others stays
+        assert!(statistics.iter().find(|statistics| statistics.guid == endpoint(0).endpoint_guid()).unwrap().history_latency.is_empty());
+        assert_eq!(statistics.iter().find(|statistics| statistics.guid == endpoint(2).endpoint_guid()).unwrap().history_latency.len(), 1);
+
+        for index in 0..count {
+            state.remove_publisher(endpoint(index));
+        }
+        assert!(state.topics().is_empty());
+        assert_eq!(state.endpoints_num(), 0);
+        assert!(state.dds_statistics().is_empty());
+        // Nodes of the participants stay until the participants leave
+        assert_eq!(state.index_len(), contexts_len + state.nodes().len());
+    }
+
+    #[derive(Debug, Clone)]
+    enum Op {
+        AddPublisher(u8),
+        AddSubscriber(u8),
+        RemovePublisher(u8),
+        RemoveSubscriber(u8),
+    }
+
+    const ENDPOINTS: u8 = 8;
+    const TOPICS: u8 = 2;
+
+    fn op() -> impl Strategy<Value=Op> {
+        return prop_oneof![
+            (0..ENDPOINTS).prop_map(Op::AddPublisher),
+            (0..ENDPOINTS).prop_map(Op::AddSubscriber),
+            (0..ENDPOINTS).prop_map(Op::RemovePublisher),
+            (0..ENDPOINTS).prop_map(Op::RemoveSubscriber),
+        ];
+    }
+
+    /// Attributes of endpoint are defined by its index, as the real endpoint always has the same topic and node
+    fn test_publisher(index: u8) -> Ros2Publisher {
+        return Ros2Publisher {
+            topic_name: format!("/topic_{}", index % TOPICS),
+            guid: format!("01.0f.{:02x}", index % 3),
+            node_name: format!("node_{}", index % 3),
+            node_namespace: "/".to_string(),
+            topic_type: "test_type".to_string(),
+            host: Host::default(),
+            domain_id: 0,
+            entity_id: format!("00.00.{:02x}.03", index),
+            qos: None,
+            kind: Ros2NameKind::Topic,
+            action: None,
+        };
+    }
+
+    fn test_subscriber(index: u8) -> Ros2Subscriber {
+        return Ros2Subscriber {
+            topic_name: format!("/topic_{}", index % TOPICS),
+            node_name: format!("node_{}", index % 3),
+            node_namespace: "/".to_string(),
+            guid: format!("01.0f.{:02x}", index % 3),
+            topic_type: "test_type".to_string(),
+            host: Host::default(),
+            domain_id: 0,
+            entity_id: format!("00.00.{:02x}.04", index),
+            qos: None,
+            kind: Ros2NameKind::Topic,
+            action: None,
+        };
+    }
+
+    proptest! {
+        #[test]
+        fn random_add_remove(ops in proptest::collection::vec(op(), 0..64)) {
+            let mut state = Ros2State::new(false);
+            let mut publishers: HashSet<u8> = HashSet::new();
+            let mut subscribers: HashSet<u8> = HashSet::new();
+
+            for op in ops {
+                match op {
+                    Op::AddPublisher(index) => { state.add_publisher(test_publisher(index)); publishers.insert(index); }
+                    Op::AddSubscriber(index) => { state.add_subscriber(test_subscriber(index)); subscribers.insert(index); }
+                    Op::RemovePublisher(index) => { state.remove_publisher(test_publisher(index)); publishers.remove(&index); }
+                    Op::RemoveSubscriber(index) => { state.remove_subscriber(test_subscriber(index)); subscribers.remove(&index); }
+                }
+
+                for topic_index in 0..TOPICS {
+                    let name = format!("/topic_{}", topic_index);
+                    let expected_publishers = publishers.iter().filter(|index| *index % TOPICS == topic_index).count() as u64;
+                    let expected_subscribers = subscribers.iter().filter(|index| *index % TOPICS == topic_index).count() as u64;
+                    let topics: Vec<_> = state.topics().iter().filter(|topic| topic.name == name).collect();
+                    if expected_publishers + expected_subscribers == 0 {
+                        prop_assert!(topics.is_empty());
+                    } else {
+                        prop_assert_eq!(topics.len(), 1);
+                        prop_assert_eq!(topics[0].publishers_num(), expected_publishers);
+                        prop_assert_eq!(topics[0].subscribers_num(), expected_subscribers);
+                    }
+                }
+
+                let node_publishers: usize = state.nodes().iter().map(|node| node.publishers.len()).sum();
+                let node_subscribers: usize = state.nodes().iter().map(|node| node.subscribers.len()).sum();
+                prop_assert_eq!(node_publishers, publishers.len());
+                prop_assert_eq!(node_subscribers, subscribers.len());
+            }
+        }
+    }
+}
@@ -0,0 +1,496 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/// Decoder of RTPS messages (DDS-RTPS 2.x wire protocol). Only the header and submessages needed to see
+/// who is talking are decoded: DATA, DATA_FRAG, HEARTBEAT, ACKNACK, GAP, INFO_TS, INFO_SRC and INFO_DST.
+/// Other submessages are kept with their id and length.
+pub mod rtps {
+    use crate::network::network::hex_str_from_uc;
+
+    pub const RTPS_MAGIC: &[u8; 4] = b"RTPS";
+    pub const HEADER_SIZE: usize = 20;
+    const SUBMESSAGE_HEADER_SIZE: usize = 4;
+
+    pub const PAD: u8 = 0x01;
+    pub const ACKNACK: u8 = 0x06;
+    pub const HEARTBEAT: u8 = 0x07;
+    pub const GAP: u8 = 0x08;
+    pub const INFO_TS: u8 = 0x09;
+    pub const INFO_SRC: u8 = 0x0c;
+    pub const INFO_DST: u8 = 0x0e;
+    pub const DATA: u8 = 0x15;
+    pub const DATA_FRAG: u8 = 0x16;
+
+    /// Submessage flags
+    const FLAG_ENDIANNESS: u8 = 0x01;
+    const FLAG_INLINE_QOS: u8 = 0x02;
+    const FLAG_DATA: u8 = 0x04;
+    const FLAG_KEY: u8 = 0x08;
+    const FLAG_INVALIDATE: u8 = 0x02;
+
+    /// Sentinel which terminates parameter lists of inline QoS and discovery data
+    const PID_SENTINEL: u16 = 0x0001;
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Header {
+        /// Major and minor protocol version
+        pub version: (u8, u8),
+        pub vendor_id: [u8; 2],
+        pub guid_prefix: [u8; 12],
+    }
+
+    /// Entity id of the endpoint: 3 bytes of key and the entity kind. Builtin entities have 0xc0 bits in kind
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct EntityId(pub [u8; 4]);
+
+    impl EntityId {
+        pub const UNKNOWN: EntityId = EntityId([0, 0, 0, 0]);
+
+        pub fn is_builtin(&self) -> bool {
+            return self.0[3] & 0xc0 == 0xc0;
+        }
+
+        pub fn is_writer(&self) -> bool {
+            return matches!(self.0[3] & 0x0f, 0x02 | 0x03);
+        }
+
+        pub fn is_reader(&self) -> bool {
+            return matches!(self.0[3] & 0x0f, 0x04 | 0x07);
+        }
+    }
+
+    /// GUID of the endpoint in the same form as in `Ros2State`: `prefix|entity_id`
+    pub fn endpoint_guid(guid_prefix: &[u8; 12], entity_id: &EntityId) -> String {
+        return format!("{}|{}", hex_str_from_uc(*guid_prefix), hex_str_from_uc(entity_id.0));
+    }
+
+    /// Inline QoS or serialized data of DATA submessage
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Parameter {
+        pub id: u16,
+        pub value: Vec<u8>,
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum Submessage {
+        Data {
+            reader_id: EntityId,
+            writer_id: EntityId,
+            writer_sn: i64,
+            inline_qos: Vec<Parameter>,
+            /// Serialized data or key, including its encapsulation header
+            payload: Vec<u8>,
+            /// Payload is a serialized key, e.g. dispose or unregister
+            is_key: bool,
+        },
+        DataFrag {
+            reader_id: EntityId,
+            writer_id: EntityId,
+            writer_sn: i64,
+            /// Number of the first fragment in this submessage, starts from 1
+            fragment_start: u32,
+            fragments_num: u16,
+            fragment_size: u16,
+            sample_size: u32,
+            payload: Vec<u8>,
+        },
+        Heartbeat {
+            reader_id: EntityId,
+            writer_id: EntityId,
+            first_sn: i64,
+            last_sn: i64,
+            count: u32,
+        },
+        AckNack {
+            reader_id: EntityId,
+            writer_id: EntityId,
+            /// The first sequence number which isn't acknowledged yet
+            base_sn: i64,
+            /// Sequence numbers requested again by the reader
+            missing: Vec<i64>,
+            count: u32,
+        },
+        Gap {
+            reader_id: EntityId,
+            writer_id: EntityId,
+            gap_start: i64,
+            gap_list_base: i64,
+        },
+        InfoTs {
+            /// Seconds since unix epoch, None if the timestamp is invalidated
+            timestamp: Option<f64>,
+        },
+        InfoSrc {
+            guid_prefix: [u8; 12],
+        },
+        InfoDst {
+            guid_prefix: [u8; 12],
+        },
+        Other {
+            id: u8,
+            length: usize,
+        },
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Message {
+        pub header: Header,
+        pub submessages: Vec<Submessage>,
+    }
+
+    /// Check that the datagram starts with RTPS header
+    pub fn is_rtps(datagram: &[u8]) -> bool {
+        return datagram.len() >= HEADER_SIZE && &datagram[0..4] == RTPS_MAGIC;
+    }
+
+    /// Reader of submessage fields in the submessage endianness
+    struct Cursor<'a> {
+        data: &'a [u8],
+        pos: usize,
+        little_endian: bool,
+    }
+
+    impl<'a> Cursor<'a> {
+        fn new(data: &'a [u8], little_endian: bool) -> Cursor<'a> {
+            return Cursor { data, pos: 0, little_endian };
+        }
+
+        fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+            if self.pos + len > self.data.len() {
+                return Err(format!("Submessage is truncated: {} bytes needed at {}, {} available", len, self.pos, self.data.len()));
+            }
+            let bytes = &self.data[self.pos..self.pos + len];
+            self.pos += len;
+            return Ok(bytes);
+        }
+
+        fn u16(&mut self) -> Result<u16, String> {
+            let bytes: [u8; 2] = self.bytes(2)?.try_into().unwrap();
+            return Ok(if self.little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) });
+        }
+
+        fn u32(&mut self) -> Result<u32, String> {
+            let bytes: [u8; 4] = self.bytes(4)?.try_into().unwrap();
+            return Ok(if self.little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) });
+        }
+
+        fn i32(&mut self) -> Result<i32, String> {
+            return Ok(self.u32()? as i32);
+        }
+
+        /// Entity ids are octet arrays, they don't depend on endianness
+        fn entity_id(&mut self) -> Result<EntityId, String> {
+            return Ok(EntityId(self.bytes(4)?.try_into().unwrap()));
+        }
+
+        fn guid_prefix(&mut self) -> Result<[u8; 12], String> {
+            return Ok(self.bytes(12)?.try_into().unwrap());
+        }
+
+        /// Sequence number is sent as high signed and low unsigned 32 bit parts
+        fn sequence_number(&mut self) -> Result<i64, String> {
+            let high = self.i32()? as i64;
+            let low = self.u32()? as i64;
+            return Ok((high << 32) | low);
+        }
+
+        /// Sequence number set: bitmap base, number of bits and bitmap words. Returns base and set numbers
+        fn sequence_number_set(&mut self) -> Result<(i64, Vec<i64>), String> {
+            let base = self.sequence_number()?;
+            let bits = self.u32()?;
+            if bits > 256 {
+                return Err(format!("Invalid sequence number set of {} bits", bits));
+            }
+            let mut numbers = Vec::new();
+            for word in 0..(bits + 31) / 32 {
+                let value = self.u32()?;
+                for bit in 0..32 {
+                    let offset = word * 32 + bit;
+                    if offset < bits && value & (0x8000_0000 >> bit) != 0 {
+                        numbers.push(base + offset as i64);
+                    }
+                }
+            }
+            return Ok((base, numbers));
+        }
+
+        /// Parameter list terminated by sentinel. Lengths are aligned to 4 bytes
+        fn parameter_list(&mut self) -> Result<Vec<Parameter>, String> {
+            let mut parameters = Vec::new();
+            loop {
+                let id = self.u16()?;
+                let length = self.u16()? as usize;
+                if id == PID_SENTINEL {
+                    return Ok(parameters);
+                }
+                parameters.push(Parameter { id, value: self.bytes(length)?.to_vec() });
+            }
+        }
+
+        fn rest(&mut self) -> &'a [u8] {
+            let rest = &self.data[self.pos.min(self.data.len())..];
+            self.pos = self.data.len();
+            return rest;
+        }
+    }
+
+    /// Parse RTPS message. Decoding stops at the first malformed submessage, already decoded ones are kept
+    /// # Arguments
+    ///
+    /// * `datagram`: UDP payload starting with RTPS header
+    ///
+    /// returns: Result<Message, String>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn parse_message(datagram: &[u8]) -> Result<Message, String> {
+        if !is_rtps(datagram) {
+            return Err("Not an RTPS message".to_string());
+        }
+        let header = Header {
+            version: (datagram[4], datagram[5]),
+            vendor_id: [datagram[6], datagram[7]],
+            guid_prefix: datagram[8..20].try_into().unwrap(),
+        };
+
+        let mut submessages = Vec::new();
+        let mut pos = HEADER_SIZE;
+        while pos + SUBMESSAGE_HEADER_SIZE <= datagram.len() {
+            let id = datagram[pos];
+            let flags = datagram[pos + 1];
+            let little_endian = flags & FLAG_ENDIANNESS != 0;
+            let length_bytes = [datagram[pos + 2], datagram[pos + 3]];
+            let length = if little_endian { u16::from_le_bytes(length_bytes) } else { u16::from_be_bytes(length_bytes) } as usize;
+            let body_start = pos + SUBMESSAGE_HEADER_SIZE;
+            // Zero length means the submessage extends to the end of the message, except PAD and INFO_TS
+            let body_end = if length == 0 && id != PAD && id != INFO_TS { datagram.len() } else { body_start + length };
+            if body_end > datagram.len() {
+                break;
+            }
+            match parse_submessage(id, flags, &datagram[body_start..body_end]) {
+                Ok(submessage) => submessages.push(submessage),
+                Err(_) => break
+            }
+            pos = body_end;
+        }
+        return Ok(Message { header, submessages });
+    }
+
+    /// Parameter list of serialized SPDP and SEDP data. Payload starts with PL_CDR encapsulation header,
+    /// which defines endianness of parameter values
+    /// # Arguments
+    ///
+    /// * `payload`: serialized data of DATA submessage
+    ///
+    /// returns: Result<(Vec<Parameter>, bool), String>, parameters and little endian flag
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn parse_parameter_list(payload: &[u8]) -> Result<(Vec<Parameter>, bool), String> {
+        if payload.len() < 4 {
+            return Err("Serialized data is too short".to_string());
+        }
+        // PL_CDR_BE, PL_CDR_LE and their XCDR2 variants
+        let little_endian = match [payload[0], payload[1]] {
+            [0x00, 0x02] | [0x00, 0x12] => false,
+            [0x00, 0x03] | [0x00, 0x13] => true,
+            [a, b] => return Err(format!("Not a parameter list encapsulation {:02x}{:02x}", a, b))
+        };
+        let mut cursor = Cursor::new(&payload[4..], little_endian);
+        return Ok((cursor.parameter_list()?, little_endian));
+    }
+
+    fn parse_submessage(id: u8, flags: u8, body: &[u8]) -> Result<Submessage, String> {
+        let mut cursor = Cursor::new(body, flags & FLAG_ENDIANNESS != 0);
+        return match id {
+            DATA => {
+                let _extra_flags = cursor.u16()?;
+                let octets_to_inline_qos = cursor.u16()? as usize;
+                let start = cursor.pos;
+                let reader_id = cursor.entity_id()?;
+                let writer_id = cursor.entity_id()?;
+                let writer_sn = cursor.sequence_number()?;
+                cursor.pos = start + octets_to_inline_qos;
+                let inline_qos = if flags & FLAG_INLINE_QOS != 0 { cursor.parameter_list()? } else { vec![] };
+                let has_payload = flags & (FLAG_DATA | FLAG_KEY) != 0;
+                let payload = if has_payload { cursor.rest().to_vec() } else { vec![] };
+                Ok(Submessage::Data { reader_id, writer_id, writer_sn, inline_qos, payload, is_key: flags & FLAG_KEY != 0 })
+            }
+            DATA_FRAG => {
+                let _extra_flags = cursor.u16()?;
+                let octets_to_inline_qos = cursor.u16()? as usize;
+                let start = cursor.pos;
+                let reader_id = cursor.entity_id()?;
+                let writer_id = cursor.entity_id()?;
+                let writer_sn = cursor.sequence_number()?;
+                let fragment_start = cursor.u32()?;
+                let fragments_num = cursor.u16()?;
+                let fragment_size = cursor.u16()?;
+                let sample_size = cursor.u32()?;
+                cursor.pos = start + octets_to_inline_qos;
+                if flags & FLAG_INLINE_QOS != 0 {
+                    cursor.parameter_list()?;
+                }
+                let payload = cursor.rest().to_vec();
+                Ok(Submessage::DataFrag { reader_id, writer_id, writer_sn, fragment_start, fragments_num, fragment_size, sample_size, payload })
+            }
+            HEARTBEAT => Ok(Submessage::Heartbeat {
+                reader_id: cursor.entity_id()?,
+                writer_id: cursor.entity_id()?,
+                first_sn: cursor.sequence_number()?,
+                last_sn: cursor.sequence_number()?,
+                count: cursor.u32()?,
+            }),
+            ACKNACK => {
+                let reader_id = cursor.entity_id()?;
+                let writer_id = cursor.entity_id()?;
+                let (base_sn, missing) = cursor.sequence_number_set()?;
+                let count = cursor.u32()?;
+                Ok(Submessage::AckNack { reader_id, writer_id, base_sn, missing, count })
+            }
+            GAP => {
+                let reader_id = cursor.entity_id()?;
+                let writer_id = cursor.entity_id()?;
+                let gap_start = cursor.sequence_number()?;
+                let (gap_list_base, _) = cursor.sequence_number_set()?;
+                Ok(Submessage::Gap { reader_id, writer_id, gap_start, gap_list_base })
+            }
+            INFO_TS => {
+                let timestamp = if flags & FLAG_INVALIDATE != 0 {
+                    None
+                } else {
+                    let seconds = cursor.i32()? as f64;
+                    let fraction = cursor.u32()? as f64 / 4294967296.0;
+                    Some(seconds + fraction)
+                };
+                Ok(Submessage::InfoTs { timestamp })
+            }
+            INFO_SRC => {
+                let _unused = cursor.u32()?;
+                let _version = cursor.u16()?;
+                let _vendor_id = cursor.u16()?;
+                Ok(Submessage::InfoSrc { guid_prefix: cursor.guid_prefix()? })
+            }
+            INFO_DST => Ok(Submessage::InfoDst { guid_prefix: cursor.guid_prefix()? }),
+            _ => Ok(Submessage::Other { id, length: body.len() })
+        };
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::rtps::rtps::{endpoint_guid, is_rtps, parse_message, EntityId, Submessage, ACKNACK, DATA, GAP, HEARTBEAT, INFO_DST, INFO_TS};
+
+    pub(crate) const PREFIX: [u8; 12] = [0x01, 0x0f, 0xaa, 0xbb, 0x39, 0x30, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00];
+    pub(crate) const WRITER: [u8; 4] = [0x00, 0x00, 0x12, 0x03];
+    pub(crate) const READER: [u8; 4] = [0x00, 0x00, 0x13, 0x04];
+
+    pub(crate) fn header(prefix: [u8; 12]) -> Vec<u8> {
+        let mut message = b"RTPS".to_vec();
+        message.extend_from_slice(&[2, 3, 0x01, 0x0f]);
+        message.extend_from_slice(&prefix);
+        return message;
+    }
+
+    /// Little endian submessage
+    pub(crate) fn submessage(message: &mut Vec<u8>, id: u8, flags: u8, body: &[u8]) {
+        message.push(id);
+        message.push(flags | 0x01);
+        message.extend_from_slice(&(body.len() as u16).to_le_bytes());
+        message.extend_from_slice(body);
+    }
+
+    fn sn(value: i64) -> Vec<u8> {
+        let mut bytes = ((value >> 32) as i32).to_le_bytes().to_vec();
+        bytes.extend_from_slice(&(value as u32).to_le_bytes());
+        return bytes;
+    }
+
+    /// DATA submessage with serialized payload and without inline QoS
+    pub(crate) fn data(message: &mut Vec<u8>, writer: [u8; 4], writer_sn: i64, payload: &[u8]) {
+        let mut body = vec![0, 0, 16, 0];
+        body.extend_from_slice(&[0, 0, 0, 0]);
+        body.extend_from_slice(&writer);
+        body.extend(sn(writer_sn));
+        body.extend_from_slice(payload);
+        submessage(message, DATA, 0x04, body.as_slice());
+    }
+
+    #[test]
+    fn decode_submessages() {
+        let mut message = header(PREFIX);
+        let mut info_ts = 1700000000i32.to_le_bytes().to_vec();
+        info_ts.extend_from_slice(&0x8000_0000u32.to_le_bytes());
+        submessage(&mut message, INFO_TS, 0, info_ts.as_slice());
+        data(&mut message, WRITER, 5, &[0x00, 0x01, 0x00, 0x00, b'h', b'i']);
+
+        let mut heartbeat = vec![0, 0, 0, 0];
+        heartbeat.extend_from_slice(&WRITER);
+        heartbeat.extend(sn(1));
+        heartbeat.extend(sn(5));
+        heartbeat.extend_from_slice(&7u32.to_le_bytes());
+        submessage(&mut message, HEARTBEAT, 0x02, heartbeat.as_slice());
+
+        // Reader acknowledges up to 3 and requests 3 and 5 again
+        let mut acknack = READER.to_vec();
+        acknack.extend_from_slice(&WRITER);
+        acknack.extend(sn(3));
+        acknack.extend_from_slice(&3u32.to_le_bytes());
+        acknack.extend_from_slice(&0xa000_0000u32.to_le_bytes());
+        acknack.extend_from_slice(&2u32.to_le_bytes());
+        submessage(&mut message, ACKNACK, 0, acknack.as_slice());
+
+        let mut gap = READER.to_vec();
+        gap.extend_from_slice(&WRITER);
+        gap.extend(sn(2));
+        gap.extend(sn(4));
+        gap.extend_from_slice(&0u32.to_le_bytes());
+        submessage(&mut message, GAP, 0, gap.as_slice());
+        submessage(&mut message, INFO_DST, 0, &[0x01; 12]);
+        submessage(&mut message, 0x80, 0, &[0; 8]);
+
+        assert!(is_rtps(message.as_slice()));
+        let parsed = parse_message(message.as_slice()).unwrap();
+        assert_eq!(parsed.header.guid_prefix, PREFIX);
+        assert_eq!(parsed.header.version, (2, 3));
+        assert_eq!(parsed.submessages, vec![
+            Submessage::InfoTs { timestamp: Some(1700000000.5) },
+            Submessage::Data {
+                reader_id: EntityId::UNKNOWN,
+                writer_id: EntityId(WRITER),
+                writer_sn: 5,
+                inline_qos: vec![],
+                payload: vec![0x00, 0x01, 0x00, 0x00, b'h', b'i'],
+                is_key: false,
+            },
+            Submessage::Heartbeat { reader_id: EntityId::UNKNOWN, writer_id: EntityId(WRITER), first_sn: 1, last_sn: 5, count: 7 },
+            Submessage::AckNack { reader_id: EntityId(READER), writer_id: EntityId(WRITER), base_sn: 3, missing: vec![3, 5], count: 2 },
+            Submessage::Gap { reader_id: EntityId(READER), writer_id: EntityId(WRITER), gap_start: 2, gap_list_base: 4 },
+            Submessage::InfoDst { guid_prefix: [0x01; 12] },
+            Submessage::Other { id: 0x80, length: 8 },
+        ]);
+        assert_eq!(endpoint_guid(&PREFIX, &EntityId(WRITER)), "01.0f.aa.bb.39.30.00.00.01.00.00.00|00.00.12.03");
+        assert!(EntityId(WRITER).is_writer() && EntityId(READER).is_reader());
+        assert!(EntityId([0x00, 0x01, 0x00, 0xc2]).is_builtin());
+    }
+
+    #[test]
+    fn malformed_messages() {
+        assert!(parse_message(b"RTPX\x02\x03\x01\x0f000000000000").is_err());
+        // Truncated submessage stops decoding, previous submessages are kept
+        let mut message = header(PREFIX);
+        data(&mut message, WRITER, 1, &[0x00, 0x01, 0x00, 0x00]);
+        message.extend_from_slice(&[HEARTBEAT, 0x01, 28, 0, 0, 0]);
+        let parsed = parse_message(message.as_slice()).unwrap();
+        assert_eq!(parsed.submessages.len(), 1);
+    }
+}
@@ -0,0 +1,369 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/// Network traffic of participants, hosts and topics over time windows. Batches of captured activity are kept
+/// in history and summed up over the requested window by `traffic` command.
+pub mod traffic {
+    use std::collections::{BTreeMap, HashMap, VecDeque};
+    use std::net::SocketAddr;
+    use std::sync::Mutex;
+    use serde::{Deserialize, Serialize};
+    use crate::capture::capture::WireActivity;
+    use crate::ros2entites::ros2entities::{Ros2State, WireEntityKind};
+
+    /// Batches older than this are dropped
+    pub const HISTORY_SECS: f64 = 3600.0;
+
+    /// Activity of GUIDs captured since the previous batch
+    struct TrafficBatch {
+        /// Capture time of the latest activity of the batch, seconds since unix epoch
+        timestamp: f64,
+        activity: Vec<WireActivity>,
+    }
+
+    /// Traffic sent and received by the participant
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct ParticipantTraffic {
+        /// GUID prefix of the participant
+        pub guid: String,
+        /// Participant name, empty for most ROS2 contexts
+        pub name: String,
+        /// None if the participant isn't discovered
+        pub domain_id: Option<u32>,
+        /// Name of the host, `unknown` if it isn't discovered
+        pub host: String,
+        /// Source address of the participant traffic, `ip:port`
+        pub source: String,
+        /// Fully qualified names of the participant nodes
+        pub nodes: Vec<String>,
+        pub messages: u64,
+        pub bytes: u64,
+        pub received_messages: u64,
+        pub received_bytes: u64,
+        /// Bytes sent per second averaged over the window
+        pub bytes_per_sec: f64,
+    }
+
+    /// Traffic of all participants of the host
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct HostTraffic {
+        pub name: String,
+        pub ips: Vec<String>,
+        /// Number of participants which sent or received traffic in the window
+        pub participants: usize,
+        pub bytes: u64,
+        pub received_bytes: u64,
+        pub bytes_per_sec: f64,
+        pub received_bytes_per_sec: f64,
+    }
+
+    /// Data published to the topic by its writers
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct TopicTraffic {
+        pub topic_name: String,
+        pub topic_type: String,
+        pub domain_id: u32,
+        /// Number of writers which published in the window
+        pub writers: usize,
+        /// DATA and DATA_FRAG submessages
+        pub samples: u64,
+        pub bytes: u64,
+        pub samples_per_sec: f64,
+        pub bytes_per_sec: f64,
+    }
+
+    /// Traffic summed up over the window
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct TrafficReport {
+        /// Length of the window in seconds
+        pub window: f64,
+        /// Capture time of the window end, seconds since unix epoch. It is the time of the latest captured traffic,
+        /// so windows of replayed and analyzed captures are in the capture time
+        pub end: f64,
+        /// Participants sorted by sent bytes
+        pub top_talkers: Vec<ParticipantTraffic>,
+        /// Hosts sorted by sent bytes
+        pub hosts: Vec<HostTraffic>,
+        /// Topics sorted by published bytes
+        pub topics: Vec<TopicTraffic>,
+    }
+
+    pub struct TrafficMonitor {
+        history: Mutex<VecDeque<TrafficBatch>>,
+        history_secs: f64,
+        /// Activity of each GUID accumulated since the monitor start
+        totals: Mutex<BTreeMap<String, WireActivity>>,
+    }
+
+    fn source_ip(source: &str) -> Option<String> {
+        return source.parse::<SocketAddr>().ok().map(|address| address.ip().to_string());
+    }
+
+    fn sort_by_bytes<T, F: Fn(&T) -> u64>(items: &mut Vec<T>, bytes: F) {
+        items.sort_by(|a, b| bytes(b).cmp(&bytes(a)));
+    }
+
+    impl TrafficMonitor {
+        pub fn new(history_secs: f64) -> TrafficMonitor {
+            return TrafficMonitor {
+                history: Mutex::new(VecDeque::new()),
+                history_secs,
+                totals: Mutex::new(BTreeMap::new()),
+            };
+        }
+
+        /// Store the batch of captured activity. Batches are expected in the capture order
+        pub fn record(&self, activity: &Vec<WireActivity>) {
+            let timestamp = match activity.iter().map(|activity| activity.last_seen).reduce(f64::max) {
+                Some(timestamp) => timestamp,
+                None => return
+            };
+            let mut totals = self.totals.lock().unwrap();
+            for activity in activity.iter() {
+                match totals.get_mut(&activity.guid) {
+                    Some(known) => known.merge(activity),
+                    None => {
+                        totals.insert(activity.guid.clone(), activity.clone());
+                    }
+                }
+            }
+            drop(totals);
+            let mut history = self.history.lock().unwrap();
+            history.push_back(TrafficBatch { timestamp, activity: activity.clone() });
+            let oldest = timestamp - self.history_secs;
+            while history.front().map_or(false, |batch| batch.timestamp < oldest) {
+                history.pop_front();
+            }
+        }
+
+        /// Activity of each GUID accumulated since the monitor start, sorted by GUID
+        pub fn totals(&self) -> Vec<WireActivity> {
+            return self.totals.lock().unwrap().values().cloned().collect();
+        }
+
+        /// Drop accumulated activity of the removed participant and its endpoints
+        pub fn forget(&self, context_guid: &str) {
+            self.totals.lock().unwrap().retain(|guid, _| guid.split('|').next() != Some(context_guid));
+        }
+
+        /// Activity of each GUID summed up over the last `window` seconds before the latest batch
+        /// # Arguments
+        ///
+        /// * `window`: length of the window in seconds
+        ///
+        /// returns: (Vec<WireActivity>, f64), activity sorted by GUID and the window end
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn window(&self, window: f64) -> (Vec<WireActivity>, f64) {
+            let history = self.history.lock().unwrap();
+            let end = match history.back() {
+                Some(batch) => batch.timestamp,
+                None => return (vec![], 0.0)
+            };
+            let mut merged: BTreeMap<String, WireActivity> = BTreeMap::new();
+            for batch in history.iter().filter(|batch| batch.timestamp > end - window) {
+                for activity in batch.activity.iter() {
+                    match merged.get_mut(&activity.guid) {
+                        Some(known) => known.merge(activity),
+                        None => {
+                            merged.insert(activity.guid.clone(), activity.clone());
+                        }
+                    }
+                }
+            }
+            return (merged.into_values().collect(), end);
+        }
+
+        /// Top talkers, per-host bandwidth and per-topic throughput over the window
+        /// # Arguments
+        ///
+        /// * `state`: discovered graph which resolves GUIDs to participants, hosts and topics
+        /// * `window`: length of the window in seconds
+        /// * `limit`: max number of top talkers
+        /// * `only_discovered`: skip traffic of participants which aren't in the state, e.g. for the single domain view
+        ///
+        /// returns: TrafficReport
+        ///
+        /// # Examples
+        ///
+        /// ```
+        ///
+        /// ```
+        pub fn report(&self, state: &Ros2State, window: f64, limit: usize, only_discovered: bool) -> TrafficReport {
+            let (activity, end) = self.window(window);
+            let hosts = state.hosts();
+            let mut participants: Vec<ParticipantTraffic> = Vec::new();
+            let mut host_traffic: HashMap<String, HostTraffic> = HashMap::new();
+            let mut topics: BTreeMap<(u32, String), TopicTraffic> = BTreeMap::new();
+
+            for activity in activity.iter() {
+                let entity = state.resolve_wire(activity);
+                if only_discovered && entity.domain_id.is_none() {
+                    continue;
+                }
+                match entity.kind {
+                    WireEntityKind::Participant | WireEntityKind::Unknown if !activity.guid.contains('|') => {
+                        let context = state.contexts().iter().find(|context| context.guid == activity.guid);
+                        let ip = source_ip(activity.source.as_str());
+                        // Undiscovered participants are attributed to hosts by the source address
+                        let host = hosts.iter().find(|host| host.contexts.contains(&activity.guid)
+                            || ip.as_ref().map_or(false, |ip| host.ips.contains(ip)));
+                        let (host_key, host_name, ips) = match (host, ip.as_ref()) {
+                            (Some(host), _) => (format!("host {}", host.ips.iter().cloned().collect::<Vec<String>>().join(",")),
+                                                host.name.clone(), host.ips.iter().cloned().collect()),
+                            (None, Some(ip)) => (format!("ip {}", ip), "unknown".to_string(), vec![ip.clone()]),
+                            (None, None) => ("unknown".to_string(), "unknown".to_string(), vec![])
+                        };
+
+                        let host_entry = host_traffic.entry(host_key).or_insert_with(|| HostTraffic {
+                            name: host_name.clone(),
+                            ips,
+                            participants: 0,
+                            bytes: 0,
+                            received_bytes: 0,
+                            bytes_per_sec: 0.0,
+                            received_bytes_per_sec: 0.0,
+                        });
+                        host_entry.participants += 1;
+                        host_entry.bytes += activity.bytes;
+                        host_entry.received_bytes += activity.received_bytes;
+
+                        participants.push(ParticipantTraffic {
+                            guid: activity.guid.clone(),
+                            name: context.map_or("".to_string(), |context| context.name.clone()),
+                            domain_id: entity.domain_id,
+                            host: host_name,
+                            source: activity.source.clone(),
+                            nodes: state.nodes().iter().filter(|node| node.context_guid == activity.guid).map(|node| node.fqn()).collect(),
+                            messages: activity.messages,
+                            bytes: activity.bytes,
+                            received_messages: activity.received_messages,
+                            received_bytes: activity.received_bytes,
+                            bytes_per_sec: activity.bytes as f64 / window,
+                        });
+                    }
+                    WireEntityKind::Writer if activity.data > 0 => {
+                        let (domain_id, topic_name) = (entity.domain_id.unwrap_or(0), entity.topic_name.clone().unwrap_or_default());
+                        let topic = topics.entry((domain_id, topic_name.clone())).or_insert_with(|| TopicTraffic {
+                            topic_name,
+                            topic_type: entity.topic_type.clone().unwrap_or_default(),
+                            domain_id,
+                            writers: 0,
+                            samples: 0,
+                            bytes: 0,
+                            samples_per_sec: 0.0,
+                            bytes_per_sec: 0.0,
+                        });
+                        topic.writers += 1;
+                        topic.samples += activity.data;
+                        topic.bytes += activity.data_bytes;
+                    }
+                    _ => {}
+                }
+            }
+
+            sort_by_bytes(&mut participants, |participant| participant.bytes);
+            participants.truncate(limit);
+            let mut hosts: Vec<HostTraffic> = host_traffic.into_values().map(|mut host| {
+                host.bytes_per_sec = host.bytes as f64 / window;
+                host.received_bytes_per_sec = host.received_bytes as f64 / window;
+                return host;
+            }).collect();
+            sort_by_bytes(&mut hosts, |host| host.bytes);
+            let mut topics: Vec<TopicTraffic> = topics.into_values().map(|mut topic| {
+                topic.samples_per_sec = topic.samples as f64 / window;
+                topic.bytes_per_sec = topic.bytes as f64 / window;
+                return topic;
+            }).collect();
+            sort_by_bytes(&mut topics, |topic| topic.bytes);
+
+            return TrafficReport { window, end, top_talkers: participants, hosts, topics };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::capture::capture::WireActivity;
+    use crate::network::network::Ros2NameKind;
+    use crate::ros2entites::ros2entities::{Host, Ros2Context, Ros2Publisher, Ros2State};
+    use crate::traffic::traffic::TrafficMonitor;
+
+    const PREFIX: &str = "01.0f.aa.bb.01.00.00.00.01.00.00.00";
+    const OTHER_PREFIX: &str = "01.0f.cc.dd.01.00.00.00.01.00.00.00";
+
+    fn activity(guid: &str, timestamp: f64, bytes: u64, data: u64) -> WireActivity {
+        return WireActivity {
+            source: "127.0.0.1:45000".to_string(),
+            bytes,
+            messages: 1,
+            data,
+            data_bytes: data * 100,
+            ..WireActivity::new(guid.to_string(), timestamp)
+        };
+    }
+
+    #[test]
+    fn traffic_report() {
+        let mut state = Ros2State::new(false);
+        state.add_context(Ros2Context::new(PREFIX.to_string(), Host::default(), 3));
+        state.add_publisher(Ros2Publisher {
+            topic_name: "rt/chatter".to_string(),
+            guid: PREFIX.to_string(),
+            node_name: "talker".to_string(),
+            node_namespace: "/".to_string(),
+            topic_type: "std_msgs::msg::dds_::String_".to_string(),
+            host: Host::default(),
+            domain_id: 3,
+            entity_id: "00.00.12.03".to_string(),
+            qos: None,
+            kind: Ros2NameKind::Topic,
+            action: None,
+        });
+
+        let monitor = TrafficMonitor::new(100.0);
+        let writer = format!("{}|00.00.12.03", PREFIX);
+        monitor.record(&vec![activity(PREFIX, 10.0, 1000, 0), activity(writer.as_str(), 10.0, 0, 2)]);
+        monitor.record(&vec![
+            activity(PREFIX, 20.0, 500, 0),
+            activity(writer.as_str(), 20.0, 0, 1),
+            WireActivity { received_bytes: 300, ..activity(OTHER_PREFIX, 20.0, 2000, 0) },
+        ]);
+
+        let report = monitor.report(&state, 5.0, 10, false);
+        assert_eq!(report.end, 20.0);
+        let talkers: Vec<(&str, u64)> = report.top_talkers.iter().map(|talker| (talker.guid.as_str(), talker.bytes)).collect();
+        assert_eq!(talkers, vec![(OTHER_PREFIX, 2000), (PREFIX, 500)]);
+        assert_eq!(report.top_talkers[1].domain_id, Some(3));
+        assert_eq!(report.top_talkers[0].domain_id, None);
+        // Both participants share the loopback source address, so they are on the same host
+        assert_eq!(report.hosts.len(), 1);
+        assert_eq!((report.hosts[0].participants, report.hosts[0].bytes, report.hosts[0].received_bytes), (2, 2500, 300));
+        assert_eq!(report.hosts[0].bytes_per_sec, 500.0);
+        assert_eq!(report.topics.len(), 1);
+        assert_eq!((report.topics[0].samples, report.topics[0].bytes, report.topics[0].writers), (1, 100, 1));
+
+        // The longer window includes the first batch
+        let report = monitor.report(&state, 60.0, 1, true);
+        assert_eq!(report.top_talkers.len(), 1);
+        assert_eq!(report.top_talkers[0].bytes, 1500);
+        assert_eq!(report.topics[0].samples, 3);
+
+        // Batches older than history are dropped
+        monitor.record(&vec![activity(PREFIX, 200.0, 10, 0)]);
+        assert_eq!(monitor.window(1000.0).0[0].bytes, 10);
+        // Totals are kept since the start until the participant is removed
+        let totals = monitor.totals();
+        assert_eq!(totals.iter().find(|activity| activity.guid == PREFIX).unwrap().bytes, 1510);
+        monitor.forget(PREFIX);
+        let guids: Vec<String> = monitor.totals().into_iter().map(|activity| activity.guid).collect();
+        assert_eq!(guids, vec![OTHER_PREFIX.to_string()]);
+    }
+}